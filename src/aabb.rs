@@ -0,0 +1,193 @@
+use matrix::{transform_point, vec3, Mat4, Plane, Ray, Scalar, Vec3};
+
+/// Axis-aligned bounding box
+#[derive(Copy, Clone, Debug)]
+pub struct Aabb {
+    pub min: Vec3,
+    pub max: Vec3,
+}
+
+impl Aabb {
+    pub fn new(min: Vec3, max: Vec3) -> Aabb {
+        Aabb { min, max }
+    }
+
+    /// Smallest box containing every point, None if there are no points
+    pub fn from_points<'a, I>(points: I) -> Option<Aabb>
+    where
+        I: IntoIterator<Item = &'a Vec3>,
+    {
+        let mut points = points.into_iter();
+        let first = *points.next()?;
+        Some(points.fold(Aabb::new(first, first), |aabb, point| Aabb {
+            min: aabb.min.min(point),
+            max: aabb.max.max(point),
+        }))
+    }
+
+    /// Smallest box containing both boxes
+    pub fn union(&self, other: &Aabb) -> Aabb {
+        Aabb {
+            min: self.min.min(&other.min),
+            max: self.max.max(&other.max),
+        }
+    }
+
+    /// Whether a point is inside the box or on its surface
+    pub fn contains(&self, point: &Vec3) -> bool {
+        self.min.x <= point.x
+            && point.x <= self.max.x
+            && self.min.y <= point.y
+            && point.y <= self.max.y
+            && self.min.z <= point.z
+            && point.z <= self.max.z
+    }
+
+    pub fn center(&self) -> Vec3 {
+        Vec3::lerp(self.min, self.max, 0.5)
+    }
+
+    /// The eight corners of the box
+    pub fn corners(&self) -> [Vec3; 8] {
+        let (min, max) = (self.min, self.max);
+        [
+            vec3(min.x, min.y, min.z),
+            vec3(max.x, min.y, min.z),
+            vec3(min.x, max.y, min.z),
+            vec3(max.x, max.y, min.z),
+            vec3(min.x, min.y, max.z),
+            vec3(max.x, min.y, max.z),
+            vec3(min.x, max.y, max.z),
+            vec3(max.x, max.y, max.z),
+        ]
+    }
+
+    /// Box containing this box after it has been transformed by a matrix
+    pub fn transformed(&self, matrix: &Mat4) -> Aabb {
+        let corners: Vec<Vec3> = self
+            .corners()
+            .iter()
+            .map(|corner| transform_point(matrix, *corner))
+            .collect();
+        // There are always eight corners
+        Aabb::from_points(&corners).unwrap()
+    }
+
+    /// Whether any part of the box may be inside the frustum planes
+    pub fn intersects_frustum(&self, planes: &[Plane; 6]) -> bool {
+        planes.iter().all(|plane| {
+            // The corner furthest along the normal is the last to leave the plane
+            let corner = vec3(
+                if plane.normal.x >= 0.0 {
+                    self.max.x
+                } else {
+                    self.min.x
+                },
+                if plane.normal.y >= 0.0 {
+                    self.max.y
+                } else {
+                    self.min.y
+                },
+                if plane.normal.z >= 0.0 {
+                    self.max.z
+                } else {
+                    self.min.z
+                },
+            );
+            plane.signed_distance(&corner) >= 0.0
+        })
+    }
+
+    /// Distance along a ray to where it first meets the box's surface, None if it misses
+    ///
+    /// A ray starting inside the box meets the surface on the way out.
+    pub fn intersect_ray(&self, ray: &Ray) -> Option<Scalar> {
+        let (origin, direction) = (ray.origin, ray.direction);
+        let axes = [
+            (origin.x, direction.x, self.min.x, self.max.x),
+            (origin.y, direction.y, self.min.y, self.max.y),
+            (origin.z, direction.z, self.min.z, self.max.z),
+        ];
+        // Narrow the part of the ray between each pair of faces
+        let mut near = Scalar::NEG_INFINITY;
+        let mut far = Scalar::INFINITY;
+        for &(origin, direction, min, max) in &axes {
+            if direction == 0.0 {
+                // Parallel to the faces, so it is between them everywhere or nowhere
+                if origin < min || origin > max {
+                    return None;
+                }
+                continue;
+            }
+            let (a, b) = ((min - origin) / direction, (max - origin) / direction);
+            near = near.max(a.min(b));
+            far = far.min(a.max(b));
+        }
+        if near > far || far < 0.0 {
+            None
+        } else if near >= 0.0 {
+            Some(near)
+        } else {
+            Some(far)
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::Aabb;
+    use matrix::consts::PI;
+    use matrix::{frustum_planes, rotate_y, translate, vec3, Projection, Ray, Scalar};
+
+    #[test]
+    fn test_aabb() {
+        let aabb = Aabb::new(vec3(-1.0, -1.0, -1.0), vec3(1.0, 1.0, 1.0));
+        assert!(aabb.contains(&vec3(1.0, 0.0, -0.5)));
+        assert!(!aabb.contains(&vec3(1.5, 0.0, 0.0)));
+        let merged = aabb.union(&Aabb::new(vec3(0.0, 0.0, 0.0), vec3(3.0, 2.0, 1.0)));
+        assert!(merged.contains(&vec3(2.5, 1.5, 0.0)));
+        // Rotating an eighth of a turn grows the box to hold the rotated corners
+        let moved = aabb.transformed(&(rotate_y(PI / 4.0) * translate(5.0, 0.0, 0.0)));
+        let half = (2.0 as Scalar).sqrt();
+        assert!((moved.min.x - (5.0 - half)).abs() < 1e-5);
+        assert!((moved.max.x - (5.0 + half)).abs() < 1e-5);
+        assert!((moved.max.y - 1.0).abs() < 1e-5);
+        assert!((moved.center().x - 5.0).abs() < 1e-5);
+    }
+
+    #[test]
+    fn test_intersects_frustum() {
+        // Camera at the origin looking down -z
+        let planes = frustum_planes(&Projection::perspective(PI / 2.0, 1.0, 0.1, 100.0).matrix());
+        let unit = |x: Scalar, y: Scalar, z: Scalar| {
+            Aabb::new(
+                vec3(x - 0.5, y - 0.5, z - 0.5),
+                vec3(x + 0.5, y + 0.5, z + 0.5),
+            )
+        };
+        assert!(unit(0.0, 0.0, -5.0).intersects_frustum(&planes));
+        // Behind the camera and past the far plane
+        assert!(!unit(0.0, 0.0, 5.0).intersects_frustum(&planes));
+        assert!(!unit(0.0, 0.0, -200.0).intersects_frustum(&planes));
+        // Off to the side, and straddling the left plane
+        assert!(!unit(-20.0, 0.0, -5.0).intersects_frustum(&planes));
+        assert!(unit(-5.0, 0.0, -5.0).intersects_frustum(&planes));
+    }
+
+    #[test]
+    fn test_intersect_ray() {
+        let aabb = Aabb::new(vec3(-1.0, -1.0, -1.0), vec3(1.0, 1.0, 1.0));
+        let hit = aabb.intersect_ray(&Ray::new(vec3(-5.0, 0.5, 0.0), vec3(1.0, 0.0, 0.0)));
+        assert!((hit.unwrap() - 4.0).abs() < 1e-5);
+        // Pointing away, or passing beside it
+        assert!(aabb
+            .intersect_ray(&Ray::new(vec3(-5.0, 0.0, 0.0), vec3(-1.0, 0.0, 0.0)))
+            .is_none());
+        assert!(aabb
+            .intersect_ray(&Ray::new(vec3(-5.0, 2.0, 0.0), vec3(1.0, 0.0, 0.0)))
+            .is_none());
+        // From inside, the ray leaves through the far face
+        let inside = aabb.intersect_ray(&Ray::new(vec3(0.0, 0.0, 0.0), vec3(0.0, 0.0, -1.0)));
+        assert!((inside.unwrap() - 1.0).abs() < 1e-5);
+    }
+}
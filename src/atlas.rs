@@ -0,0 +1,129 @@
+use std::io;
+
+use image::{imageops, RgbaImage};
+
+use error::io_error;
+use matrix::{vec2, Scalar, Vec2};
+
+/// Where a packed image ended up in an atlas, in texture coordinates
+#[derive(Copy, Clone, Debug)]
+pub struct AtlasRegion {
+    pub offset: Vec2,
+    pub scale: Vec2,
+}
+
+impl AtlasRegion {
+    /// Maps texture coordinates of the original image into the atlas
+    pub fn map(&self, coord: Vec2) -> Vec2 {
+        // Coordinates outside the image would land on its neighbors, so clamp them
+        vec2(
+            self.offset.x + coord.x.clamp(0.0, 1.0) * self.scale.x,
+            self.offset.y + coord.y.clamp(0.0, 1.0) * self.scale.y,
+        )
+    }
+}
+
+/// Packs images into rows of a single atlas image
+pub struct AtlasBuilder {
+    width: u32,
+    height: u32,
+    /// Space left between images so filtering doesn't bleed across them
+    padding: u32,
+    images: Vec<RgbaImage>,
+}
+
+impl AtlasBuilder {
+    pub fn new(width: u32, height: u32, padding: u32) -> Self {
+        AtlasBuilder {
+            width,
+            height,
+            padding,
+            images: Vec::new(),
+        }
+    }
+
+    /// Adds an image, returning its index in the built regions
+    pub fn add(&mut self, image: RgbaImage) -> usize {
+        self.images.push(image);
+        self.images.len() - 1
+    }
+
+    /// Packs every image, returning the atlas and the region of each image
+    pub fn build(self) -> Result<(RgbaImage, Vec<AtlasRegion>), io::Error> {
+        let mut atlas = RgbaImage::new(self.width, self.height);
+        let mut regions = vec![
+            AtlasRegion {
+                offset: Vec2::origin(),
+                scale: Vec2::origin(),
+            };
+            self.images.len()
+        ];
+        // Tallest images first keeps rows tight
+        let mut order: Vec<usize> = (0..self.images.len()).collect();
+        order.sort_by_key(|&index| std::cmp::Reverse(self.images[index].height()));
+        // Current position and height of the current row
+        let (mut x, mut y, mut row_height) = (0, 0, 0);
+        for index in order {
+            let image = &self.images[index];
+            let (width, height) = image.dimensions();
+            // Start a new row when this one is full
+            if x + width > self.width {
+                x = 0;
+                y += row_height + self.padding;
+                row_height = 0;
+            }
+            if x + width > self.width || y + height > self.height {
+                return Err(io_error(format!(
+                    "{}x{} image does not fit in the {}x{} atlas",
+                    width, height, self.width, self.height
+                )));
+            }
+            imageops::replace(&mut atlas, image, x, y);
+            regions[index] = AtlasRegion {
+                offset: vec2(
+                    x as Scalar / self.width as Scalar,
+                    y as Scalar / self.height as Scalar,
+                ),
+                scale: vec2(
+                    width as Scalar / self.width as Scalar,
+                    height as Scalar / self.height as Scalar,
+                ),
+            };
+            x += width + self.padding;
+            row_height = row_height.max(height);
+        }
+        Ok((atlas, regions))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::io;
+
+    use image::{Rgba, RgbaImage};
+
+    use super::AtlasBuilder;
+    use matrix::vec2;
+
+    #[test]
+    fn test_pack() -> io::Result<()> {
+        let mut builder = AtlasBuilder::new(8, 8, 0);
+        let tall = builder.add(RgbaImage::from_pixel(4, 8, Rgba([255, 0, 0, 255])));
+        let small = builder.add(RgbaImage::from_pixel(4, 4, Rgba([0, 255, 0, 255])));
+        let (atlas, regions) = builder.build()?;
+        // Each image keeps its own pixels
+        assert_eq!(atlas.get_pixel(0, 7), &Rgba([255, 0, 0, 255]));
+        assert_eq!(atlas.get_pixel(4, 0), &Rgba([0, 255, 0, 255]));
+        // Coordinates are remapped into the image's region and clamped to it
+        let corner = regions[small].map(vec2(1.0, 1.0));
+        assert_eq!((corner.x, corner.y), (1.0, 0.5));
+        let outside = regions[tall].map(vec2(2.0, -1.0));
+        assert_eq!((outside.x, outside.y), (0.5, 0.0));
+        // Running out of room is an error
+        let mut builder = AtlasBuilder::new(4, 4, 0);
+        builder.add(RgbaImage::new(4, 4));
+        builder.add(RgbaImage::new(1, 1));
+        assert!(builder.build().is_err());
+        Ok(())
+    }
+}
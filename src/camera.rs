@@ -0,0 +1,406 @@
+use std::fmt;
+use std::io;
+use std::str::FromStr;
+
+use error::io_error;
+use matrix::consts::PI;
+use matrix::{
+    transform_point, translate, vec3, viewing_matrix, Mat4, Projection, Ray, Scalar, Vec3,
+};
+
+/// Keeps the camera from flipping over the poles when orbiting
+const MIN_PITCH_ANGLE: Scalar = 0.01;
+/// Number of bookmark slots, one for each digit key
+pub const BOOKMARK_SLOTS: usize = 9;
+
+/// Jitter added to the view that dies down over time
+#[derive(Copy, Clone, Debug)]
+struct Shake {
+    /// Largest distance the view is moved
+    amplitude: Scalar,
+    /// Seconds the shake lasts
+    duration: Scalar,
+    /// Seconds since the shake started
+    elapsed: Scalar,
+}
+
+impl Shake {
+    fn none() -> Shake {
+        Shake {
+            amplitude: 0.0,
+            duration: 0.0,
+            elapsed: 0.0,
+        }
+    }
+
+    /// How far the view is moved right now, in eye coordinates
+    fn offset(&self) -> Vec3 {
+        if self.elapsed >= self.duration {
+            return Vec3::origin();
+        }
+        let strength = self.amplitude * (1.0 - self.elapsed / self.duration);
+        // Unrelated frequencies on each axis keep the motion from looking regular
+        let t = self.elapsed;
+        vec3(
+            (t * 47.0).sin(),
+            (t * 53.0 + 1.0).sin(),
+            (t * 59.0 + 2.0).sin() * 0.5,
+        ) * strength
+    }
+}
+
+/// Where the scene is viewed from
+#[derive(Copy, Clone, Debug)]
+pub struct Camera {
+    pub eye: Vec3,
+    pub target: Vec3,
+    pub up: Vec3,
+    shake: Shake,
+}
+
+impl Camera {
+    pub fn new(eye: Vec3, target: Vec3, up: Vec3) -> Camera {
+        Camera {
+            eye,
+            target,
+            up,
+            shake: Shake::none(),
+        }
+    }
+
+    /// Matrix taking world coordinates to eye coordinates
+    pub fn view_matrix(&self) -> Mat4 {
+        let offset = self.shake.offset();
+        // The shake is applied after the view so it is the same on screen wherever the camera is
+        viewing_matrix(self.eye, self.up, self.target) * translate(offset.x, offset.y, offset.z)
+    }
+
+    /// Shakes the view by up to `amplitude` units, dying down over `duration` seconds
+    pub fn shake(&mut self, amplitude: Scalar, duration: Scalar) {
+        self.shake = Shake {
+            amplitude,
+            duration,
+            elapsed: 0.0,
+        };
+    }
+
+    /// Advances the shake by `delta` seconds
+    pub fn update_shake(&mut self, delta: Scalar) {
+        self.shake.elapsed += delta;
+    }
+
+    /// Unit vector the camera is looking along
+    pub fn direction(&self) -> Vec3 {
+        (self.target - self.eye).normalize()
+    }
+
+    /// Moves the eye and target together
+    pub fn translate(&mut self, offset: Vec3) {
+        self.eye += offset;
+        self.target += offset;
+    }
+
+    /// Swings the eye around the target, `yaw` radians around the y axis and
+    /// `pitch` radians up towards it
+    pub fn orbit(&mut self, yaw: Scalar, pitch: Scalar) {
+        let (radius, theta, phi) = (self.eye - self.target).to_spherical();
+        let phi = (phi - pitch).clamp(MIN_PITCH_ANGLE, PI - MIN_PITCH_ANGLE);
+        self.eye = self.target + Vec3::from_spherical(radius, theta + yaw, phi);
+    }
+
+    /// Scales the distance between the eye and target, keeping it between
+    /// `min_distance` and `max_distance`
+    pub fn dolly(&mut self, factor: Scalar, min_distance: Scalar, max_distance: Scalar) {
+        let distance = (self.eye.distance(&self.target) * factor).clamp(min_distance, max_distance);
+        self.eye = self.target - self.direction() * distance;
+    }
+
+    /// Turns the camera in place, `yaw` radians around the y axis and `pitch`
+    /// radians up
+    pub fn turn(&mut self, yaw: Scalar, pitch: Scalar) {
+        let (radius, theta, phi) = (self.target - self.eye).to_spherical();
+        let phi = (phi - pitch).clamp(MIN_PITCH_ANGLE, PI - MIN_PITCH_ANGLE);
+        self.target = self.eye + Vec3::from_spherical(radius, theta + yaw, phi);
+    }
+
+    /// Turns the camera towards a point without moving it
+    pub fn look_at(&mut self, target: Vec3) {
+        self.target = target;
+    }
+
+    /// Where the camera is and what it is looking at
+    pub fn pose(&self) -> CameraPose {
+        CameraPose {
+            eye: self.eye,
+            target: self.target,
+        }
+    }
+
+    /// Moves the camera to a saved pose
+    pub fn set_pose(&mut self, pose: CameraPose) {
+        self.eye = pose.eye;
+        self.target = pose.target;
+    }
+
+    /// Ray from the camera through a point on the screen, for picking what is under the mouse
+    ///
+    /// `x` and `y` are in pixels from the top left, and `viewport` is the x, y, width and
+    /// height of the area drawn to. None if the projection can't be inverted.
+    pub fn screen_ray(
+        &self,
+        x: Scalar,
+        y: Scalar,
+        viewport: [Scalar; 4],
+        projection: &Projection,
+    ) -> Option<Ray> {
+        let [left, top, width, height] = viewport;
+        // Normalized device coordinates have y pointing up
+        let ndc_x = 2.0 * (x - left) / width - 1.0;
+        let ndc_y = 1.0 - 2.0 * (y - top) / height;
+        let inverse = (self.view_matrix() * projection.matrix()).inverse()?;
+        // Points under the cursor on the near and far planes
+        let near = transform_point(&inverse, vec3(ndc_x, ndc_y, -1.0));
+        let far = transform_point(&inverse, vec3(ndc_x, ndc_y, 1.0));
+        Some(Ray::new(near, far - near))
+    }
+}
+
+/// Eye and target of a camera, written as six comma separated numbers so it can be put in a link
+#[derive(Copy, Clone, Debug)]
+pub struct CameraPose {
+    pub eye: Vec3,
+    pub target: Vec3,
+}
+
+impl fmt::Display for CameraPose {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let (eye, target) = (self.eye, self.target);
+        write!(
+            f,
+            "{},{},{},{},{},{}",
+            eye.x, eye.y, eye.z, target.x, target.y, target.z
+        )
+    }
+}
+
+impl FromStr for CameraPose {
+    type Err = io::Error;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let values = s
+            .split(',')
+            .map(|value| value.trim().parse::<Scalar>().map_err(io_error))
+            .collect::<Result<Vec<Scalar>, io::Error>>()?;
+        if values.len() != 6 {
+            return Err(io_error("A camera pose needs six numbers"));
+        }
+        Ok(CameraPose {
+            eye: vec3(values[0], values[1], values[2]),
+            target: vec3(values[3], values[4], values[5]),
+        })
+    }
+}
+
+/// Camera poses saved to numbered slots
+pub struct Bookmarks {
+    slots: [Option<CameraPose>; BOOKMARK_SLOTS],
+}
+
+impl Bookmarks {
+    pub fn new() -> Bookmarks {
+        Bookmarks {
+            slots: [None; BOOKMARK_SLOTS],
+        }
+    }
+
+    /// Saves a pose to a slot numbered from 1, returning whether the slot exists
+    pub fn save(&mut self, slot: usize, pose: CameraPose) -> bool {
+        match slot
+            .checked_sub(1)
+            .and_then(|index| self.slots.get_mut(index))
+        {
+            Some(saved) => {
+                *saved = Some(pose);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Pose saved to a slot numbered from 1
+    pub fn get(&self, slot: usize) -> Option<CameraPose> {
+        slot.checked_sub(1)
+            .and_then(|index| self.slots.get(index))
+            .and_then(|saved| *saved)
+    }
+}
+
+/// Moves a value toward a goal with a critically damped spring, so it settles as fast as
+/// possible without overshooting
+///
+/// `velocity` carries over between calls and `smooth_time` is roughly how many seconds
+/// it takes to catch up.
+pub fn smooth_damp(
+    current: Scalar,
+    goal: Scalar,
+    velocity: &mut Scalar,
+    smooth_time: Scalar,
+    delta: Scalar,
+) -> Scalar {
+    let omega = 2.0 / smooth_time.max(1e-4);
+    let x = omega * delta;
+    // Approximates e^-x, exact enough for a frame at a time
+    let decay = 1.0 / (1.0 + x + 0.48 * x * x + 0.235 * x * x * x);
+    let change = current - goal;
+    let temp = (*velocity + omega * change) * delta;
+    *velocity = (*velocity - omega * temp) * decay;
+    goal + (change + temp) * decay
+}
+
+/// Glides a camera toward where the controls have put another one
+#[derive(Copy, Clone, Debug)]
+pub struct CameraSmoothing {
+    /// Roughly how many seconds the camera takes to catch up
+    pub smooth_time: Scalar,
+    eye_velocity: Vec3,
+    target_velocity: Vec3,
+}
+
+impl CameraSmoothing {
+    pub fn new(smooth_time: Scalar) -> CameraSmoothing {
+        CameraSmoothing {
+            smooth_time,
+            eye_velocity: Vec3::origin(),
+            target_velocity: Vec3::origin(),
+        }
+    }
+
+    /// Moves `camera` toward `goal` over `delta` seconds
+    pub fn update(&mut self, delta: Scalar, goal: &Camera, camera: &mut Camera) {
+        let smooth_time = self.smooth_time;
+        let damp = |current: Vec3, goal: Vec3, velocity: &mut Vec3| {
+            vec3(
+                smooth_damp(current.x, goal.x, &mut velocity.x, smooth_time, delta),
+                smooth_damp(current.y, goal.y, &mut velocity.y, smooth_time, delta),
+                smooth_damp(current.z, goal.z, &mut velocity.z, smooth_time, delta),
+            )
+        };
+        camera.eye = damp(camera.eye, goal.eye, &mut self.eye_velocity);
+        camera.target = damp(camera.target, goal.target, &mut self.target_velocity);
+        camera.up = goal.up;
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{smooth_damp, Bookmarks, Camera, CameraPose};
+    use matrix::consts::PI;
+    use matrix::{rotate_y, transform_point, vec3, Projection};
+
+    #[test]
+    fn test_camera() {
+        let mut camera = Camera::new(
+            vec3(0.0, 0.0, 5.0),
+            vec3(0.0, 0.0, 0.0),
+            vec3(0.0, 1.0, 0.0),
+        );
+        // The target is straight ahead
+        let ahead = transform_point(&camera.view_matrix(), camera.target);
+        assert!(ahead.distance(&vec3(0.0, 0.0, -5.0)) < 1e-5);
+        // Orbiting matches spinning the world the other way
+        let view = rotate_y(0.5) * camera.view_matrix();
+        camera.orbit(0.5, 0.0);
+        let point = vec3(1.0, 2.0, 3.0);
+        let expected = transform_point(&view, point);
+        assert!(transform_point(&camera.view_matrix(), point).distance(&expected) < 1e-4);
+        // Pitching stops short of the pole
+        camera.orbit(0.0, PI);
+        assert!(camera.eye.y < 5.0 && camera.eye.distance(&camera.target) > 4.99);
+        camera.dolly(0.1, 2.0, 10.0);
+        assert!((camera.eye.distance(&camera.target) - 2.0).abs() < 1e-5);
+        camera.translate(vec3(1.0, 0.0, 0.0));
+        assert_eq!(camera.target.x, 1.0);
+    }
+
+    #[test]
+    fn test_screen_ray() {
+        let camera = Camera::new(
+            vec3(3.0, 4.0, 5.0),
+            vec3(0.0, 1.0, 0.0),
+            vec3(0.0, 1.0, 0.0),
+        );
+        let viewport = [0.0, 0.0, 800.0, 600.0];
+        for projection in &[
+            Projection::perspective(PI / 3.0, 4.0 / 3.0, 0.1, 100.0),
+            Projection::ortho(-4.0, 4.0, -3.0, 3.0, 0.1, 100.0),
+        ] {
+            // The middle of the screen looks at the target
+            let ray = camera
+                .screen_ray(400.0, 300.0, viewport, projection)
+                .unwrap();
+            let to_target = camera.target - ray.origin;
+            assert!(ray.direction.distance(&to_target.normalize()) < 1e-4);
+            // A point drawn at some pixel is somewhere along the ray through it
+            let point = vec3(1.0, 0.5, -1.0);
+            let ndc = transform_point(&(camera.view_matrix() * projection.matrix()), point);
+            let (x, y) = ((ndc.x + 1.0) * 400.0, (1.0 - ndc.y) * 300.0);
+            let ray = camera.screen_ray(x, y, viewport, projection).unwrap();
+            let t = (point - ray.origin).dot(&ray.direction);
+            assert!(ray.at(t).distance(&point) < 1e-3);
+        }
+    }
+
+    #[test]
+    fn test_bookmarks() {
+        let camera = Camera::new(
+            vec3(1.5, 2.0, -3.0),
+            vec3(0.0, 1.0, 0.25),
+            vec3(0.0, 1.0, 0.0),
+        );
+        let mut bookmarks = Bookmarks::new();
+        assert!(bookmarks.save(3, camera.pose()));
+        assert!(!bookmarks.save(0, camera.pose()));
+        assert!(!bookmarks.save(10, camera.pose()));
+        assert!(bookmarks.get(2).is_none());
+        // Poses survive being written out and read back
+        let pose: CameraPose = bookmarks.get(3).unwrap().to_string().parse().unwrap();
+        assert!(pose.eye.distance(&camera.eye) < 1e-5);
+        assert!(pose.target.distance(&camera.target) < 1e-5);
+        assert!("1,2,3".parse::<CameraPose>().is_err());
+        assert!("1,2,3,4,5,x".parse::<CameraPose>().is_err());
+    }
+
+    #[test]
+    fn test_shake() {
+        let mut camera = Camera::new(
+            vec3(0.0, 0.0, 5.0),
+            vec3(0.0, 0.0, 0.0),
+            vec3(0.0, 1.0, 0.0),
+        );
+        let still = transform_point(&camera.view_matrix(), camera.target);
+        camera.shake(0.5, 1.0);
+        camera.update_shake(0.3);
+        let shaken = transform_point(&camera.view_matrix(), camera.target);
+        let distance = shaken.distance(&still);
+        assert!(distance > 0.0 && distance < 0.5);
+        // Once it is over the view is back where it was
+        camera.update_shake(1.0);
+        let settled = transform_point(&camera.view_matrix(), camera.target);
+        assert!(settled.distance(&still) < 1e-5);
+    }
+
+    #[test]
+    fn test_smooth_damp() {
+        let (mut value, mut velocity) = (0.0, 0.0);
+        let mut last = value;
+        for _ in 0..120 {
+            value = smooth_damp(value, 10.0, &mut velocity, 0.25, 1.0 / 60.0);
+            // Always heading toward the goal and never past it
+            assert!(value >= last && value <= 10.0);
+            last = value;
+        }
+        assert!((value - 10.0).abs() < 1e-2);
+        // A long frame doesn't overshoot either
+        let mut velocity = 0.0;
+        assert!(smooth_damp(0.0, 10.0, &mut velocity, 0.25, 5.0) <= 10.0);
+    }
+}
@@ -0,0 +1,118 @@
+use extrusion::Extrusion;
+use light::Light;
+use material::Material;
+use matrix::consts::PI;
+use matrix::{vec3, Scalar, Transform, Vec3};
+use mesh::{Instance, MeshData};
+use render::Drawable;
+use revolution::Revolution;
+
+/// Radius of the rod the shade hangs from
+const ROD_RADIUS: Scalar = 0.04;
+/// Sides of the rod
+const ROD_SIDES: u16 = 8;
+/// Thickness of the shade's wall
+const SHADE_THICKNESS: Scalar = 0.02;
+
+/// Light hanging on a rod from the ceiling under a bowl shaped shade
+///
+/// The fixture is placed by where the rod meets the ceiling and hangs below it, so attaching
+/// it to a room at the ceiling's height hangs it from the ceiling. Its bulb lights the room.
+pub struct CeilingLight {
+    /// Length of the rod
+    drop: Scalar,
+    /// Radius of the shade's rim
+    radius: Scalar,
+    light: Light,
+    instance: Instance,
+}
+
+impl CeilingLight {
+    /// Creates a fixture whose bulb shines `color`, `drop` below its top
+    pub fn new(drop: Scalar, radius: Scalar, color: Vec3, translate: Vec3) -> CeilingLight {
+        CeilingLight {
+            drop,
+            radius,
+            // Bright enough to light the whole room from above
+            light: Light::point(vec3(0.0, -drop - radius * 0.4, 0.0), color)
+                .with_intensity(1.4)
+                .with_attenuation(1.0, 0.07, 0.017),
+            instance: Instance::new(
+                Transform::from_translation(translate),
+                Material::bronze().with_emissive(color * 0.2),
+            ),
+        }
+    }
+}
+
+impl Drawable for CeilingLight {
+    /// Fixtures of the same size share a mesh
+    fn mesh_key(&self) -> String {
+        format!("ceiling light {} {}", self.drop, self.radius)
+    }
+    /// Generates the vertices of the shape
+    fn mesh_data(&self) -> MeshData {
+        // Outline of the rod, ending back where it starts
+        let outline: Vec<Vec3> = (0..=ROD_SIDES)
+            .map(|i| {
+                let angle = -2.0 * PI * Scalar::from(i) / Scalar::from(ROD_SIDES);
+                vec3(
+                    ROD_RADIUS * angle.cos(),
+                    -self.drop,
+                    ROD_RADIUS * angle.sin(),
+                )
+            })
+            .collect();
+        let rod = Extrusion::new(outline, vec3(0.0, self.drop, 0.0), Vec3::origin())
+            .with_smooth(true)
+            .mesh_data();
+        // Down the inside of the shade to its rim, then back up the outside to the rod
+        let (top, rim) = (-self.drop, -self.drop - self.radius * 0.8);
+        let profile = vec![
+            vec3(ROD_RADIUS * 2.0, top - SHADE_THICKNESS, 0.0),
+            vec3(self.radius - SHADE_THICKNESS, rim, 0.0),
+            vec3(self.radius, rim, 0.0),
+            vec3(ROD_RADIUS * 2.0 + SHADE_THICKNESS, top, 0.0),
+        ];
+        let shade = Revolution::new(profile, 24, Vec3::origin()).mesh_data();
+        let bounds = rod
+            .bounds
+            .and_then(|rod| shade.bounds.map(|shade| rod.union(&shade)));
+        let mut vertices = rod.vertices;
+        vertices.extend(shade.vertices);
+        MeshData { vertices, bounds }
+    }
+    /// Mesh of the object and how it is placed and looks
+    fn instance(&self) -> &Instance {
+        &self.instance
+    }
+    fn instance_mut(&mut self) -> &mut Instance {
+        &mut self.instance
+    }
+    /// The bulb, under the shade
+    fn light(&self) -> Option<Light> {
+        Some(self.light)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::CeilingLight;
+    use light::LightKind;
+    use matrix::{vec3, Vec3};
+    use render::Drawable;
+
+    #[test]
+    fn test_ceiling_light() {
+        let fixture = CeilingLight::new(1.0, 0.5, vec3(1.0, 1.0, 1.0), Vec3::origin());
+        // Everything hangs below the point it is attached at
+        let bounds = fixture.mesh_data().bounds.unwrap();
+        assert!(bounds.max.y.abs() < 1e-5 && (bounds.min.y + 1.4).abs() < 1e-5);
+        match fixture.light().unwrap().kind {
+            LightKind::Point { position } => {
+                assert!(bounds.contains(&position) && position.y < -1.0);
+            }
+            _ => panic!("The bulb isn't a point light"),
+        }
+    }
+}
@@ -0,0 +1,300 @@
+use camera::Camera;
+use matrix::consts::PI;
+use matrix::{catmull_rom_path, catmull_rom_path_tangent, vec3, Scalar, Vec3};
+
+/// How the camera responds to input
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum CameraMode {
+    /// Dragging orbits the camera around the room
+    Orbit,
+    /// Walking around with the keyboard and looking with the mouse
+    Fly,
+    /// Following a path through the room on its own
+    Tour,
+    /// The camera stays put
+    Static,
+}
+
+impl CameraMode {
+    /// Mode switched to after this one
+    pub fn next(self) -> CameraMode {
+        match self {
+            CameraMode::Orbit => CameraMode::Fly,
+            CameraMode::Fly => CameraMode::Tour,
+            CameraMode::Tour => CameraMode::Static,
+            CameraMode::Static => CameraMode::Orbit,
+        }
+    }
+}
+
+/// Turns mouse drags into the camera orbiting its target
+pub struct OrbitController {
+    /// Radians turned per pixel dragged
+    pub sensitivity: Scalar,
+    /// Lowest angle above the horizon the camera can be pitched to
+    pub min_pitch: Scalar,
+    /// Highest angle above the horizon the camera can be pitched to
+    pub max_pitch: Scalar,
+    /// Pixels dragged since the camera was last updated
+    delta_x: Scalar,
+    delta_y: Scalar,
+}
+
+impl OrbitController {
+    pub fn new(sensitivity: Scalar) -> OrbitController {
+        OrbitController {
+            sensitivity,
+            min_pitch: -PI * 0.45,
+            max_pitch: PI * 0.45,
+            delta_x: 0.0,
+            delta_y: 0.0,
+        }
+    }
+
+    /// Records the mouse moving while a button is held
+    pub fn drag(&mut self, delta_x: Scalar, delta_y: Scalar) {
+        self.delta_x += delta_x;
+        self.delta_y += delta_y;
+    }
+
+    /// Applies everything dragged since the last update to the camera
+    pub fn update(&mut self, camera: &mut Camera) {
+        let yaw = self.delta_x * self.sensitivity;
+        // Dragging down lifts the camera to look down on the target
+        let (_, _, phi) = (camera.eye - camera.target).to_spherical();
+        let pitch = PI / 2.0 - phi;
+        let new_pitch =
+            (pitch + self.delta_y * self.sensitivity).clamp(self.min_pitch, self.max_pitch);
+        camera.orbit(yaw, new_pitch - pitch);
+        self.delta_x = 0.0;
+        self.delta_y = 0.0;
+    }
+}
+
+/// First person camera, moved with WASD and turned by dragging the mouse
+pub struct FlyController {
+    /// Distance moved per frame while a key is held
+    pub speed: Scalar,
+    /// Radians turned per pixel dragged
+    pub sensitivity: Scalar,
+    forward: bool,
+    backward: bool,
+    left: bool,
+    right: bool,
+    /// Pixels dragged since the camera was last updated
+    delta_x: Scalar,
+    delta_y: Scalar,
+}
+
+impl FlyController {
+    pub fn new(speed: Scalar, sensitivity: Scalar) -> FlyController {
+        FlyController {
+            speed,
+            sensitivity,
+            forward: false,
+            backward: false,
+            left: false,
+            right: false,
+            delta_x: 0.0,
+            delta_y: 0.0,
+        }
+    }
+
+    /// Records a key being pressed or released, returning whether it moves the camera
+    pub fn key(&mut self, code: &str, pressed: bool) -> bool {
+        let held = match code {
+            "KeyW" => &mut self.forward,
+            "KeyS" => &mut self.backward,
+            "KeyA" => &mut self.left,
+            "KeyD" => &mut self.right,
+            _ => return false,
+        };
+        *held = pressed;
+        true
+    }
+
+    /// Records the mouse moving while a button is held
+    pub fn look(&mut self, delta_x: Scalar, delta_y: Scalar) {
+        self.delta_x += delta_x;
+        self.delta_y += delta_y;
+    }
+
+    /// Turns and moves the camera by the input since the last update
+    pub fn update(&mut self, camera: &mut Camera) {
+        // Dragging right turns right and dragging up looks up
+        camera.turn(
+            -self.delta_x * self.sensitivity,
+            -self.delta_y * self.sensitivity,
+        );
+        self.delta_x = 0.0;
+        self.delta_y = 0.0;
+        // Walk along the floor whichever way the camera is looking
+        let direction = camera.direction();
+        let forward = vec3(direction.x, 0.0, direction.z).normalize();
+        let right = forward.cross(vec3(0.0, 1.0, 0.0));
+        let axis = |positive: bool, negative: bool| match (positive, negative) {
+            (true, false) => 1.0,
+            (false, true) => -1.0,
+            _ => 0.0,
+        };
+        let movement: Vec3 =
+            forward * axis(self.forward, self.backward) + right * axis(self.right, self.left);
+        if movement.length_squared() > 0.0 {
+            camera.translate(movement.normalize() * self.speed);
+        }
+    }
+}
+
+/// Where the camera looks during a fly-through
+#[derive(Copy, Clone, Debug)]
+pub enum TourLook {
+    /// Ahead along the path
+    Tangent,
+    /// Always at the same point
+    Target(Vec3),
+}
+
+/// Moves the camera along a Catmull-Rom path through a list of waypoints
+pub struct FlyThrough {
+    pub waypoints: Vec<Vec3>,
+    /// Seconds taken to travel the whole path
+    pub duration: Scalar,
+    pub look: TourLook,
+    /// Starts over from the first waypoint after reaching the last
+    pub looping: bool,
+    /// Seconds since the tour started
+    elapsed: Scalar,
+}
+
+impl FlyThrough {
+    pub fn new(waypoints: Vec<Vec3>, duration: Scalar, look: TourLook) -> FlyThrough {
+        FlyThrough {
+            waypoints,
+            duration,
+            look,
+            looping: true,
+            elapsed: 0.0,
+        }
+    }
+
+    /// Goes back to the first waypoint
+    pub fn restart(&mut self) {
+        self.elapsed = 0.0;
+    }
+
+    /// Changes how many seconds the whole path takes, keeping the camera where it is along it
+    pub fn set_duration(&mut self, duration: Scalar) {
+        if self.duration > 0.0 {
+            self.elapsed *= duration / self.duration;
+        }
+        self.duration = duration;
+    }
+
+    /// Whether a non-looping tour has reached its last waypoint
+    pub fn finished(&self) -> bool {
+        !self.looping && self.elapsed >= self.duration
+    }
+
+    /// Advances the tour by `delta` seconds and moves the camera to match
+    pub fn update(&mut self, delta: Scalar, camera: &mut Camera) {
+        if self.waypoints.is_empty() {
+            return;
+        }
+        self.elapsed += delta;
+        let progress = self.elapsed / self.duration;
+        let t = if self.looping {
+            progress.fract()
+        } else {
+            progress.min(1.0)
+        };
+        camera.eye = catmull_rom_path(&self.waypoints, t);
+        camera.target = match self.look {
+            TourLook::Tangent => {
+                let tangent = catmull_rom_path_tangent(&self.waypoints, t);
+                // Keep the old heading where the path stops moving
+                if tangent.length_squared() > 0.0 {
+                    camera.eye + tangent.normalize()
+                } else {
+                    camera.eye + camera.direction()
+                }
+            }
+            TourLook::Target(target) => target,
+        };
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{FlyController, FlyThrough, OrbitController, TourLook};
+    use camera::Camera;
+    use matrix::{vec3, Scalar};
+
+    #[test]
+    fn test_orbit_clamp() {
+        let target = vec3(5.0, 5.0, 5.0);
+        let mut camera = Camera::new(vec3(5.0, 5.0, 10.0), target, vec3(0.0, 1.0, 0.0));
+        let mut controller = OrbitController::new(0.01);
+        controller.min_pitch = -0.5;
+        controller.max_pitch = 0.5;
+        // Dragging far down stops at the highest pitch
+        controller.drag(0.0, 1000.0);
+        controller.update(&mut camera);
+        let offset = camera.eye - target;
+        assert!((offset.y / offset.length() - (0.5 as Scalar).sin()).abs() < 1e-4);
+        // Updates only apply new drags
+        let eye = camera.eye;
+        controller.update(&mut camera);
+        assert!(camera.eye.distance(&eye) < 1e-5);
+    }
+    #[test]
+    fn test_fly() {
+        let mut camera = Camera::new(
+            vec3(0.0, 1.0, 0.0),
+            vec3(0.0, 1.0, -1.0),
+            vec3(0.0, 1.0, 0.0),
+        );
+        let mut controller = FlyController::new(0.5, 0.01);
+        assert!(controller.key("KeyW", true));
+        assert!(controller.key("KeyD", true));
+        assert!(!controller.key("KeyQ", true));
+        // Forward and right at once moves diagonally at the same speed
+        controller.update(&mut camera);
+        let step = 0.5 * (0.5 as Scalar).sqrt();
+        assert!(camera.eye.distance(&vec3(step, 1.0, -step)) < 1e-5);
+        controller.key("KeyW", false);
+        controller.key("KeyD", false);
+        let eye = camera.eye;
+        controller.update(&mut camera);
+        assert!(camera.eye.distance(&eye) < 1e-5);
+    }
+    #[test]
+    fn test_fly_through() {
+        let mut camera = Camera::new(
+            vec3(0.0, 0.0, 0.0),
+            vec3(0.0, 0.0, -1.0),
+            vec3(0.0, 1.0, 0.0),
+        );
+        let waypoints = vec![vec3(0.0, 1.0, 0.0), vec3(4.0, 1.0, 0.0)];
+        let mut tour = FlyThrough::new(waypoints, 2.0, TourLook::Tangent);
+        tour.looping = false;
+        // Halfway through the time is halfway along, looking ahead
+        tour.update(1.0, &mut camera);
+        assert!(camera.eye.distance(&vec3(2.0, 1.0, 0.0)) < 1e-5);
+        assert!(camera.direction().distance(&vec3(1.0, 0.0, 0.0)) < 1e-5);
+        // A finished tour stays at the end
+        tour.update(5.0, &mut camera);
+        assert!(tour.finished());
+        assert!(camera.eye.distance(&vec3(4.0, 1.0, 0.0)) < 1e-5);
+        // Looking at a fixed target
+        tour.look = TourLook::Target(vec3(4.0, 0.0, 4.0));
+        tour.restart();
+        tour.update(0.0, &mut camera);
+        assert!(camera.target.distance(&vec3(4.0, 0.0, 4.0)) < 1e-5);
+        // Slowing down keeps the camera where it was along the path
+        tour.update(1.0, &mut camera);
+        tour.set_duration(4.0);
+        tour.update(0.0, &mut camera);
+        assert!(camera.eye.distance(&vec3(2.0, 1.0, 0.0)) < 1e-5);
+        assert!(!tour.finished());
+    }
+}
@@ -0,0 +1,441 @@
+use std::mem;
+
+use material::Material;
+use matrix::{Scalar, Transform, Vec3};
+use mesh::{Instance, MeshData};
+use render::{Drawable, Vertex, VERTEX_SIZE};
+
+/// How far a point can be from a plane and still count as on it
+const EPSILON: Scalar = 1e-5;
+
+/// How two solids are combined
+#[derive(Serialize, Deserialize, Copy, Clone, Debug, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum Operation {
+    /// Everything inside either
+    Union,
+    /// Everything inside the first but not the second
+    Subtract,
+    /// Everything inside both
+    Intersect,
+}
+
+/// Closed mesh that can be combined with others, kept as flat convex polygons
+///
+/// Solids are combined by building a BSP tree of each one's polygons and clipping each tree
+/// by the other, as csg.js does. Shapes need to be closed, with every face pointing out,
+/// for their insides to be known.
+#[derive(Clone, Debug, Default)]
+pub struct Solid {
+    polygons: Vec<Polygon>,
+}
+
+impl Solid {
+    /// Solid bounded by triangles, as shapes generate them
+    pub fn new(vertices: &[Vertex]) -> Solid {
+        Solid {
+            polygons: vertices
+                .chunks_exact(3)
+                .filter_map(|triangle| Polygon::new(triangle.to_vec()))
+                .collect(),
+        }
+    }
+
+    pub fn apply(&self, operation: Operation, other: &Solid) -> Solid {
+        let mut a = Node::new(self.polygons.clone());
+        let mut b = Node::new(other.polygons.clone());
+        match operation {
+            Operation::Union => {
+                a.clip_to(&b);
+                b.clip_to(&a);
+                // Leave out faces the solids share, once
+                b.invert();
+                b.clip_to(&a);
+                b.invert();
+                a.build(b.all_polygons());
+            }
+            Operation::Subtract => {
+                a.invert();
+                a.clip_to(&b);
+                b.clip_to(&a);
+                b.invert();
+                b.clip_to(&a);
+                b.invert();
+                a.build(b.all_polygons());
+                a.invert();
+            }
+            Operation::Intersect => {
+                a.invert();
+                b.clip_to(&a);
+                b.invert();
+                a.clip_to(&b);
+                b.clip_to(&a);
+                a.build(b.all_polygons());
+                a.invert();
+            }
+        }
+        Solid {
+            polygons: a.all_polygons(),
+        }
+    }
+
+    /// Triangles covering the solid's surface
+    pub fn vertices(&self) -> Vec<Vertex> {
+        self.polygons
+            .iter()
+            .flat_map(|polygon| {
+                let vertices = &polygon.vertices;
+                (1..vertices.len() - 1)
+                    .flat_map(move |i| vec![vertices[0], vertices[i], vertices[i + 1]])
+            })
+            .collect()
+    }
+}
+
+/// Plane through a polygon, the side its normal points to is in front
+#[derive(Copy, Clone, Debug)]
+struct Plane {
+    normal: Vec3,
+    /// Distance from the origin along the normal
+    w: Scalar,
+}
+
+/// Which side of a plane a point or polygon is on, polygons on both sides span it
+const COPLANAR: u8 = 0;
+const FRONT: u8 = 1;
+const BACK: u8 = 2;
+const SPANNING: u8 = FRONT | BACK;
+
+/// Pieces of polygons split by a plane
+#[derive(Default)]
+struct Split {
+    coplanar_front: Vec<Polygon>,
+    coplanar_back: Vec<Polygon>,
+    front: Vec<Polygon>,
+    back: Vec<Polygon>,
+}
+
+impl Plane {
+    fn flip(&mut self) {
+        self.normal = -self.normal;
+        self.w = -self.w;
+    }
+
+    fn side(&self, point: Vec3) -> u8 {
+        let distance = self.normal.dot(&point) - self.w;
+        if distance < -EPSILON {
+            BACK
+        } else if distance > EPSILON {
+            FRONT
+        } else {
+            COPLANAR
+        }
+    }
+
+    /// Sorts a polygon by the side it is on, cutting it in two if it spans the plane
+    ///
+    /// Polygons in the plane are sorted by whether they face the same way.
+    fn split(&self, polygon: Polygon, split: &mut Split) {
+        let sides: Vec<u8> = polygon
+            .vertices
+            .iter()
+            .map(|vertex| self.side(vertex.position()))
+            .collect();
+        match sides.iter().fold(COPLANAR, |all, side| all | side) {
+            COPLANAR => {
+                if self.normal.dot(&polygon.plane.normal) > 0.0 {
+                    split.coplanar_front.push(polygon);
+                } else {
+                    split.coplanar_back.push(polygon);
+                }
+            }
+            FRONT => split.front.push(polygon),
+            BACK => split.back.push(polygon),
+            _ => {
+                let (mut front, mut back) = (Vec::new(), Vec::new());
+                let count = polygon.vertices.len();
+                for i in 0..count {
+                    let j = (i + 1) % count;
+                    let (a, b) = (polygon.vertices[i], polygon.vertices[j]);
+                    if sides[i] != BACK {
+                        front.push(a);
+                    }
+                    if sides[i] != FRONT {
+                        back.push(a);
+                    }
+                    // Cut edges going from one side to the other where they cross
+                    if sides[i] | sides[j] == SPANNING {
+                        let t = (self.w - self.normal.dot(&a.position()))
+                            / self.normal.dot(&(b.position() - a.position()));
+                        let cut = a.lerp(&b, t);
+                        front.push(cut);
+                        back.push(cut);
+                    }
+                }
+                let plane = polygon.plane;
+                if front.len() >= 3 {
+                    split.front.push(Polygon {
+                        vertices: front,
+                        plane,
+                    });
+                }
+                if back.len() >= 3 {
+                    split.back.push(Polygon {
+                        vertices: back,
+                        plane,
+                    });
+                }
+            }
+        }
+    }
+}
+
+/// Flat convex polygon, wound counter-clockwise seen from in front
+#[derive(Clone, Debug)]
+struct Polygon {
+    vertices: Vec<Vertex>,
+    plane: Plane,
+}
+
+impl Polygon {
+    /// None for polygons too thin to have a plane
+    fn new(vertices: Vec<Vertex>) -> Option<Polygon> {
+        let (a, b, c) = (
+            vertices[0].position(),
+            vertices[1].position(),
+            vertices[2].position(),
+        );
+        let normal = (b - a).cross(c - a);
+        if normal.length() < EPSILON * EPSILON {
+            return None;
+        }
+        let normal = normal.normalize();
+        Some(Polygon {
+            vertices,
+            plane: Plane {
+                normal,
+                w: normal.dot(&a),
+            },
+        })
+    }
+
+    /// Turns the polygon to face the other way
+    fn flip(&mut self) {
+        self.vertices.reverse();
+        for vertex in &mut self.vertices {
+            *vertex = vertex.with_normal(-vertex.normal());
+        }
+        self.plane.flip();
+    }
+}
+
+/// Part of a BSP tree, holding the polygons in its plane with the rest sorted in front of
+/// and behind it
+#[derive(Default)]
+struct Node {
+    plane: Option<Plane>,
+    front: Option<Box<Node>>,
+    back: Option<Box<Node>>,
+    polygons: Vec<Polygon>,
+}
+
+impl Node {
+    fn new(polygons: Vec<Polygon>) -> Node {
+        let mut node = Node::default();
+        node.build(polygons);
+        node
+    }
+
+    /// Swaps the inside of the solid for the outside
+    fn invert(&mut self) {
+        for polygon in &mut self.polygons {
+            polygon.flip();
+        }
+        if let Some(plane) = self.plane.as_mut() {
+            plane.flip();
+        }
+        if let Some(front) = self.front.as_mut() {
+            front.invert();
+        }
+        if let Some(back) = self.back.as_mut() {
+            back.invert();
+        }
+        mem::swap(&mut self.front, &mut self.back);
+    }
+
+    /// Parts of the polygons outside the solid
+    fn clip_polygons(&self, polygons: Vec<Polygon>) -> Vec<Polygon> {
+        let plane = match self.plane {
+            Some(plane) => plane,
+            None => return polygons,
+        };
+        let mut split = Split::default();
+        for polygon in polygons {
+            plane.split(polygon, &mut split);
+        }
+        let mut front = split.front;
+        front.extend(split.coplanar_front);
+        let mut back = split.back;
+        back.extend(split.coplanar_back);
+        let mut front = match self.front {
+            Some(ref node) => node.clip_polygons(front),
+            None => front,
+        };
+        // Behind the deepest planes is inside
+        if let Some(ref node) = self.back {
+            front.extend(node.clip_polygons(back));
+        }
+        front
+    }
+
+    /// Removes the parts of this tree's polygons inside another's solid
+    fn clip_to(&mut self, other: &Node) {
+        self.polygons = other.clip_polygons(mem::take(&mut self.polygons));
+        if let Some(front) = self.front.as_mut() {
+            front.clip_to(other);
+        }
+        if let Some(back) = self.back.as_mut() {
+            back.clip_to(other);
+        }
+    }
+
+    fn all_polygons(&self) -> Vec<Polygon> {
+        let mut polygons = self.polygons.clone();
+        if let Some(ref front) = self.front {
+            polygons.extend(front.all_polygons());
+        }
+        if let Some(ref back) = self.back {
+            polygons.extend(back.all_polygons());
+        }
+        polygons
+    }
+
+    /// Adds polygons to the tree, splitting them by the planes they cross
+    fn build(&mut self, polygons: Vec<Polygon>) {
+        if polygons.is_empty() {
+            return;
+        }
+        let plane = *self.plane.get_or_insert(polygons[0].plane);
+        let mut split = Split::default();
+        for polygon in polygons {
+            plane.split(polygon, &mut split);
+        }
+        self.polygons.extend(split.coplanar_front);
+        self.polygons.extend(split.coplanar_back);
+        if !split.front.is_empty() {
+            self.front
+                .get_or_insert_with(Default::default)
+                .build(split.front);
+        }
+        if !split.back.is_empty() {
+            self.back
+                .get_or_insert_with(Default::default)
+                .build(split.back);
+        }
+    }
+}
+
+/// Shapes combined into one by a boolean operation, such as a wall with a hole cut out of it
+///
+/// Each shape is placed by its own transform before they are combined, and the result looks
+/// like the first.
+pub struct Csg {
+    operation: Operation,
+    operands: Vec<Box<Drawable>>,
+    instance: Instance,
+}
+
+impl Csg {
+    /// Combines the first shape with each of the others in turn
+    pub fn new(operation: Operation, operands: Vec<Box<Drawable>>, translate: Vec3) -> Csg {
+        let material = operands
+            .first()
+            .map_or(Material::wood(), |operand| *operand.material());
+        Csg {
+            operation,
+            operands,
+            instance: Instance::new(Transform::from_translation(translate), material),
+        }
+    }
+}
+
+/// Solid bounded by a shape's mesh, where its transform places it
+fn solid(object: &Drawable) -> Solid {
+    let matrix = object.transform().to_matrix();
+    let vertices: Vec<Vertex> = object
+        .mesh_data()
+        .vertices
+        .chunks(VERTEX_SIZE)
+        .map(|data| Vertex::from_data(data).transformed(&matrix))
+        .collect();
+    Solid::new(&vertices)
+}
+
+impl Drawable for Csg {
+    /// Combinations of the same shapes in the same places share a mesh
+    fn mesh_key(&self) -> String {
+        let operands: Vec<String> = self
+            .operands
+            .iter()
+            .map(|operand| format!("[{} {:?}]", operand.mesh_key(), operand.transform()))
+            .collect();
+        format!("csg {:?} {}", self.operation, operands.join(" "))
+    }
+    /// Generates the vertices of the shape
+    fn mesh_data(&self) -> MeshData {
+        let mut operands = self.operands.iter().map(|operand| solid(operand.as_ref()));
+        let first = operands.next().unwrap_or_default();
+        let result = operands.fold(first, |result, operand| {
+            result.apply(self.operation, &operand)
+        });
+        MeshData::new(&result.vertices())
+    }
+    /// Mesh of the object and how it is placed and looks
+    fn instance(&self) -> &Instance {
+        &self.instance
+    }
+    fn instance_mut(&mut self) -> &mut Instance {
+        &mut self.instance
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Operation, Solid};
+    use matrix::{vec3, Scalar};
+    use render::rectangular_prism;
+
+    /// Space enclosed by a solid's surface
+    fn volume(solid: &Solid) -> Scalar {
+        solid
+            .vertices()
+            .chunks(3)
+            .map(|triangle| {
+                let (a, b, c) = (
+                    triangle[0].position(),
+                    triangle[1].position(),
+                    triangle[2].position(),
+                );
+                a.dot(&b.cross(c)) / 6.0
+            })
+            .sum()
+    }
+
+    #[test]
+    fn test_csg() {
+        // A cube two units across, and a taller, deeper box overlapping half of it
+        let cube = Solid::new(&rectangular_prism(vec3(0.0, 0.0, 0.0), 2.0, 2.0, 2.0));
+        let slab = Solid::new(&rectangular_prism(vec3(1.0, 0.0, 0.0), 2.0, 4.0, 4.0));
+        assert!((volume(&cube) - 8.0).abs() < 1e-4);
+        let union = cube.apply(Operation::Union, &slab);
+        assert!((volume(&union) - 36.0).abs() < 1e-4);
+        let intersection = cube.apply(Operation::Intersect, &slab);
+        assert!((volume(&intersection) - 4.0).abs() < 1e-4);
+        let cut = cube.apply(Operation::Subtract, &slab);
+        assert!((volume(&cut) - 4.0).abs() < 1e-4);
+        // Only the half of the cube outside the slab is left
+        for vertex in cut.vertices() {
+            assert!(vertex.position().x < 1e-4);
+        }
+    }
+}
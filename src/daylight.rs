@@ -0,0 +1,115 @@
+use matrix::{vec3, Scalar, Vec3};
+
+/// Sunlight and ambient light at some hour of the day
+#[derive(Copy, Clone, Debug)]
+pub struct Daylight {
+    /// Way the sun shines, pointing down while it is up
+    pub sun_direction: Vec3,
+    pub sun_color: Vec3,
+    pub sun_intensity: Scalar,
+    pub ambient: Vec3,
+}
+
+/// How the sun looks at an hour, the rest of the day is interpolated between these
+struct Key {
+    hour: Scalar,
+    /// Degrees above the horizon
+    elevation: Scalar,
+    /// Color temperature in kelvin
+    temperature: Scalar,
+    intensity: Scalar,
+    ambient: Vec3,
+}
+
+/// Sunrise at 6, midday matching the room's original lighting, and dark by 21
+fn keys() -> Vec<Key> {
+    let key = |hour, elevation, temperature, intensity, ambient| Key {
+        hour,
+        elevation,
+        temperature,
+        intensity,
+        ambient,
+    };
+    vec![
+        key(0.0, -30.0, 2000.0, 0.0, vec3(0.15, 0.17, 0.25)),
+        key(6.0, 0.0, 2000.0, 0.0, vec3(0.3, 0.3, 0.35)),
+        key(8.0, 20.0, 3500.0, 0.4, vec3(0.7, 0.65, 0.6)),
+        key(12.0, 53.0, 5500.0, 0.55, vec3(1.0, 1.0, 1.0)),
+        key(17.0, 20.0, 3500.0, 0.45, vec3(0.75, 0.65, 0.55)),
+        key(19.0, 2.0, 2000.0, 0.1, vec3(0.35, 0.3, 0.3)),
+        key(21.0, -10.0, 2000.0, 0.0, vec3(0.15, 0.17, 0.25)),
+        key(24.0, -30.0, 2000.0, 0.0, vec3(0.15, 0.17, 0.25)),
+    ]
+}
+
+/// Approximate color of a black body at a temperature in kelvin, brightest channel at 1
+pub fn color_temperature(kelvin: Scalar) -> Vec3 {
+    // Curve fit by Tanner Helland, in hundreds of kelvin
+    let t = kelvin.clamp(1000.0, 40000.0) / 100.0;
+    let red = if t <= 66.0 {
+        255.0
+    } else {
+        329.7 * (t - 60.0).powf(-0.133)
+    };
+    let green = if t <= 66.0 {
+        99.47 * t.ln() - 161.12
+    } else {
+        288.12 * (t - 60.0).powf(-0.0755)
+    };
+    let blue = if t >= 66.0 {
+        255.0
+    } else if t <= 19.0 {
+        0.0
+    } else {
+        138.52 * (t - 10.0).ln() - 305.04
+    };
+    vec3(red, green, blue) * (1.0 / 255.0)
+}
+
+impl Daylight {
+    /// Lighting at an hour from 0 to 24, wrapping around midnight
+    pub fn at(hour: Scalar) -> Daylight {
+        let hour = hour.rem_euclid(24.0);
+        let keys = keys();
+        // Keys on either side of the hour
+        let next = keys
+            .iter()
+            .position(|key| key.hour > hour)
+            .unwrap_or(keys.len() - 1);
+        let (a, b) = (&keys[next - 1], &keys[next]);
+        let t = ((hour - a.hour) / (b.hour - a.hour)).clamp(0.0, 1.0);
+        let mix = |a: Scalar, b: Scalar| a + (b - a) * t;
+        // The sun comes in through the open wall, rising and setting along one line
+        let elevation = mix(a.elevation, b.elevation).to_radians();
+        let across = vec3(-1.0, 0.0, -0.5).normalize();
+        Daylight {
+            sun_direction: across * elevation.cos() + vec3(0.0, -elevation.sin(), 0.0),
+            sun_color: color_temperature(mix(a.temperature, b.temperature)),
+            sun_intensity: mix(a.intensity, b.intensity),
+            ambient: Vec3::lerp(a.ambient, b.ambient, t),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{color_temperature, Daylight};
+
+    #[test]
+    fn test_daylight() {
+        // Warm light is red, cool light is blue
+        let candle = color_temperature(2000.0);
+        assert!(candle.x > candle.y && candle.y > candle.z);
+        let sky = color_temperature(10000.0);
+        assert!(sky.z > sky.x);
+        // The sun shines down at midday and is gone at night
+        let noon = Daylight::at(12.0);
+        assert!(noon.sun_direction.y < -0.5);
+        assert_eq!(noon.sun_intensity, 0.55);
+        assert_eq!(Daylight::at(23.0).sun_intensity, 0.0);
+        // Hours in between are interpolated, and the day wraps around
+        let afternoon = Daylight::at(14.5);
+        assert!(afternoon.sun_intensity < 0.55 && afternoon.sun_intensity > 0.45);
+        assert_eq!(Daylight::at(36.0).sun_intensity, 0.55);
+    }
+}
@@ -0,0 +1,110 @@
+use gleam::gl::{self, GLuint};
+
+use super::{create_program, Context, GlPtr, FLOAT_SIZE};
+use aabb::Aabb;
+use matrix::{gl_float, scale, translate, Vec3};
+
+/// Draws bounding boxes as lines, for checking where objects are placed and culled
+pub struct DebugBoxes {
+    program: GLuint,
+    vertex_array: GLuint,
+}
+
+/// Corners of the unit cube joined by each of its twelve edges
+#[cfg_attr(rustfmt, rustfmt_skip)]
+const EDGES: [[[f32; 3]; 2]; 12] = [
+    [[0.0, 0.0, 0.0], [1.0, 0.0, 0.0]], [[1.0, 0.0, 0.0], [1.0, 1.0, 0.0]],
+    [[1.0, 1.0, 0.0], [0.0, 1.0, 0.0]], [[0.0, 1.0, 0.0], [0.0, 0.0, 0.0]],
+    [[0.0, 0.0, 1.0], [1.0, 0.0, 1.0]], [[1.0, 0.0, 1.0], [1.0, 1.0, 1.0]],
+    [[1.0, 1.0, 1.0], [0.0, 1.0, 1.0]], [[0.0, 1.0, 1.0], [0.0, 0.0, 1.0]],
+    [[0.0, 0.0, 0.0], [0.0, 0.0, 1.0]], [[1.0, 0.0, 0.0], [1.0, 0.0, 1.0]],
+    [[1.0, 1.0, 0.0], [1.0, 1.0, 1.0]], [[0.0, 1.0, 0.0], [0.0, 1.0, 1.0]],
+];
+
+impl DebugBoxes {
+    pub fn new(gl: &GlPtr) -> DebugBoxes {
+        let program = create_program(gl, DEBUG_VS_SRC, DEBUG_FS_SRC).unwrap();
+        let vertices: Vec<f32> = EDGES
+            .iter()
+            .flat_map(|edge| edge.iter())
+            .flat_map(|corner| corner.to_vec())
+            .collect();
+        // Buffer the edges into their own vertex array
+        let vertex_array = gl.gen_vertex_arrays(1)[0];
+        gl.bind_vertex_array(vertex_array);
+        let buffer = gl.gen_buffers(1)[0];
+        gl.bind_buffer(gl::ARRAY_BUFFER, buffer);
+        gl.buffer_data_untyped(
+            gl::ARRAY_BUFFER,
+            (FLOAT_SIZE * vertices.len()) as isize,
+            vertices.as_ptr() as *const _,
+            gl::STATIC_DRAW,
+        );
+        gl.enable_vertex_attrib_array(0);
+        gl.vertex_attrib_pointer(0, 3, gl::FLOAT, false, 3 * FLOAT_SIZE as i32, 0);
+        gl.bind_vertex_array(0);
+        DebugBoxes {
+            program,
+            vertex_array,
+        }
+    }
+
+    /// Draws world space boxes over the scene in a color
+    pub fn draw(&self, ctx: &Context, boxes: &[Aabb], color: Vec3) {
+        let gl = &ctx.gl;
+        gl.use_program(self.program);
+        let vp_location = gl.get_uniform_location(self.program, "uVPMatrix");
+        let vp_matrix = ctx.camera.view_matrix() * ctx.projection.matrix();
+        gl.uniform_matrix_4fv(vp_location, false, &vp_matrix.to_gl());
+        let color_location = gl.get_uniform_location(self.program, "uColor");
+        gl.uniform_3f(
+            color_location,
+            gl_float(color.x),
+            gl_float(color.y),
+            gl_float(color.z),
+        );
+        let m_location = gl.get_uniform_location(self.program, "uMMatrix");
+        // Boxes inside walls are still shown
+        gl.disable(gl::DEPTH_TEST);
+        gl.bind_vertex_array(self.vertex_array);
+        for aabb in boxes {
+            // Stretch the unit cube over the box
+            let size = aabb.max - aabb.min;
+            let m_matrix =
+                scale(size.x, size.y, size.z) * translate(aabb.min.x, aabb.min.y, aabb.min.z);
+            gl.uniform_matrix_4fv(m_location, false, &m_matrix.to_gl());
+            gl.draw_arrays(gl::LINES, 0, 2 * EDGES.len() as i32);
+        }
+        gl.bind_vertex_array(0);
+        gl.enable(gl::DEPTH_TEST);
+    }
+}
+
+#[cfg_attr(rustfmt, rustfmt_skip)]
+const DEBUG_VS_SRC: &[&[u8]] = &[
+b"#version 300 es
+
+layout(location = 0) in vec3 aPosition;
+
+uniform mat4 uMMatrix;
+uniform mat4 uVPMatrix;
+
+void main() {
+    gl_Position = uVPMatrix * uMMatrix * vec4(aPosition, 1.0);
+}
+"];
+
+#[cfg_attr(rustfmt, rustfmt_skip)]
+const DEBUG_FS_SRC: &[&[u8]] = &[
+b"#version 300 es
+
+precision mediump float;
+
+uniform vec3 uColor;
+
+out vec4 oFragColor;
+
+void main() {
+    oFragColor = vec4(uColor, 1.0);
+}
+"];
@@ -1,10 +1,18 @@
 #![allow(non_camel_case_types)]
 #![allow(non_snake_case)]
 
-use std::os::raw::{c_char, c_int, c_void};
+use std::os::raw::{c_char, c_int, c_long, c_ulong, c_ushort, c_void};
 
 pub type em_arg_callback_func = Option<unsafe extern "C" fn(arg1: *mut c_void)>;
 pub type EMSCRIPTEN_WEBGL_CONTEXT_HANDLE = c_int;
+pub type EM_BOOL = c_int;
+pub type em_mouse_callback_func = Option<
+    unsafe extern "C" fn(
+        eventType: c_int,
+        mouseEvent: *const EmscriptenMouseEvent,
+        userData: *mut c_void,
+    ) -> EM_BOOL,
+>;
 
 #[repr(C)]
 #[derive(Debug, Copy)]
@@ -28,6 +36,102 @@ impl Clone for EmscriptenWebGLContextAttributes {
         *self
     }
 }
+pub type em_key_callback_func = Option<
+    unsafe extern "C" fn(
+        eventType: c_int,
+        keyEvent: *const EmscriptenKeyboardEvent,
+        userData: *mut c_void,
+    ) -> EM_BOOL,
+>;
+
+pub type em_wheel_callback_func = Option<
+    unsafe extern "C" fn(
+        eventType: c_int,
+        wheelEvent: *const EmscriptenWheelEvent,
+        userData: *mut c_void,
+    ) -> EM_BOOL,
+>;
+
+pub type em_ui_callback_func = Option<
+    unsafe extern "C" fn(
+        eventType: c_int,
+        uiEvent: *const EmscriptenUiEvent,
+        userData: *mut c_void,
+    ) -> EM_BOOL,
+>;
+
+pub const EMSCRIPTEN_EVENT_KEYDOWN: c_int = 2;
+
+/// Special target for events sent to the window
+pub const EMSCRIPTEN_EVENT_TARGET_WINDOW: *const c_char = 2 as *const c_char;
+
+#[repr(C)]
+#[derive(Debug, Copy, Clone)]
+pub struct EmscriptenMouseEvent {
+    pub timestamp: f64,
+    pub screenX: c_long,
+    pub screenY: c_long,
+    pub clientX: c_long,
+    pub clientY: c_long,
+    pub ctrlKey: EM_BOOL,
+    pub shiftKey: EM_BOOL,
+    pub altKey: EM_BOOL,
+    pub metaKey: EM_BOOL,
+    pub button: c_ushort,
+    pub buttons: c_ushort,
+    pub movementX: c_long,
+    pub movementY: c_long,
+    pub targetX: c_long,
+    pub targetY: c_long,
+    pub canvasX: c_long,
+    pub canvasY: c_long,
+    pub padding: c_long,
+}
+
+pub const DOM_DELTA_PIXEL: c_ulong = 0;
+pub const DOM_DELTA_LINE: c_ulong = 1;
+
+#[repr(C)]
+#[derive(Debug, Copy, Clone)]
+pub struct EmscriptenWheelEvent {
+    pub mouse: EmscriptenMouseEvent,
+    pub deltaX: f64,
+    pub deltaY: f64,
+    pub deltaZ: f64,
+    pub deltaMode: c_ulong,
+}
+
+#[repr(C)]
+#[derive(Copy, Clone)]
+pub struct EmscriptenKeyboardEvent {
+    pub key: [c_char; 32],
+    pub code: [c_char; 32],
+    pub location: c_ulong,
+    pub ctrlKey: EM_BOOL,
+    pub shiftKey: EM_BOOL,
+    pub altKey: EM_BOOL,
+    pub metaKey: EM_BOOL,
+    pub repeat: EM_BOOL,
+    pub locale: [c_char; 32],
+    pub charValue: [c_char; 32],
+    pub charCode: c_ulong,
+    pub keyCode: c_ulong,
+    pub which: c_ulong,
+}
+
+#[repr(C)]
+#[derive(Debug, Copy, Clone)]
+pub struct EmscriptenUiEvent {
+    pub detail: c_long,
+    pub documentBodyClientWidth: c_int,
+    pub documentBodyClientHeight: c_int,
+    pub windowInnerWidth: c_int,
+    pub windowInnerHeight: c_int,
+    pub windowOuterWidth: c_int,
+    pub windowOuterHeight: c_int,
+    pub scrollTop: c_int,
+    pub scrollLeft: c_int,
+}
 
 extern "C" {
     pub fn emscripten_set_main_loop_arg(
@@ -57,5 +161,55 @@ extern "C" {
         height: *mut f64,
     ) -> c_int;
 
+    pub fn emscripten_set_canvas_element_size(
+        target: *const c_char,
+        width: c_int,
+        height: c_int,
+    ) -> c_int;
+
+    pub fn emscripten_set_resize_callback(
+        target: *const c_char,
+        userData: *mut c_void,
+        useCapture: EM_BOOL,
+        callback: em_ui_callback_func,
+    ) -> c_int;
+
+    pub fn emscripten_set_mousemove_callback(
+        target: *const c_char,
+        userData: *mut c_void,
+        useCapture: EM_BOOL,
+        callback: em_mouse_callback_func,
+    ) -> c_int;
+
+    pub fn emscripten_set_click_callback(
+        target: *const c_char,
+        userData: *mut c_void,
+        useCapture: EM_BOOL,
+        callback: em_mouse_callback_func,
+    ) -> c_int;
+
+    pub fn emscripten_set_wheel_callback(
+        target: *const c_char,
+        userData: *mut c_void,
+        useCapture: EM_BOOL,
+        callback: em_wheel_callback_func,
+    ) -> c_int;
+
+    pub fn emscripten_set_keydown_callback(
+        target: *const c_char,
+        userData: *mut c_void,
+        useCapture: EM_BOOL,
+        callback: em_key_callback_func,
+    ) -> c_int;
+
+    pub fn emscripten_set_keyup_callback(
+        target: *const c_char,
+        userData: *mut c_void,
+        useCapture: EM_BOOL,
+        callback: em_key_callback_func,
+    ) -> c_int;
+
+    pub fn emscripten_get_now() -> f64;
+
     pub fn emscripten_asm_const_int(code: *const c_char, ...) -> c_int;
 }
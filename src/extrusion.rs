@@ -1,88 +1,172 @@
-use super::Context;
-use gleam::gl::{self, GLint, GLsizei};
-use matrix::{identity, matmul, rotate_y, translate, vec3, Vec3};
-use render::{polygon, quad, rectangular_prism, Color, Drawable, Vertex};
-
-pub struct Extrusion {
-    points: Vec<Vec3>,
-    extrusion: Vec3,
-    vert_start: GLint,
-    num_verts: GLsizei,
-    translate: Vec3,
-}
-
-impl Extrusion {
-    pub fn new(points: Vec<Vec3>, extrusion: Vec3, translate: Vec3) -> Self {
-        Extrusion {
-            points,
-            extrusion,
-            vert_start: 0,
-            num_verts: 0,
-            translate,
-        }
-    }
-}
-
-impl Drawable for Extrusion {
-    fn buffer_data(&mut self, vertex_start: GLint) -> Vec<f32> {
-        self.vert_start = vertex_start;
-        let mut vertices: Vec<Vertex> = polygon(&self.points);
-
-        let top_verts: Vec<Vec3> = self
-            .points
-            .iter()
-            .map(|vert| vert + self.extrusion)
-            .collect();
-
-        let sides: Vec<Vertex> = self
-            .points
-            .windows(2)
-            .zip(top_verts.windows(2))
-            .cycle()
-            .take(self.points.len())
-            .flat_map(|(b, t)| quad(t[0], b[0], b[1], t[1]).to_vec())
-            .collect();
-
-        vertices.extend_from_slice(&sides);
-
-        vertices.extend_from_slice(&polygon(&top_verts));
-
-        self.num_verts = vertices.len() as GLint;
-
-        vertices
-            .iter()
-            .flat_map(|vertex| vertex.to_data().to_vec())
-            .collect()
-    }
-
-    fn draw(&self, ctx: &Context) {
-        let gl = &ctx.gl;
-        let mv_location = gl.get_uniform_location(ctx.program, "uMVMatrix");
-        let m_matrix = identity(); //translate(self.translate.x, self.translate.y, self.translate.z);
-        let v_matrix = matmul(
-            rotate_y(ctx.theta),
-            matmul(
-                translate(self.translate.x, self.translate.y, self.translate.z),
-                ctx.camera,
-            ),
-        ); //matmul(rotate_y(ctx.theta), ctx.camera);
-        let mv_matrix = matmul(v_matrix, m_matrix);
-        gl.uniform_matrix_4fv(mv_location, false, &mv_matrix);
-
-        // Lighting properties
-        let ambient_location = gl.get_uniform_location(ctx.program, "uAmbientProduct");
-        let diffuse_location = gl.get_uniform_location(ctx.program, "uDiffuseProduct");
-        let specular_location = gl.get_uniform_location(ctx.program, "uSpecularProduct");
-        // Light position
-        let shininess_location = gl.get_uniform_location(ctx.program, "uShininess");
-
-        // Set lighting properties
-        gl.uniform_4f(ambient_location, 0.396, 0.263, 0.129, 1.0);
-        gl.uniform_4f(diffuse_location, 0.64, 0.64, 0.64, 1.0);
-        gl.uniform_4f(specular_location, 0.0, 0.0, 0.0, 1.0);
-
-        gl.uniform_1f(shininess_location, 96.078_43);
-
-        gl.draw_arrays(gl::TRIANGLES, self.vert_start / 8, self.num_verts);
-    }
-}
+use super::Context;
+use material::Material;
+use matrix::consts::PI;
+use matrix::{rotate_y, vec2, vec3, Scalar, Transform, Vec3};
+use mesh::{Instance, MeshData};
+use render::{newell, polygon, quad, rectangular_prism, smooth_normals, Color, Drawable, Vertex};
+
+/// Sides of a smooth extrusion meeting at a sharper angle than this keep a hard edge
+const SMOOTH_CREASE: Scalar = PI / 3.0;
+
+pub struct Extrusion {
+    points: Vec<Vec3>,
+    extrusion: Vec3,
+    /// The outline joins its last point back to its first and is capped at both ends,
+    /// otherwise it is a wall standing along the points
+    closed: bool,
+    /// Normals follow the curve of the outline instead of each side
+    smooth: bool,
+    instance: Instance,
+}
+
+impl Extrusion {
+    pub fn new(points: Vec<Vec3>, extrusion: Vec3, translate: Vec3) -> Self {
+        Extrusion {
+            points,
+            extrusion,
+            closed: true,
+            smooth: false,
+            instance: Instance::new(Transform::from_translation(translate), Material::wood()),
+        }
+    }
+
+    /// Leaves the outline open, without the side between its ends or caps
+    pub fn with_closed(self, closed: bool) -> Self {
+        Extrusion { closed, ..self }
+    }
+
+    /// Shades rounded outlines as a smooth curve, keeping the caps and sharp corners flat
+    pub fn with_smooth(self, smooth: bool) -> Self {
+        Extrusion { smooth, ..self }
+    }
+}
+
+impl Drawable for Extrusion {
+    /// Shapes with the same outline and depth share a mesh
+    fn mesh_key(&self) -> String {
+        format!(
+            "extrusion {:?} {:?} {} {}",
+            self.points, self.extrusion, self.closed, self.smooth
+        )
+    }
+    /// Generates the vertices of the shape
+    fn mesh_data(&self) -> MeshData {
+        let mut outline = self.points.clone();
+        // Outlines ending on their first point are closed already
+        if self.closed
+            && outline.len() > 1
+            && outline[0].distance(&outline[outline.len() - 1]) <= 1e-6
+        {
+            outline.pop();
+        }
+        if outline.len() < 2 {
+            return MeshData::new(&[]);
+        }
+        // Wound so the bottom faces away from the extrusion and the sides face out
+        if newell(outline.clone()).dot(&self.extrusion) > 0.0 {
+            outline.reverse();
+        }
+
+        let mut vertices: Vec<Vertex> = Vec::new();
+        if self.closed {
+            vertices.extend(polygon(&outline));
+        }
+
+        // The texture runs around the outline and up the extrusion, one unit to one unit
+        let height = self.extrusion.length();
+        let edges = if self.closed {
+            outline.len()
+        } else {
+            outline.len() - 1
+        };
+        let mut around: Scalar = 0.0;
+        for i in 0..edges {
+            let (a, b) = (outline[i], outline[(i + 1) % outline.len()]);
+            let width = a.distance(&b);
+            if width <= 0.0 {
+                continue;
+            }
+            let (u0, u1) = (around, around + width);
+            around = u1;
+            let tangent = (b - a).normalize();
+            let coords = [
+                (u0, 0.0),
+                (u0, height),
+                (u1, height),
+                (u1, height),
+                (u1, 0.0),
+                (u0, 0.0),
+            ];
+            vertices.extend(
+                quad(a, a + self.extrusion, b + self.extrusion, b)
+                    .iter()
+                    .zip(coords.iter())
+                    .map(|(vertex, &(u, v))| vertex.with_texture(vec2(u, v), tangent)),
+            );
+        }
+
+        if self.closed {
+            let top: Vec<Vec3> = outline
+                .iter()
+                .rev()
+                .map(|vert| vert + self.extrusion)
+                .collect();
+            vertices.extend(polygon(&top));
+        }
+
+        if self.smooth {
+            vertices = smooth_normals(&vertices, SMOOTH_CREASE);
+        }
+        MeshData::spun(&vertices)
+    }
+
+    /// Mesh of the object and how it is placed and looks
+    fn instance(&self) -> &Instance {
+        &self.instance
+    }
+    fn instance_mut(&mut self) -> &mut Instance {
+        &mut self.instance
+    }
+
+    /// Spinning isn't part of the model matrix instances are drawn with
+    fn instanced(&self) -> bool {
+        false
+    }
+
+    fn draw(&self, ctx: &Context) {
+        // Spin in place when animating
+        let m_matrix = rotate_y(ctx.theta) * self.instance.model_matrix(ctx);
+        self.instance.draw_with(ctx, &m_matrix);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::Extrusion;
+    use matrix::{vec3, Scalar, Vec3};
+    use render::{Drawable, VERTEX_SIZE};
+
+    #[test]
+    fn test_extrusion() {
+        let square = vec![
+            vec3(-1.0, 0.0, -1.0),
+            vec3(1.0, 0.0, -1.0),
+            vec3(1.0, 0.0, 1.0),
+            vec3(-1.0, 0.0, 1.0),
+        ];
+        let up = vec3(0.0, 1.0, 0.0);
+        // Four sides, the one closing the outline among them, and both caps
+        let data = Extrusion::new(square.clone(), up, Vec3::origin()).mesh_data();
+        assert_eq!(data.vertices.len(), (4 + 2) * 6 * VERTEX_SIZE);
+        // Every face points out of the box
+        for vertex in data.vertices.chunks(VERTEX_SIZE) {
+            let value = |i: usize| Scalar::from(vertex[i]);
+            let position = vec3(value(0), value(1), value(2));
+            let normal = vec3(value(3), value(4), value(5));
+            assert!(normal.dot(&(position - vec3(0.0, 0.5, 0.0))) > 0.0);
+        }
+        // Open, only the sides along the outline are left
+        let open = Extrusion::new(square, up, Vec3::origin()).with_closed(false);
+        assert_eq!(open.mesh_data().vertices.len(), 3 * 6 * VERTEX_SIZE);
+    }
+}
@@ -0,0 +1,138 @@
+use std::io;
+
+use gleam::gl::{self, GLuint};
+
+use super::GlPtr;
+use error::io_error;
+use texture::{TextureId, TextureManager};
+
+/// Offscreen target that renders into a texture
+pub struct Framebuffer {
+    gl: GlPtr,
+    framebuffer: GLuint,
+    /// Depth buffer, only needed while drawing so it is never sampled, None when depth is the
+    /// texture
+    depth: Option<GLuint>,
+    /// Color or depth attachment, sampled like any other texture
+    texture: TextureId,
+    width: u32,
+    height: u32,
+}
+
+impl Framebuffer {
+    /// Creates a framebuffer with a color texture and a depth buffer
+    // Only shadow maps render into textures so far, this is for mirrors and post-processing
+    #[allow(dead_code)]
+    pub fn new(
+        gl: &GlPtr,
+        textures: &mut TextureManager,
+        width: u32,
+        height: u32,
+    ) -> Result<Framebuffer, io::Error> {
+        let texture = textures.create_target(width, height);
+        // Depth is stored in a renderbuffer
+        let depth = gl.gen_renderbuffers(1)[0];
+        gl.bind_renderbuffer(gl::RENDERBUFFER, depth);
+        gl.renderbuffer_storage(
+            gl::RENDERBUFFER,
+            gl::DEPTH_COMPONENT24,
+            width as i32,
+            height as i32,
+        );
+        gl.bind_renderbuffer(gl::RENDERBUFFER, 0);
+        // Attach both
+        let framebuffer = gl.gen_framebuffers(1)[0];
+        gl.bind_framebuffer(gl::FRAMEBUFFER, framebuffer);
+        gl.framebuffer_texture_2d(
+            gl::FRAMEBUFFER,
+            gl::COLOR_ATTACHMENT0,
+            gl::TEXTURE_2D,
+            textures.gl_name(texture),
+            0,
+        );
+        gl.framebuffer_renderbuffer(
+            gl::FRAMEBUFFER,
+            gl::DEPTH_ATTACHMENT,
+            gl::RENDERBUFFER,
+            depth,
+        );
+        Framebuffer::complete(gl, framebuffer, Some(depth), texture, width, height)
+    }
+
+    /// Creates a framebuffer that only keeps depth, in a texture, for shadow maps
+    pub fn depth_only(
+        gl: &GlPtr,
+        textures: &mut TextureManager,
+        width: u32,
+        height: u32,
+    ) -> Result<Framebuffer, io::Error> {
+        let texture = textures.create_depth_target(width, height);
+        let framebuffer = gl.gen_framebuffers(1)[0];
+        gl.bind_framebuffer(gl::FRAMEBUFFER, framebuffer);
+        gl.framebuffer_texture_2d(
+            gl::FRAMEBUFFER,
+            gl::DEPTH_ATTACHMENT,
+            gl::TEXTURE_2D,
+            textures.gl_name(texture),
+            0,
+        );
+        // Nothing is drawn to color
+        gl.draw_buffers(&[gl::NONE]);
+        gl.read_buffer(gl::NONE);
+        Framebuffer::complete(gl, framebuffer, None, texture, width, height)
+    }
+
+    /// Checks the bound framebuffer is usable, cleaning it up if it isn't
+    fn complete(
+        gl: &GlPtr,
+        framebuffer: GLuint,
+        depth: Option<GLuint>,
+        texture: TextureId,
+        width: u32,
+        height: u32,
+    ) -> Result<Framebuffer, io::Error> {
+        let status = gl.check_frame_buffer_status(gl::FRAMEBUFFER);
+        gl.bind_framebuffer(gl::FRAMEBUFFER, 0);
+        let framebuffer = Framebuffer {
+            gl: gl.clone(),
+            framebuffer,
+            depth,
+            texture,
+            width,
+            height,
+        };
+        if status != gl::FRAMEBUFFER_COMPLETE {
+            // Dropping it deletes the framebuffer and depth buffer
+            return Err(io_error(format!(
+                "Framebuffer is incomplete (status {:#x})",
+                status
+            )));
+        }
+        Ok(framebuffer)
+    }
+
+    /// Directs drawing into the framebuffer
+    pub fn bind(&self) {
+        self.gl.bind_framebuffer(gl::FRAMEBUFFER, self.framebuffer);
+        self.gl
+            .viewport(0, 0, self.width as i32, self.height as i32);
+    }
+
+    /// Directs drawing back to the canvas and returns the finished texture
+    ///
+    /// A texture can't be sampled while it is being drawn to.
+    pub fn resolve(&self, width: u32, height: u32) -> TextureId {
+        self.gl.bind_framebuffer(gl::FRAMEBUFFER, 0);
+        self.gl.viewport(0, 0, width as i32, height as i32);
+        self.texture
+    }
+}
+
+impl Drop for Framebuffer {
+    fn drop(&mut self) {
+        self.gl.delete_framebuffers(&[self.framebuffer]);
+        if let Some(depth) = self.depth {
+            self.gl.delete_renderbuffers(&[depth]);
+        }
+    }
+}
@@ -0,0 +1,481 @@
+use material::Material;
+use matrix::{translate, vec3, Quat, Scalar, Transform, Vec3};
+use mesh::{Instance, MeshData};
+use render::{rectangular_prism, rounded_prism, Drawable, Vertex};
+use subdivision::subdivide;
+
+/// How much the edges of cushions and mattresses are rounded off
+const CUSHION_RADIUS: Scalar = 0.08;
+/// Segments in each rounded edge
+const CUSHION_SEGMENTS: u16 = 3;
+/// Times a pillow is subdivided, rounding it from a box
+const PILLOW_SUBDIVISIONS: u32 = 3;
+/// How much the edges of tops, legs and shelves are rounded off unless set otherwise
+const EDGE_RADIUS: Scalar = 0.02;
+/// Segments in each rounded edge of a top, leg or shelf
+const EDGE_SEGMENTS: u16 = 2;
+
+/// Back of a seat, rising from the edge of the top facing -x
+#[derive(Copy, Clone, Debug)]
+pub struct Back {
+    /// How far the uprights rise above the top
+    pub height: Scalar,
+    /// Radians the back leans away from the top
+    pub tilt: Scalar,
+}
+
+/// A top on four legs, with an optional back and shelves between the legs
+///
+/// Sizes are width along x, height along z and depth along y, like `rectangular_prism`.
+/// Desks, chairs, stools, benches and bookcases are all built from this.
+pub struct Furniture {
+    top_width: Scalar,
+    top_height: Scalar,
+    top_depth: Scalar,
+    leg_width: Scalar,
+    leg_height: Scalar,
+    leg_depth: Scalar,
+    back: Option<Back>,
+    /// Boards spaced evenly between the floor and the top
+    shelves: u32,
+    /// How much the edges of every part are rounded off, so they catch the light
+    edge_radius: Scalar,
+    instance: Instance,
+}
+
+impl Furniture {
+    /// Creates a top standing on legs, with its legs on the floor at `translate`
+    pub fn new(
+        top_width: Scalar,
+        top_height: Scalar,
+        top_depth: Scalar,
+        leg_width: Scalar,
+        leg_height: Scalar,
+        leg_depth: Scalar,
+        translate: Vec3,
+    ) -> Self {
+        Furniture {
+            top_width,
+            top_height,
+            top_depth,
+            leg_width,
+            leg_height,
+            leg_depth,
+            back: None,
+            shelves: 0,
+            edge_radius: EDGE_RADIUS,
+            instance: Instance::new(Transform::from_translation(translate), Material::wood()),
+        }
+    }
+
+    /// Bronze table
+    pub fn desk(
+        top_width: Scalar,
+        top_height: Scalar,
+        top_depth: Scalar,
+        leg_width: Scalar,
+        leg_height: Scalar,
+        leg_depth: Scalar,
+        translate: Vec3,
+    ) -> Self {
+        Furniture::new(
+            top_width, top_height, top_depth, leg_width, leg_height, leg_depth, translate,
+        )
+        .with_material(Material::bronze())
+    }
+
+    /// Wooden seat with an upright back as tall as the seat is wide
+    pub fn chair(
+        top_width: Scalar,
+        top_height: Scalar,
+        top_depth: Scalar,
+        leg_width: Scalar,
+        leg_height: Scalar,
+        leg_depth: Scalar,
+        translate: Vec3,
+    ) -> Self {
+        Furniture::new(
+            top_width, top_height, top_depth, leg_width, leg_height, leg_depth, translate,
+        )
+        .with_back(top_width, 0.0)
+    }
+
+    /// Adds a back rising above the top, leaning `tilt` radians away from it
+    pub fn with_back(self, height: Scalar, tilt: Scalar) -> Self {
+        Furniture {
+            back: Some(Back { height, tilt }),
+            ..self
+        }
+    }
+
+    /// Adds shelves between the legs
+    pub fn with_shelves(self, shelves: u32) -> Self {
+        Furniture { shelves, ..self }
+    }
+
+    /// Rounds the edges of every part off by `edge_radius`, or leaves them sharp if it is 0
+    pub fn with_edge_radius(self, edge_radius: Scalar) -> Self {
+        Furniture {
+            edge_radius,
+            ..self
+        }
+    }
+
+    pub fn with_material(mut self, material: Material) -> Self {
+        self.instance.material = material;
+        self
+    }
+
+    /// Box for one part, its edges rounded like the rest
+    fn block(&self, center: Vec3, width: Scalar, height: Scalar, depth: Scalar) -> Vec<Vertex> {
+        rounded_prism(
+            center,
+            width,
+            height,
+            depth,
+            self.edge_radius,
+            EDGE_SEGMENTS,
+        )
+    }
+
+    /// Uprights carrying on from the rear legs, joined by a slab across their upper half
+    fn back_vertices(&self, back: Back) -> Vec<Vertex> {
+        let x = -self.top_width / 2.0 + self.leg_width / 2.0;
+        let z = self.top_height / 2.0 - self.leg_height / 2.0;
+        let seat = self.leg_depth + self.top_depth;
+        let mut vertices: Vec<Vertex> = Vec::new();
+        for &z in &[-z, z] {
+            vertices.extend(self.block(
+                vec3(x, seat + back.height / 2.0, z),
+                self.leg_width,
+                self.leg_height,
+                back.height,
+            ));
+        }
+        vertices.extend(self.block(
+            vec3(x, seat + back.height * 0.75, 0.0),
+            self.leg_width / 2.0,
+            self.top_height - 2.0 * self.leg_height,
+            back.height / 2.0,
+        ));
+        // Lean back around the rear edge of the top, which turns +y towards -x
+        let tilt = translate(-x, -seat, 0.0)
+            * Quat::from_axis_angle(vec3(0.0, 0.0, 1.0), back.tilt).to_mat4()
+            * translate(x, seat, 0.0);
+        vertices
+            .iter()
+            .map(|vertex| vertex.transformed(&tilt))
+            .collect()
+    }
+}
+
+impl Drawable for Furniture {
+    /// Shapes with the same sizes share a mesh
+    fn mesh_key(&self) -> String {
+        format!(
+            "furniture {} {} {} {} {} {} {:?} {} {}",
+            self.top_width,
+            self.top_height,
+            self.top_depth,
+            self.leg_width,
+            self.leg_height,
+            self.leg_depth,
+            self.back,
+            self.shelves,
+            self.edge_radius
+        )
+    }
+    /// Generates the vertices of the shape
+    fn mesh_data(&self) -> MeshData {
+        let mut vertices: Vec<Vertex> = Vec::new();
+        // Top resting on the legs
+        vertices.extend(self.block(
+            vec3(0.0, self.leg_depth + self.top_depth / 2.0, 0.0),
+            self.top_width,
+            self.top_height,
+            self.top_depth,
+        ));
+        // A leg under each corner of the top
+        let leg_x = self.top_width / 2.0 - self.leg_width / 2.0;
+        let leg_z = self.top_height / 2.0 - self.leg_height / 2.0;
+        for &(x, z) in &[
+            (-leg_x, -leg_z),
+            (leg_x, -leg_z),
+            (-leg_x, leg_z),
+            (leg_x, leg_z),
+        ] {
+            vertices.extend(self.block(
+                vec3(x, self.leg_depth / 2.0, z),
+                self.leg_width,
+                self.leg_height,
+                self.leg_depth,
+            ));
+        }
+        if let Some(back) = self.back {
+            vertices.extend(self.back_vertices(back));
+        }
+        // Shelves fit between the legs, as thick as the top
+        for shelf in 1..=self.shelves {
+            let y = self.leg_depth * shelf as Scalar / (self.shelves + 1) as Scalar;
+            vertices.extend(self.block(
+                vec3(0.0, y, 0.0),
+                self.top_width - 2.0 * self.leg_width,
+                self.top_height,
+                self.top_depth,
+            ));
+        }
+
+        MeshData::new(&vertices)
+    }
+    /// Mesh of the object and how it is placed and looks
+    fn instance(&self) -> &Instance {
+        &self.instance
+    }
+    fn instance_mut(&mut self) -> &mut Instance {
+        &mut self.instance
+    }
+}
+
+/// Upholstered seat facing +x, with cushions between two armrests
+pub struct Sofa {
+    /// Along z, armrests included
+    width: Scalar,
+    /// Along x, back included
+    depth: Scalar,
+    /// Top of the back
+    height: Scalar,
+    instance: Instance,
+}
+
+impl Sofa {
+    pub fn new(width: Scalar, depth: Scalar, height: Scalar, translate: Vec3) -> Sofa {
+        Sofa {
+            width,
+            depth,
+            height,
+            instance: Instance::new(Transform::from_translation(translate), Material::red()),
+        }
+    }
+}
+
+impl Drawable for Sofa {
+    /// Sofas with the same sizes share a mesh
+    fn mesh_key(&self) -> String {
+        format!("sofa {} {} {}", self.width, self.depth, self.height)
+    }
+    /// Generates the vertices of the shape
+    fn mesh_data(&self) -> MeshData {
+        let (base_top, seat_top, arm_top) =
+            (self.height * 0.3, self.height * 0.5, self.height * 0.65);
+        let arm_width = self.depth * 0.2;
+        let back_depth = self.depth * 0.25;
+        let inner_width = self.width - 2.0 * arm_width;
+        let cushion = |center: Vec3, width: Scalar, height: Scalar, depth: Scalar| {
+            rounded_prism(
+                center,
+                width,
+                height,
+                depth,
+                CUSHION_RADIUS,
+                CUSHION_SEGMENTS,
+            )
+        };
+        let mut vertices: Vec<Vertex> = Vec::new();
+        // Base everything else rests on
+        vertices.extend_from_slice(&rectangular_prism(
+            vec3(0.0, base_top / 2.0, 0.0),
+            self.depth,
+            self.width,
+            base_top,
+        ));
+        // Armrests down to the floor on both ends
+        for &z in &[
+            -self.width / 2.0 + arm_width / 2.0,
+            self.width / 2.0 - arm_width / 2.0,
+        ] {
+            vertices.extend(cushion(
+                vec3(0.0, arm_top / 2.0, z),
+                self.depth,
+                arm_width,
+                arm_top,
+            ));
+        }
+        // Back between the armrests
+        vertices.extend(cushion(
+            vec3(
+                (back_depth - self.depth) / 2.0,
+                (base_top + self.height) / 2.0,
+                0.0,
+            ),
+            back_depth,
+            inner_width,
+            self.height - base_top,
+        ));
+        // Roughly square seat cushions in front of it
+        let seat_depth = self.depth - back_depth;
+        let cushions = (inner_width / seat_depth).round().max(1.0);
+        let cushion_width = inner_width / cushions;
+        for i in 0..cushions as u32 {
+            vertices.extend(cushion(
+                vec3(
+                    back_depth / 2.0,
+                    (base_top + seat_top) / 2.0,
+                    -inner_width / 2.0 + cushion_width * (i as Scalar + 0.5),
+                ),
+                seat_depth,
+                cushion_width,
+                seat_top - base_top,
+            ));
+        }
+
+        MeshData::new(&vertices)
+    }
+    /// Mesh of the object and how it is placed and looks
+    fn instance(&self) -> &Instance {
+        &self.instance
+    }
+    fn instance_mut(&mut self) -> &mut Instance {
+        &mut self.instance
+    }
+}
+
+/// Mattress on a frame, with its headboard at -x
+pub struct Bed {
+    /// Along z
+    width: Scalar,
+    /// Along x, headboard included
+    length: Scalar,
+    /// Top of the mattress, the headboard is twice as tall
+    height: Scalar,
+    instance: Instance,
+}
+
+impl Bed {
+    pub fn new(width: Scalar, length: Scalar, height: Scalar, translate: Vec3) -> Bed {
+        Bed {
+            width,
+            length,
+            height,
+            instance: Instance::new(Transform::from_translation(translate), Material::wood()),
+        }
+    }
+}
+
+impl Drawable for Bed {
+    /// Beds with the same sizes share a mesh
+    fn mesh_key(&self) -> String {
+        format!("bed {} {} {}", self.width, self.length, self.height)
+    }
+    /// Generates the vertices of the shape
+    fn mesh_data(&self) -> MeshData {
+        let headboard = 0.1;
+        let frame_top = self.height / 2.0;
+        // The frame and mattress start past the headboard
+        let length = self.length - headboard;
+        let middle = headboard / 2.0;
+        let mut vertices: Vec<Vertex> = Vec::new();
+        vertices.extend_from_slice(&rectangular_prism(
+            vec3((headboard - self.length) / 2.0, self.height, 0.0),
+            headboard,
+            self.width,
+            self.height * 2.0,
+        ));
+        vertices.extend_from_slice(&rectangular_prism(
+            vec3(middle, frame_top / 2.0, 0.0),
+            length,
+            self.width,
+            frame_top,
+        ));
+        // Mattress sitting just inside the frame, with a plump pillow at the head
+        vertices.extend(rounded_prism(
+            vec3(middle, (frame_top + self.height) / 2.0, 0.0),
+            length - 0.05,
+            self.width - 0.05,
+            self.height - frame_top,
+            CUSHION_RADIUS,
+            CUSHION_SEGMENTS,
+        ));
+        let pillow = (self.width * 0.6).min(length * 0.25);
+        vertices.extend(subdivide(
+            &rectangular_prism(
+                vec3(
+                    headboard - self.length / 2.0 + pillow / 2.0 + 0.05,
+                    self.height + 0.05,
+                    0.0,
+                ),
+                pillow,
+                self.width * 0.6,
+                0.15,
+            ),
+            PILLOW_SUBDIVISIONS,
+        ));
+
+        MeshData::new(&vertices)
+    }
+    /// Mesh of the object and how it is placed and looks
+    fn instance(&self) -> &Instance {
+        &self.instance
+    }
+    fn instance_mut(&mut self) -> &mut Instance {
+        &mut self.instance
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Bed, Furniture, Sofa};
+    use matrix::{Scalar, Vec3};
+    use render::{Drawable, VERTEX_SIZE};
+
+    #[test]
+    fn test_furniture() {
+        let origin = Vec3::origin();
+        // A desk's legs stand on the floor and hold up its top
+        let desk = Furniture::desk(4.0, 4.0, 0.2, 0.2, 0.2, 3.0, origin).mesh_data();
+        let bounds = desk.bounds.unwrap();
+        assert!(bounds.min.y.abs() < 1e-5 && (bounds.max.y - 3.2).abs() < 1e-5);
+        assert!((bounds.max.x - 2.0).abs() < 1e-5 && (bounds.max.z - 2.0).abs() < 1e-5);
+        // A chair's back rises above its seat, and leans back behind it when tilted
+        let chair = Furniture::chair(1.0, 1.0, 0.2, 0.2, 0.2, 2.0, origin).mesh_data();
+        assert!((chair.bounds.unwrap().max.y - 3.2).abs() < 1e-5);
+        assert!((chair.bounds.unwrap().min.x + 0.5).abs() < 1e-5);
+        let tilted = Furniture::new(1.0, 1.0, 0.2, 0.2, 0.2, 2.0, origin)
+            .with_back(1.0, (20.0 as Scalar).to_radians())
+            .mesh_data();
+        let bounds = tilted.bounds.unwrap();
+        assert!(bounds.min.x < -0.5 - 0.3 && bounds.max.y < 3.2);
+        // Each shelf is one more box, every side split each way into its flat middle and two
+        // segments rounding each edge, unless the edges are left sharp
+        let bookcase = Furniture::new(1.0, 3.0, 0.1, 0.1, 0.1, 5.0, origin).with_shelves(4);
+        assert_eq!(
+            bookcase.mesh_data().vertices.len(),
+            (1 + 4 + 4) * 6 * 5 * 5 * 6 * VERTEX_SIZE
+        );
+        assert_eq!(
+            Furniture::new(1.0, 3.0, 0.1, 0.1, 0.1, 5.0, origin)
+                .with_shelves(4)
+                .with_edge_radius(0.0)
+                .mesh_data()
+                .vertices
+                .len(),
+            (1 + 4 + 4) * 36 * VERTEX_SIZE
+        );
+        assert_ne!(
+            bookcase.mesh_key(),
+            Furniture::new(1.0, 3.0, 0.1, 0.1, 0.1, 5.0, origin).mesh_key()
+        );
+    }
+
+    #[test]
+    fn test_sofa_and_bed() {
+        let origin = Vec3::origin();
+        // Rounded cushions stay inside the sofa's box
+        let sofa = Sofa::new(3.0, 1.5, 1.2, origin).mesh_data().bounds.unwrap();
+        assert!(sofa.min.y.abs() < 1e-5 && (sofa.max.y - 1.2).abs() < 1e-5);
+        assert!((sofa.max.x - 0.75).abs() < 1e-5 && (sofa.max.z - 1.5).abs() < 1e-5);
+        // The headboard rises to twice the mattress' height at the head of the bed
+        let bed = Bed::new(2.0, 3.0, 0.8, origin).mesh_data().bounds.unwrap();
+        assert!((bed.max.y - 1.6).abs() < 1e-5 && (bed.min.x + 1.5).abs() < 1e-5);
+        assert!((bed.max.x - 1.5).abs() < 1e-5 && (bed.max.z - 1.0).abs() < 1e-5);
+    }
+}
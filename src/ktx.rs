@@ -0,0 +1,230 @@
+use std::fs::File;
+use std::io::{self, Read};
+use std::path::Path;
+
+use gleam::gl::{self, GLenum};
+
+use error::io_error;
+
+/// Identifier every KTX2 file starts with
+const KTX2_IDENTIFIER: [u8; 12] = [
+    0xAB, 0x4B, 0x54, 0x58, 0x20, 0x32, 0x30, 0xBB, 0x0D, 0x0A, 0x1A, 0x0A,
+];
+/// Size of the fixed header plus the index that follows it
+const HEADER_SIZE: usize = 80;
+/// Size of one entry in the level index
+const LEVEL_INDEX_SIZE: usize = 24;
+
+// Supercompression schemes, BasisLZ holds Basis Universal ETC1S data
+const SUPERCOMPRESSION_BASIS_LZ: u32 = 1;
+const SUPERCOMPRESSION_ZSTD: u32 = 2;
+const SUPERCOMPRESSION_ZLIB: u32 = 3;
+
+/// Basis Universal textures, ETC1S or UASTC, have to be transcoded to a GPU format, which
+/// isn't done here
+const BASIS_UNSUPPORTED: &str = "Basis Universal KTX2 textures are not supported, transcode \
+                                 them to a BC, ETC2 or ASTC format first";
+
+// Compressed formats gleam doesn't define
+const COMPRESSED_RGB_S3TC_DXT1_EXT: GLenum = 0x83F0;
+const COMPRESSED_RGBA_S3TC_DXT1_EXT: GLenum = 0x83F1;
+const COMPRESSED_RGBA_S3TC_DXT3_EXT: GLenum = 0x83F2;
+const COMPRESSED_RGBA_S3TC_DXT5_EXT: GLenum = 0x83F3;
+const COMPRESSED_RGBA_ASTC_4X4_KHR: GLenum = 0x93B0;
+const COMPRESSED_SRGB_S3TC_DXT1_EXT: GLenum = 0x8C4C;
+const COMPRESSED_SRGB_ALPHA_S3TC_DXT1_EXT: GLenum = 0x8C4D;
+const COMPRESSED_SRGB_ALPHA_S3TC_DXT3_EXT: GLenum = 0x8C4E;
+const COMPRESSED_SRGB_ALPHA_S3TC_DXT5_EXT: GLenum = 0x8C4F;
+const COMPRESSED_SRGB8_ALPHA8_ASTC_4X4_KHR: GLenum = 0x93D0;
+
+/// A compressed texture read from a KTX2 container
+#[derive(Debug)]
+pub struct Ktx2 {
+    /// GL compressed internal format
+    pub internal_format: GLenum,
+    /// WebGL extension needed to upload the format
+    pub extension: &'static str,
+    pub width: u32,
+    pub height: u32,
+    /// Mip levels, largest first
+    pub levels: Vec<Vec<u8>>,
+}
+
+fn read_u32(data: &[u8], offset: usize) -> Result<u32, io::Error> {
+    data.get(offset..offset + 4)
+        .map(|b| {
+            u32::from(b[0]) | u32::from(b[1]) << 8 | u32::from(b[2]) << 16 | u32::from(b[3]) << 24
+        })
+        .ok_or_else(|| io_error("Truncated KTX2 file"))
+}
+
+fn read_u64(data: &[u8], offset: usize) -> Result<u64, io::Error> {
+    Ok(u64::from(read_u32(data, offset)?) | u64::from(read_u32(data, offset + 4)?) << 32)
+}
+
+/// Maps a Vulkan format to a GL compressed format and the extension providing it
+fn gl_format(vk_format: u32) -> Result<(GLenum, &'static str), io::Error> {
+    match vk_format {
+        // BC1-BC3, the even formats are sRGB
+        131 => Ok((COMPRESSED_RGB_S3TC_DXT1_EXT, "compressed_texture_s3tc")),
+        132 => Ok((
+            COMPRESSED_SRGB_S3TC_DXT1_EXT,
+            "compressed_texture_s3tc_srgb",
+        )),
+        133 => Ok((COMPRESSED_RGBA_S3TC_DXT1_EXT, "compressed_texture_s3tc")),
+        134 => Ok((
+            COMPRESSED_SRGB_ALPHA_S3TC_DXT1_EXT,
+            "compressed_texture_s3tc_srgb",
+        )),
+        135 => Ok((COMPRESSED_RGBA_S3TC_DXT3_EXT, "compressed_texture_s3tc")),
+        136 => Ok((
+            COMPRESSED_SRGB_ALPHA_S3TC_DXT3_EXT,
+            "compressed_texture_s3tc_srgb",
+        )),
+        137 => Ok((COMPRESSED_RGBA_S3TC_DXT5_EXT, "compressed_texture_s3tc")),
+        138 => Ok((
+            COMPRESSED_SRGB_ALPHA_S3TC_DXT5_EXT,
+            "compressed_texture_s3tc_srgb",
+        )),
+        // ETC2
+        147 => Ok((gl::COMPRESSED_RGB8_ETC2, "compressed_texture_etc")),
+        148 => Ok((gl::COMPRESSED_SRGB8_ETC2, "compressed_texture_etc")),
+        151 => Ok((gl::COMPRESSED_RGBA8_ETC2_EAC, "compressed_texture_etc")),
+        152 => Ok((
+            gl::COMPRESSED_SRGB8_ALPHA8_ETC2_EAC,
+            "compressed_texture_etc",
+        )),
+        // ASTC
+        157 => Ok((COMPRESSED_RGBA_ASTC_4X4_KHR, "compressed_texture_astc")),
+        158 => Ok((
+            COMPRESSED_SRGB8_ALPHA8_ASTC_4X4_KHR,
+            "compressed_texture_astc",
+        )),
+        0 => Err(io_error(BASIS_UNSUPPORTED)),
+        other => Err(io_error(format!("Unsupported KTX2 format {}", other))),
+    }
+}
+
+impl Ktx2 {
+    /// Reads a KTX2 file from a path
+    pub fn load<P>(path: P) -> Result<Ktx2, io::Error>
+    where
+        P: AsRef<Path>,
+    {
+        let mut data = Vec::new();
+        File::open(path)?.read_to_end(&mut data)?;
+        Ktx2::parse(&data)
+    }
+
+    /// Parses the contents of a KTX2 file
+    pub fn parse(data: &[u8]) -> Result<Ktx2, io::Error> {
+        if data.len() < HEADER_SIZE || data[..12] != KTX2_IDENTIFIER {
+            return Err(io_error("Not a KTX2 file"));
+        }
+        // Header
+        let vk_format = read_u32(data, 12)?;
+        let width = read_u32(data, 20)?;
+        let height = read_u32(data, 24)?;
+        let depth = read_u32(data, 28)?;
+        let layers = read_u32(data, 32)?;
+        let faces = read_u32(data, 36)?;
+        let level_count = read_u32(data, 40)?.max(1);
+        let supercompression = read_u32(data, 44)?;
+        // Only plain 2D textures are handled here
+        if depth > 0 || layers > 1 || faces != 1 {
+            return Err(io_error("Only 2D KTX2 textures are supported"));
+        }
+        match supercompression {
+            0 => {}
+            SUPERCOMPRESSION_BASIS_LZ => return Err(io_error(BASIS_UNSUPPORTED)),
+            SUPERCOMPRESSION_ZSTD | SUPERCOMPRESSION_ZLIB => {
+                return Err(io_error(
+                    "Zstandard and zlib supercompressed KTX2 files are not supported",
+                ))
+            }
+            other => {
+                return Err(io_error(format!(
+                    "Unknown KTX2 supercompression scheme {}",
+                    other
+                )))
+            }
+        }
+        let (internal_format, extension) = gl_format(vk_format)?;
+        // Read each level from the level index
+        let levels = (0..level_count as usize)
+            .map(|level| {
+                let entry = HEADER_SIZE + level * LEVEL_INDEX_SIZE;
+                let offset = read_u64(data, entry)? as usize;
+                let length = read_u64(data, entry + 8)? as usize;
+                data.get(offset..offset + length)
+                    .map(|level| level.to_vec())
+                    .ok_or_else(|| io_error("Truncated KTX2 level"))
+            })
+            .collect::<Result<Vec<Vec<u8>>, io::Error>>()?;
+        Ok(Ktx2 {
+            internal_format,
+            extension,
+            width,
+            height,
+            levels,
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::io;
+
+    use super::{Ktx2, COMPRESSED_RGBA_S3TC_DXT5_EXT, HEADER_SIZE, KTX2_IDENTIFIER};
+
+    fn push_u32(data: &mut Vec<u8>, x: u32) {
+        data.extend_from_slice(&[x as u8, (x >> 8) as u8, (x >> 16) as u8, (x >> 24) as u8]);
+    }
+
+    fn push_u64(data: &mut Vec<u8>, x: u64) {
+        push_u32(data, x as u32);
+        push_u32(data, (x >> 32) as u32);
+    }
+
+    #[test]
+    fn test_parse() -> io::Result<()> {
+        let mut data = KTX2_IDENTIFIER.to_vec();
+        // BC3, 8x4, one face, two levels
+        for x in &[137, 1, 8, 4, 0, 0, 1, 2, 0] {
+            push_u32(&mut data, *x);
+        }
+        // Empty DFD/KVD/SGD
+        for _ in 0..4 {
+            push_u32(&mut data, 0);
+        }
+        push_u64(&mut data, 0);
+        push_u64(&mut data, 0);
+        assert_eq!(data.len(), HEADER_SIZE);
+        // Level index
+        let start = (HEADER_SIZE + 2 * 24) as u64;
+        for &(offset, length) in &[(start, 32), (start + 32, 16)] {
+            push_u64(&mut data, offset);
+            push_u64(&mut data, length);
+            push_u64(&mut data, length);
+        }
+        data.extend_from_slice(&[1; 32]);
+        data.extend_from_slice(&[2; 16]);
+
+        let ktx = Ktx2::parse(&data)?;
+        assert_eq!(ktx.internal_format, COMPRESSED_RGBA_S3TC_DXT5_EXT);
+        assert_eq!((ktx.width, ktx.height), (8, 4));
+        assert_eq!(ktx.levels, vec![vec![1; 32], vec![2; 16]]);
+        // Truncating the data must fail instead of panicking
+        assert!(Ktx2::parse(&data[..data.len() - 1]).is_err());
+        // Basis Universal files, UASTC with no format or ETC1S supercompressed, are refused
+        let mut uastc = data.clone();
+        uastc[12] = 0;
+        let mut etc1s = uastc.clone();
+        etc1s[44] = 1;
+        for basis in &[uastc, etc1s] {
+            let err = Ktx2::parse(basis).unwrap_err();
+            assert!(err.to_string().contains("Basis Universal"));
+        }
+        Ok(())
+    }
+}
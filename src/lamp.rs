@@ -0,0 +1,119 @@
+use light::Light;
+use material::Material;
+use matrix::consts::PI;
+use matrix::{vec3, Scalar, Transform, Vec3};
+use mesh::{Instance, MeshData};
+use render::{quad, Drawable, Vertex};
+use revolution::Revolution;
+
+/// Sides of the shade
+const SHADE_SIDES: u16 = 8;
+
+/// Standing lamp, a turned base under an open shade with a bulb lighting the room
+///
+/// The bulb's light is part of the lamp, so it follows the lamp wherever it is moved.
+pub struct Lamp {
+    height: Scalar,
+    light: Light,
+    instance: Instance,
+}
+
+impl Lamp {
+    /// Creates a lamp `height` tall whose bulb shines `color`
+    pub fn new(height: Scalar, color: Vec3, translate: Vec3) -> Lamp {
+        Lamp {
+            height,
+            light: Light::point(vec3(0.0, height * 0.72, 0.0), color)
+                .with_attenuation(1.0, 0.22, 0.2),
+            // The shade glows a little with the bulb inside it
+            instance: Instance::new(
+                Transform::from_translation(translate),
+                Material::pearl().with_emissive(color * 0.3),
+            ),
+        }
+    }
+
+    /// Outline of the shade's bottom edge extruded up and inwards, seen from both sides
+    fn shade(&self) -> Vec<Vertex> {
+        let (bottom, top) = (self.height * 0.55, self.height * 0.9);
+        let (bottom_radius, top_radius) = (self.height * 0.25, self.height * 0.15);
+        let corner = |i: u16, radius: Scalar, y: Scalar| {
+            let angle = 2.0 * PI * Scalar::from(i) / Scalar::from(SHADE_SIDES);
+            vec3(radius * angle.cos(), y, radius * angle.sin())
+        };
+        let mut vertices: Vec<Vertex> = Vec::new();
+        for i in 0..SHADE_SIDES {
+            let (a, b) = (
+                corner(i + 1, top_radius, top),
+                corner(i + 1, bottom_radius, bottom),
+            );
+            let (c, d) = (corner(i, bottom_radius, bottom), corner(i, top_radius, top));
+            // Outside, then inside
+            vertices.extend_from_slice(&quad(a, b, c, d));
+            vertices.extend_from_slice(&quad(d, c, b, a));
+        }
+        vertices
+    }
+}
+
+impl Drawable for Lamp {
+    /// Lamps of the same height share a mesh
+    fn mesh_key(&self) -> String {
+        format!("lamp {}", self.height)
+    }
+    /// Generates the vertices of the shape
+    fn mesh_data(&self) -> MeshData {
+        let h = self.height;
+        // Foot, then the stem rising into the shade
+        let profile = vec![
+            vec3(0.2 * h, 0.0, 0.0),
+            vec3(0.2 * h, 0.04 * h, 0.0),
+            vec3(0.04 * h, 0.08 * h, 0.0),
+            vec3(0.03 * h, 0.7 * h, 0.0),
+        ];
+        let base = Revolution::new(profile, 24, Vec3::origin()).mesh_data();
+        let shade = MeshData::new(&self.shade());
+        let bounds = base
+            .bounds
+            .and_then(|base| shade.bounds.map(|shade| base.union(&shade)));
+        let mut vertices = base.vertices;
+        vertices.extend(shade.vertices);
+        MeshData { vertices, bounds }
+    }
+    /// Mesh of the object and how it is placed and looks
+    fn instance(&self) -> &Instance {
+        &self.instance
+    }
+    fn instance_mut(&mut self) -> &mut Instance {
+        &mut self.instance
+    }
+    /// The bulb, inside the shade
+    fn light(&self) -> Option<Light> {
+        Some(self.light)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::Lamp;
+    use light::LightKind;
+    use matrix::vec3;
+    use render::Drawable;
+
+    #[test]
+    fn test_lamp() {
+        let lamp = Lamp::new(2.0, vec3(1.0, 1.0, 1.0), vec3(3.0, 0.0, 4.0));
+        let bounds = lamp.mesh_data().bounds.unwrap();
+        assert!((bounds.max.y - 1.8).abs() < 1e-5 && (bounds.max.x - 0.5).abs() < 1e-5);
+        // The bulb is inside the lamp and moves with it
+        let light = lamp.light().unwrap();
+        let placed = light.transformed(&lamp.transform().to_matrix());
+        match (light.kind, placed.kind) {
+            (LightKind::Point { position }, LightKind::Point { position: placed }) => {
+                assert!(bounds.contains(&position));
+                assert!(placed.distance(&(position + vec3(3.0, 0.0, 4.0))) < 1e-5);
+            }
+            _ => panic!("The bulb isn't a point light"),
+        }
+    }
+}
@@ -0,0 +1,328 @@
+use gleam::gl::GLuint;
+
+use super::GlPtr;
+use matrix::{gl_float, transform_direction, transform_point, vec3, Mat4, Scalar, Vec3};
+
+/// Most lights the shader loops over
+pub const MAX_LIGHTS: usize = 4;
+
+// Light types as the shader numbers them
+const DIRECTIONAL_LIGHT: i32 = 0;
+const POINT_LIGHT: i32 = 1;
+const SPOT_LIGHT: i32 = 2;
+
+/// How a light is shaped
+#[derive(Copy, Clone, Debug)]
+pub enum LightKind {
+    /// Parallel rays shining along `direction`, like the sun
+    Directional { direction: Vec3 },
+    /// Shines every way from a point, like a lamp
+    Point { position: Vec3 },
+    /// Shines from a point in a cone around `direction`, `cutoff` radians wide on each side
+    Spot {
+        position: Vec3,
+        direction: Vec3,
+        cutoff: Scalar,
+        /// Higher values fade more towards the edge of the cone
+        exponent: Scalar,
+    },
+}
+
+/// How a light dims with distance `d`, as `1 / (constant + linear * d + quadratic * d^2)`
+#[derive(Copy, Clone, Debug)]
+pub struct Attenuation {
+    pub constant: Scalar,
+    pub linear: Scalar,
+    pub quadratic: Scalar,
+}
+
+impl Attenuation {
+    /// The light is equally bright however far away it is
+    pub fn none() -> Attenuation {
+        Attenuation {
+            constant: 1.0,
+            linear: 0.0,
+            quadratic: 0.0,
+        }
+    }
+}
+
+/// How a light changes over time
+#[derive(Copy, Clone, Debug)]
+pub enum LightAnimation {
+    Still,
+    /// Circles `radius` units around `center` at the light's height, `speed` radians a second
+    Orbit {
+        center: Vec3,
+        radius: Scalar,
+        speed: Scalar,
+    },
+    /// Wavers in brightness like a candle, dimming by up to `amount` of its intensity
+    Flicker {
+        amount: Scalar,
+        speed: Scalar,
+    },
+}
+
+/// A light in world coordinates
+///
+/// Color and intensity are kept apart from the materials they light, so changing them lights
+/// every object the same way.
+#[derive(Copy, Clone, Debug)]
+pub struct Light {
+    pub kind: LightKind,
+    pub color: Vec3,
+    /// Scales the color, may go above 1
+    pub intensity: Scalar,
+    /// Ignored by directional lights
+    pub attenuation: Attenuation,
+    pub animation: LightAnimation,
+    /// Seconds the animation has run for
+    time: Scalar,
+}
+
+impl Light {
+    pub fn directional(direction: Vec3, color: Vec3) -> Light {
+        Light {
+            kind: LightKind::Directional {
+                direction: direction.normalize(),
+            },
+            color,
+            intensity: 1.0,
+            attenuation: Attenuation::none(),
+            animation: LightAnimation::Still,
+            time: 0.0,
+        }
+    }
+
+    pub fn point(position: Vec3, color: Vec3) -> Light {
+        Light {
+            kind: LightKind::Point { position },
+            color,
+            intensity: 1.0,
+            attenuation: Attenuation::none(),
+            animation: LightAnimation::Still,
+            time: 0.0,
+        }
+    }
+
+    pub fn spot(position: Vec3, direction: Vec3, cutoff: Scalar, color: Vec3) -> Light {
+        Light {
+            kind: LightKind::Spot {
+                position,
+                direction: direction.normalize(),
+                cutoff,
+                exponent: 8.0,
+            },
+            color,
+            intensity: 1.0,
+            attenuation: Attenuation::none(),
+            animation: LightAnimation::Still,
+            time: 0.0,
+        }
+    }
+
+    /// Dims the light with distance
+    pub fn with_attenuation(self, constant: Scalar, linear: Scalar, quadratic: Scalar) -> Light {
+        Light {
+            attenuation: Attenuation {
+                constant,
+                linear,
+                quadratic,
+            },
+            ..self
+        }
+    }
+
+    pub fn with_intensity(self, intensity: Scalar) -> Light {
+        Light { intensity, ..self }
+    }
+
+    pub fn with_animation(self, animation: LightAnimation) -> Light {
+        Light { animation, ..self }
+    }
+
+    /// The light carried from an object's coordinates into the world by its model matrix
+    pub fn transformed(&self, matrix: &Mat4) -> Light {
+        let kind = match self.kind {
+            LightKind::Directional { direction } => LightKind::Directional {
+                direction: transform_direction(matrix, direction).normalize(),
+            },
+            LightKind::Point { position } => LightKind::Point {
+                position: transform_point(matrix, position),
+            },
+            LightKind::Spot {
+                position,
+                direction,
+                cutoff,
+                exponent,
+            } => LightKind::Spot {
+                position: transform_point(matrix, position),
+                direction: transform_direction(matrix, direction).normalize(),
+                cutoff,
+                exponent,
+            },
+        };
+        Light { kind, ..*self }
+    }
+
+    /// Advances the animation by `delta` seconds
+    pub fn update(&mut self, delta: Scalar) {
+        self.time += delta;
+    }
+
+    /// The light as it is at this point of its animation
+    pub fn current(&self) -> Light {
+        let t = self.time;
+        match self.animation {
+            LightAnimation::Still => *self,
+            LightAnimation::Orbit {
+                center,
+                radius,
+                speed,
+            } => {
+                let orbit = |position: Vec3| {
+                    let angle = t * speed;
+                    vec3(
+                        center.x + radius * angle.cos(),
+                        position.y,
+                        center.z + radius * angle.sin(),
+                    )
+                };
+                let kind = match self.kind {
+                    LightKind::Point { position } => LightKind::Point {
+                        position: orbit(position),
+                    },
+                    LightKind::Spot {
+                        position,
+                        direction,
+                        cutoff,
+                        exponent,
+                    } => LightKind::Spot {
+                        position: orbit(position),
+                        direction,
+                        cutoff,
+                        exponent,
+                    },
+                    // Directional lights have nowhere to move
+                    kind => kind,
+                };
+                Light { kind, ..*self }
+            }
+            LightAnimation::Flicker { amount, speed } => {
+                // Unrelated frequencies keep the flicker from looking regular, in 0..1
+                let t = t * speed;
+                let noise = 0.5 + 0.3 * (t * 7.3).sin() + 0.2 * (t * 13.1 + 1.7).sin();
+                Light {
+                    intensity: self.intensity * (1.0 - amount * noise),
+                    ..*self
+                }
+            }
+        }
+    }
+
+    /// Sets `uLights[index]`, moving the light into eye coordinates so object transforms
+    /// don't move it
+    fn upload(&self, gl: &GlPtr, program: GLuint, index: usize, v_matrix: &Mat4) {
+        let location = |field: &str| {
+            gl.get_uniform_location(program, &format!("uLights[{}].{}", index, field))
+        };
+        let set_vec3 = |field: &str, v: Vec3| {
+            gl.uniform_3f(location(field), gl_float(v.x), gl_float(v.y), gl_float(v.z))
+        };
+        let light = self.current();
+        let (kind, position, direction, cutoff, exponent) = match light.kind {
+            LightKind::Directional { direction } => {
+                (DIRECTIONAL_LIGHT, Vec3::origin(), direction, 0.0, 0.0)
+            }
+            LightKind::Point { position } => (POINT_LIGHT, position, Vec3::origin(), 0.0, 0.0),
+            LightKind::Spot {
+                position,
+                direction,
+                cutoff,
+                exponent,
+            } => (SPOT_LIGHT, position, direction, cutoff, exponent),
+        };
+        gl.uniform_1i(location("kind"), kind);
+        set_vec3("position", transform_point(v_matrix, position));
+        set_vec3("direction", transform_direction(v_matrix, direction));
+        set_vec3("color", light.color);
+        gl.uniform_1f(location("intensity"), gl_float(light.intensity));
+        let attenuation = light.attenuation;
+        set_vec3(
+            "attenuation",
+            vec3(
+                attenuation.constant,
+                attenuation.linear,
+                attenuation.quadratic,
+            ),
+        );
+        gl.uniform_1f(location("cosCutoff"), gl_float(cutoff.cos()));
+        gl.uniform_1f(location("exponent"), gl_float(exponent));
+    }
+}
+
+/// Sets every light the shader uses, dropping any past `MAX_LIGHTS`, along with the ambient
+/// light reaching everything
+pub fn upload_lights(
+    gl: &GlPtr,
+    program: GLuint,
+    lights: &[Light],
+    ambient: Vec3,
+    v_matrix: &Mat4,
+) {
+    let ambient_location = gl.get_uniform_location(program, "uAmbientLight");
+    gl.uniform_3f(
+        ambient_location,
+        gl_float(ambient.x),
+        gl_float(ambient.y),
+        gl_float(ambient.z),
+    );
+    let lights = &lights[..lights.len().min(MAX_LIGHTS)];
+    for (index, light) in lights.iter().enumerate() {
+        light.upload(gl, program, index, v_matrix);
+    }
+    let count_location = gl.get_uniform_location(program, "uNumLights");
+    gl.uniform_1i(count_location, lights.len() as i32);
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Light, LightAnimation, LightKind};
+    use matrix::consts::PI;
+    use matrix::vec3;
+
+    #[test]
+    fn test_animation() {
+        let mut lamp = Light::point(vec3(7.0, 8.0, 5.0), vec3(1.0, 1.0, 1.0)).with_animation(
+            LightAnimation::Orbit {
+                center: vec3(5.0, 0.0, 5.0),
+                radius: 2.0,
+                speed: PI,
+            },
+        );
+        // Half a second is a quarter turn, at the same height
+        lamp.update(0.5);
+        match lamp.current().kind {
+            LightKind::Point { position } => {
+                assert!(position.distance(&vec3(5.0, 8.0, 7.0)) < 1e-5)
+            }
+            _ => panic!("Orbiting changed the kind of light"),
+        }
+        let mut candle = Light::point(vec3(0.0, 0.0, 0.0), vec3(1.0, 1.0, 1.0)).with_animation(
+            LightAnimation::Flicker {
+                amount: 0.5,
+                speed: 1.0,
+            },
+        );
+        for _ in 0..100 {
+            candle.update(0.05);
+            let current = candle.current();
+            assert!((0.5..=1.0).contains(&current.intensity));
+            // Only the brightness wavers, not the color
+            assert_eq!(current.color.x, 1.0);
+        }
+        // The flicker doesn't build up on the base intensity
+        assert_eq!(candle.intensity, 1.0);
+    }
+}
@@ -1,46 +1,143 @@
 extern crate gleam;
 extern crate image;
+extern crate serde;
+#[macro_use]
+extern crate serde_derive;
+extern crate serde_json;
 
-mod chair;
-mod desk;
+mod aabb;
+mod atlas;
+mod camera;
+mod ceiling_light;
+mod controller;
+mod csg;
+mod daylight;
+mod debug_boxes;
 mod emscripten;
 mod error;
 mod extrusion;
+mod framebuffer;
+mod furniture;
+mod ktx;
+mod lamp;
+mod light;
+mod material;
 mod matrix;
+mod mesh;
 mod obj;
+mod parametric;
+mod plant;
+mod polyhedron;
+mod primitive;
 mod render;
 mod revolution;
 mod room;
+mod rug;
+mod scene;
+mod scene_file;
+mod skybox;
+mod sphere;
+mod stairs;
+mod subdivision;
+mod sweep;
+mod terrain;
+mod text;
+mod texture;
+mod tonemap;
+mod tube;
 
 pub extern "C" fn hello() {
     println!("hello");
 }
 
-use std::f32::consts::PI;
+use std::cell::Cell;
+use std::cmp::Ordering;
+use std::ffi::CStr;
 use std::mem::{self, size_of};
+use std::os::raw::{c_char, c_int, c_void};
+use std::path::Path;
 use std::ptr;
 
 use emscripten::{
     emscripten_GetProcAddress, emscripten_asm_const_int, emscripten_get_element_css_size,
-    emscripten_set_main_loop_arg, emscripten_webgl_create_context,
+    emscripten_get_now, emscripten_set_canvas_element_size, emscripten_set_click_callback,
+    emscripten_set_keydown_callback, emscripten_set_keyup_callback, emscripten_set_main_loop_arg,
+    emscripten_set_mousemove_callback, emscripten_set_resize_callback,
+    emscripten_set_wheel_callback, emscripten_webgl_create_context,
     emscripten_webgl_init_context_attributes, emscripten_webgl_make_context_current,
-    EmscriptenWebGLContextAttributes,
+    EmscriptenKeyboardEvent, EmscriptenMouseEvent, EmscriptenUiEvent,
+    EmscriptenWebGLContextAttributes, EmscriptenWheelEvent, DOM_DELTA_LINE, DOM_DELTA_PIXEL,
+    EMSCRIPTEN_EVENT_KEYDOWN, EMSCRIPTEN_EVENT_TARGET_WINDOW, EM_BOOL,
 };
 use gleam::gl;
-use gleam::gl::{GLenum, GLint, GLuint};
+use gleam::gl::{GLenum, GLint, GLsizei, GLuint};
 
-use chair::Chair;
-use desk::Desk;
+use aabb::Aabb;
+use camera::{smooth_damp, Bookmarks, Camera, CameraPose, CameraSmoothing};
+use controller::{CameraMode, FlyController, FlyThrough, OrbitController, TourLook};
+use daylight::Daylight;
+use debug_boxes::DebugBoxes;
+use framebuffer::Framebuffer;
+use light::{upload_lights, Light, LightKind, MAX_LIGHTS};
+use material::{Material, ShadingModel};
 use matrix::{
-    matmul, orthogonal_matrix, perspective_matrix, rotate_x, rotate_y, vec3, viewing_matrix,
-    Matrix44, Vec3,
+    frustum_planes, gl_float, identity, rotate_x, scale, transform_point, translate, vec3,
+    viewing_matrix, Mat4, Plane, Projection, Quat, Scalar, Transform, Vec3,
 };
-use obj::Obj;
-use render::{star, Drawable};
-use room::Room;
+use mesh::{Mesh, MeshLibrary};
+use render::{DrawStats, Drawable, VERTEX_SIZE};
+use scene::{Layer, Layers, PlacedObject, SceneNode};
+use scene_file::SceneFile;
+use skybox::Skybox;
+use texture::{TextureId, TextureManager};
+use tonemap::{upload_tone_map, ToneMap, TONE_MAP_SRC};
+
+/// Radians the camera orbits per pixel dragged
+const ORBIT_SENSITIVITY: Scalar = 0.005;
+/// How much one pixel of scrolling zooms by
+const ZOOM_RATE: Scalar = 1.001;
+/// Closest and furthest the camera can be zoomed in perspective
+const MIN_ZOOM_DISTANCE: Scalar = 2.0;
+const MAX_ZOOM_DISTANCE: Scalar = 50.0;
+/// Smallest and largest height of the orthographic view
+const MIN_ZOOM_HEIGHT: Scalar = 2.0;
+const MAX_ZOOM_HEIGHT: Scalar = 40.0;
+/// Roughly how many seconds the camera takes to catch up with the controls
+const CAMERA_SMOOTH_TIME: Scalar = 0.15;
+/// Roughly how many seconds scrolling takes to finish zooming
+const ZOOM_SMOOTH_TIME: Scalar = 0.1;
+/// Hour of the day the scene starts at
+const START_HOUR: Scalar = 12.0;
+/// Roughly how many seconds the lighting takes to reach a new time of day
+const DAYLIGHT_SMOOTH_TIME: Scalar = 1.0;
+/// Seconds the tour takes to go around the room
+const TOUR_DURATION: Scalar = 30.0;
+/// Distance the camera flies per frame
+const FLY_SPEED: Scalar = 0.1;
+/// Radians the camera turns per pixel dragged while flying
+const FLY_SENSITIVITY: Scalar = 0.003;
+/// Where the page puts the last saved scene before the program starts
+const SAVED_SCENE_PATH: &str = "/saved_scene.json";
+/// Pixels the mouse can move while held and still count as a click
+const MAX_CLICK_DRAG: Scalar = 4.0;
+/// How far the selection outline reaches past an object, as a share of its longest side
+const OUTLINE_WIDTH: Scalar = 0.03;
+
+/// Width and height of the shadow map in texels
+const SHADOW_MAP_SIZE: u32 = 2048;
+/// Texture unit the shadow map is sampled from, after the material maps
+const SHADOW_MAP_UNIT: u32 = 4;
+/// Texture unit the sky is sampled from for reflections
+const ENVIRONMENT_UNIT: u32 = 5;
+/// Half the width of the area the sun casts shadows in, around the middle of the room
+const SHADOW_EXTENT: Scalar = 10.0;
+/// How far back along its direction the sun's shadow map is drawn from
+const SHADOW_DISTANCE: Scalar = 20.0;
 
 // Used for buffering data properly
 const FLOAT_SIZE: usize = size_of::<f32>();
+/// First of the four attributes holding the columns of an instance's model matrix
+const MODEL_MATRIX_LOCATION: u32 = 4;
 
 type GlPtr = std::rc::Rc<gl::Gl>;
 
@@ -49,13 +146,74 @@ pub struct Context {
     gl: GlPtr,
     program: GLuint,
     buffer: Option<GLuint>,
-    theta: f32,
-    camera: Matrix44,
-    p_matrix: Matrix44,
+    /// Model matrices of the objects drawn by the last instanced draw
+    instance_buffer: Option<GLuint>,
+    theta: Scalar,
+    /// Camera the scene is drawn from, gliding toward the goal
+    camera: Camera,
+    /// Where the controls have put the camera
+    camera_goal: Camera,
+    smoothing: CameraSmoothing,
+    /// Poses saved with the number keys
+    bookmarks: Bookmarks,
+    /// Scrolling not yet applied to the orthographic view
+    pending_zoom: Scalar,
+    zoom_velocity: Scalar,
+    /// Which controller moves the camera
+    camera_mode: CameraMode,
+    /// Orbits the camera around the room when the mouse is dragged
+    orbit: OrbitController,
+    /// Walks the camera around in fly mode
+    fly: FlyController,
+    /// Path the camera follows in tour mode
+    tour: FlyThrough,
+    /// Time of the last frame in milliseconds
+    last_frame: f64,
+    projection: Projection,
     width: u32,
     height: u32,
-    objects: Vec<Box<Drawable>>,
+    /// Every object, placed relative to what it sits on
+    scene: SceneNode,
+    /// Layers of the scene shown, highlighted or moved together
+    layers: Layers,
+    /// Name of the object last clicked, which is drawn highlighted
+    selected: Option<String>,
+    /// How far the mouse has been dragged since the last click, so orbiting doesn't select
+    drag_distance: Scalar,
+    textures: TextureManager,
+    /// Bound for objects without a texture so they aren't tinted
+    white_texture: TextureId,
+    /// Bound for objects whose texture failed to load
+    missing_texture: TextureId,
+    skybox: Option<Skybox>,
+    /// Outlines each object's bounds when `show_bounds` is set
+    debug_boxes: DebugBoxes,
+    show_bounds: bool,
+    /// Lights shining on the scene, at most `MAX_LIGHTS`
+    lights: Vec<Light>,
+    /// Light reaching every surface from every direction, scaled by each material's ambient
+    ambient_light: Vec3,
+    /// Hour the sun and ambient light are set for, moving towards the one set by the page
+    time_of_day: Scalar,
+    time_of_day_goal: Scalar,
+    time_of_day_velocity: Scalar,
+    /// Depth of the scene seen from the sun, None if it couldn't be created
+    shadow_map: Option<Framebuffer>,
+    /// View matrix of the pass being drawn, the camera's or the light's
+    view: Cell<Mat4>,
+    /// World matrix of the scene node being drawn
+    node: Cell<Mat4>,
+    /// Whether the pass being drawn is the shadow map's
+    drawing_shadows: Cell<bool>,
+    /// Lights vertices instead of fragments, faster but blotchy on big faces
+    per_vertex_lighting: bool,
+    /// Brings the scene's light into the canvas's range
+    tone_map: ToneMap,
+    /// Scales all light before it is tone mapped
+    exposure: Scalar,
     animate: bool,
+    /// Objects drawn and culled by the last frame
+    stats: Cell<DrawStats>,
 }
 
 fn load_shader(gl: &GlPtr, shader_type: GLenum, source: &[&[u8]]) -> Option<GLuint> {
@@ -89,117 +247,74 @@ fn load_shader(gl: &GlPtr, shader_type: GLenum, source: &[&[u8]]) -> Option<GLui
     }
 }
 
+fn create_program(gl: &GlPtr, vs_source: &[&[u8]], fs_source: &[&[u8]]) -> Option<GLuint> {
+    // Load and compile shaders
+    let v_shader = load_shader(gl, gl::VERTEX_SHADER, vs_source)?;
+    let f_shader = load_shader(gl, gl::FRAGMENT_SHADER, fs_source)?;
+    // Link them into a program
+    let program = gl.create_program();
+    gl.attach_shader(program, v_shader);
+    gl.attach_shader(program, f_shader);
+    gl.link_program(program);
+    Some(program)
+}
+
 impl Context {
     fn init_buffer(&mut self) {
         let gl = &self.gl;
 
-        // Keep track of texture indices
-        let mut cur_texture: u8 = 0;
-
-        // Create the room
-        let room = Room::new(10.0, 10.0, 10.0);
-        self.objects.push(Box::new(room));
-
-        let clock = Obj::load(
-            "/clock.obj",
-            "wood.tga",
-            &mut cur_texture,
-            // Half size
-            vec3(0.5, 0.5, 0.5),
-            // Behind the table
-            vec3(3.0, 5.0, 0.0),
-        )
-        .unwrap();
-
-        self.objects.push(Box::new(clock));
-
-        let girl = Obj::load(
-            "/girl.obj",
-            "girl_texture.tga",
-            &mut cur_texture,
-            // Half size
-            vec3(0.5, 0.5, 0.5),
-            // Behind the table
-            vec3(5.0, 4.0, 1.0),
-        )
-        .unwrap();
-        self.objects.push(Box::new(girl));
-
-        let stack = Obj::load(
-            "/stack.obj",
-            "white.tga",
-            &mut cur_texture,
-            vec3(0.05, 0.05, 0.05),
-            vec3(7.0, 3.5, 5.0),
-        )
-        .unwrap();
-        self.objects.push(Box::new(stack));
-
-        // Create the table
-        let table = Desk::new(4.0, 4.0, 0.2, 0.2, 0.2, 3.0, vec3(5.0, 0.0, 5.0));
-        self.objects.push(Box::new(table));
-
-        let chair = Chair::new(1.0, 1.0, 0.2, 0.2, 0.2, 3.0, vec3(2.0, 0.0, 3.5));
-        self.objects.push(Box::new(chair));
-
-        let chair2 = Chair::new(1.0, 1.0, 0.2, 0.2, 0.2, 3.0, vec3(2.0, 0.0, 5.5));
-        self.objects.push(Box::new(chair2));
-
-        // Load the cat
-        let cat = Obj::load(
-            "/cat.obj",
-            "/cat_diff.tga",
-            &mut cur_texture,
-            vec3(2.0, 2.3, 2.0),
-            vec3(5.0, 3.5, 5.0),
-        )
-        .unwrap();
-        self.objects.push(Box::new(cat));
-
-        let star =
-            extrusion::Extrusion::new(star(5, 0.3, 1.0), vec3(0.0, 0.5, 0.0), vec3(5.0, 8.0, 5.0));
-        self.objects.push(Box::new(star));
-
-        let staff = Obj::load(
-            "/staff.obj",
-            "/staff.tga",
-            //"/cat_diff.tga",
-            &mut cur_texture,
-            vec3(1.0, 1.0, 1.0),
-            vec3(7.0, 3.0, 7.0),
-        )
-        .unwrap();
-        self.objects.push(Box::new(staff));
-
-        #[cfg_attr(rustfmt, rustfmt_skip)]
-        let rot = revolution::Revolution::new(vec![
-            vec3(0.5, 0.0, 0.0),
-            vec3(0.55, 0.15, 0.0),
-            vec3(0.5, 0.2, 0.0),
-            vec3(0.4, 0.3, 0.0),
-            vec3(0.15, 0.5, 0.0),
-            vec3(0.15, 0.9, 0.0),
-            vec3(0.175, 0.95, 0.0),
-            vec3(0.15, 0.9, 0.0),
-        ], 200, vec3(3.8, 3.3, 5.3));
-        self.objects.push(Box::new(rot));
+        // Pack the textures of models without maps that are loaded like the atlas
+        if let Err(err) =
+            self.textures
+                .load_atlas(&["girl_texture.tga", "/cat_diff.tga"], 2048, 1024)
+        {
+            eprintln!("Failed to build texture atlas: {}", err);
+        }
+
+        // Place the furniture as the scene file describes, or as it was last saved
+        let path = if Path::new(SAVED_SCENE_PATH).exists() {
+            SAVED_SCENE_PATH
+        } else {
+            "/scene.json"
+        };
+        match SceneFile::load(path).and_then(|file| Ok((file.build()?, file.camera))) {
+            Ok((scene, camera)) => {
+                self.scene = scene;
+                if let Some(camera) = camera {
+                    self.camera.set_pose(camera.to_pose());
+                    self.camera_goal.set_pose(camera.to_pose());
+                }
+            }
+            Err(err) => eprintln!("Failed to load scene: {}", err),
+        }
+
+        // Load the sky seen outside the room
+        self.skybox = match self.textures.load_cubemap_cross("/sky.tga") {
+            Ok(texture) => Some(Skybox::new(&self.gl, texture)),
+            Err(err) => {
+                eprintln!("Failed to load skybox: {}", err);
+                None
+            }
+        };
 
         //let mut potion = Obj::load("/potion.obj", vec3(5.0, 3.5, 5.0), 1).unwrap();
         //self.objects.push(Box::new(potion));
 
         // load texture data in here
 
-        // Create a vertex buffer
-        let mut vertices: Vec<f32> = Vec::new();
-        // Buffer each object's data
-        for mut object in &mut self.objects {
-            let cur_verts = object.buffer_data(vertices.len() as GLint);
-            vertices.extend_from_slice(&cur_verts);
+        // Load each object's textures, before buffering so atlas coordinates are known
+        for object in self.scene.objects_mut() {
+            // The rest of the scene still draws if a texture is missing
+            if let Err(err) = object.load_texture(&mut self.textures) {
+                eprintln!("Failed to load texture: {}", err);
+            }
         }
-        // Load each object's textures
-        for object in &self.objects {
-            object.load_texture(&self);
+        // Buffer each shape once, however many objects draw it
+        let mut meshes = MeshLibrary::new();
+        for object in self.scene.objects_mut() {
+            meshes.add(object.as_mut());
         }
+        let vertices = meshes.vertices();
 
         // Parse the model
         //let mut potion = Obj::load("/potion.obj", vec3(5.5, 8.5, 5.5)).unwrap();
@@ -218,12 +333,14 @@ impl Context {
         let position_location = gl.get_attrib_location(self.program, "aPosition") as u32;
         let normal_location = gl.get_attrib_location(self.program, "aNormal") as u32;
         let texture_location = gl.get_attrib_location(self.program, "aTexture") as u32;
+        let tangent_location = gl.get_attrib_location(self.program, "aTangent") as u32;
         // Set up arrays for loading buffers
         let array = gl.gen_vertex_arrays(1)[0];
         gl.bind_vertex_array(array);
         gl.enable_vertex_attrib_array(position_location);
         gl.enable_vertex_attrib_array(normal_location);
         gl.enable_vertex_attrib_array(texture_location);
+        gl.enable_vertex_attrib_array(tangent_location);
 
         // Load vertex data into buffer
         gl.bind_buffer(gl::ARRAY_BUFFER, vertex_buffer);
@@ -239,7 +356,7 @@ impl Context {
             3,
             gl::FLOAT,
             false,
-            8 * FLOAT_SIZE as i32,
+            (VERTEX_SIZE * FLOAT_SIZE) as i32,
             0,
         );
         // Set offsets and load information for vertex normals
@@ -248,7 +365,7 @@ impl Context {
             3,
             gl::FLOAT,
             false,
-            8 * FLOAT_SIZE as i32,
+            (VERTEX_SIZE * FLOAT_SIZE) as i32,
             3 * FLOAT_SIZE as u32,
         );
         // Set offsets and load information for vertex texture coordinates
@@ -257,9 +374,34 @@ impl Context {
             2,
             gl::FLOAT,
             false,
-            8 * FLOAT_SIZE as i32,
+            (VERTEX_SIZE * FLOAT_SIZE) as i32,
             6 * FLOAT_SIZE as u32,
         );
+        // Set offsets and load information for vertex tangents
+        gl.vertex_attrib_pointer(
+            tangent_location,
+            3,
+            gl::FLOAT,
+            false,
+            (VERTEX_SIZE * FLOAT_SIZE) as i32,
+            8 * FLOAT_SIZE as u32,
+        );
+        // Each instance reads one model matrix, a column per attribute, enabled while drawn
+        let instance_buffer = gl.gen_buffers(1)[0];
+        gl.bind_buffer(gl::ARRAY_BUFFER, instance_buffer);
+        for column in 0..4 {
+            let location = MODEL_MATRIX_LOCATION + column;
+            gl.vertex_attrib_pointer(
+                location,
+                4,
+                gl::FLOAT,
+                false,
+                (16 * FLOAT_SIZE) as i32,
+                column * 4 * FLOAT_SIZE as u32,
+            );
+            gl.vertex_attrib_divisor(location, 1);
+        }
+        self.instance_buffer = Some(instance_buffer);
         // ???
         gl.bind_vertex_array(0);
         // Return vertex array pointer
@@ -267,13 +409,8 @@ impl Context {
     }
 
     fn new(gl: GlPtr) -> Context {
-        // Load and compile shaders
-        let v_shader = load_shader(&gl, gl::VERTEX_SHADER, VS_SRC).unwrap();
-        let f_shader = load_shader(&gl, gl::FRAGMENT_SHADER, FS_SRC).unwrap();
-        let program = gl.create_program();
-        gl.attach_shader(program, v_shader);
-        gl.attach_shader(program, f_shader);
-        gl.link_program(program);
+        // Load, compile, and link shaders
+        let program = create_program(&gl, VS_SRC, FS_SRC).unwrap();
         // Set gl to use a black background
         gl.clear_color(0.0, 0.0, 0.0, 1.0);
         // Enable depth testing
@@ -281,101 +418,1237 @@ impl Context {
         gl.enable(gl::CULL_FACE);
         // Get canvas size
         let (width, height) = get_canvas_size();
+        // Create the texture manager
+        let mut textures = TextureManager::new(gl.clone());
+        let white_texture = textures.solid([255, 255, 255, 255]);
+        let missing_texture = textures.checkerboard(8, [255, 0, 255, 255], [0, 0, 0, 255]);
+        textures.set_missing(missing_texture);
+        let shadow_map =
+            match Framebuffer::depth_only(&gl, &mut textures, SHADOW_MAP_SIZE, SHADOW_MAP_SIZE) {
+                Ok(shadow_map) => Some(shadow_map),
+                Err(err) => {
+                    eprintln!("Shadows are disabled: {}", err);
+                    None
+                }
+            };
+        let debug_boxes = DebugBoxes::new(&gl);
         // Store all state
         Context {
             gl,
             program,
             buffer: None,
+            instance_buffer: None,
             theta: 0.0,
-            // Set up view matrix
-            camera: viewing_matrix(
-                // eye
-                vec3(12.0, 12.0, 12.0),
-                //vec3(5.0, 5.0, 10.0),
-                //vec3(5.0, 10.0, 5.0),
-                //vec3(0.0, 5.0, 0.0),
-                //vec3(0.0, 10.0, 0.0),
-                //vec3(0.0, 0.0, 10.0),
-
-                // up
-                //vec3(1.0, 0.0, 0.0),
-                vec3(0.0, 1.0, 0.0),
-                // at
-                vec3(0.0, 0.0, 0.0),
-                //vec3(5.0, 5.0, 5.0),
-            ),
-            /*p_matrix: perspective_matrix(
+            camera: initial_camera(),
+            camera_goal: initial_camera(),
+            smoothing: CameraSmoothing::new(CAMERA_SMOOTH_TIME),
+            bookmarks: Bookmarks::new(),
+            pending_zoom: 0.0,
+            zoom_velocity: 0.0,
+            camera_mode: CameraMode::Orbit,
+            orbit: OrbitController::new(ORBIT_SENSITIVITY),
+            fly: FlyController::new(FLY_SPEED, FLY_SENSITIVITY),
+            tour: FlyThrough::new(tour_waypoints(), TOUR_DURATION, TourLook::Tangent),
+            last_frame: unsafe { emscripten_get_now() },
+            /*projection: Projection::perspective(
                 // FOV
-                (60.0 as f32).to_radians(),
+                (60.0 as Scalar).to_radians(),
                 // Aspect ratio
-                width as f32 / height as f32,
+                width as Scalar / height as Scalar,
                 // Near plane
                 0.1,
                 // Far plane
                 1000.0,
             ),*/
             #[cfg_attr(rustfmt, rustfmt_skip)]
-            p_matrix: orthogonal_matrix(
+            projection: Projection::ortho(
                 // Left, right
                 -9.6, 9.6,
-                // Top, bottom
-                6.0, -6.0,
+                // Bottom, top
+                -6.0, 6.0,
                 // Near, far
                 0.1, 1000.0
             ),
             width,
             height,
-            objects: Vec::new(),
+            scene: SceneNode::new(Transform::default()),
+            layers: Layers::new(),
+            textures,
+            white_texture,
+            missing_texture,
+            skybox: None,
+            debug_boxes,
+            show_bounds: false,
+            lights: scene_lights(),
+            ambient_light: Daylight::at(START_HOUR).ambient,
+            time_of_day: START_HOUR,
+            time_of_day_goal: START_HOUR,
+            time_of_day_velocity: 0.0,
+            shadow_map,
+            view: Cell::new(initial_camera().view_matrix()),
+            node: Cell::new(identity()),
+            drawing_shadows: Cell::new(false),
+            selected: None,
+            drag_distance: 0.0,
+            per_vertex_lighting: false,
+            tone_map: ToneMap::Aces,
+            exposure: 1.0,
             animate: false,
+            stats: Cell::new(DrawStats::default()),
         }
     }
 
     fn draw(&self) {
         let gl = &self.gl;
+        gl.use_program(self.program);
+        // Every sampler needs a unit of its own, even when it isn't used
+        let shadow_map_location = gl.get_uniform_location(self.program, "uShadowMap");
+        gl.uniform_1i(shadow_map_location, SHADOW_MAP_UNIT as GLint);
+        let environment_location = gl.get_uniform_location(self.program, "uEnvironment");
+        gl.uniform_1i(environment_location, ENVIRONMENT_UNIT as GLint);
+        // Draw the shadows first so the scene can use them
+        let shadows = self.draw_shadow_map();
         // Set view port
         gl.viewport(0, 0, self.width as i32, self.height as i32);
         // Clear the canvas
         gl.clear(gl::COLOR_BUFFER_BIT);
+        // Draw the sky behind the scene
+        if let Some(ref skybox) = self.skybox {
+            skybox.draw(&self);
+        }
         // Set shader program
         gl.use_program(self.program);
         // Universally set perspective
         let p_location = gl.get_uniform_location(self.program, "uPMatrix");
-        gl.uniform_matrix_4fv(p_location, false, &self.p_matrix);
+        gl.uniform_matrix_4fv(p_location, false, &self.projection.matrix().to_gl());
+
+        let v_matrix = self.camera.view_matrix();
+        self.view.set(v_matrix);
+        upload_lights(
+            gl,
+            self.program,
+            &self.all_lights(),
+            self.ambient_light,
+            &v_matrix,
+        );
+        // Shadows map eye coordinates onto the light's view of the scene
+        let has_shadows_location = gl.get_uniform_location(self.program, "uHasShadows");
+        let shadow_light_location = gl.get_uniform_location(self.program, "uShadowLight");
+        match (shadows, v_matrix.inverse()) {
+            (Some((texture, light_index, light_matrix)), Some(eye_to_world)) => {
+                // Clip coordinates in -1..1 are looked up in the map in 0..1
+                let bias = scale(0.5, 0.5, 0.5) * translate(0.5, 0.5, 0.5);
+                let shadow_matrix = eye_to_world * light_matrix * bias;
+                let shadow_matrix_location = gl.get_uniform_location(self.program, "uShadowMatrix");
+                gl.uniform_matrix_4fv(shadow_matrix_location, false, &shadow_matrix.to_gl());
+                self.textures
+                    .bind_sampler(texture, self.program, "uShadowMap", SHADOW_MAP_UNIT);
+                gl.uniform_1i(has_shadows_location, 1);
+                gl.uniform_1i(shadow_light_location, light_index as GLint);
+            }
+            _ => {
+                gl.uniform_1i(has_shadows_location, 0);
+                gl.uniform_1i(shadow_light_location, -1);
+            }
+        }
+        // Reflections look up the sky in world coordinates
+        let has_environment_location = gl.get_uniform_location(self.program, "uHasEnvironment");
+        match (&self.skybox, v_matrix.inverse()) {
+            (Some(skybox), Some(eye_to_world)) => {
+                let inverse_view_location =
+                    gl.get_uniform_location(self.program, "uInverseViewMatrix");
+                gl.uniform_matrix_4fv(inverse_view_location, false, &eye_to_world.to_gl());
+                self.textures.bind_sampler(
+                    skybox.texture(),
+                    self.program,
+                    "uEnvironment",
+                    ENVIRONMENT_UNIT,
+                );
+                gl.uniform_1i(has_environment_location, 1);
+            }
+            _ => gl.uniform_1i(has_environment_location, 0),
+        }
+        let per_vertex_location = gl.get_uniform_location(self.program, "uPerVertexLighting");
+        gl.uniform_1i(per_vertex_location, self.per_vertex_lighting as GLint);
+        upload_tone_map(gl, self.program, self.tone_map, self.exposure);
 
-        let light_position_location = gl.get_uniform_location(self.program, "uLightPosition");
-        gl.uniform_3f(light_position_location, 5.0, 7.0, 5.0);
+        // Skip objects the camera can't see
+        let planes = frustum_planes(&(v_matrix * self.projection.matrix()));
+        let (visible, culled): (Vec<PlacedObject>, Vec<PlacedObject>) = self
+            .scene
+            .objects(&self.layers)
+            .into_iter()
+            .partition(|(world, object)| Context::in_view(*object, world, &planes));
 
-        // Render each object
+        // Render the opaque objects, repeated ones together
         gl.bind_vertex_array(self.buffer.unwrap());
-        for object in &self.objects {
-            object.draw(&self);
+        let opaque: Vec<PlacedObject> = visible
+            .iter()
+            .cloned()
+            .filter(|(_, object)| !object.translucent(&self.textures))
+            .collect();
+        let mut draw_calls = self.draw_objects(&opaque);
+        // Ring the selected object, which is already drawn over its outline
+        if let Some((world, object)) = visible.iter().find(|(_, object)| self.is_selected(*object))
+        {
+            self.draw_outline(world, *object);
+            draw_calls += 1;
+        }
+        // Sort translucent objects back to front
+        let mut translucent: Vec<(Scalar, Mat4, &Drawable)> = visible
+            .iter()
+            .filter(|(_, object)| object.translucent(&self.textures))
+            .map(|(world, object)| {
+                let position = transform_point(world, object.position());
+                let depth = transform_point(&v_matrix, position).z;
+                (depth, *world, *object)
+            })
+            .collect();
+        translucent.sort_by(|(a, _, _), (b, _, _)| a.partial_cmp(b).unwrap_or(Ordering::Equal));
+        // Blend translucent objects over the opaque ones without writing depth
+        gl.enable(gl::BLEND);
+        gl.blend_func(gl::SRC_ALPHA, gl::ONE_MINUS_SRC_ALPHA);
+        gl.depth_mask(false);
+        for (_, world, object) in translucent {
+            self.draw_object(&world, object);
+            draw_calls += 1;
         }
+        gl.depth_mask(true);
+        gl.disable(gl::BLEND);
         gl.bind_vertex_array(0);
+        self.stats.set(DrawStats {
+            drawn: visible.len() as u32,
+            culled: culled.len() as u32,
+            draw_calls,
+        });
+        // Outline the boxes objects are culled and picked by
+        if self.show_bounds {
+            let boxes: Vec<Aabb> = visible
+                .iter()
+                .filter_map(|(world, object)| {
+                    Some(
+                        object
+                            .bounds()?
+                            .transformed(&(object.transform().to_matrix() * *world)),
+                    )
+                })
+                .collect();
+            self.debug_boxes.draw(self, &boxes, vec3(0.0, 1.0, 0.0));
+        }
     }
 
-    fn reset(&mut self) {
-        // Reset the camera
-        self.camera = viewing_matrix(
-            // eye
-            vec3(12.0, 12.0, 12.0),
-            //vec3(5.0, 5.0, 10.0),
-            //vec3(5.0, 10.0, 5.0),
-            //vec3(0.0, 5.0, 0.0),
-            //vec3(0.0, 10.0, 0.0),
-            //vec3(0.0, 0.0, 10.0),
-
-            // up
-            //vec3(1.0, 0.0, 0.0),
+    /// Lights of the room followed by those given off by visible objects, placed where the
+    /// objects are
+    fn all_lights(&self) -> Vec<Light> {
+        let mut lights = self.lights.clone();
+        lights.extend(
+            self.scene
+                .objects(&self.layers)
+                .iter()
+                .filter_map(|(world, object)| {
+                    Some(
+                        object
+                            .light()?
+                            .transformed(&(object.transform().to_matrix() * *world)),
+                    )
+                }),
+        );
+        lights
+    }
+
+    /// Draws objects sharing a mesh and material with one call, and the rest one by one
+    ///
+    /// Returns the number of draw calls made.
+    fn draw_objects(&self, objects: &[PlacedObject]) -> u32 {
+        // Objects drawing each mesh and material
+        let mut batches: Vec<(Mesh, Vec<PlacedObject>)> = Vec::new();
+        let mut draw_calls = 0;
+        for &(world, object) in objects {
+            match object.instance().mesh {
+                // Highlighted objects are drawn on their own
+                Some(mesh) if object.instanced() && !self.is_highlighted(object) => {
+                    let batch = batches.iter_mut().find(|(other, placed)| {
+                        other.first == mesh.first
+                            && placed[0].1.material() == object.material()
+                            && placed[0].1.instance().texture == object.instance().texture
+                    });
+                    match batch {
+                        Some((_, placed)) => placed.push((world, object)),
+                        None => batches.push((mesh, vec![(world, object)])),
+                    }
+                }
+                _ => {
+                    self.draw_object(&world, object);
+                    draw_calls += 1;
+                }
+            }
+        }
+        for (mesh, placed) in batches {
+            if let [(world, object)] = placed[..] {
+                self.draw_object(&world, object);
+            } else {
+                let models: Vec<Mat4> = placed
+                    .iter()
+                    .map(|(world, object)| object.transform().to_matrix() * *world)
+                    .collect();
+                let first = placed[0].1;
+                self.draw_instanced(&mesh, first.material(), first.instance().texture, &models);
+            }
+            draw_calls += 1;
+        }
+        draw_calls
+    }
+
+    /// Draws a mesh once for every model matrix with one draw call
+    fn draw_instanced(
+        &self,
+        mesh: &Mesh,
+        material: &Material,
+        texture: Option<TextureId>,
+        models: &[Mat4],
+    ) {
+        let gl = &self.gl;
+        self.reset_object_state(false);
+        if let Some(texture) = texture {
+            self.textures.bind(texture, self.program);
+        }
+        let data: Vec<f32> = models
+            .iter()
+            .flat_map(|model| model.to_gl().to_vec())
+            .collect();
+        gl.bind_buffer(gl::ARRAY_BUFFER, self.instance_buffer.unwrap());
+        gl.buffer_data_untyped(
+            gl::ARRAY_BUFFER,
+            (FLOAT_SIZE * data.len()) as isize,
+            data.as_ptr() as *const _,
+            gl::STREAM_DRAW,
+        );
+        let instanced_location = gl.get_uniform_location(self.program, "uInstanced");
+        gl.uniform_1i(instanced_location, 1);
+        let v_location = gl.get_uniform_location(self.program, "uViewMatrix");
+        gl.uniform_matrix_4fv(v_location, false, &self.view_matrix().to_gl());
+        self.set_material(material);
+        for column in 0..4 {
+            gl.enable_vertex_attrib_array(MODEL_MATRIX_LOCATION + column);
+        }
+        gl.draw_arrays_instanced(
+            gl::TRIANGLES,
+            mesh.first,
+            mesh.count,
+            models.len() as GLsizei,
+        );
+        for column in 0..4 {
+            gl.disable_vertex_attrib_array(MODEL_MATRIX_LOCATION + column);
+        }
+        gl.uniform_1i(instanced_location, 0);
+    }
+
+    /// Draws the depth of every opaque object as seen from the first directional light
+    ///
+    /// Returns the shadow map, which light it is for, and the matrix taking world coordinates
+    /// into the light's clip coordinates.
+    fn draw_shadow_map(&self) -> Option<(TextureId, usize, Mat4)> {
+        let shadow_map = self.shadow_map.as_ref()?;
+        let (light_index, direction) =
+            self.lights.iter().enumerate().find_map(|(index, light)| {
+                match light.current().kind {
+                    LightKind::Directional { direction } => Some((index, direction)),
+                    _ => None,
+                }
+            })?;
+        let gl = &self.gl;
+        // Look along the light at the middle of the room
+        let center = vec3(5.0, 5.0, 5.0);
+        let light_view = viewing_matrix(
+            center - direction * SHADOW_DISTANCE,
             vec3(0.0, 1.0, 0.0),
-            // at
-            vec3(0.0, 0.0, 0.0),
-            //vec3(5.0, 5.0, 5.0),
+            center,
+        );
+        let light_projection = Projection::ortho(
+            -SHADOW_EXTENT,
+            SHADOW_EXTENT,
+            -SHADOW_EXTENT,
+            SHADOW_EXTENT,
+            0.1,
+            SHADOW_DISTANCE * 2.0,
+        )
+        .matrix();
+        // The map can't be sampled while it is drawn into
+        gl.active_texture(gl::TEXTURE0 + SHADOW_MAP_UNIT);
+        gl.bind_texture(gl::TEXTURE_2D, 0);
+        shadow_map.bind();
+        gl.clear(gl::DEPTH_BUFFER_BIT);
+        gl.color_mask(false, false, false, false);
+        // Pushing depth back keeps surfaces from shadowing themselves
+        gl.enable(gl::POLYGON_OFFSET_FILL);
+        gl.polygon_offset(2.0, 4.0);
+        let p_location = gl.get_uniform_location(self.program, "uPMatrix");
+        gl.uniform_matrix_4fv(p_location, false, &light_projection.to_gl());
+        self.view.set(light_view);
+        self.drawing_shadows.set(true);
+        gl.bind_vertex_array(self.buffer.unwrap());
+        let opaque: Vec<PlacedObject> = self
+            .scene
+            .objects(&self.layers)
+            .into_iter()
+            .filter(|(_, object)| !object.translucent(&self.textures))
+            .collect();
+        self.draw_objects(&opaque);
+        self.drawing_shadows.set(false);
+        gl.bind_vertex_array(0);
+        gl.disable(gl::POLYGON_OFFSET_FILL);
+        gl.color_mask(true, true, true, true);
+        let texture = shadow_map.resolve(self.width, self.height);
+        Some((texture, light_index, light_view * light_projection))
+    }
+
+    /// Selects the object under a point on the canvas and tells the page its name
+    ///
+    /// Clicking nothing, or an object without a name, clears the selection.
+    fn select_at(&mut self, x: Scalar, y: Scalar) {
+        let viewport = [0.0, 0.0, self.width as Scalar, self.height as Scalar];
+        self.selected = self
+            .camera
+            .screen_ray(x, y, viewport, &self.projection)
+            .and_then(|ray| self.scene.pick(&ray, &self.layers))
+            .and_then(|node| node.name.clone());
+        let name = format!("{}\0", self.selected.as_deref().unwrap_or(""));
+        let code = "{set_selected_object(UTF8ToString($0)); return 0;}\0";
+        unsafe {
+            emscripten_asm_const_int(code.as_ptr() as *const _, name.as_ptr());
+        }
+    }
+
+    /// Whether an object is the one drawn by the selected node
+    fn is_selected(&self, object: &Drawable) -> bool {
+        let selected = self
+            .selected
+            .as_ref()
+            .and_then(|name| self.scene.find(name))
+            .and_then(|node| node.object.as_ref());
+        match selected {
+            // Compare addresses alone, the same object can have different vtables
+            Some(selected) => ptr::eq(
+                selected.as_ref() as *const Drawable as *const u8,
+                object as *const Drawable as *const u8,
+            ),
+            None => false,
+        }
+    }
+
+    /// Whether an object is tinted, as the selected object or one in a highlighted layer
+    fn is_highlighted(&self, object: &Drawable) -> bool {
+        self.is_selected(object)
+            || self
+                .scene
+                .layer_of(object)
+                .is_some_and(|layer| self.layers.get(layer).highlighted)
+    }
+
+    /// Object with a name given by the scene file, to move it or change how it looks
+    pub fn get_object_mut(&mut self, name: &str) -> Option<&mut SceneNode> {
+        self.scene.find_mut(name)
+    }
+
+    /// View matrix of the pass being drawn, objects draw with this instead of the camera's
+    pub fn view_matrix(&self) -> Mat4 {
+        self.view.get()
+    }
+
+    /// World matrix of the scene node being drawn, which objects place themselves in
+    pub fn node_matrix(&self) -> Mat4 {
+        self.node.get()
+    }
+
+    /// Whether objects are being drawn into the shadow map instead of for the camera
+    pub fn drawing_shadows(&self) -> bool {
+        self.drawing_shadows.get()
+    }
+
+    /// Whether any part of an object is inside the frustum, objects without bounds always are
+    fn in_view(object: &Drawable, world: &Mat4, planes: &[Plane; 6]) -> bool {
+        match object.bounds() {
+            Some(bounds) => bounds
+                .transformed(&(object.transform().to_matrix() * *world))
+                .intersects_frustum(planes),
+            None => true,
+        }
+    }
+
+    /// Objects drawn and culled by the last frame
+    pub fn draw_stats(&self) -> DrawStats {
+        self.stats.get()
+    }
+
+    /// Sets how the next draw reflects light
+    pub fn set_material(&self, material: &Material) {
+        let gl = &self.gl;
+        let set_color = |name: &str, color: Vec3| {
+            let location = gl.get_uniform_location(self.program, name);
+            gl.uniform_4f(
+                location,
+                gl_float(color.x),
+                gl_float(color.y),
+                gl_float(color.z),
+                1.0,
+            );
+        };
+        set_color("uAmbient", material.ambient);
+        set_color("uDiffuse", material.diffuse);
+        set_color("uSpecular", material.specular);
+        let shininess_location = gl.get_uniform_location(self.program, "uShininess");
+        gl.uniform_1f(shininess_location, gl_float(material.shininess));
+        let (blinn_phong, metallic_roughness, metallic, roughness) = match material.shading {
+            ShadingModel::Phong => (false, false, 0.0, 1.0),
+            ShadingModel::BlinnPhong => (true, false, 0.0, 1.0),
+            ShadingModel::MetallicRoughness {
+                metallic,
+                roughness,
+            } => (false, true, metallic, roughness),
+        };
+        let blinn_phong_location = gl.get_uniform_location(self.program, "uBlinnPhong");
+        gl.uniform_1i(blinn_phong_location, blinn_phong as GLint);
+        let metallic_roughness_location =
+            gl.get_uniform_location(self.program, "uMetallicRoughness");
+        gl.uniform_1i(metallic_roughness_location, metallic_roughness as GLint);
+        let metallic_location = gl.get_uniform_location(self.program, "uMetallic");
+        gl.uniform_1f(metallic_location, gl_float(metallic));
+        let roughness_location = gl.get_uniform_location(self.program, "uRoughness");
+        gl.uniform_1f(roughness_location, gl_float(roughness));
+        let alpha_cutoff_location = gl.get_uniform_location(self.program, "uAlphaCutoff");
+        gl.uniform_1f(alpha_cutoff_location, gl_float(material.alpha_cutoff));
+        let emissive_location = gl.get_uniform_location(self.program, "uEmissive");
+        gl.uniform_3f(
+            emissive_location,
+            gl_float(material.emissive.x),
+            gl_float(material.emissive.y),
+            gl_float(material.emissive.z),
         );
+    }
+
+    /// Sets the model-view matrix of the next draw, along with the matrix for its normals
+    pub fn set_mv_matrix(&self, mv_matrix: &Mat4) {
+        let gl = &self.gl;
+        let mv_location = gl.get_uniform_location(self.program, "uMVMatrix");
+        gl.uniform_matrix_4fv(mv_location, false, &mv_matrix.to_gl());
+        let normal_location = gl.get_uniform_location(self.program, "uNormalMatrix");
+        gl.uniform_matrix_3fv(normal_location, false, &mv_matrix.normal_matrix());
+    }
+
+    /// Resets optional per-object state and draws an object in the node placing it
+    fn draw_object(&self, world: &Mat4, object: &Drawable) {
+        self.node.set(*world);
+        self.reset_object_state(self.is_highlighted(object));
+        object.draw(&self);
+    }
+
+    /// Draws the back faces of an object grown around its middle in a flat color
+    ///
+    /// The object itself hides all but the rim, leaving an outline. Objects without bounds
+    /// have no middle to grow around and aren't outlined.
+    fn draw_outline(&self, world: &Mat4, object: &Drawable) {
+        let model = object.transform().to_matrix();
+        let (bounds, inverse) = match (object.bounds(), model.inverse()) {
+            (Some(bounds), Some(inverse)) => (bounds, inverse),
+            _ => return,
+        };
+        // Every side grows by the same amount, so thin parts are outlined too
+        let size = bounds.max - bounds.min;
+        let width = OUTLINE_WIDTH * size.x.max(size.y).max(size.z);
+        let grow = |side: Scalar| {
+            if side > 0.0 {
+                (side + 2.0 * width) / side
+            } else {
+                1.0
+            }
+        };
+        let center = bounds.center();
+        let grown = translate(-center.x, -center.y, -center.z)
+            * scale(grow(size.x), grow(size.y), grow(size.z))
+            * translate(center.x, center.y, center.z);
+        // The object applies its own transform after the node's, so the growth is wrapped in
+        // that transform's inverse to come first
+        self.node.set(inverse * grown * model * *world);
+        self.reset_object_state(false);
+        let gl = &self.gl;
+        let outline_location = gl.get_uniform_location(self.program, "uOutline");
+        gl.uniform_1i(outline_location, 1);
+        gl.cull_face(gl::FRONT);
+        object.draw(self);
+        gl.cull_face(gl::BACK);
+        gl.uniform_1i(outline_location, 0);
+    }
+
+    /// Turns off the maps objects set for themselves and binds the white texture
+    fn reset_object_state(&self, highlighted: bool) {
+        let gl = &self.gl;
+        // Objects without optional maps don't set these themselves
+        let has_normal_map_location = gl.get_uniform_location(self.program, "uHasNormalMap");
+        gl.uniform_1i(has_normal_map_location, 0);
+        let has_specular_map_location = gl.get_uniform_location(self.program, "uHasSpecularMap");
+        gl.uniform_1i(has_specular_map_location, 0);
+        let has_emissive_map_location = gl.get_uniform_location(self.program, "uHasEmissiveMap");
+        gl.uniform_1i(has_emissive_map_location, 0);
+        let highlighted_location = gl.get_uniform_location(self.program, "uHighlighted");
+        gl.uniform_1i(highlighted_location, highlighted as GLint);
+        // Textured objects bind over this
+        self.textures.bind(self.white_texture, self.program);
+    }
+
+    /// Follows the canvas size, fitting the drawing buffer and projection to its new shape
+    fn resize(&mut self, width: u32, height: u32) {
+        // A hidden canvas has no size to fit
+        if width == 0 || height == 0 {
+            return;
+        }
+        self.width = width;
+        self.height = height;
+        // Draw at the size the canvas is shown at instead of stretching
+        unsafe {
+            emscripten_set_canvas_element_size(
+                "#canvas\0".as_ptr() as *const _,
+                width as c_int,
+                height as c_int,
+            );
+        }
+        self.gl.viewport(0, 0, width as i32, height as i32);
+        self.projection
+            .set_aspect(width as Scalar / height as Scalar);
+    }
+
+    /// Zooms in for negative amounts and out for positive ones
+    fn zoom(&mut self, amount: Scalar) {
+        match self.projection {
+            Projection::Perspective { .. } => {
+                self.camera_goal
+                    .dolly(ZOOM_RATE.powf(amount), MIN_ZOOM_DISTANCE, MAX_ZOOM_DISTANCE)
+            }
+            // Applied a bit at a time by update_zoom
+            Projection::Ortho { .. } => self.pending_zoom += amount,
+        }
+    }
+
+    /// Moves the time of day towards its goal, setting the sun and ambient light for it
+    fn update_daylight(&mut self, delta: Scalar) {
+        self.time_of_day = smooth_damp(
+            self.time_of_day,
+            self.time_of_day_goal,
+            &mut self.time_of_day_velocity,
+            DAYLIGHT_SMOOTH_TIME,
+            delta,
+        );
+        let daylight = Daylight::at(self.time_of_day);
+        self.ambient_light = daylight.ambient;
+        // The first directional light is the sun
+        let sun = self
+            .lights
+            .iter_mut()
+            .find(|light| matches!(light.kind, LightKind::Directional { .. }));
+        if let Some(sun) = sun {
+            sun.kind = LightKind::Directional {
+                direction: daylight.sun_direction,
+            };
+            sun.color = daylight.sun_color;
+            sun.intensity = daylight.sun_intensity;
+        }
+    }
+
+    /// Applies some of the pending orthographic zoom, finishing it over a few frames
+    fn update_zoom(&mut self, delta: Scalar) {
+        let remaining = smooth_damp(
+            self.pending_zoom,
+            0.0,
+            &mut self.zoom_velocity,
+            ZOOM_SMOOTH_TIME,
+            delta,
+        );
+        self.projection.zoom(
+            ZOOM_RATE.powf(self.pending_zoom - remaining),
+            MIN_ZOOM_HEIGHT,
+            MAX_ZOOM_HEIGHT,
+        );
+        self.pending_zoom = remaining;
+    }
+
+    /// Switches how the camera is controlled
+    fn set_camera_mode(&mut self, mode: CameraMode) {
+        // Flying starts from wherever the camera is, the others from the usual view
+        if mode != CameraMode::Fly {
+            self.camera_goal = initial_camera();
+        }
+        if mode == CameraMode::Tour {
+            self.tour.restart();
+        }
+        self.camera_mode = mode;
+    }
+
+    /// Saves where the camera is headed to a slot, and shows the page a link back to it
+    fn save_bookmark(&mut self, slot: usize) {
+        let pose = self.camera_goal.pose();
+        if !self.bookmarks.save(slot, pose) {
+            return;
+        }
+        let link = format!("{}\0", pose);
+        let code = "{set_view_link(UTF8ToString($0)); return 0;}\0";
+        unsafe {
+            emscripten_asm_const_int(code.as_ptr() as *const _, link.as_ptr());
+        }
+    }
+
+    /// Hands the page the scene as it is arranged now, to keep for the next visit
+    fn save_scene(&self) {
+        let json = SceneFile::from_scene(&self.scene, Some(self.camera_goal.pose()))
+            .to_json()
+            .map(|json| format!("{}\0", json));
+        match json {
+            Ok(json) => {
+                let code = "{save_scene(UTF8ToString($0)); return 0;}\0";
+                unsafe {
+                    emscripten_asm_const_int(code.as_ptr() as *const _, json.as_ptr());
+                }
+            }
+            Err(err) => eprintln!("Failed to save scene: {}", err),
+        }
+    }
+
+    /// Glides the camera to a saved slot if there is one
+    fn load_bookmark(&mut self, slot: usize) {
+        if let Some(pose) = self.bookmarks.get(slot) {
+            self.go_to_pose(pose);
+        }
+    }
+
+    /// Glides the camera to a pose
+    fn go_to_pose(&mut self, pose: CameraPose) {
+        // The tour would pull the camera straight back onto its path
+        if self.camera_mode == CameraMode::Tour {
+            self.camera_mode = CameraMode::Static;
+        }
+        self.camera_goal.set_pose(pose);
+    }
+
+    fn reset(&mut self) {
+        // Reset the camera
+        self.camera_goal = initial_camera();
         // Reset spinning
         self.theta = 0.0;
     }
 }
 
+/// Camera looking at the center of the room from above one corner
+fn initial_camera() -> Camera {
+    Camera::new(
+        // eye
+        vec3(12.0, 12.0, 12.0),
+        // target
+        vec3(5.0, 5.0, 5.0),
+        // up
+        vec3(0.0, 1.0, 0.0),
+    )
+}
+
+/// Sun coming in where the room is open at a time of day
+fn sun(daylight: &Daylight) -> Light {
+    Light::directional(daylight.sun_direction, daylight.sun_color)
+        .with_intensity(daylight.sun_intensity)
+}
+
+/// Sun coming in where the room is open and a spotlight on the clock
+///
+/// The light over the table comes from the fixture hanging from the ceiling in the scene.
+fn scene_lights() -> Vec<Light> {
+    let lights = vec![
+        sun(&Daylight::at(START_HOUR)),
+        Light::spot(
+            vec3(4.0, 9.0, 3.0),
+            vec3(-1.0, -4.0, -3.0),
+            (20.0 as Scalar).to_radians(),
+            vec3(1.0, 0.875, 0.67),
+        )
+        .with_intensity(1.2)
+        .with_attenuation(1.0, 0.05, 0.01),
+    ];
+    debug_assert!(lights.len() <= MAX_LIGHTS);
+    lights
+}
+
+/// Loop around the room for the camera tour, passing between the furniture
+fn tour_waypoints() -> Vec<Vec3> {
+    vec![
+        vec3(9.0, 6.0, 9.0),
+        vec3(9.0, 4.0, 1.5),
+        vec3(5.0, 5.0, 1.0),
+        vec3(1.5, 6.0, 2.0),
+        vec3(1.0, 4.0, 5.0),
+        vec3(2.0, 5.0, 9.0),
+        vec3(9.0, 6.0, 9.0),
+    ]
+}
+
+/// Orbits the camera while a mouse button is held over the canvas
+unsafe extern "C" fn mouse_move_callback(
+    _event_type: c_int,
+    event: *const EmscriptenMouseEvent,
+    ctx: *mut c_void,
+) -> EM_BOOL {
+    let ctx = &mut *(ctx as *mut Context);
+    let event = &*event;
+    if event.buttons & 1 == 0 {
+        return 0;
+    }
+    let (delta_x, delta_y) = (event.movementX as Scalar, event.movementY as Scalar);
+    ctx.drag_distance += delta_x.abs() + delta_y.abs();
+    match ctx.camera_mode {
+        CameraMode::Orbit => ctx.orbit.drag(delta_x, delta_y),
+        CameraMode::Fly => ctx.fly.look(delta_x, delta_y),
+        CameraMode::Tour | CameraMode::Static => return 0,
+    }
+    1
+}
+
+/// Selects the object clicked on, unless the click ended a drag
+unsafe extern "C" fn click_callback(
+    _event_type: c_int,
+    event: *const EmscriptenMouseEvent,
+    ctx: *mut c_void,
+) -> EM_BOOL {
+    let ctx = &mut *(ctx as *mut Context);
+    let event = &*event;
+    let dragged = ctx.drag_distance > MAX_CLICK_DRAG;
+    ctx.drag_distance = 0.0;
+    if dragged {
+        return 0;
+    }
+    ctx.select_at(event.targetX as Scalar, event.targetY as Scalar);
+    1
+}
+
+/// Zooms the view when the mouse wheel is scrolled over the canvas
+unsafe extern "C" fn wheel_callback(
+    _event_type: c_int,
+    event: *const EmscriptenWheelEvent,
+    ctx: *mut c_void,
+) -> EM_BOOL {
+    let ctx = &mut *(ctx as *mut Context);
+    let event = &*event;
+    // Scroll in pixels however the browser reports it
+    let pixels = match event.deltaMode {
+        DOM_DELTA_PIXEL => event.deltaY,
+        DOM_DELTA_LINE => event.deltaY * 40.0,
+        _ => event.deltaY * 800.0,
+    };
+    ctx.zoom(pixels as Scalar);
+    1
+}
+
+/// Handles the camera and lighting keys and passes movement keys to the fly controller
+unsafe extern "C" fn key_callback(
+    event_type: c_int,
+    event: *const EmscriptenKeyboardEvent,
+    ctx: *mut c_void,
+) -> EM_BOOL {
+    let ctx = &mut *(ctx as *mut Context);
+    let code = CStr::from_ptr((*event).code.as_ptr()).to_string_lossy();
+    let pressed = event_type == EMSCRIPTEN_EVENT_KEYDOWN;
+    if code == "KeyC" {
+        if pressed {
+            ctx.set_camera_mode(ctx.camera_mode.next());
+        }
+        return 1;
+    }
+    // Q switches between per-fragment and faster per-vertex lighting
+    if code == "KeyQ" {
+        if pressed {
+            ctx.per_vertex_lighting = !ctx.per_vertex_lighting;
+        }
+        return 1;
+    }
+    // T cycles through the tone maps
+    if code == "KeyT" {
+        if pressed {
+            ctx.tone_map = ctx.tone_map.next();
+        }
+        return 1;
+    }
+    // H hides the room to look at the furniture from outside
+    if code == "KeyH" {
+        if pressed {
+            if let Some(room) = ctx.get_object_mut("room") {
+                room.visible = !room.visible;
+            }
+        }
+        return 1;
+    }
+    // X outlines the bounds of every object
+    if code == "KeyX" {
+        if pressed {
+            ctx.show_bounds = !ctx.show_bounds;
+        }
+        return 1;
+    }
+    // Digits recall bookmarks, or save them with shift held
+    if let Some(digit) = code.strip_prefix("Digit") {
+        let slot = match digit.parse::<usize>() {
+            Ok(slot) if slot > 0 => slot,
+            _ => return 0,
+        };
+        if pressed {
+            if (*event).shiftKey != 0 {
+                ctx.save_bookmark(slot);
+            } else {
+                ctx.load_bookmark(slot);
+            }
+        }
+        return 1;
+    }
+    // Keys are left to the page outside of fly mode, A toggles the animation there
+    if ctx.camera_mode != CameraMode::Fly {
+        return 0;
+    }
+    ctx.fly.key(&code, pressed) as EM_BOOL
+}
+
+/// Context the main loop runs on, reached by functions called from the page
+static mut CONTEXT: *mut Context = ptr::null_mut();
+
+/// Runs a function on the context if the main loop has started
+fn with_context<F>(f: F)
+where
+    F: FnOnce(&mut Context),
+{
+    unsafe {
+        if let Some(ctx) = CONTEXT.as_mut() {
+            f(ctx);
+        }
+    }
+}
+
+/// Runs a closure with a named object, returning whether there was one for the page
+///
+/// # Safety
+///
+/// `name` must point to a nul terminated string.
+unsafe fn with_object<F>(name: *const c_char, f: F) -> EM_BOOL
+where
+    F: FnOnce(&mut SceneNode),
+{
+    let name = CStr::from_ptr(name).to_string_lossy();
+    let mut found = 0;
+    with_context(|ctx| {
+        if let Some(object) = ctx.get_object_mut(&name) {
+            f(object);
+            found = 1;
+        }
+    });
+    found
+}
+
+/// Moves the camera to a point, called from the page
+#[no_mangle]
+pub extern "C" fn camera_set_eye(x: f64, y: f64, z: f64) {
+    with_context(|ctx| ctx.camera_goal.eye = vec3(x as Scalar, y as Scalar, z as Scalar));
+}
+
+/// Points the camera at a point, called from the page
+#[no_mangle]
+pub extern "C" fn camera_look_at(x: f64, y: f64, z: f64) {
+    with_context(|ctx| {
+        ctx.camera_goal
+            .look_at(vec3(x as Scalar, y as Scalar, z as Scalar))
+    });
+}
+
+/// Sets how many seconds the tour takes to go around the room, called from the page
+#[no_mangle]
+pub extern "C" fn tour_set_duration(seconds: f64) {
+    with_context(|ctx| ctx.tour.set_duration((seconds as Scalar).max(1.0)));
+}
+
+/// Keeps the tour looking at a point instead of ahead along its path, called from the page
+#[no_mangle]
+pub extern "C" fn tour_look_at(x: f64, y: f64, z: f64) {
+    with_context(|ctx| {
+        ctx.tour.look = TourLook::Target(vec3(x as Scalar, y as Scalar, z as Scalar))
+    });
+}
+
+/// Looks ahead along the tour's path again, called from the page
+#[no_mangle]
+pub extern "C" fn tour_look_ahead() {
+    with_context(|ctx| ctx.tour.look = TourLook::Tangent);
+}
+
+/// Sets whether the tour starts over after going around once, called from the page
+///
+/// A tour that doesn't leaves the camera where it ends.
+#[no_mangle]
+pub extern "C" fn tour_set_looping(looping: c_int) {
+    with_context(|ctx| ctx.tour.looping = looping != 0);
+}
+
+/// Sets the hour of the day from 0 to 24, called from the page
+///
+/// The sun and ambient light move to it over about a second.
+#[no_mangle]
+pub extern "C" fn set_time_of_day(hour: f64) {
+    with_context(|ctx| ctx.time_of_day_goal = (hour as Scalar).clamp(0.0, 24.0));
+}
+
+/// Scales all light before it is tone mapped, called from the page
+#[no_mangle]
+pub extern "C" fn set_exposure(exposure: f64) {
+    with_context(|ctx| ctx.exposure = (exposure as Scalar).max(0.0));
+}
+
+/// Changes the color of a light, called from the page
+#[no_mangle]
+pub extern "C" fn light_set_color(index: c_int, r: f64, g: f64, b: f64) {
+    with_context(|ctx| {
+        if let Some(light) = ctx.lights.get_mut(index as usize) {
+            light.color = vec3(r as Scalar, g as Scalar, b as Scalar);
+        }
+    });
+}
+
+/// Changes how bright a light is, called from the page
+#[no_mangle]
+pub extern "C" fn light_set_intensity(index: c_int, intensity: f64) {
+    with_context(|ctx| {
+        if let Some(light) = ctx.lights.get_mut(index as usize) {
+            light.intensity = intensity as Scalar;
+        }
+    });
+}
+
+/// Orbits the camera around its target by angles in radians, called from the page
+#[no_mangle]
+pub extern "C" fn camera_orbit_delta(yaw: f64, pitch: f64) {
+    with_context(|ctx| ctx.camera_goal.orbit(yaw as Scalar, pitch as Scalar));
+}
+
+/// Fits the view to the canvas after the window changes size
+unsafe extern "C" fn resize_callback(
+    _event_type: c_int,
+    _event: *const EmscriptenUiEvent,
+    ctx: *mut c_void,
+) -> EM_BOOL {
+    let ctx = &mut *(ctx as *mut Context);
+    let (width, height) = get_canvas_size();
+    ctx.resize(width, height);
+    0
+}
+
+/// Shakes the view by up to `amplitude` units for `duration` seconds, called from the page
+#[no_mangle]
+pub extern "C" fn camera_shake(amplitude: f64, duration: f64) {
+    with_context(|ctx| ctx.camera.shake(amplitude as Scalar, duration as Scalar));
+}
+
+/// Saves the camera to a numbered slot, called from the page
+#[no_mangle]
+pub extern "C" fn camera_save_bookmark(slot: c_int) {
+    with_context(|ctx| ctx.save_bookmark(slot as usize));
+}
+
+/// Moves the camera to a numbered slot, called from the page
+#[no_mangle]
+pub extern "C" fn camera_load_bookmark(slot: c_int) {
+    with_context(|ctx| ctx.load_bookmark(slot as usize));
+}
+
+/// Moves the camera to a pose written out by a bookmark, called from the page
+///
+/// # Safety
+///
+/// `pose` must point to a nul terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn camera_restore(pose: *const c_char) -> EM_BOOL {
+    let pose = CStr::from_ptr(pose).to_string_lossy();
+    match pose.parse::<CameraPose>() {
+        Ok(pose) => {
+            with_context(|ctx| ctx.go_to_pose(pose));
+            1
+        }
+        Err(err) => {
+            eprintln!("Invalid camera pose {}: {}", pose, err);
+            0
+        }
+    }
+}
+
+/// Saves the arrangement of the scene and the camera, called from the page
+#[no_mangle]
+pub extern "C" fn scene_save() {
+    with_context(|ctx| ctx.save_scene());
+}
+
+/// Moves a named object relative to what it sits on, called from the page
+///
+/// Returns whether there was an object with the name.
+///
+/// # Safety
+///
+/// `name` must point to a nul terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn object_set_position(
+    name: *const c_char,
+    x: f64,
+    y: f64,
+    z: f64,
+) -> EM_BOOL {
+    with_object(name, |object| {
+        object.set_translation(vec3(x as Scalar, y as Scalar, z as Scalar))
+    })
+}
+
+/// Turns a named object by an angle around an axis, called from the page
+///
+/// Returns whether there was an object with the name.
+///
+/// # Safety
+///
+/// `name` must point to a nul terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn object_set_rotation(
+    name: *const c_char,
+    x: f64,
+    y: f64,
+    z: f64,
+    degrees: f64,
+) -> EM_BOOL {
+    let axis = vec3(x as Scalar, y as Scalar, z as Scalar);
+    if axis.length() == 0.0 {
+        return 0;
+    }
+    with_object(name, |object| {
+        object.set_rotation(Quat::from_axis_angle(
+            axis.normalize(),
+            (degrees as Scalar).to_radians(),
+        ))
+    })
+}
+
+/// Sizes a named object along its axes, called from the page
+///
+/// Returns whether there was an object with the name.
+///
+/// # Safety
+///
+/// `name` must point to a nul terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn object_set_scale(name: *const c_char, x: f64, y: f64, z: f64) -> EM_BOOL {
+    with_object(name, |object| {
+        object.set_scale(vec3(x as Scalar, y as Scalar, z as Scalar))
+    })
+}
+
+/// Moves a named object onto another at an offset from it, called from the page
+///
+/// The object then follows the one it is attached to. Returns whether it could be attached.
+///
+/// # Safety
+///
+/// `child` and `parent` must point to nul terminated strings.
+#[no_mangle]
+pub unsafe extern "C" fn object_attach(
+    child: *const c_char,
+    parent: *const c_char,
+    x: f64,
+    y: f64,
+    z: f64,
+) -> EM_BOOL {
+    let child = CStr::from_ptr(child).to_string_lossy();
+    let parent = CStr::from_ptr(parent).to_string_lossy();
+    let offset = Transform::from_translation(vec3(x as Scalar, y as Scalar, z as Scalar));
+    let mut attached = 0;
+    with_context(|ctx| attached = ctx.scene.attach(&child, &parent, offset) as EM_BOOL);
+    attached
+}
+
+/// Shows or hides a named object and everything on it, called from the page
+///
+/// Returns whether there was an object with the name.
+///
+/// # Safety
+///
+/// `name` must point to a nul terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn object_set_visible(name: *const c_char, visible: EM_BOOL) -> EM_BOOL {
+    with_object(name, |object| object.visible = visible != 0)
+}
+
+/// Changes a layer of the scene by name, layers no node is in yet are kept for later
+///
+/// # Safety
+///
+/// `name` must point to a nul terminated string.
+unsafe fn with_layer<F>(name: *const c_char, f: F)
+where
+    F: FnOnce(&mut Layer),
+{
+    let name = CStr::from_ptr(name).to_string_lossy();
+    with_context(|ctx| f(ctx.layers.get_mut(&name)));
+}
+
+/// Shows or hides every object in a layer, called from the page
+///
+/// # Safety
+///
+/// `name` must point to a nul terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn layer_set_visible(name: *const c_char, visible: EM_BOOL) {
+    with_layer(name, |layer| layer.visible = visible != 0);
+}
+
+/// Tints every object in a layer like the selected object, called from the page
+///
+/// # Safety
+///
+/// `name` must point to a nul terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn layer_set_highlighted(name: *const c_char, highlighted: EM_BOOL) {
+    with_layer(name, |layer| layer.highlighted = highlighted != 0);
+}
+
+/// Moves every object in a layer from where it is placed, called from the page
+///
+/// # Safety
+///
+/// `name` must point to a nul terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn layer_set_position(name: *const c_char, x: f64, y: f64, z: f64) {
+    with_layer(name, |layer| {
+        layer.transform.translation = vec3(x as Scalar, y as Scalar, z as Scalar)
+    });
+}
+
+/// Turns every object in a layer by an angle around an axis through the world's origin,
+/// called from the page
+///
+/// Returns whether the axis had a direction.
+///
+/// # Safety
+///
+/// `name` must point to a nul terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn layer_set_rotation(
+    name: *const c_char,
+    x: f64,
+    y: f64,
+    z: f64,
+    degrees: f64,
+) -> EM_BOOL {
+    let axis = vec3(x as Scalar, y as Scalar, z as Scalar);
+    if axis.length() == 0.0 {
+        return 0;
+    }
+    with_layer(name, |layer| {
+        layer.transform.rotation =
+            Quat::from_axis_angle(axis.normalize(), (degrees as Scalar).to_radians())
+    });
+    1
+}
+
 fn get_canvas_size() -> (u32, u32) {
     unsafe {
         let mut width = mem::uninitialized();
@@ -394,10 +1667,6 @@ fn step(ctx: &mut Context) {
     let reset = state % 2;
     state /= 2;
     let animate = state % 2;
-    state /= 2;
-    let delta_y = state % 101;
-    state /= 101;
-    let delta_x = state % 101;
 
     if reset == 1 {
         ctx.reset()
@@ -408,15 +1677,49 @@ fn step(ctx: &mut Context) {
     } else if animate == 1 && !ctx.animate {
         ctx.animate = true;
     }
-    // Modify the camera
-    if delta_x != 0 {
-        ctx.camera = matmul(rotate_y((PI / 3.0) * (delta_x as f32) / 101.0), ctx.camera);
+    // Seconds since the last frame
+    let now = unsafe { emscripten_get_now() };
+    let delta = ((now - ctx.last_frame) / 1000.0) as Scalar;
+    ctx.last_frame = now;
+    // Move the camera by the input since the last frame
+    match ctx.camera_mode {
+        CameraMode::Orbit => ctx.orbit.update(&mut ctx.camera_goal),
+        CameraMode::Fly => ctx.fly.update(&mut ctx.camera_goal),
+        CameraMode::Tour => {
+            ctx.tour.update(delta, &mut ctx.camera_goal);
+            if ctx.tour.finished() {
+                ctx.camera_mode = CameraMode::Static;
+            }
+        }
+        CameraMode::Static => {}
+    }
+    // Glide toward wherever the camera was moved
+    ctx.smoothing
+        .update(delta, &ctx.camera_goal, &mut ctx.camera);
+    ctx.update_zoom(delta);
+    ctx.update_daylight(delta);
+    ctx.camera.update_shake(delta);
+    for light in &mut ctx.lights {
+        light.update(delta);
     }
     // Apply animation
     if ctx.animate {
         ctx.theta -= 0.1;
     }
+    // Swap in the next decoded texture
+    ctx.textures.update();
     ctx.draw();
+    // Show how much of the scene was culled
+    let stats = ctx.draw_stats();
+    let code = "{set_draw_stats($0, $1, $2); return 0;}\0";
+    unsafe {
+        emscripten_asm_const_int(
+            code.as_ptr() as *const _,
+            stats.drawn as c_int,
+            stats.culled as c_int,
+            stats.draw_calls as c_int,
+        );
+    }
 }
 
 extern "C" fn loop_wrapper(ctx: *mut std::os::raw::c_void) {
@@ -440,7 +1743,38 @@ fn main() {
         let mut ctx = Context::new(gl);
         // Create a buffer for GL data
         ctx.init_buffer();
+        CONTEXT = &mut ctx;
         let ptr = &mut ctx as *mut _ as *mut std::os::raw::c_void;
+        emscripten_set_mousemove_callback(
+            "#canvas\0".as_ptr() as *const _,
+            ptr,
+            1,
+            Some(mouse_move_callback),
+        );
+        emscripten_set_click_callback(
+            "#canvas\0".as_ptr() as *const _,
+            ptr,
+            1,
+            Some(click_callback),
+        );
+        emscripten_set_wheel_callback(
+            "#canvas\0".as_ptr() as *const _,
+            ptr,
+            1,
+            Some(wheel_callback),
+        );
+        // The canvas only changes size along with the window
+        emscripten_set_resize_callback(
+            EMSCRIPTEN_EVENT_TARGET_WINDOW,
+            ptr,
+            1,
+            Some(resize_callback),
+        );
+        let (width, height) = get_canvas_size();
+        ctx.resize(width, height);
+        // A null target listens on the whole window
+        emscripten_set_keydown_callback(ptr::null(), ptr, 1, Some(key_callback));
+        emscripten_set_keyup_callback(ptr::null(), ptr, 1, Some(key_callback));
         emscripten_set_main_loop_arg(Some(loop_wrapper), ptr, 0, 1);
     }
 }
@@ -453,61 +1787,60 @@ b"#version 300 es
 layout(location = 0) in vec3 aPosition;
 layout(location = 1) in vec3 aNormal;
 layout(location = 2) in vec2 aTexture;
+layout(location = 3) in vec3 aTangent;
+// Per-instance model matrix, used instead of uMVMatrix when drawing instances
+layout(location = 4) in mat4 aModelMatrix;
 
 // All-vertex uniforms
 // MV matrix
 uniform mat4 uMVMatrix;
+// Inverse transpose of the MV matrix, keeps normals perpendicular under scaling
+uniform mat3 uNormalMatrix;
+// Whether each instance has its own model matrix, which is then combined with uViewMatrix
+uniform bool uInstanced;
+uniform mat4 uViewMatrix;
 // Perspective matrix
 uniform mat4 uPMatrix;
-// Lighting properties
-uniform vec4 uAmbientProduct;
-uniform vec4 uDiffuseProduct;
-uniform vec4 uSpecularProduct;
-// Light position
-uniform vec3 uLightPosition;
-uniform float uShininess;
-
+// Eye coordinates to shadow map coordinates
+uniform mat4 uShadowMatrix;
+",
+LIGHTING_SRC,
+b"
 // Variables sent to fragment shader
-out vec4 vColor;
+out vec3 vPosition;
+out vec3 vNormal;
+out vec3 vTangent;
 out vec2 vTexCoord;
+// Lighting when it is done per vertex
+out vec3 vLighting;
+// Position in the shadow map
+out vec4 vShadowCoord;
 
 void main() {
-    // Convert vertex and light position into camera coordinates
-    vec3 pos = -(uMVMatrix * vec4(aPosition, 1.0)).xyz;
-    // TODO: if this is uniform, why calculate it in each vertex
-    vec3 light = -(uMVMatrix * vec4(uLightPosition, 1.0)).xyz;
-
-    // light source direction
-    vec3 L = normalize(light - pos);
-    
-    // eye - point location  (eye is at origin of eye frame)
-    vec3 E = normalize(-pos); 
-    
-    // Half-way vector
-    vec3 H = normalize(L + E);
+    mat4 mvMatrix = uMVMatrix;
+    mat3 normalMatrix = uNormalMatrix;
+    if (uInstanced) {
+        mvMatrix = uViewMatrix * aModelMatrix;
+        normalMatrix = transpose(inverse(mat3(mvMatrix)));
+    }
+    // Convert vertex position into camera coordinates
+    vPosition = -(mvMatrix * vec4(aPosition, 1.0)).xyz;
 
-    // Transform vertex normal into eye coordinates
-    vec3 N = normalize((uMVMatrix * vec4(aNormal, 1.0)).xyz);
+    vShadowCoord = uShadowMatrix * mvMatrix * vec4(aPosition, 1.0);
 
-    // Compute terms in the illumination equation
-    
-    // ambient is already given
-    
-    float Kd = max(dot(L, N), 0.0);
-    vec4 diffuse = Kd * uDiffuseProduct;
+    // Transform vertex normal and tangent into eye coordinates
+    vNormal = normalMatrix * aNormal;
+    vTangent = (mvMatrix * vec4(aTangent, 0.0)).xyz;
 
-    float Ks = pow(max(dot(N, H), 0.0), uShininess);
-    vec4 specular = Ks * uSpecularProduct;
-    
-    if( dot(L, N) < 0.0 )  specular = vec4(0.0, 0.0, 0.0, 1.0);
-
-    gl_Position = uPMatrix * uMVMatrix * vec4(aPosition, 1.0);
-    
-    vColor = uAmbientProduct + diffuse + specular;
+    // Cheaper lighting, interpolated across each triangle
+    vLighting = vec3(0.0);
+    if (uPerVertexLighting) {
+        vLighting = shade(normalize(vNormal), vPosition, toLinear(uSpecular.rgb), 1.0);
+    }
 
-    vColor.a = 1.0;
+    gl_Position = uPMatrix * mvMatrix * vec4(aPosition, 1.0);
 
-    vTexCoord  = aTexture;
+    vTexCoord = aTexture;
 }
 
 "
@@ -517,17 +1850,285 @@ void main() {
 const FS_SRC: &[&[u8]] = &[
 b"#version 300 es
 
-precision mediump float;
+// Uniforms shared with the vertex shader need its precision
+precision highp float;
+precision highp int;
 
-in vec4 vColor;
+in vec3 vPosition;
+in vec3 vNormal;
+in vec3 vTangent;
 in vec2 vTexCoord;
+in vec3 vLighting;
+in vec4 vShadowCoord;
 
 uniform sampler2D uSampler;
-
+// Tangent space normal map
+uniform sampler2D uNormalMap;
+uniform bool uHasNormalMap;
+// Per-texel specular color
+uniform sampler2D uSpecularMap;
+uniform bool uHasSpecularMap;
+// Light given off by the surface itself
+uniform vec3 uEmissive;
+// Texels less opaque than this are cut out of the surface
+uniform float uAlphaCutoff;
+// Tints the selected object
+uniform bool uHighlighted;
+// Draws the selected object's outline in a flat color
+uniform bool uOutline;
+uniform sampler2D uEmissiveMap;
+uniform bool uHasEmissiveMap;
+// Depth of the scene seen from the light casting shadows
+uniform highp sampler2DShadow uShadowMap;
+uniform bool uHasShadows;
+// Physically based shading instead of Phong
+uniform bool uMetallicRoughness;
+uniform float uMetallic;
+uniform float uRoughness;
+// Sky reflected by physically shaded surfaces
+uniform samplerCube uEnvironment;
+uniform bool uHasEnvironment;
+// Eye coordinates to world coordinates
+uniform mat4 uInverseViewMatrix;
+",
+LIGHTING_SRC,
+TONE_MAP_SRC,
+b"
 out vec4 oFragColor;
 
+// How much of the shadow casting light reaches this fragment
+float shadowed() {
+    vec3 coord = vShadowCoord.xyz / vShadowCoord.w;
+    // Outside the map nothing is known to be in the way
+    if (!uHasShadows || any(lessThan(coord, vec3(0.0))) || any(greaterThan(coord, vec3(1.0)))) {
+        return 1.0;
+    }
+    return texture(uShadowMap, vec3(coord.xy, coord.z - 0.001));
+}
+
+const float PI = 3.14159265;
+
+// Share of microfacets facing along the half-way vector
+float distributionGGX(float NdotH, float roughness) {
+    float a = roughness * roughness;
+    float a2 = a * a;
+    float d = NdotH * NdotH * (a2 - 1.0) + 1.0;
+    return a2 / (PI * d * d);
+}
+
+// Share of microfacets neither shadowed nor hidden by others
+float geometrySmith(float NdotV, float NdotL, float roughness) {
+    float k = (roughness + 1.0) * (roughness + 1.0) / 8.0;
+    return NdotV / (NdotV * (1.0 - k) + k) * NdotL / (NdotL * (1.0 - k) + k);
+}
+
+// Share of light reflected instead of refracted
+vec3 fresnelSchlick(float cosTheta, vec3 F0) {
+    return F0 + (1.0 - F0) * pow(1.0 - cosTheta, 5.0);
+}
+
+// Cook-Torrance version of shade, with albedo as the base color
+vec3 shadePbr(vec3 N, vec3 position, vec3 albedo, float shadow) {
+    vec3 E = normalize(-position);
+    float NdotV = max(dot(N, E), 0.0001);
+    // Metals tint their reflections and have no diffuse light
+    vec3 F0 = mix(vec3(0.04), albedo, uMetallic);
+    float roughness = clamp(uRoughness, 0.05, 1.0);
+
+    vec3 lit = toLinear(uAmbientLight) * toLinear(uAmbient.rgb) * albedo * (1.0 - uMetallic);
+    for (int i = 0; i < MAX_LIGHTS; i++) {
+        if (i >= uNumLights) {
+            break;
+        }
+        vec3 L;
+        float intensity = lightIntensity(i, position, shadow, L);
+        vec3 H = normalize(L + E);
+        float NdotL = max(dot(N, L), 0.0);
+
+        vec3 F = fresnelSchlick(max(dot(H, E), 0.0), F0);
+        vec3 specular = distributionGGX(max(dot(N, H), 0.0), roughness)
+            * geometrySmith(NdotV, NdotL, roughness) * F / (4.0 * NdotV * max(NdotL, 0.0001));
+        vec3 kD = (1.0 - F) * (1.0 - uMetallic);
+        // Light colors are the brightness of a white surface facing them, so Lambert's 1/PI
+        // is folded into them
+        lit += intensity * uLights[i].color * (kD * albedo + PI * specular) * NdotL;
+    }
+
+    if (uHasEnvironment) {
+        // Both vectors are negated in eye coordinates, which reflect doesn't mind
+        vec3 R = (uInverseViewMatrix * vec4(reflect(E, N), 0.0)).xyz;
+        vec3 F = F0 + (max(vec3(1.0 - roughness), F0) - F0) * pow(1.0 - NdotV, 5.0);
+        // Rough surfaces scatter the sky instead of mirroring it
+        lit += texture(uEnvironment, R).rgb * F * (1.0 - roughness) * (1.0 - roughness);
+    }
+    return lit;
+}
+
 void main() {
-    //oFragColor = vColor;
-    oFragColor = vColor * texture(uSampler, vTexCoord);
+    if (uOutline) {
+        oFragColor = vec4(1.0, 0.6, 0.1, 1.0);
+        return;
+    }
+    vec4 texel = texture(uSampler, vTexCoord);
+    if (texel.a < uAlphaCutoff) {
+        discard;
+    }
+    vec3 color;
+    if (uPerVertexLighting) {
+        // Physically shaded surfaces fall back to Phong
+        color = vLighting * texel.rgb;
+    } else {
+        vec3 N = normalize(vNormal);
+        // Perturb the normal by the normal map
+        if (uHasNormalMap) {
+            vec3 T = normalize(vTangent - dot(vTangent, N) * N);
+            vec3 B = cross(N, T);
+            vec3 mapped = texture(uNormalMap, vTexCoord).xyz * 2.0 - 1.0;
+            N = normalize(mat3(T, B, N) * mapped);
+        }
+        vec3 specularReflectance = toLinear(uSpecular.rgb);
+        // Shiny and matte regions of the same mesh
+        if (uHasSpecularMap) {
+            specularReflectance *= texture(uSpecularMap, vTexCoord).rgb;
+        }
+        if (uMetallicRoughness) {
+            // The texture tints the base color, not the reflections
+            vec3 albedo = toLinear(uDiffuse.rgb) * texel.rgb;
+            color = shadePbr(N, vPosition, albedo, shadowed());
+        } else {
+            color = shade(N, vPosition, specularReflectance, shadowed()) * texel.rgb;
+        }
+    }
+
+    oFragColor = vec4(color, texel.a);
+
+    // Glow is added after lighting so it doesn't depend on it
+    vec3 emissive = toLinear(uEmissive);
+    if (uHasEmissiveMap) {
+        emissive *= texture(uEmissiveMap, vTexCoord).rgb;
+    }
+    oFragColor.rgb += emissive;
+    if (uHighlighted) {
+        oFragColor.rgb = mix(oFragColor.rgb, vec3(1.0, 0.6, 0.1), 0.35);
+    }
+
+    oFragColor.rgb = toneMap(oFragColor.rgb);
 }
 "];
+
+/// Lighting used by both shaders, so it can be done per vertex or per fragment
+const LIGHTING_SRC: &[u8] = b"
+// Material reflectances, multiplied by the lights here
+uniform vec4 uAmbient;
+uniform vec4 uDiffuse;
+uniform vec4 uSpecular;
+uniform float uShininess;
+// Highlights from the half-way vector instead of the reflected light
+uniform bool uBlinnPhong;
+// Light reaching every surface from every direction
+uniform vec3 uAmbientLight;
+
+// Light types
+const int DIRECTIONAL_LIGHT = 0;
+const int POINT_LIGHT = 1;
+const int SPOT_LIGHT = 2;
+const int MAX_LIGHTS = 4;
+
+// A light in eye coordinates
+struct Light {
+    int kind;
+    vec3 position;
+    // Way a directional or spot light shines
+    vec3 direction;
+    vec3 color;
+    // Scales the color, may go above 1
+    float intensity;
+    // Cosine of the angle between the middle and edge of a spot light's cone
+    float cosCutoff;
+    float exponent;
+    // Constant, linear and quadratic falloff with distance
+    vec3 attenuation;
+};
+uniform Light uLights[MAX_LIGHTS];
+uniform int uNumLights;
+
+// Light in the vertex shader instead of for every fragment
+uniform bool uPerVertexLighting;
+// Index of the light casting shadows, -1 if none does
+uniform int uShadowLight;
+
+// Colors are given in sRGB, lighting happens in linear space
+vec3 toLinear(vec3 color) {
+    return pow(color, vec3(2.2));
+}
+
+// Brightness of a light at a point, with the point negated like vPosition
+// L is set to the light's direction, negated the same way
+// shadow is how much of the shadow casting light reaches the point
+float lightIntensity(int i, vec3 position, float shadow, out vec3 L) {
+    Light light = uLights[i];
+    float intensity = light.intensity;
+    if (light.kind == DIRECTIONAL_LIGHT) {
+        L = normalize(light.direction);
+    } else {
+        vec3 toPoint = -light.position - position;
+        L = normalize(toPoint);
+        // Nearby lights are brighter
+        float d = length(toPoint);
+        intensity /= dot(light.attenuation, vec3(1.0, d, d * d));
+    }
+    // Spot lights fade towards the edge of their cone and stop past it
+    if (light.kind == SPOT_LIGHT) {
+        float spot = dot(L, normalize(light.direction));
+        intensity *= spot < light.cosCutoff ? 0.0 : pow(spot, light.exponent);
+    }
+
+    if (i == uShadowLight) {
+        intensity *= shadow;
+    }
+    return intensity;
+}
+
+// Light reaching a point from every light, with the point negated like vPosition
+// shadow is how much of the shadow casting light reaches the point
+// The light colors are multiplied by the material here, specularReflectance may be mapped
+vec3 shade(vec3 N, vec3 position, vec3 specularReflectance, float shadow) {
+    // eye - point location  (eye is at origin of eye frame)
+    vec3 E = normalize(-position);
+
+    // Compute terms in the illumination equation
+
+    // ambient is the same whichever lights there are
+    vec3 lit = toLinear(uAmbientLight) * toLinear(uAmbient.rgb);
+    vec3 diffuseReflectance = toLinear(uDiffuse.rgb);
+
+    for (int i = 0; i < MAX_LIGHTS; i++) {
+        if (i >= uNumLights) {
+            break;
+        }
+        // light source direction
+        vec3 L;
+        float intensity = lightIntensity(i, position, shadow, L);
+
+        float Kd = max(dot(L, N), 0.0);
+        vec3 diffuse = Kd * diffuseReflectance;
+
+        float Ks;
+        if (uBlinnPhong) {
+            // Half-way vector
+            vec3 H = normalize(L + E);
+            Ks = pow(max(dot(N, H), 0.0), uShininess);
+        } else {
+            // L and E are both negated, so the reflection is compared against -E
+            vec3 R = reflect(L, N);
+            Ks = pow(max(dot(R, -E), 0.0), uShininess);
+        }
+        vec3 specular = Ks * specularReflectance;
+        if( dot(L, N) < 0.0 )  specular = vec3(0.0);
+
+        // Each light's color times what the material reflects of it
+        lit += intensity * uLights[i].color * (diffuse + specular);
+    }
+    return lit;
+}
+";
@@ -0,0 +1,149 @@
+use matrix::{vec3, Scalar, Vec3};
+
+/// Which lighting equation a surface is shaded with
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum ShadingModel {
+    /// Phong with the ambient, diffuse and specular colors, which the classic material tables
+    /// are given for
+    Phong,
+    /// Phong with highlights from the half-way vector, which spread more for the same shininess
+    BlinnPhong,
+    /// Cook-Torrance with the diffuse color as the base color
+    ///
+    /// Metallic and roughness both go from 0 to 1. The ambient color still lights the surface,
+    /// and the sky is reflected when there is one.
+    MetallicRoughness { metallic: Scalar, roughness: Scalar },
+}
+
+/// How a surface reflects light, in sRGB
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Material {
+    pub ambient: Vec3,
+    pub diffuse: Vec3,
+    pub specular: Vec3,
+    /// Higher values give smaller, sharper highlights
+    pub shininess: Scalar,
+    /// Light given off regardless of the scene lights
+    pub emissive: Vec3,
+    pub shading: ShadingModel,
+    /// Texels less opaque than this aren't drawn at all, cutting shapes such as leaves out of
+    /// flat faces without blending
+    pub alpha_cutoff: Scalar,
+}
+
+impl Material {
+    pub fn new(ambient: Vec3, diffuse: Vec3, specular: Vec3, shininess: Scalar) -> Material {
+        Material {
+            ambient,
+            diffuse,
+            specular,
+            shininess,
+            emissive: Vec3::origin(),
+            shading: ShadingModel::BlinnPhong,
+            alpha_cutoff: 0.0,
+        }
+    }
+
+    /// Makes the surface glow with a color
+    pub fn with_emissive(mut self, emissive: Vec3) -> Material {
+        self.emissive = emissive;
+        self
+    }
+
+    /// Leaves out the parts of the surface where the texture is less opaque than `cutoff`
+    pub fn with_alpha_cutoff(mut self, cutoff: Scalar) -> Material {
+        self.alpha_cutoff = cutoff;
+        self
+    }
+
+    /// Shades the surface physically, ignoring the specular color and shininess
+    pub fn with_metallic_roughness(mut self, metallic: Scalar, roughness: Scalar) -> Material {
+        self.shading = ShadingModel::MetallicRoughness {
+            metallic,
+            roughness,
+        };
+        self
+    }
+
+    /// Looks up a preset by the name of its function, as scene files give them
+    pub fn preset(name: &str) -> Option<Material> {
+        match name {
+            "wood" => Some(Material::wood()),
+            "bronze" => Some(Material::bronze()),
+            "pearl" => Some(Material::pearl()),
+            "gold" => Some(Material::gold()),
+            "glass" => Some(Material::glass()),
+            "red" => Some(Material::red()),
+            _ => None,
+        }
+    }
+
+    /// Warm brown with no highlights
+    pub fn wood() -> Material {
+        Material::new(
+            vec3(0.396, 0.263, 0.129),
+            vec3(0.64, 0.64, 0.64),
+            Vec3::origin(),
+            96.078_43,
+        )
+    }
+
+    /// From the classic material table, so shaded with Phong
+    pub fn bronze() -> Material {
+        Material {
+            shading: ShadingModel::Phong,
+            ..Material::new(
+                vec3(0.2125, 0.1275, 0.054),
+                vec3(0.714, 0.4284, 0.18144),
+                vec3(0.393_548, 0.271_906, 0.166_721),
+                0.2 * 128.0,
+            )
+        }
+    }
+
+    /// From the classic material table, so shaded with Phong
+    pub fn pearl() -> Material {
+        Material {
+            shading: ShadingModel::Phong,
+            ..Material::new(
+                vec3(0.25, 0.20725, 0.20725),
+                vec3(1.0, 0.829, 0.829),
+                vec3(0.296_648, 0.296_648, 0.296_648),
+                0.088 * 128.0,
+            )
+        }
+    }
+
+    /// Gold from the classic material table, brightly lit so textures show through
+    pub fn gold() -> Material {
+        Material {
+            shading: ShadingModel::Phong,
+            ..Material::new(
+                vec3(0.8, 0.8, 0.8),
+                vec3(0.75164, 0.60648, 0.22648),
+                vec3(0.628_281, 0.555_802, 0.366_065),
+                0.4 * 128.0,
+            )
+        }
+    }
+
+    /// Frosted glass for light bulbs, meant to be given a glow
+    pub fn glass() -> Material {
+        Material::new(
+            vec3(0.3, 0.3, 0.3),
+            vec3(0.5, 0.5, 0.5),
+            vec3(0.9, 0.9, 0.9),
+            64.0,
+        )
+    }
+
+    /// Matte red
+    pub fn red() -> Material {
+        Material::new(
+            vec3(0.6, 0.0, 0.0),
+            vec3(0.64, 0.64, 0.64),
+            Vec3::origin(),
+            40.078_43,
+        )
+    }
+}
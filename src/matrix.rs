@@ -1,35 +1,97 @@
-#[derive(Copy, Clone, Debug)]
+/// Precision of the math types, f64 with the `f64` feature
+#[cfg(not(feature = "f64"))]
+pub type Scalar = f32;
+#[cfg(feature = "f64")]
+pub type Scalar = f64;
+
+#[cfg(not(feature = "f64"))]
+pub use std::f32::consts;
+#[cfg(feature = "f64")]
+pub use std::f64::consts;
+
+/// Converts a scalar to the precision GL takes
+#[cfg(not(feature = "f64"))]
+pub fn gl_float(x: Scalar) -> f32 {
+    x
+}
+#[cfg(feature = "f64")]
+pub fn gl_float(x: Scalar) -> f32 {
+    x as f32
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
 pub struct Vec3 {
-    pub x: f32,
-    pub y: f32,
-    pub z: f32,
+    pub x: Scalar,
+    pub y: Scalar,
+    pub z: Scalar,
 }
-impl<'a> std::ops::Add<Vec3> for &'a Vec3 {
-    type Output = Vec3;
+/// Implements a component-wise operator for every mix of owned and borrowed operands
+macro_rules! vec3_op {
+    ($trait:ident, $method:ident, $op:tt) => {
+        impl std::ops::$trait<Vec3> for Vec3 {
+            type Output = Vec3;
 
-    fn add(self, other: Vec3) -> Self::Output {
-        Vec3 {
-            x: self.x + other.x,
-            y: self.y + other.y,
-            z: self.z + other.z,
+            fn $method(self, other: Vec3) -> Self::Output {
+                Vec3 {
+                    x: self.x $op other.x,
+                    y: self.y $op other.y,
+                    z: self.z $op other.z,
+                }
+            }
+        }
+        impl<'a> std::ops::$trait<&'a Vec3> for Vec3 {
+            type Output = Vec3;
+
+            fn $method(self, other: &'a Vec3) -> Self::Output {
+                self $op *other
+            }
+        }
+        impl<'a> std::ops::$trait<Vec3> for &'a Vec3 {
+            type Output = Vec3;
+
+            fn $method(self, other: Vec3) -> Self::Output {
+                *self $op other
+            }
+        }
+        impl<'a, 'b> std::ops::$trait<&'b Vec3> for &'a Vec3 {
+            type Output = Vec3;
+
+            fn $method(self, other: &'b Vec3) -> Self::Output {
+                *self $op *other
+            }
         }
+    };
+}
+vec3_op!(Add, add, +);
+vec3_op!(Sub, sub, -);
+
+impl std::ops::AddAssign for Vec3 {
+    fn add_assign(&mut self, other: Vec3) {
+        *self = *self + other;
+    }
+}
+impl std::ops::SubAssign for Vec3 {
+    fn sub_assign(&mut self, other: Vec3) {
+        *self = *self - other;
     }
 }
-impl<'a> std::ops::Sub<Vec3> for &'a Vec3 {
-    type Output = Vec3;
 
-    fn sub(self, other: Vec3) -> Self::Output {
-        Vec3 {
-            x: self.x - other.x,
-            y: self.y - other.y,
-            z: self.z - other.z,
-        }
+impl std::ops::Neg for Vec3 {
+    type Output = Vec3;
+    fn neg(self) -> Self::Output {
+        self * -1.0
+    }
+}
+impl<'a> std::ops::Neg for &'a Vec3 {
+    type Output = Vec3;
+    fn neg(self) -> Self::Output {
+        *self * -1.0
     }
 }
 
-impl std::ops::Mul<f32> for Vec3 {
+impl std::ops::Mul<Scalar> for Vec3 {
     type Output = Vec3;
-    fn mul(self, other: f32) -> Self::Output {
+    fn mul(self, other: Scalar) -> Self::Output {
         Vec3 {
             x: other * self.x,
             y: other * self.y,
@@ -37,8 +99,14 @@ impl std::ops::Mul<f32> for Vec3 {
         }
     }
 }
+impl<'a> std::ops::Mul<Scalar> for &'a Vec3 {
+    type Output = Vec3;
+    fn mul(self, other: Scalar) -> Self::Output {
+        *self * other
+    }
+}
 
-pub fn vec3(x: f32, y: f32, z: f32) -> Vec3 {
+pub fn vec3(x: Scalar, y: Scalar, z: Scalar) -> Vec3 {
     Vec3 { x, y, z }
 }
 impl Vec3 {
@@ -57,12 +125,75 @@ impl Vec3 {
             z: self.x * other.y - self.y * other.x,
         }
     }
-    pub fn dot(&self, other: &Vec3) -> f32 {
+    pub fn dot(&self, other: &Vec3) -> Scalar {
         self.x * other.x + self.y * other.y + self.z * other.z
     }
 
+    pub fn length_squared(&self) -> Scalar {
+        self.dot(self)
+    }
+
+    pub fn length(&self) -> Scalar {
+        self.length_squared().sqrt()
+    }
+
+    pub fn distance(&self, other: &Vec3) -> Scalar {
+        (self - other).length()
+    }
+
+    /// Point at `radius` from the origin, `theta` radians around the y axis
+    /// from +z towards +x and `phi` radians down from +y
+    pub fn from_spherical(radius: Scalar, theta: Scalar, phi: Scalar) -> Vec3 {
+        let (sin_theta, cos_theta) = theta.sin_cos();
+        let (sin_phi, cos_phi) = phi.sin_cos();
+        vec3(
+            radius * sin_phi * sin_theta,
+            radius * cos_phi,
+            radius * sin_phi * cos_theta,
+        )
+    }
+
+    /// Radius, theta and phi of the point, the inverse of `from_spherical`
+    pub fn to_spherical(self) -> (Scalar, Scalar, Scalar) {
+        let radius = self.length();
+        if radius == 0.0 {
+            return (0.0, 0.0, 0.0);
+        }
+        let theta = self.x.atan2(self.z);
+        let phi = (self.y / radius).clamp(-1.0, 1.0).acos();
+        (radius, theta, phi)
+    }
+
+    /// Linearly interpolates from a to b, t = 0 gives a
+    pub fn lerp(a: Vec3, b: Vec3, t: Scalar) -> Vec3 {
+        a + (b - a) * t
+    }
+
+    /// Component-wise minimum
+    pub fn min(&self, other: &Vec3) -> Vec3 {
+        vec3(
+            self.x.min(other.x),
+            self.y.min(other.y),
+            self.z.min(other.z),
+        )
+    }
+
+    /// Component-wise maximum
+    pub fn max(&self, other: &Vec3) -> Vec3 {
+        vec3(
+            self.x.max(other.x),
+            self.y.max(other.y),
+            self.z.max(other.z),
+        )
+    }
+
+    /// Clamps each component between the components of min and max
+    pub fn clamp(&self, min: &Vec3, max: &Vec3) -> Vec3 {
+        self.max(min).min(max)
+    }
+
     pub fn normalize(self) -> Vec3 {
-        let sum = (self.x * self.x + self.y * self.y + self.z * self.z).sqrt();
+        let sum = self.length();
         Vec3 {
             x: self.x / sum,
             y: self.y / sum,
@@ -70,7 +201,7 @@ impl Vec3 {
         }
     }
 
-    pub fn scale(&self, x: f32, y: f32, z: f32) -> Vec3 {
+    pub fn scale(&self, x: Scalar, y: Scalar, z: Scalar) -> Vec3 {
         Vec3 {
             x: self.x * x,
             y: self.y * y,
@@ -78,7 +209,7 @@ impl Vec3 {
         }
     }
 
-    pub fn rotate_y(&self, theta: f32) -> Vec3 {
+    pub fn rotate_y(&self, theta: Scalar) -> Vec3 {
         Vec3 {
             x: self.x * theta.cos() + self.z * theta.sin(),
             y: self.y,
@@ -89,11 +220,11 @@ impl Vec3 {
 
 #[derive(Copy, Clone, Debug)]
 pub struct Vec2 {
-    pub x: f32,
-    pub y: f32,
+    pub x: Scalar,
+    pub y: Scalar,
 }
 
-pub fn vec2(x: f32, y: f32) -> Vec2 {
+pub fn vec2(x: Scalar, y: Scalar) -> Vec2 {
     Vec2 { x, y }
 }
 
@@ -103,25 +234,175 @@ impl Vec2 {
     }
 }
 
-pub type Matrix44 = [f32; 16];
+/// 4x4 matrix stored row by row, applied to row vectors
+///
+/// `a * b` applies `a` first, then `b`.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Mat4([Scalar; 16]);
+
+impl Mat4 {
+    pub fn get(&self, row: usize, col: usize) -> Scalar {
+        self.0[row * 4 + col]
+    }
+
+    pub fn set(&mut self, row: usize, col: usize, x: Scalar) {
+        self.0[row * 4 + col] = x
+    }
+
+    /// Elements in the order GL uniforms expect
+    pub fn to_gl(self) -> [f32; 16] {
+        let mut elements = [0.0; 16];
+        for (element, x) in elements.iter_mut().zip(self.0.iter()) {
+            *element = gl_float(*x);
+        }
+        elements
+    }
+
+    pub fn transpose(&self) -> Mat4 {
+        let mut matrix = zeros();
+        for row in 0..4 {
+            for col in 0..4 {
+                matrix.set(col, row, self.get(row, col));
+            }
+        }
+        matrix
+    }
+
+    /// Inverse of the matrix, or None if it is singular
+    pub fn inverse(&self) -> Option<Mat4> {
+        let m = &self.0;
+        // Cofactors of the first column give the determinant
+        let mut inv: [Scalar; 16] = [0.0; 16];
+        inv[0] = m[5] * m[10] * m[15] - m[5] * m[11] * m[14] - m[9] * m[6] * m[15]
+            + m[9] * m[7] * m[14]
+            + m[13] * m[6] * m[11]
+            - m[13] * m[7] * m[10];
+        inv[4] = -m[4] * m[10] * m[15] + m[4] * m[11] * m[14] + m[8] * m[6] * m[15]
+            - m[8] * m[7] * m[14]
+            - m[12] * m[6] * m[11]
+            + m[12] * m[7] * m[10];
+        inv[8] = m[4] * m[9] * m[15] - m[4] * m[11] * m[13] - m[8] * m[5] * m[15]
+            + m[8] * m[7] * m[13]
+            + m[12] * m[5] * m[11]
+            - m[12] * m[7] * m[9];
+        inv[12] = -m[4] * m[9] * m[14] + m[4] * m[10] * m[13] + m[8] * m[5] * m[14]
+            - m[8] * m[6] * m[13]
+            - m[12] * m[5] * m[10]
+            + m[12] * m[6] * m[9];
+        let det = m[0] * inv[0] + m[1] * inv[4] + m[2] * inv[8] + m[3] * inv[12];
+        if det.abs() < Scalar::EPSILON {
+            return None;
+        }
+        inv[1] = -m[1] * m[10] * m[15] + m[1] * m[11] * m[14] + m[9] * m[2] * m[15]
+            - m[9] * m[3] * m[14]
+            - m[13] * m[2] * m[11]
+            + m[13] * m[3] * m[10];
+        inv[5] = m[0] * m[10] * m[15] - m[0] * m[11] * m[14] - m[8] * m[2] * m[15]
+            + m[8] * m[3] * m[14]
+            + m[12] * m[2] * m[11]
+            - m[12] * m[3] * m[10];
+        inv[9] = -m[0] * m[9] * m[15] + m[0] * m[11] * m[13] + m[8] * m[1] * m[15]
+            - m[8] * m[3] * m[13]
+            - m[12] * m[1] * m[11]
+            + m[12] * m[3] * m[9];
+        inv[13] = m[0] * m[9] * m[14] - m[0] * m[10] * m[13] - m[8] * m[1] * m[14]
+            + m[8] * m[2] * m[13]
+            + m[12] * m[1] * m[10]
+            - m[12] * m[2] * m[9];
+        inv[2] = m[1] * m[6] * m[15] - m[1] * m[7] * m[14] - m[5] * m[2] * m[15]
+            + m[5] * m[3] * m[14]
+            + m[13] * m[2] * m[7]
+            - m[13] * m[3] * m[6];
+        inv[6] = -m[0] * m[6] * m[15] + m[0] * m[7] * m[14] + m[4] * m[2] * m[15]
+            - m[4] * m[3] * m[14]
+            - m[12] * m[2] * m[7]
+            + m[12] * m[3] * m[6];
+        inv[10] = m[0] * m[5] * m[15] - m[0] * m[7] * m[13] - m[4] * m[1] * m[15]
+            + m[4] * m[3] * m[13]
+            + m[12] * m[1] * m[7]
+            - m[12] * m[3] * m[5];
+        inv[14] = -m[0] * m[5] * m[14] + m[0] * m[6] * m[13] + m[4] * m[1] * m[14]
+            - m[4] * m[2] * m[13]
+            - m[12] * m[1] * m[6]
+            + m[12] * m[2] * m[5];
+        inv[3] = -m[1] * m[6] * m[11] + m[1] * m[7] * m[10] + m[5] * m[2] * m[11]
+            - m[5] * m[3] * m[10]
+            - m[9] * m[2] * m[7]
+            + m[9] * m[3] * m[6];
+        inv[7] = m[0] * m[6] * m[11] - m[0] * m[7] * m[10] - m[4] * m[2] * m[11]
+            + m[4] * m[3] * m[10]
+            + m[8] * m[2] * m[7]
+            - m[8] * m[3] * m[6];
+        inv[11] = -m[0] * m[5] * m[11] + m[0] * m[7] * m[9] + m[4] * m[1] * m[11]
+            - m[4] * m[3] * m[9]
+            - m[8] * m[1] * m[7]
+            + m[8] * m[3] * m[5];
+        inv[15] = m[0] * m[5] * m[10] - m[0] * m[6] * m[9] - m[4] * m[1] * m[10]
+            + m[4] * m[2] * m[9]
+            + m[8] * m[1] * m[6]
+            - m[8] * m[2] * m[5];
+        for x in inv.iter_mut() {
+            *x /= det;
+        }
+        Some(Mat4(inv))
+    }
+
+    /// Inverse transpose of the upper 3x3, for transforming normals
+    pub fn normal_matrix(&self) -> [f32; 9] {
+        // A singular matrix flattens everything anyway, so fall back to it
+        let matrix = self.inverse().map_or(*self, |inverse| inverse.transpose());
+        [
+            gl_float(matrix.get(0, 0)),
+            gl_float(matrix.get(0, 1)),
+            gl_float(matrix.get(0, 2)),
+            gl_float(matrix.get(1, 0)),
+            gl_float(matrix.get(1, 1)),
+            gl_float(matrix.get(1, 2)),
+            gl_float(matrix.get(2, 0)),
+            gl_float(matrix.get(2, 1)),
+            gl_float(matrix.get(2, 2)),
+        ]
+    }
+}
+
+impl From<[Scalar; 16]> for Mat4 {
+    fn from(elements: [Scalar; 16]) -> Mat4 {
+        Mat4(elements)
+    }
+}
 
-trait Matrix {
-    fn get(&self, row: usize, col: usize) -> f32;
-    fn set(&mut self, row: usize, col: usize, x: f32);
+impl std::ops::Index<usize> for Mat4 {
+    type Output = Scalar;
+    fn index(&self, index: usize) -> &Scalar {
+        &self.0[index]
+    }
 }
-impl Matrix for Matrix44 {
-    fn get(&self, row: usize, col: usize) -> f32 {
-        self[row * 4 + col]
+impl std::ops::IndexMut<usize> for Mat4 {
+    fn index_mut(&mut self, index: usize) -> &mut Scalar {
+        &mut self.0[index]
     }
-    fn set(&mut self, row: usize, col: usize, x: f32) {
-        self[row * 4 + col] = x
+}
+
+impl std::ops::Mul for Mat4 {
+    type Output = Mat4;
+    fn mul(self, other: Mat4) -> Mat4 {
+        let mut c = zeros();
+        for i in 0..4 {
+            for j in 0..4 {
+                for k in 0..4 {
+                    c.0[i * 4 + j] += self.get(i, k) * other.get(k, j);
+                }
+            }
+        }
+        c
     }
 }
-pub fn zeros() -> Matrix44 {
-    [0f32; 16]
+
+pub fn zeros() -> Mat4 {
+    Mat4([0.0; 16])
 }
 
-pub fn identity() -> Matrix44 {
+pub fn identity() -> Mat4 {
     let mut matrix = zeros();
     matrix.set(0, 0, 1.0);
     matrix.set(1, 1, 1.0);
@@ -129,7 +410,7 @@ pub fn identity() -> Matrix44 {
     matrix.set(3, 3, 1.0);
     matrix
 }
-pub fn scale(s_x: f32, s_y: f32, s_z: f32) -> Matrix44 {
+pub fn scale(s_x: Scalar, s_y: Scalar, s_z: Scalar) -> Mat4 {
     let mut matrix = zeros();
     matrix.set(0, 0, s_x);
     matrix.set(1, 1, s_y);
@@ -138,7 +419,7 @@ pub fn scale(s_x: f32, s_y: f32, s_z: f32) -> Matrix44 {
     matrix
 }
 
-pub fn rotate_x(theta: f32) -> Matrix44 {
+pub fn rotate_x(theta: Scalar) -> Mat4 {
     let mut matrix = identity();
     matrix[5] = theta.cos();
     matrix[6] = theta.sin();
@@ -147,7 +428,7 @@ pub fn rotate_x(theta: f32) -> Matrix44 {
     matrix
 }
 
-pub fn rotate_y(theta: f32) -> Matrix44 {
+pub fn rotate_y(theta: Scalar) -> Mat4 {
     let mut matrix = identity();
     matrix[0] = theta.cos();
     matrix[2] = theta.sin();
@@ -156,7 +437,7 @@ pub fn rotate_y(theta: f32) -> Matrix44 {
     matrix
 }
 
-pub fn translate(x: f32, y: f32, z: f32) -> Matrix44 {
+pub fn translate(x: Scalar, y: Scalar, z: Scalar) -> Mat4 {
     let mut matrix = identity();
     matrix.set(3, 0, x);
     matrix.set(3, 1, y);
@@ -164,12 +445,12 @@ pub fn translate(x: f32, y: f32, z: f32) -> Matrix44 {
     matrix
 }
 
-pub fn viewing_matrix(eye: Vec3, up: Vec3, target: Vec3) -> Matrix44 {
-    let v = (&target - eye).normalize();
+pub fn viewing_matrix(eye: Vec3, up: Vec3, target: Vec3) -> Mat4 {
+    let v = (target - eye).normalize();
     let n = v.cross(up).normalize();
     let u = n.cross(v).normalize();
 
-    let v = v * -1.0;
+    let v = -v;
 
     let mut matrix = identity();
 
@@ -190,50 +471,735 @@ pub fn viewing_matrix(eye: Vec3, up: Vec3, target: Vec3) -> Matrix44 {
     matrix
 }
 
-pub fn orthogonal_matrix(
-    left: f32,
-    right: f32,
-    top: f32,
-    bottom: f32,
-    near: f32,
-    far: f32,
-) -> Matrix44 {
-    // Start with zeroes
-    let mut matrix = zeros();
+/// Projection of the camera's view onto the canvas
+#[derive(Copy, Clone, Debug)]
+pub enum Projection {
+    /// `fov_y` is the full vertical field of view in radians
+    Perspective {
+        fov_y: Scalar,
+        aspect: Scalar,
+        near: Scalar,
+        far: Scalar,
+    },
+    Ortho {
+        left: Scalar,
+        right: Scalar,
+        bottom: Scalar,
+        top: Scalar,
+        near: Scalar,
+        far: Scalar,
+    },
+}
 
-    // Calculate some frequently used values
-    let w = right - left;
-    let h = top - bottom;
-    let d = far - near;
-
-    matrix.set(0, 0, 2.0 / w);
-    matrix.set(1, 1, 2.0 / h);
-    matrix.set(2, 2, -2.0 / d);
-    matrix.set(3, 0, -(right + left) / w);
-    matrix.set(3, 1, -(top + bottom) / h);
-    matrix.set(3, 2, -(far + near) / d);
-    matrix.set(3, 3, 1.0);
-    matrix
+impl Projection {
+    pub fn perspective(fov_y: Scalar, aspect: Scalar, near: Scalar, far: Scalar) -> Projection {
+        Projection::Perspective {
+            fov_y,
+            aspect,
+            near,
+            far,
+        }
+    }
+
+    pub fn ortho(
+        left: Scalar,
+        right: Scalar,
+        bottom: Scalar,
+        top: Scalar,
+        near: Scalar,
+        far: Scalar,
+    ) -> Projection {
+        Projection::Ortho {
+            left,
+            right,
+            bottom,
+            top,
+            near,
+            far,
+        }
+    }
+
+    /// Changes the width to match a new aspect ratio, keeping the vertical extent
+    pub fn set_aspect(&mut self, new_aspect: Scalar) {
+        match self {
+            Projection::Perspective { aspect, .. } => *aspect = new_aspect,
+            Projection::Ortho {
+                left,
+                right,
+                bottom,
+                top,
+                ..
+            } => {
+                let center = (*left + *right) / 2.0;
+                let half_width = (*top - *bottom) * new_aspect / 2.0;
+                *left = center - half_width;
+                *right = center + half_width;
+            }
+        }
+    }
+
+    /// Scales an orthographic volume around its center, keeping its height
+    /// between `min_height` and `max_height`
+    ///
+    /// Perspective projections zoom by moving the camera instead, so they are left alone.
+    pub fn zoom(&mut self, factor: Scalar, min_height: Scalar, max_height: Scalar) {
+        if let Projection::Ortho {
+            left,
+            right,
+            bottom,
+            top,
+            ..
+        } = self
+        {
+            let height = *top - *bottom;
+            let factor = (height * factor).clamp(min_height, max_height) / height;
+            let (center_x, center_y) = ((*left + *right) / 2.0, (*bottom + *top) / 2.0);
+            *left = center_x + (*left - center_x) * factor;
+            *right = center_x + (*right - center_x) * factor;
+            *bottom = center_y + (*bottom - center_y) * factor;
+            *top = center_y + (*top - center_y) * factor;
+        }
+    }
+
+    pub fn matrix(&self) -> Mat4 {
+        match *self {
+            Projection::Perspective {
+                fov_y,
+                aspect,
+                near,
+                far,
+            } => {
+                let top = near * (fov_y / 2.0).tan();
+                let right = top * aspect;
+                frustum(-right, right, -top, top, near, far)
+            }
+            Projection::Ortho {
+                left,
+                right,
+                bottom,
+                top,
+                near,
+                far,
+            } => {
+                let mut matrix = zeros();
+                matrix.set(0, 0, 2.0 / (right - left));
+                matrix.set(1, 1, 2.0 / (top - bottom));
+                matrix.set(2, 2, -2.0 / (far - near));
+                matrix.set(3, 0, -(right + left) / (right - left));
+                matrix.set(3, 1, -(top + bottom) / (top - bottom));
+                matrix.set(3, 2, -(far + near) / (far - near));
+                matrix.set(3, 3, 1.0);
+                matrix
+            }
+        }
+    }
 }
 
-pub fn perspective_matrix(fov: f32, aspect: f32, near: f32, far: f32) -> Matrix44 {
+/// Perspective projection of the box between the given planes at the near plane
+pub fn frustum(
+    left: Scalar,
+    right: Scalar,
+    bottom: Scalar,
+    top: Scalar,
+    near: Scalar,
+    far: Scalar,
+) -> Mat4 {
     let mut matrix = zeros();
-    matrix[0] = 1.0 / fov.tan() / aspect;
-    matrix[5] = 1.0 / fov.tan();
-    matrix[10] = -(far + near) / (far - near);
-    matrix[11] = -1.0;
-    matrix[14] = -2.0 * far * near / (far - near);
+    matrix.set(0, 0, 2.0 * near / (right - left));
+    matrix.set(1, 1, 2.0 * near / (top - bottom));
+    matrix.set(2, 0, (right + left) / (right - left));
+    matrix.set(2, 1, (top + bottom) / (top - bottom));
+    matrix.set(2, 2, -(far + near) / (far - near));
+    matrix.set(2, 3, -1.0);
+    matrix.set(3, 2, -2.0 * far * near / (far - near));
     matrix
 }
 
-pub fn matmul(a: Matrix44, b: Matrix44) -> Matrix44 {
-    let mut c = zeros();
-    for i in 0..4 {
-        for j in 0..4 {
-            for k in 0..4 {
-                c[i * 4 + j] += a.get(i, k) * b.get(k, j);
+/// Which side of a plane a point is on
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Side {
+    /// The side the normal points to
+    Front,
+    Back,
+    On,
+}
+
+/// Plane of points where `normal.dot(point) + d` is zero
+#[derive(Copy, Clone, Debug)]
+pub struct Plane {
+    pub normal: Vec3,
+    pub d: Scalar,
+}
+
+impl Plane {
+    /// Plane from the coefficients of `a * x + b * y + c * z + d = 0`
+    pub fn new(a: Scalar, b: Scalar, c: Scalar, d: Scalar) -> Plane {
+        // A unit normal makes the plane equation give real distances
+        let length = vec3(a, b, c).length();
+        Plane {
+            normal: vec3(a / length, b / length, c / length),
+            d: d / length,
+        }
+    }
+
+    /// Plane through a point, facing along a normal
+    pub fn from_point_normal(point: Vec3, normal: Vec3) -> Plane {
+        let normal = normal.normalize();
+        Plane {
+            normal,
+            d: -normal.dot(&point),
+        }
+    }
+
+    /// Distance from the plane, negative behind it
+    pub fn signed_distance(&self, point: &Vec3) -> Scalar {
+        self.normal.dot(point) + self.d
+    }
+
+    /// Classifies a point, treating points within `epsilon` as on the plane
+    pub fn classify(&self, point: &Vec3, epsilon: Scalar) -> Side {
+        let distance = self.signed_distance(point);
+        if distance > epsilon {
+            Side::Front
+        } else if distance < -epsilon {
+            Side::Back
+        } else {
+            Side::On
+        }
+    }
+
+    /// Where a ray hits the plane, None if it is parallel or points away
+    pub fn intersect_ray(&self, origin: Vec3, direction: Vec3) -> Option<Vec3> {
+        let speed = self.normal.dot(&direction);
+        if speed.abs() < Scalar::EPSILON {
+            return None;
+        }
+        let t = -self.signed_distance(&origin) / speed;
+        if t < 0.0 {
+            None
+        } else {
+            Some(origin + direction * t)
+        }
+    }
+}
+
+/// Half-line starting at a point
+#[derive(Copy, Clone, Debug)]
+pub struct Ray {
+    pub origin: Vec3,
+    /// Unit length
+    pub direction: Vec3,
+}
+
+impl Ray {
+    pub fn new(origin: Vec3, direction: Vec3) -> Ray {
+        Ray {
+            origin,
+            direction: direction.normalize(),
+        }
+    }
+
+    /// Point `t` units along the ray
+    pub fn at(&self, t: Scalar) -> Vec3 {
+        self.origin + self.direction * t
+    }
+}
+
+/// Extracts the left, right, bottom, top, near and far clip planes of a
+/// view * projection matrix, with normals pointing into the frustum
+pub fn frustum_planes(matrix: &Mat4) -> [Plane; 6] {
+    // Column i holds the coefficients producing clip coordinate i
+    let column = |col: usize| {
+        [
+            matrix.get(0, col),
+            matrix.get(1, col),
+            matrix.get(2, col),
+            matrix.get(3, col),
+        ]
+    };
+    let w = column(3);
+    let plane = |c: [Scalar; 4], sign: Scalar| {
+        // -w <= c <= w
+        Plane::new(
+            w[0] + sign * c[0],
+            w[1] + sign * c[1],
+            w[2] + sign * c[2],
+            w[3] + sign * c[3],
+        )
+    };
+    let (x, y, z) = (column(0), column(1), column(2));
+    [
+        plane(x, 1.0),
+        plane(x, -1.0),
+        plane(y, 1.0),
+        plane(y, -1.0),
+        plane(z, 1.0),
+        plane(z, -1.0),
+    ]
+}
+
+/// Transforms a point by a matrix
+pub fn transform_point(matrix: &Mat4, point: Vec3) -> Vec3 {
+    let w = point.x * matrix.get(0, 3)
+        + point.y * matrix.get(1, 3)
+        + point.z * matrix.get(2, 3)
+        + matrix.get(3, 3);
+    Vec3 {
+        x: (point.x * matrix.get(0, 0)
+            + point.y * matrix.get(1, 0)
+            + point.z * matrix.get(2, 0)
+            + matrix.get(3, 0))
+            / w,
+        y: (point.x * matrix.get(0, 1)
+            + point.y * matrix.get(1, 1)
+            + point.z * matrix.get(2, 1)
+            + matrix.get(3, 1))
+            / w,
+        z: (point.x * matrix.get(0, 2)
+            + point.y * matrix.get(1, 2)
+            + point.z * matrix.get(2, 2)
+            + matrix.get(3, 2))
+            / w,
+    }
+}
+
+/// Transforms a direction by a matrix, ignoring its translation
+pub fn transform_direction(matrix: &Mat4, direction: Vec3) -> Vec3 {
+    Vec3 {
+        x: direction.x * matrix.get(0, 0)
+            + direction.y * matrix.get(1, 0)
+            + direction.z * matrix.get(2, 0),
+        y: direction.x * matrix.get(0, 1)
+            + direction.y * matrix.get(1, 1)
+            + direction.z * matrix.get(2, 1),
+        z: direction.x * matrix.get(0, 2)
+            + direction.y * matrix.get(1, 2)
+            + direction.z * matrix.get(2, 2),
+    }
+}
+
+/// Point on a Bézier curve of any degree at t in 0..1
+// Paths are Catmull-Rom splines for now, nothing evaluates Bézier curves yet
+#[allow(dead_code)]
+pub fn bezier(points: &[Vec3], t: Scalar) -> Vec3 {
+    // de Casteljau's algorithm
+    let mut points = points.to_vec();
+    while points.len() > 1 {
+        points = points
+            .windows(2)
+            .map(|pair| Vec3::lerp(pair[0], pair[1], t))
+            .collect();
+    }
+    points.first().cloned().unwrap_or_else(Vec3::origin)
+}
+
+/// Derivative of a Bézier curve at t in 0..1
+#[allow(dead_code)]
+pub fn bezier_tangent(points: &[Vec3], t: Scalar) -> Vec3 {
+    // The derivative is a curve one degree lower through the scaled differences
+    let degree = points.len().saturating_sub(1) as Scalar;
+    let differences: Vec<Vec3> = points
+        .windows(2)
+        .map(|pair| (pair[1] - pair[0]) * degree)
+        .collect();
+    bezier(&differences, t)
+}
+
+/// Point at t in 0..1 on the Catmull-Rom segment from `points[1]` to `points[2]`
+pub fn catmull_rom(points: &[Vec3; 4], t: Scalar) -> Vec3 {
+    let [p0, p1, p2, p3] = *points;
+    let (t2, t3) = (t * t, t * t * t);
+    (p1 * 2.0
+        + (p2 - p0) * t
+        + (p0 * 2.0 - p1 * 5.0 + p2 * 4.0 - p3) * t2
+        + (-p0 + p1 * 3.0 - p2 * 3.0 + p3) * t3)
+        * 0.5
+}
+
+/// Derivative of the Catmull-Rom segment from `points[1]` to `points[2]`
+pub fn catmull_rom_tangent(points: &[Vec3; 4], t: Scalar) -> Vec3 {
+    let [p0, p1, p2, p3] = *points;
+    ((p2 - p0)
+        + (p0 * 2.0 - p1 * 5.0 + p2 * 4.0 - p3) * (2.0 * t)
+        + (-p0 + p1 * 3.0 - p2 * 3.0 + p3) * (3.0 * t * t))
+        * 0.5
+}
+
+/// Control points and local t of the segment containing t in 0..1 along a path
+/// through every point
+fn catmull_rom_segment(points: &[Vec3], t: Scalar) -> ([Vec3; 4], Scalar) {
+    let segments = points.len() - 1;
+    let position = t.clamp(0.0, 1.0) * segments as Scalar;
+    let index = (position as usize).min(segments - 1);
+    // The ends are repeated so the path reaches them
+    let point = |i: isize| points[i.clamp(0, segments as isize) as usize];
+    let i = index as isize;
+    (
+        [point(i - 1), point(i), point(i + 1), point(i + 2)],
+        position - index as Scalar,
+    )
+}
+
+/// Point at t in 0..1 along a Catmull-Rom path through every point
+pub fn catmull_rom_path(points: &[Vec3], t: Scalar) -> Vec3 {
+    match points.len() {
+        0 => Vec3::origin(),
+        1 => points[0],
+        _ => {
+            let (segment, t) = catmull_rom_segment(points, t);
+            catmull_rom(&segment, t)
+        }
+    }
+}
+
+/// Derivative with respect to t in 0..1 along a Catmull-Rom path
+pub fn catmull_rom_path_tangent(points: &[Vec3], t: Scalar) -> Vec3 {
+    if points.len() < 2 {
+        return Vec3::origin();
+    }
+    let (segment, t) = catmull_rom_segment(points, t);
+    // Each segment covers 1 / segments of the path
+    catmull_rom_tangent(&segment, t) * (points.len() - 1) as Scalar
+}
+
+/// Rotation stored as a unit quaternion
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Quat {
+    pub w: Scalar,
+    pub x: Scalar,
+    pub y: Scalar,
+    pub z: Scalar,
+}
+
+impl Quat {
+    pub fn identity() -> Quat {
+        Quat {
+            w: 1.0,
+            x: 0.0,
+            y: 0.0,
+            z: 0.0,
+        }
+    }
+
+    /// Rotation of `angle` radians counterclockwise around `axis`
+    pub fn from_axis_angle(axis: Vec3, angle: Scalar) -> Quat {
+        let axis = axis.normalize();
+        let (sin, cos) = (angle / 2.0).sin_cos();
+        Quat {
+            w: cos,
+            x: axis.x * sin,
+            y: axis.y * sin,
+            z: axis.z * sin,
+        }
+    }
+
+    /// Axis and angle in radians of the rotation, the x axis when there is no rotation
+    pub fn to_axis_angle(self) -> (Vec3, Scalar) {
+        let Quat { w, x, y, z } = self.normalize();
+        let sin = (1.0 - w * w).max(0.0).sqrt();
+        if sin < 1e-6 {
+            return (vec3(1.0, 0.0, 0.0), 0.0);
+        }
+        (
+            vec3(x / sin, y / sin, z / sin),
+            2.0 * w.clamp(-1.0, 1.0).acos(),
+        )
+    }
+
+    fn dot(&self, other: &Quat) -> Scalar {
+        self.w * other.w + self.x * other.x + self.y * other.y + self.z * other.z
+    }
+
+    pub fn normalize(self) -> Quat {
+        let length = self.dot(&self).sqrt();
+        Quat {
+            w: self.w / length,
+            x: self.x / length,
+            y: self.y / length,
+            z: self.z / length,
+        }
+    }
+
+    /// Interpolates along the shortest arc, `t` = 0 gives `self` and 1 gives `other`
+    pub fn slerp(self, other: Quat, t: Scalar) -> Quat {
+        let mut cos = self.dot(&other);
+        // q and -q are the same rotation, take the shorter way around
+        let other = if cos < 0.0 {
+            cos = -cos;
+            Quat {
+                w: -other.w,
+                x: -other.x,
+                y: -other.y,
+                z: -other.z,
             }
+        } else {
+            other
+        };
+        // Nearly equal rotations would divide by zero, blend linearly instead
+        let (a, b) = if cos > 0.9995 {
+            (1.0 - t, t)
+        } else {
+            let theta = cos.acos();
+            let sin = theta.sin();
+            (((1.0 - t) * theta).sin() / sin, (t * theta).sin() / sin)
+        };
+        Quat {
+            w: a * self.w + b * other.w,
+            x: a * self.x + b * other.x,
+            y: a * self.y + b * other.y,
+            z: a * self.z + b * other.z,
+        }
+        .normalize()
+    }
+
+    /// Rotation matrix for row vectors, matching `rotate_x`
+    pub fn to_mat4(self) -> Mat4 {
+        let Quat { w, x, y, z } = self;
+        let mut matrix = identity();
+        matrix.set(0, 0, 1.0 - 2.0 * (y * y + z * z));
+        matrix.set(0, 1, 2.0 * (x * y + w * z));
+        matrix.set(0, 2, 2.0 * (x * z - w * y));
+        matrix.set(1, 0, 2.0 * (x * y - w * z));
+        matrix.set(1, 1, 1.0 - 2.0 * (x * x + z * z));
+        matrix.set(1, 2, 2.0 * (y * z + w * x));
+        matrix.set(2, 0, 2.0 * (x * z + w * y));
+        matrix.set(2, 1, 2.0 * (y * z - w * x));
+        matrix.set(2, 2, 1.0 - 2.0 * (x * x + y * y));
+        matrix
+    }
+}
+
+/// `a * b` rotates by `b`, then by `a`
+impl std::ops::Mul for Quat {
+    type Output = Quat;
+    fn mul(self, other: Quat) -> Quat {
+        Quat {
+            w: self.w * other.w - self.x * other.x - self.y * other.y - self.z * other.z,
+            x: self.w * other.x + self.x * other.w + self.y * other.z - self.z * other.y,
+            y: self.w * other.y - self.x * other.z + self.y * other.w + self.z * other.x,
+            z: self.w * other.z + self.x * other.y - self.y * other.x + self.z * other.w,
+        }
+    }
+}
+
+/// Where an object sits in the world
+#[derive(Copy, Clone, Debug)]
+pub struct Transform {
+    pub translation: Vec3,
+    pub rotation: Quat,
+    pub scale: Vec3,
+}
+
+impl Default for Transform {
+    fn default() -> Transform {
+        Transform {
+            translation: Vec3::origin(),
+            rotation: Quat::identity(),
+            scale: vec3(1.0, 1.0, 1.0),
+        }
+    }
+}
+
+impl Transform {
+    pub fn from_translation(translation: Vec3) -> Transform {
+        Transform {
+            translation,
+            ..Default::default()
         }
     }
-    c
+
+    pub fn with_rotation(self, rotation: Quat) -> Transform {
+        Transform { rotation, ..self }
+    }
+
+    pub fn with_scale(self, scale: Vec3) -> Transform {
+        Transform { scale, ..self }
+    }
+
+    /// Model matrix that scales, then rotates, then translates
+    pub fn to_matrix(self) -> Mat4 {
+        scale(self.scale.x, self.scale.y, self.scale.z)
+            * self.rotation.to_mat4()
+            * translate(self.translation.x, self.translation.y, self.translation.z)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::consts::PI;
+
+    use super::{
+        bezier, bezier_tangent, catmull_rom, catmull_rom_path, catmull_rom_path_tangent,
+        catmull_rom_tangent, frustum, frustum_planes, identity, rotate_x, rotate_y, scale,
+        transform_point, translate, vec3, Mat4, Plane, Projection, Quat, Side, Transform, Vec3,
+    };
+
+    fn assert_close(a: Mat4, b: Mat4) {
+        for i in 0..16 {
+            assert!((a[i] - b[i]).abs() < 1e-5, "{:?} != {:?}", a, b);
+        }
+    }
+
+    #[test]
+    fn test_inverse() {
+        let matrix = scale(2.0, 3.0, 4.0) * rotate_y(0.5) * translate(1.0, 2.0, 3.0);
+        let inverse = matrix.inverse().unwrap();
+        assert_close(matrix * inverse, identity());
+        assert_close(inverse * matrix, identity());
+        // Transposing twice is a no-op
+        assert_close(matrix.transpose().transpose(), matrix);
+        assert!(scale(1.0, 0.0, 1.0).inverse().is_none());
+    }
+
+    #[test]
+    fn test_quat() {
+        let x_axis = vec3(1.0, 0.0, 0.0);
+        let quarter = Quat::from_axis_angle(x_axis, PI / 2.0);
+        assert_close(quarter.to_mat4(), rotate_x(PI / 2.0));
+        // Two quarter turns make a half turn
+        assert_close((quarter * quarter).to_mat4(), rotate_x(PI));
+        // Halfway to a third of a turn is a sixth of a turn
+        let third = Quat::from_axis_angle(x_axis, 2.0 * PI / 3.0);
+        assert_close(
+            Quat::identity().slerp(third, 0.5).to_mat4(),
+            rotate_x(PI / 3.0),
+        );
+        // The axis and angle can be recovered
+        let (axis, angle) = third.to_axis_angle();
+        assert!(axis.distance(&x_axis) < 1e-5);
+        assert!((angle - 2.0 * PI / 3.0).abs() < 1e-5);
+        assert_eq!(Quat::identity().to_axis_angle().1, 0.0);
+    }
+
+    #[test]
+    fn test_frustum_planes() {
+        // A 90 degree frustum looking down -z
+        let planes = frustum_planes(&frustum(-1.0, 1.0, -1.0, 1.0, 1.0, 10.0));
+        let inside = vec3(0.5, -0.5, -5.0);
+        assert!(planes
+            .iter()
+            .all(|plane| plane.classify(&inside, 0.0) == Side::Front));
+        // Behind the camera is outside the near plane, beyond far is outside the far plane
+        assert!((planes[4].signed_distance(&vec3(0.0, 0.0, 0.0)) + 1.0).abs() < 1e-5);
+        assert!((planes[5].signed_distance(&vec3(0.0, 0.0, -12.0)) + 2.0).abs() < 1e-5);
+        // The left plane passes through the corner of the near plane
+        assert_eq!(planes[0].classify(&vec3(-1.0, 0.0, -1.0), 1e-5), Side::On);
+    }
+
+    #[test]
+    fn test_plane() {
+        let floor = Plane::from_point_normal(vec3(0.0, 2.0, 0.0), vec3(0.0, 3.0, 0.0));
+        assert_eq!(floor.signed_distance(&vec3(1.0, 5.0, 1.0)), 3.0);
+        assert_eq!(floor.classify(&vec3(0.0, 1.0, 0.0), 0.0), Side::Back);
+        let hit = floor
+            .intersect_ray(vec3(1.0, 4.0, 0.0), vec3(1.0, -1.0, 0.0))
+            .unwrap();
+        assert_eq!((hit.x, hit.y, hit.z), (3.0, 2.0, 0.0));
+        // Pointing away or along the plane misses
+        assert!(floor
+            .intersect_ray(vec3(1.0, 4.0, 0.0), vec3(0.0, 1.0, 0.0))
+            .is_none());
+        assert!(floor
+            .intersect_ray(vec3(1.0, 4.0, 0.0), vec3(1.0, 0.0, 0.0))
+            .is_none());
+    }
+
+    #[test]
+    fn test_projection() {
+        // The top of the field of view maps to the top of the canvas
+        let perspective = Projection::perspective(PI / 2.0, 2.0, 1.0, 10.0).matrix();
+        let top = transform_point(&perspective, vec3(0.0, 3.0, -3.0));
+        assert!((top.y - 1.0).abs() < 1e-5);
+        let right = transform_point(&perspective, vec3(6.0, 0.0, -3.0));
+        assert!((right.x - 1.0).abs() < 1e-5);
+        // Near and far map to the ends of the depth range
+        assert!((transform_point(&perspective, vec3(0.0, 0.0, -1.0)).z + 1.0).abs() < 1e-5);
+        assert!((transform_point(&perspective, vec3(0.0, 0.0, -10.0)).z - 1.0).abs() < 1e-5);
+
+        let mut ortho = Projection::ortho(-2.0, 2.0, -1.0, 1.0, 0.5, 5.0);
+        let corner = transform_point(&ortho.matrix(), vec3(2.0, -1.0, -5.0));
+        assert!((corner.x - 1.0).abs() < 1e-5);
+        assert!((corner.y + 1.0).abs() < 1e-5);
+        assert!((corner.z - 1.0).abs() < 1e-5);
+        // Widening keeps the height and grows the sides
+        ortho.set_aspect(4.0);
+        let edge = transform_point(&ortho.matrix(), vec3(4.0, 1.0, -0.5));
+        assert!((edge.x - 1.0).abs() < 1e-5);
+        assert!((edge.y - 1.0).abs() < 1e-5);
+        // Zooming out is clamped to the largest height
+        ortho.zoom(10.0, 1.0, 4.0);
+        let edge = transform_point(&ortho.matrix(), vec3(8.0, 2.0, -0.5));
+        assert!((edge.x - 1.0).abs() < 1e-5);
+        assert!((edge.y - 1.0).abs() < 1e-5);
+    }
+
+    #[test]
+    fn test_transform() {
+        let transform = Transform::from_translation(vec3(1.0, 2.0, 3.0))
+            .with_rotation(Quat::from_axis_angle(vec3(1.0, 0.0, 0.0), PI / 2.0))
+            .with_scale(vec3(2.0, 2.0, 2.0));
+        assert_close(
+            transform.to_matrix(),
+            scale(2.0, 2.0, 2.0) * rotate_x(PI / 2.0) * translate(1.0, 2.0, 3.0),
+        );
+        assert_close(Transform::default().to_matrix(), identity());
+    }
+
+    #[test]
+    fn test_spherical() {
+        // Straight up, and a quarter turn around from +z
+        let top = Vec3::from_spherical(2.0, 0.0, 0.0);
+        assert!(top.distance(&vec3(0.0, 2.0, 0.0)) < 1e-5);
+        let side = Vec3::from_spherical(2.0, PI / 2.0, PI / 2.0);
+        assert!(side.distance(&vec3(2.0, 0.0, 0.0)) < 1e-5);
+        // Round trip
+        let point = vec3(1.0, -2.0, 3.0);
+        let (radius, theta, phi) = point.to_spherical();
+        assert!(Vec3::from_spherical(radius, theta, phi).distance(&point) < 1e-5);
+    }
+
+    #[test]
+    fn test_curves() {
+        // A quadratic Bézier arch
+        let arch = [
+            vec3(0.0, 0.0, 0.0),
+            vec3(1.0, 2.0, 0.0),
+            vec3(2.0, 0.0, 0.0),
+        ];
+        assert!(bezier(&arch, 0.5).distance(&vec3(1.0, 1.0, 0.0)) < 1e-5);
+        assert!(bezier(&arch, 1.0).distance(&arch[2]) < 1e-5);
+        // Flat at the top, starting towards the middle control point
+        assert!(bezier_tangent(&arch, 0.5).distance(&vec3(2.0, 0.0, 0.0)) < 1e-5);
+        assert!(bezier_tangent(&arch, 0.0).distance(&vec3(2.0, 4.0, 0.0)) < 1e-5);
+        // Catmull-Rom passes through its points and is straight along a line
+        let line = [
+            vec3(0.0, 0.0, 0.0),
+            vec3(1.0, 0.0, 0.0),
+            vec3(2.0, 0.0, 0.0),
+            vec3(3.0, 0.0, 0.0),
+        ];
+        assert!(catmull_rom(&line, 0.0).distance(&line[1]) < 1e-5);
+        assert!(catmull_rom(&line, 0.5).distance(&vec3(1.5, 0.0, 0.0)) < 1e-5);
+        assert!(catmull_rom_tangent(&line, 0.5).distance(&vec3(1.0, 0.0, 0.0)) < 1e-5);
+        assert!(catmull_rom_path(&line, 1.0).distance(&line[3]) < 1e-5);
+        assert!(catmull_rom_path(&line, 0.5).distance(&vec3(1.5, 0.0, 0.0)) < 1e-5);
+        assert!(catmull_rom_path_tangent(&line, 0.5).distance(&vec3(3.0, 0.0, 0.0)) < 1e-5);
+    }
+
+    #[test]
+    fn test_vec3_ops() {
+        let mut a = vec3(1.0, 2.0, 3.0);
+        let b = vec3(4.0, 6.0, 3.0);
+        assert_eq!(b.distance(&a), 5.0);
+        assert_eq!((b - a).length_squared(), 25.0);
+        a += b;
+        a -= &b * 2.0;
+        let c = -a;
+        assert_eq!((c.x, c.y, c.z), (3.0, 4.0, 0.0));
+        // Helpers
+        let mid = Vec3::lerp(a, c, 0.25);
+        assert_eq!((mid.x, mid.y, mid.z), (-1.5, -2.0, 0.0));
+        let clamped = vec3(-5.0, 0.5, 5.0).clamp(&Vec3::origin(), &vec3(1.0, 1.0, 1.0));
+        assert_eq!((clamped.x, clamped.y, clamped.z), (0.0, 0.5, 1.0));
+    }
 }
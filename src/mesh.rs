@@ -0,0 +1,182 @@
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+use gleam::gl::{self, GLint, GLsizei};
+
+use super::Context;
+use aabb::Aabb;
+use material::Material;
+use matrix::{Mat4, Transform};
+use render::{bounds, spin_bounds, Drawable, Vertex, VERTEX_SIZE};
+use texture::{TextureId, TextureOptions, WrapMode};
+
+/// Vertices of a shape ready to buffer, with the box around them
+pub struct MeshData {
+    pub vertices: Vec<f32>,
+    pub bounds: Option<Aabb>,
+}
+
+impl MeshData {
+    pub fn new(vertices: &[Vertex]) -> MeshData {
+        MeshData {
+            vertices: flatten(vertices),
+            bounds: bounds(vertices),
+        }
+    }
+
+    /// Bounds are taken however far the shape is spun around the y axis
+    pub fn spun(vertices: &[Vertex]) -> MeshData {
+        MeshData {
+            vertices: flatten(vertices),
+            bounds: spin_bounds(vertices),
+        }
+    }
+}
+
+fn flatten(vertices: &[Vertex]) -> Vec<f32> {
+    vertices
+        .iter()
+        .flat_map(|vertex| vertex.to_data().to_vec())
+        .collect()
+}
+
+/// Range of the vertex buffer holding a shape, drawn by every instance of it
+#[derive(Copy, Clone, Debug)]
+pub struct Mesh {
+    /// Index of the first vertex
+    pub first: GLint,
+    pub count: GLsizei,
+    /// Extent of the vertices, None if the mesh is never culled
+    pub bounds: Option<Aabb>,
+}
+
+impl Mesh {
+    pub fn draw(&self, ctx: &Context) {
+        ctx.gl.draw_arrays(gl::TRIANGLES, self.first, self.count);
+    }
+}
+
+/// Vertices of every mesh in the scene, each shape buffered once however often it is drawn
+#[derive(Default)]
+pub struct MeshLibrary {
+    vertices: Vec<f32>,
+    meshes: HashMap<String, Mesh>,
+}
+
+impl MeshLibrary {
+    pub fn new() -> MeshLibrary {
+        Default::default()
+    }
+
+    /// Gives an object its mesh, only generating vertices for shapes not seen before
+    pub fn add(&mut self, object: &mut Drawable) -> Mesh {
+        let vertices = &mut self.vertices;
+        let mesh = *self.meshes.entry(object.mesh_key()).or_insert_with(|| {
+            let data = object.mesh_data();
+            let mesh = Mesh {
+                first: (vertices.len() / VERTEX_SIZE) as GLint,
+                count: (data.vertices.len() / VERTEX_SIZE) as GLsizei,
+                bounds: data.bounds,
+            };
+            vertices.extend_from_slice(&data.vertices);
+            mesh
+        });
+        object.instance_mut().mesh = Some(mesh);
+        mesh
+    }
+
+    /// Vertices of every mesh added, for the vertex buffer
+    pub fn vertices(&self) -> &[f32] {
+        &self.vertices
+    }
+}
+
+/// A mesh placed in the scene with a look of its own
+pub struct Instance {
+    /// None until the mesh has been buffered
+    pub mesh: Option<Mesh>,
+    pub transform: Transform,
+    pub material: Material,
+    /// Bound over the white texture when there is one
+    pub texture: Option<TextureId>,
+    /// Where `texture` is loaded from for shapes that don't load their own
+    pub texture_path: Option<PathBuf>,
+    /// How `texture` is sampled, repeated unless set
+    pub texture_options: TextureOptions,
+}
+
+impl Instance {
+    pub fn new(transform: Transform, material: Material) -> Instance {
+        Instance {
+            mesh: None,
+            transform,
+            material,
+            texture: None,
+            texture_path: None,
+            texture_options: WrapMode::Repeat.into(),
+        }
+    }
+
+    /// Model matrix placing the mesh in the node drawing it
+    pub fn model_matrix(&self, ctx: &Context) -> Mat4 {
+        self.transform.to_matrix() * ctx.node_matrix()
+    }
+
+    pub fn draw(&self, ctx: &Context) {
+        self.draw_with(ctx, &self.model_matrix(ctx));
+    }
+
+    /// Draws the mesh with a model matrix other than the instance's own
+    pub fn draw_with(&self, ctx: &Context, m_matrix: &Mat4) {
+        if let Some(mesh) = self.mesh {
+            self.draw_parts(ctx, m_matrix, &[mesh]);
+        }
+    }
+
+    /// Draws ranges of the mesh's vertices, for shapes leaving some of their parts out
+    pub fn draw_parts(&self, ctx: &Context, m_matrix: &Mat4, parts: &[Mesh]) {
+        let mv_matrix = *m_matrix * ctx.view_matrix();
+        ctx.set_mv_matrix(&mv_matrix);
+        match self.texture {
+            Some(texture) => ctx.textures.bind(texture, ctx.program),
+            // Shapes whose texture failed to load are marked by the missing texture
+            None if self.texture_path.is_some() => {
+                ctx.textures.bind(ctx.missing_texture, ctx.program)
+            }
+            None => {}
+        }
+        ctx.set_material(&self.material);
+        for part in parts {
+            part.draw(ctx);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::MeshLibrary;
+    use render::{Drawable, VERTEX_SIZE};
+    use room::Room;
+
+    #[test]
+    fn test_mesh_library() {
+        let mut meshes = MeshLibrary::new();
+        let mut rooms = [
+            Room::new(1.0, 2.0, 3.0, Vec::new()),
+            Room::new(1.0, 2.0, 3.0, Vec::new()),
+            Room::new(4.0, 4.0, 4.0, Vec::new()),
+        ];
+        let added: Vec<_> = rooms
+            .iter_mut()
+            .map(|room| meshes.add(room as &mut Drawable))
+            .collect();
+        // Identical rooms share their vertices, and the different one follows them
+        assert_eq!(added[0].first, added[1].first);
+        assert_eq!(added[2].first, added[0].count);
+        assert_eq!(meshes.vertices().len(), 2 * 36 * VERTEX_SIZE);
+        assert!(rooms[1]
+            .bounds()
+            .unwrap()
+            .contains(&added[0].bounds.unwrap().max));
+    }
+}
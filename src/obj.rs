@@ -1,19 +1,20 @@
 use std::error::Error;
-use std::f32::consts::PI;
 use std::ffi::OsStr;
 use std::fs::File;
 use std::io::{self, BufRead, BufReader};
 use std::path::{Path, PathBuf};
 use std::str::FromStr;
 
-use gleam::gl;
-use gleam::gl::types::{GLint, GLsizei};
-use image::GenericImageView;
-
 use super::Context;
+use aabb::Aabb;
+use atlas::AtlasRegion;
 use error::io_error;
-use matrix::{identity, matmul, rotate_x, rotate_y, scale, translate, vec2, vec3, Vec2, Vec3};
-use render::{get_tex_const, Color, Drawable};
+use material::Material;
+use matrix::consts::PI;
+use matrix::{gl_float, vec2, vec3, Quat, Scalar, Transform, Vec2, Vec3};
+use mesh::{Instance, MeshData};
+use render::{tangent, Color, Drawable};
+use texture::{TextureId, TextureManager, TextureOptions, WrapMode};
 
 #[derive(Debug)]
 pub struct Face<T> {
@@ -73,7 +74,8 @@ impl Group {
     }
 }
 
-struct Material {
+/// Material as described by an MTL file
+struct MtlMaterial {
     /// Ka
     ambient_color: Color,
     /// Kd
@@ -91,24 +93,34 @@ struct Material {
 }
 
 pub struct Obj {
+    /// Where the model was loaded from
+    path: String,
     groups: Vec<Group>,
-    vert_start: GLint,
-    num_verts: GLsizei,
     pub vertices: Vec<Vec3>,
     pub normals: Vec<Vec3>,
     pub texture_coords: Vec<Vec2>,
     center: Vec3,
-    scale: Vec3,
-    translate: Vec3,
+    instance: Instance,
+    /// Extent of the vertices once centered
+    bounds: Option<Aabb>,
     texture_path: PathBuf,
-    cur_texture: u8,
+    /// How the texture and its maps are sampled
+    texture_options: TextureOptions,
+    /// Where the texture is if it was packed into an atlas
+    region: Option<AtlasRegion>,
+    normal_map_path: Option<PathBuf>,
+    normal_map: Option<TextureId>,
+    specular_map_path: Option<PathBuf>,
+    specular_map: Option<TextureId>,
+    emissive_map_path: Option<PathBuf>,
+    emissive_map: Option<TextureId>,
 }
 impl Obj {
     /// Loads a render object from a path
     pub fn load<P, PP>(
         obj_path: P,
         texture_path: PP,
-        cur_texture: &mut u8,
+        wrap: WrapMode,
         scale: Vec3,
         translate: Vec3,
     ) -> Result<Self, io::Error>
@@ -134,7 +146,7 @@ impl Obj {
         let mut center: Vec3 = Vec3::origin();
         // Keep track of vertices for averaging center
         // Float is used here for division
-        let mut num_vertices: f32 = 0.0;
+        let mut num_vertices: Scalar = 0.0;
 
         for line in obj_file.lines() {
             // Unwrap the line
@@ -167,17 +179,17 @@ impl Obj {
                 }
                 "v" => {
                     // Read coordinates
-                    let x: f32 = tokens
+                    let x: Scalar = tokens
                         .next()
                         .unwrap_or_else(|| "0")
                         .parse()
                         .unwrap_or_else(|_| 0.0);
-                    let y: f32 = tokens
+                    let y: Scalar = tokens
                         .next()
                         .unwrap_or_else(|| "0")
                         .parse()
                         .unwrap_or_else(|_| 0.0);
-                    let z: f32 = tokens
+                    let z: Scalar = tokens
                         .next()
                         .unwrap_or_else(|| "0")
                         .parse()
@@ -185,7 +197,7 @@ impl Obj {
                     // Collect into a vector
                     let v = vec3(x, y, z);
                     // Factor vertex into the center
-                    center = &center + v;
+                    center += v;
                     // Add to number of vertices
                     num_vertices += 1.0;
                     // Add vector into the list
@@ -193,17 +205,17 @@ impl Obj {
                 }
                 "vn" => {
                     // Read coordinates
-                    let x: f32 = tokens
+                    let x: Scalar = tokens
                         .next()
                         .unwrap_or_else(|| "0")
                         .parse()
                         .unwrap_or_else(|_| 0.0);
-                    let y: f32 = tokens
+                    let y: Scalar = tokens
                         .next()
                         .unwrap_or_else(|| "0")
                         .parse()
                         .unwrap_or_else(|_| 0.0);
-                    let z: f32 = tokens
+                    let z: Scalar = tokens
                         .next()
                         .unwrap_or_else(|| "0")
                         .parse()
@@ -212,12 +224,12 @@ impl Obj {
                 }
                 "vt" => {
                     // Read coordinates
-                    let x: f32 = tokens
+                    let x: Scalar = tokens
                         .next()
                         .unwrap_or_else(|| "0")
                         .parse()
                         .unwrap_or_else(|_| 0.0);
-                    let y: f32 = tokens
+                    let y: Scalar = tokens
                         .next()
                         .unwrap_or_else(|| "0")
                         .parse()
@@ -236,26 +248,79 @@ impl Obj {
         // Push the last group
         groups.push(cur_group);
         // Average out the center
-        let center = center * (1.0 / (num_vertices as f32));
+        let center = center * (1.0 / (num_vertices as Scalar));
         println!("Center for {} is {:?}", path_str, center);
-        // Iterate texture counter forward
-        *cur_texture += 1;
+        // Bounds are taken around the center the vertices are drawn from
+        let bounds = Aabb::from_points(&vertices)
+            .map(|aabb| Aabb::new(aabb.min - center, aabb.max - center));
         // Generate the render object
         Ok(Obj {
+            path: path_str,
             groups,
-            vert_start: 0,
-            num_verts: 0,
             vertices,
             normals,
             texture_coords,
             center,
-            scale,
-            translate,
+            // Models are authored facing the other way
+            instance: Instance::new(
+                Transform::from_translation(translate)
+                    .with_rotation(Quat::from_axis_angle(vec3(0.0, 1.0, 0.0), PI))
+                    .with_scale(scale),
+                Material::gold(),
+            ),
+            bounds,
             texture_path: Path::new(&texture_path).to_path_buf(),
-            cur_texture: *cur_texture,
+            texture_options: wrap.into(),
+            region: None,
+            normal_map_path: None,
+            normal_map: None,
+            specular_map_path: None,
+            specular_map: None,
+            emissive_map_path: None,
+            emissive_map: None,
         })
     }
 
+    /// Sets how the texture and its maps are sampled, replacing the wrap mode given to `load`
+    pub fn set_texture_options(&mut self, options: TextureOptions) {
+        self.texture_options = options;
+    }
+
+    /// Sets a tangent space normal map, loaded along with the texture
+    pub fn set_normal_map<P>(&mut self, path: P)
+    where
+        P: AsRef<Path>,
+    {
+        self.normal_map_path = Some(path.as_ref().to_path_buf());
+    }
+
+    /// Sets a map scaling the specular color, loaded along with the texture
+    pub fn set_specular_map<P>(&mut self, path: P)
+    where
+        P: AsRef<Path>,
+    {
+        self.specular_map_path = Some(path.as_ref().to_path_buf());
+    }
+
+    /// Sets a map of glowing texels, loaded along with the texture
+    ///
+    /// The emissive color becomes white so the map glows as is. Setting the color afterwards
+    /// tints it instead.
+    pub fn set_emissive_map<P>(&mut self, path: P)
+    where
+        P: AsRef<Path>,
+    {
+        self.emissive_map_path = Some(path.as_ref().to_path_buf());
+        self.instance.material.emissive = vec3(1.0, 1.0, 1.0);
+    }
+
+    /// Whether any normal, specular or emissive map is set
+    fn has_maps(&self) -> bool {
+        self.normal_map_path.is_some()
+            || self.specular_map_path.is_some()
+            || self.emissive_map_path.is_some()
+    }
+
     pub fn to_vertices(&self, group: &Group) -> Vec<f32> {
         // Generate vertex list from face list
         group
@@ -264,42 +329,71 @@ impl Obj {
             // For each face, get the vertex, normal, and texture coordinates
             // of all its components
             .flat_map(|face| {
-                face.indices.iter().map(|index| {
-                    (
-                        // Get the vertex for this
-                        /*(&(&self.vertices[(index.vertex_index - 1) as usize] - self.center)
-                        + self.translate)
-                        .scale(self.scale.x, self.scale.y, self.scale.z),*/
-                        // Get the vertex for this
-                        &self.vertices[(index.vertex_index - 1) as usize] - self.center,
-                        index
-                            .normal_index
-                            .map(|normal_index| self.normals[(normal_index - 1) as usize])
-                            .unwrap_or_else(Vec3::origin),
-                        index
-                            .texture_index
-                            .map(|texture_index| self.texture_coords[(texture_index - 1) as usize])
-                            .unwrap_or_else(Vec2::origin),
+                let corners: Vec<(Vec3, Vec3, Vec2)> = face
+                    .indices
+                    .iter()
+                    .map(|index| {
+                        (
+                            // Get the vertex for this
+                            /*(&(&self.vertices[(index.vertex_index - 1) as usize] - self.center)
+                            + self.translate)
+                            .scale(self.scale.x, self.scale.y, self.scale.z),*/
+                            // Get the vertex for this
+                            self.vertices[(index.vertex_index - 1) as usize] - self.center,
+                            index
+                                .normal_index
+                                .map(|normal_index| self.normals[(normal_index - 1) as usize])
+                                .unwrap_or_else(Vec3::origin),
+                            index
+                                .texture_index
+                                .map(|texture_index| {
+                                    self.texture_coords[(texture_index - 1) as usize]
+                                })
+                                .unwrap_or_else(Vec2::origin),
+                        )
+                    })
+                    .collect();
+                // The whole face shares one tangent
+                let face_tangent = if corners.len() >= 3 {
+                    tangent(
+                        [corners[0].0, corners[1].0, corners[2].0],
+                        [corners[0].2, corners[1].2, corners[2].2],
                     )
-                })
+                } else {
+                    Vec3::origin()
+                };
+                corners
+                    .into_iter()
+                    .map(move |(vertex, normal, texture)| (vertex, normal, texture, face_tangent))
             })
             // Flatten out everything
-            .flat_map(|(vertex, normal, texture)| {
+            .flat_map(|(vertex, normal, texture, tangent)| {
+                // Move texture coordinates into the texture's atlas region, unless maps
+                // need them as they are
+                let texture = match self.region {
+                    Some(region) if !self.has_maps() => region.map(texture),
+                    _ => texture,
+                };
                 #[cfg_attr(rustfmt, rustfmt_skip)]
                 vec![
                     vertex.x, vertex.y, vertex.z,
                     normal.x, normal.y, normal.z,
                     texture.x, texture.y,
+                    tangent.x, tangent.y, tangent.z,
                 ]
             })
+            .map(gl_float)
             .collect()
     }
 }
 impl Drawable for Obj {
-    /// Returns buffer data
-    fn buffer_data(&mut self, vertex_start: GLint) -> Vec<f32> {
-        // Store element start
-        self.vert_start = vertex_start;
+    /// Texture coordinates depend on where the texture is, so models share a mesh only when
+    /// they share a texture
+    fn mesh_key(&self) -> String {
+        format!("obj {} {}", self.path, self.texture_path.display())
+    }
+    /// Generates the vertices of the model
+    fn mesh_data(&self) -> MeshData {
         // Store vertex data
         let mut vertices: Vec<f32> = Vec::new();
         // Iterate over groups
@@ -309,82 +403,120 @@ impl Drawable for Obj {
             // Add existing data
             vertices.extend_from_slice(&cur_vertices);
         }
-        // Store the number of vertices
-        self.num_verts = (vertices.len() / 8) as GLsizei;
-        // Return vertices
-        vertices
+        MeshData {
+            vertices,
+            bounds: self.bounds,
+        }
     }
     /// Loads textures
-    fn load_texture(&self, ctx: &Context) {
-        let gl = &ctx.gl;
-        // Read texture
-        let tex_image = image::open(self.texture_path.clone()).unwrap();
-
-        // Extract dimensions
-        let (width, height) = tex_image.dimensions();
-        // Get image as raw bytes
-        let tex_image = tex_image.as_rgb8().unwrap().clone();
-        // Create a texture
-        let texture = gl.gen_textures(1)[0];
-        // Get the texture index as a glenum
-        let tex_enum = get_tex_const(self.cur_texture);
-        gl.active_texture(tex_enum);
-        gl.bind_texture(gl::TEXTURE_2D, texture);
-        gl.tex_parameter_i(gl::TEXTURE_2D, gl::TEXTURE_MAG_FILTER, gl::LINEAR as i32);
-        gl.tex_image_2d(
-            gl::TEXTURE_2D,
-            0,
-            gl::RGB as i32,
-            width as i32,
-            height as i32,
-            0,
-            gl::RGB,
-            gl::UNSIGNED_BYTE,
-            Some(&tex_image),
-        );
-        gl.generate_mipmap(gl::TEXTURE_2D);
-
-        gl.tex_parameter_i(
-            gl::TEXTURE_2D,
-            gl::TEXTURE_MIN_FILTER,
-            gl::LINEAR_MIPMAP_LINEAR as i32,
-        );
+    fn load_texture(&mut self, textures: &mut TextureManager) -> Result<(), io::Error> {
+        // Objects whose texture fails are drawn with the missing texture
+        // Maps are sampled with the texture's coordinates, so they only line up with a
+        // texture that isn't packed into an atlas
+        let texture = if self.has_maps() {
+            textures.load(&self.texture_path, self.texture_options)?
+        } else {
+            textures.load_packed(&self.texture_path, self.texture_options)?
+        };
+        self.instance.texture = Some(texture);
+        self.region = textures.region(texture, &self.texture_path);
+        // Load the normal map if there is one
+        if let Some(ref normal_map_path) = self.normal_map_path {
+            self.normal_map = Some(textures.load_linear(normal_map_path, self.texture_options)?);
+        }
+        // Load the specular map if there is one
+        if let Some(ref specular_map_path) = self.specular_map_path {
+            self.specular_map =
+                Some(textures.load_linear(specular_map_path, self.texture_options)?);
+        }
+        // Load the emissive map if there is one
+        if let Some(ref emissive_map_path) = self.emissive_map_path {
+            self.emissive_map = Some(textures.load(emissive_map_path, self.texture_options)?);
+        }
+        Ok(())
+    }
+    /// Whether the object needs blending
+    fn translucent(&self, textures: &TextureManager) -> bool {
+        // Textures with an alpha channel need blending
+        match self.instance.texture {
+            Some(texture) => textures.has_alpha(texture),
+            None => false,
+        }
+    }
+    /// Models bind their own textures and maps
+    fn instanced(&self) -> bool {
+        false
     }
-    /// Draws the object
-    // Return groups
+    /// Mesh of the model and how it is placed and looks
+    fn instance(&self) -> &Instance {
+        &self.instance
+    }
+    fn instance_mut(&mut self) -> &mut Instance {
+        &mut self.instance
+    }
+    /// Draws the model with its maps
     fn draw(&self, ctx: &Context) {
         let gl = &ctx.gl;
-        let mv_location = gl.get_uniform_location(ctx.program, "uMVMatrix");
-        let m_matrix = identity();
-        let v_matrix = matmul(
-            rotate_y(PI),
-            matmul(
-                scale(self.scale.x, self.scale.y, self.scale.z),
-                matmul(
-                    translate(self.translate.x, self.translate.y, self.translate.z),
-                    ctx.camera,
-                ),
-            ),
-        );
-        let mv_matrix = matmul(v_matrix, m_matrix);
-        gl.uniform_matrix_4fv(mv_location, false, &mv_matrix);
-
-        let sampler_location = gl.get_uniform_location(ctx.program, "uSampler");
-        gl.uniform_1i(sampler_location, self.cur_texture as i32);
+        // Models whose texture failed to load are marked by the missing texture
+        if self.instance.texture.is_none() {
+            ctx.textures.bind(ctx.missing_texture, ctx.program);
+        }
+        // Maps are left off until decoded, their placeholder would skew lighting
+        // Each map gets its own unit after the texture's
+        let loaded = |map: &TextureId| ctx.textures.loaded(*map);
+        if let Some(normal_map) = self.normal_map.filter(loaded) {
+            ctx.textures
+                .bind_sampler(normal_map, ctx.program, "uNormalMap", 1);
+            let has_normal_map_location = gl.get_uniform_location(ctx.program, "uHasNormalMap");
+            gl.uniform_1i(has_normal_map_location, 1);
+        }
+        if let Some(specular_map) = self.specular_map.filter(loaded) {
+            ctx.textures
+                .bind_sampler(specular_map, ctx.program, "uSpecularMap", 2);
+            let has_specular_map_location = gl.get_uniform_location(ctx.program, "uHasSpecularMap");
+            gl.uniform_1i(has_specular_map_location, 1);
+        }
+        if let Some(emissive_map) = self.emissive_map.filter(loaded) {
+            ctx.textures
+                .bind_sampler(emissive_map, ctx.program, "uEmissiveMap", 3);
+            let has_emissive_map_location = gl.get_uniform_location(ctx.program, "uHasEmissiveMap");
+            gl.uniform_1i(has_emissive_map_location, 1);
+        }
 
-        // Lighting properties
-        let ambient_location = gl.get_uniform_location(ctx.program, "uAmbientProduct");
-        let diffuse_location = gl.get_uniform_location(ctx.program, "uDiffuseProduct");
-        let specular_location = gl.get_uniform_location(ctx.program, "uSpecularProduct");
-        // Light position
-        let shininess_location = gl.get_uniform_location(ctx.program, "uShininess");
+        self.instance.draw(ctx);
+    }
+}
 
-        gl.uniform_4f(ambient_location, 0.8, 0.8, 0.8, 1.0);
-        gl.uniform_4f(diffuse_location, 0.75164, 0.60648, 0.22648, 1.0);
-        gl.uniform_4f(specular_location, 0.628281, 0.555802, 0.366065, 1.0);
+#[cfg(test)]
+mod test {
+    use std::io;
 
-        gl.uniform_1f(shininess_location, 0.4 * 128.0);
+    use super::Obj;
+    use atlas::AtlasRegion;
+    use matrix::{vec2, vec3};
+    use render::Drawable;
+    use texture::WrapMode;
 
-        gl.draw_arrays(gl::TRIANGLES, self.vert_start / 8, self.num_verts);
+    #[test]
+    fn test_region() -> io::Result<()> {
+        let mut staff = Obj::load(
+            "public/staff.obj",
+            "public/staff.tga",
+            WrapMode::ClampToEdge,
+            vec3(1.0, 1.0, 1.0),
+            vec3(0.0, 0.0, 0.0),
+        )?;
+        let unpacked = staff.mesh_data().vertices;
+        // Without maps, texture coordinates move into the atlas region
+        staff.region = Some(AtlasRegion {
+            offset: vec2(0.5, 0.0),
+            scale: vec2(0.5, 0.5),
+        });
+        assert_ne!(staff.mesh_data().vertices, unpacked);
+        // Maps are sampled with the same coordinates, so they stay as they are
+        staff.set_specular_map("public/staff_specular.tga");
+        staff.set_emissive_map("public/staff_emissive.tga");
+        assert_eq!(staff.mesh_data().vertices, unpacked);
+        Ok(())
     }
 }
@@ -0,0 +1,265 @@
+use material::Material;
+use matrix::consts::PI;
+use matrix::{vec2, vec3, Scalar, Transform, Vec3};
+use mesh::{Instance, MeshData};
+use render::{vertex, Drawable, Vertex};
+
+/// Distance in u and v either side of a point used to find which way the surface runs
+const STEP: Scalar = 1e-3;
+/// Times a shell's spiral turns from its tip to its opening
+const SHELL_TURNS: Scalar = 3.0;
+
+/// Surfaces the scene can trace without code of its own, each about `size` across
+#[derive(Copy, Clone, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ParametricShape {
+    /// Spiral shell standing on its opening with its tip up
+    Seashell,
+    /// Tube tied in the simplest knot, lying in the xy plane
+    TrefoilKnot,
+}
+
+impl ParametricShape {
+    /// Point at u around the shape's tube and v along it
+    pub fn point(self, size: Scalar, u: Scalar, v: Scalar) -> Vec3 {
+        match self {
+            ParametricShape::Seashell => {
+                // Around the tube backwards, so its faces point out
+                let (around, turn) = (2.0 * PI * (1.0 - u), 2.0 * PI * SHELL_TURNS * v);
+                let radius = size * 0.35 * v;
+                let out = radius * (1.0 + 0.6 * around.cos());
+                vec3(
+                    out * turn.cos(),
+                    size * 1.2 * (1.0 - v) + 0.6 * radius * around.sin(),
+                    -out * turn.sin(),
+                )
+            }
+            ParametricShape::TrefoilKnot => {
+                let middle = trefoil(size, v);
+                let along = trefoil(size, v + STEP) - trefoil(size, v - STEP);
+                // The knot never runs along z, so this frame turns smoothly all the way round
+                let side = vec3(0.0, 0.0, 1.0).cross(along).normalize();
+                let other = along.cross(side).normalize();
+                let around = 2.0 * PI * u;
+                middle + (side * around.cos() + other * around.sin()) * (size * 0.15)
+            }
+        }
+    }
+}
+
+/// Point `t` of the way along the line through the middle of a trefoil knot
+fn trefoil(size: Scalar, t: Scalar) -> Vec3 {
+    let t = 2.0 * PI * t;
+    vec3(
+        t.sin() + 2.0 * (2.0 * t).sin(),
+        t.cos() - 2.0 * (2.0 * t).cos(),
+        -(3.0 * t).sin(),
+    ) * (size / 3.0)
+}
+
+/// Surface traced by a function from u and v, each 0 to 1, to a point, for shapes none of the
+/// other drawables make
+///
+/// The function is sampled on a grid when the surface is created, so it doesn't have to
+/// outlive it. The faces point along the direction of growing u crossed with growing v, and
+/// the texture is laid out by u and v.
+pub struct ParametricSurface {
+    /// Names the function, so surfaces sampling the same one alike share a mesh
+    name: String,
+    /// Rows of points along u, one for each step of v
+    points: Vec<Vec<Vec3>>,
+    normals: Vec<Vec<Vec3>>,
+    /// Direction of growing u at each point, for the texture
+    tangents: Vec<Vec<Vec3>>,
+    instance: Instance,
+}
+
+impl ParametricSurface {
+    /// Samples `function` at `u_resolution` steps along u and `v_resolution` along v
+    ///
+    /// Surfaces given the same name and resolutions must trace the same function.
+    pub fn new<F>(
+        name: &str,
+        function: F,
+        u_resolution: u16,
+        v_resolution: u16,
+        translate: Vec3,
+    ) -> ParametricSurface
+    where
+        F: Fn(Scalar, Scalar) -> Vec3,
+    {
+        let (mut points, mut normals, mut tangents) = (Vec::new(), Vec::new(), Vec::new());
+        for j in 0..=v_resolution {
+            let v = Scalar::from(j) / Scalar::from(v_resolution.max(1));
+            let (mut row, mut row_normals, mut row_tangents) = (Vec::new(), Vec::new(), Vec::new());
+            for i in 0..=u_resolution {
+                let u = Scalar::from(i) / Scalar::from(u_resolution.max(1));
+                let (along_u, along_v) = partials(&function, u, v);
+                // Where the surface pinches to a point, such as a sphere's poles, look just
+                // inside it instead
+                let longest = along_u.length().max(along_v.length());
+                let (along_u, along_v) =
+                    if along_u.cross(along_v).length() > 1e-4 * longest * longest {
+                        (along_u, along_v)
+                    } else {
+                        let inside = |t: Scalar| t + (0.5 - t).signum() * STEP;
+                        partials(&function, inside(u), inside(v))
+                    };
+                let normal = along_u.cross(along_v);
+                row.push(function(u, v));
+                row_normals.push(if normal.length() > 0.0 {
+                    normal.normalize()
+                } else {
+                    normal
+                });
+                row_tangents.push(if along_u.length() > 0.0 {
+                    along_u.normalize()
+                } else {
+                    along_u
+                });
+            }
+            points.push(row);
+            normals.push(row_normals);
+            tangents.push(row_tangents);
+        }
+        ParametricSurface {
+            name: name.to_string(),
+            points,
+            normals,
+            tangents,
+            instance: Instance::new(Transform::from_translation(translate), Material::bronze()),
+        }
+    }
+
+    /// Traces one of the shapes the scene knows, split into `resolution` steps around its
+    /// tube and four times as many along it
+    pub fn shape(
+        shape: ParametricShape,
+        size: Scalar,
+        resolution: u16,
+        translate: Vec3,
+    ) -> ParametricSurface {
+        ParametricSurface::new(
+            &format!("{:?} {}", shape, size),
+            |u, v| shape.point(size, u, v),
+            resolution,
+            resolution.saturating_mul(4),
+            translate,
+        )
+    }
+
+    /// Point of the grid with its normal and texture
+    fn point(&self, i: usize, j: usize) -> Vertex {
+        let u_steps = (self.points[j].len() - 1).max(1) as Scalar;
+        let v_steps = (self.points.len() - 1).max(1) as Scalar;
+        vertex(self.points[j][i], self.normals[j][i]).with_texture(
+            vec2(i as Scalar / u_steps, j as Scalar / v_steps),
+            self.tangents[j][i],
+        )
+    }
+}
+
+/// How the surface changes with u and with v at a point, by the difference either side of it
+fn partials<F>(function: &F, u: Scalar, v: Scalar) -> (Vec3, Vec3)
+where
+    F: Fn(Scalar, Scalar) -> Vec3,
+{
+    (
+        function(u + STEP, v) - function(u - STEP, v),
+        function(u, v + STEP) - function(u, v - STEP),
+    )
+}
+
+impl Drawable for ParametricSurface {
+    /// Surfaces sampling the same function alike share a mesh
+    fn mesh_key(&self) -> String {
+        format!(
+            "parametric {} {} {}",
+            self.name,
+            self.points.first().map_or(0, |row| row.len()),
+            self.points.len()
+        )
+    }
+    /// Generates the vertices of the shape
+    fn mesh_data(&self) -> MeshData {
+        let mut vertices: Vec<Vertex> = Vec::new();
+        for j in 0..self.points.len().saturating_sub(1) {
+            for i in 0..self.points[j].len().saturating_sub(1) {
+                // Counter-clockwise seen from the side the normals point to
+                let (a, b) = (self.point(i, j), self.point(i + 1, j));
+                let (c, d) = (self.point(i + 1, j + 1), self.point(i, j + 1));
+                vertices.extend_from_slice(&[a, b, c, c, d, a]);
+            }
+        }
+        MeshData::new(&vertices)
+    }
+    /// Mesh of the object and how it is placed and looks
+    fn instance(&self) -> &Instance {
+        &self.instance
+    }
+    fn instance_mut(&mut self) -> &mut Instance {
+        &mut self.instance
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{trefoil, ParametricShape, ParametricSurface};
+    use matrix::consts::PI;
+    use matrix::{vec3, Scalar, Vec3};
+    use render::{Drawable, VERTEX_SIZE};
+
+    #[test]
+    fn test_parametric() {
+        // A sphere, u around it and v from the bottom up so its faces point out
+        let sphere = ParametricSurface::new(
+            "sphere",
+            |u, v| {
+                let (around, up) = (u * 2.0 * PI, (v - 0.5) * PI);
+                vec3(around.cos() * up.cos(), up.sin(), -around.sin() * up.cos())
+            },
+            16,
+            8,
+            Vec3::origin(),
+        );
+        let data = sphere.mesh_data();
+        assert_eq!(data.vertices.len(), 16 * 8 * 6 * VERTEX_SIZE);
+        // Normals match the direction out from the middle, even at the poles
+        for (point, normal) in sphere
+            .points
+            .iter()
+            .flatten()
+            .zip(sphere.normals.iter().flatten())
+        {
+            assert!(normal.distance(point) < 1e-2);
+        }
+        // A flat square facing +z, with its texture laid out by u and v
+        let square = ParametricSurface::new("square", |u, v| vec3(u, v, 0.0), 4, 4, Vec3::origin());
+        let corner = square.point(4, 4);
+        assert!((corner.normal().z - 1.0).abs() < 1e-5);
+        assert!(corner.position().distance(&vec3(1.0, 1.0, 0.0)) < 1e-5);
+        // Keyed by name and grid, not every point
+        assert_eq!(square.mesh_key(), "parametric square 5 5");
+    }
+
+    #[test]
+    fn test_shapes() {
+        // The knot's faces point out from the line through its middle
+        let size = 0.9;
+        let knot = ParametricSurface::shape(ParametricShape::TrefoilKnot, size, 8, Vec3::origin());
+        let steps = (knot.points.len() - 1) as Scalar;
+        for (j, row) in knot.points.iter().enumerate() {
+            let middle = trefoil(size, j as Scalar / steps);
+            for (point, normal) in row.iter().zip(&knot.normals[j]) {
+                assert!(point.distance(&(middle + *normal * (size * 0.15))) < 1e-2);
+            }
+        }
+        // The shell's opening faces out from its spiral, and its tip is on top
+        let shell = ParametricSurface::shape(ParametricShape::Seashell, 1.0, 8, Vec3::origin());
+        let opening = shell.points.len() - 1;
+        let (point, normal) = (shell.points[opening][0], shell.normals[opening][0]);
+        assert!(normal.dot(&(point - vec3(0.35, 0.0, 0.0))) > 0.0);
+        let bounds = shell.mesh_data().bounds.unwrap();
+        assert!((bounds.max.y - 1.2).abs() < 1e-5);
+    }
+}
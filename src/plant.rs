@@ -0,0 +1,261 @@
+use std::io;
+
+use image::{Rgba, RgbaImage};
+
+use material::Material;
+use matrix::consts::PI;
+use matrix::{vec2, vec3, Scalar, Transform, Vec2, Vec3};
+use mesh::{Instance, MeshData};
+use render::{quad, Drawable, Vertex, VERTEX_SIZE};
+use revolution::Revolution;
+use texture::{TextureManager, WrapMode};
+use tube::Tube;
+
+/// Times the trunk splits into branches before they end in leaves
+const BRANCH_LEVELS: u32 = 3;
+/// Branches growing from the end of each one
+const BRANCHES: u16 = 3;
+/// Leaves at the end of each twig
+const LEAVES: u16 = 4;
+/// Sides around each branch
+const BRANCH_SIDES: u16 = 6;
+/// Texels of the leaf less opaque than this are cut away
+const LEAF_CUTOFF: Scalar = 0.5;
+
+/// Pixels down the plant's texture, and across its leaf
+const TEXTURE_SIZE: u32 = 32;
+/// Texture coords of the bark and pot colors, beside the leaf filling the texture's left half
+const BARK: Vec2 = Vec2 { x: 0.625, y: 0.5 };
+const POT: Vec2 = Vec2 { x: 0.875, y: 0.5 };
+
+/// Texture of the whole plant, a leaf with the space around it transparent, then a strip of
+/// bark and one of the pot's clay
+///
+/// The leaf is pointed at both ends, so it doesn't matter which way up it is drawn.
+pub fn plant_texture() -> RgbaImage {
+    RgbaImage::from_fn(TEXTURE_SIZE * 2, TEXTURE_SIZE, |x, y| {
+        if x >= TEXTURE_SIZE + TEXTURE_SIZE / 2 {
+            return Rgba([181, 90, 60, 255]);
+        } else if x >= TEXTURE_SIZE {
+            return Rgba([96, 64, 40, 255]);
+        }
+        let across = (x as Scalar + 0.5) / TEXTURE_SIZE as Scalar - 0.5;
+        let along = (y as Scalar + 0.5) / TEXTURE_SIZE as Scalar;
+        let inside = across.abs() < 0.45 * (PI * along).sin();
+        // Lighter down the vein, and the same green around the leaf so filtering doesn't
+        // darken its edge
+        let (green, alpha) = match (across.abs() < 0.04, inside) {
+            (true, true) => ([90, 170, 70], 255),
+            (false, true) => ([60, 140, 50], 255),
+            _ => ([60, 140, 50], 0),
+        };
+        Rgba([green[0], green[1], green[2], alpha])
+    })
+}
+
+/// Small generator of pseudo-random numbers, so the same seed always grows the same plant
+struct Random(u32);
+
+impl Random {
+    fn new(seed: u32) -> Random {
+        // Xorshift never leaves zero, so start anywhere else
+        Random(seed.wrapping_mul(2_654_435_761) | 1)
+    }
+
+    /// Next number from 0 to 1
+    fn next(&mut self) -> Scalar {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 17;
+        self.0 ^= self.0 << 5;
+        self.0 as Scalar / u32::MAX as Scalar
+    }
+}
+
+/// Two directions across `direction` and each other
+fn across(direction: Vec3) -> (Vec3, Vec3) {
+    let other = if direction.y.abs() < 0.9 {
+        vec3(0.0, 1.0, 0.0)
+    } else {
+        vec3(1.0, 0.0, 0.0)
+    };
+    let side = direction.cross(other).normalize();
+    (side, direction.cross(side).normalize())
+}
+
+/// Vertices of a shape all colored by one point of the texture
+fn painted(data: MeshData, texture: Vec2) -> Vec<Vertex> {
+    data.vertices
+        .chunks(VERTEX_SIZE)
+        .map(|vertex| {
+            let vertex = Vertex::from_data(vertex);
+            vertex.with_texture(texture, vec3(1.0, 0.0, 0.0))
+        })
+        .collect()
+}
+
+/// Potted plant, a trunk splitting into branches that end in leaves, about `height` tall
+///
+/// The branches grow from a seed, so plants with different seeds grow differently. Leaves are
+/// flat and cut out of their texture, seen from both sides.
+pub struct Plant {
+    height: Scalar,
+    seed: u32,
+    instance: Instance,
+}
+
+impl Plant {
+    pub fn new(height: Scalar, seed: u32, translate: Vec3) -> Plant {
+        Plant {
+            height,
+            seed,
+            instance: Instance::new(
+                Transform::from_translation(translate),
+                Material::new(
+                    vec3(0.5, 0.5, 0.5),
+                    vec3(0.64, 0.64, 0.64),
+                    vec3(0.1, 0.1, 0.1),
+                    20.0,
+                )
+                .with_alpha_cutoff(LEAF_CUTOFF),
+            ),
+        }
+    }
+
+    /// Adds a branch from `start`, then the branches growing from its end, or leaves there
+    /// once `level` runs out
+    fn grow(
+        &self,
+        random: &mut Random,
+        start: Vec3,
+        direction: Vec3,
+        length: Scalar,
+        level: u32,
+        vertices: &mut Vec<Vertex>,
+    ) {
+        let end = start + direction * length;
+        // Thinner the further out the branch is
+        let radius = self.height * 0.02 * (0.6 as Scalar).powi((BRANCH_LEVELS - level) as i32);
+        let branch = Tube::new(vec![start, end], radius, BRANCH_SIDES, Vec3::origin());
+        vertices.extend(painted(branch.mesh_data(), BARK));
+        let (side, other) = across(direction);
+        let outwards = |around: Scalar| side * around.cos() + other * around.sin();
+
+        if level == 0 {
+            for _ in 0..LEAVES {
+                let around = 2.0 * PI * random.next();
+                let tilt = (30.0 + 40.0 * random.next()).to_radians();
+                let along = (direction * tilt.cos() + outwards(around) * tilt.sin()).normalize();
+                let width = outwards(around + PI / 2.0) * (self.height * 0.03);
+                let tip = end + along * (self.height * 0.12);
+                let corners = [end - width, end + width, tip + width, tip - width];
+                // The leaf fills the left half of the texture, across it and along it
+                let coords = [
+                    vec2(0.0, 0.0),
+                    vec2(0.5, 0.0),
+                    vec2(0.5, 1.0),
+                    vec2(0.0, 1.0),
+                ];
+                // Front, then back
+                for &order in &[[0, 1, 2, 3], [3, 2, 1, 0]] {
+                    let [a, b, c, d] = order;
+                    let texture = [a, b, c, c, d, a];
+                    vertices.extend(
+                        quad(corners[a], corners[b], corners[c], corners[d])
+                            .iter()
+                            .zip(texture.iter())
+                            .map(|(vertex, &i)| vertex.with_texture(coords[i], width.normalize())),
+                    );
+                }
+            }
+            return;
+        }
+
+        let turn = 2.0 * PI * random.next();
+        for i in 0..BRANCHES {
+            let around =
+                turn + 2.0 * PI * (Scalar::from(i) + 0.4 * random.next()) / Scalar::from(BRANCHES);
+            let tilt = (25.0 + 20.0 * random.next()).to_radians();
+            // Spreading out, but still reaching up for the light
+            let branch = direction * tilt.cos() + outwards(around) * tilt.sin();
+            let branch = (branch + vec3(0.0, 0.3, 0.0)).normalize();
+            self.grow(random, end, branch, length * 0.7, level - 1, vertices);
+        }
+    }
+}
+
+impl Drawable for Plant {
+    /// Plants of the same size grown from the same seed share a mesh
+    fn mesh_key(&self) -> String {
+        format!("plant {} {}", self.height, self.seed)
+    }
+    /// Generates the vertices of the shape
+    fn mesh_data(&self) -> MeshData {
+        let h = self.height;
+        // Up the outside of the pot, over the rim and down the inside to the soil
+        let profile = vec![
+            vec3(0.11 * h, 0.0, 0.0),
+            vec3(0.15 * h, 0.25 * h, 0.0),
+            vec3(0.135 * h, 0.25 * h, 0.0),
+            vec3(0.13 * h, 0.22 * h, 0.0),
+        ];
+        let pot = Revolution::new(profile, 24, Vec3::origin()).mesh_data();
+        let mut vertices = painted(pot, POT);
+        let mut random = Random::new(self.seed);
+        self.grow(
+            &mut random,
+            vec3(0.0, 0.22 * h, 0.0),
+            vec3(0.0, 1.0, 0.0),
+            0.3 * h,
+            BRANCH_LEVELS,
+            &mut vertices,
+        );
+        MeshData::new(&vertices)
+    }
+    /// Grows the texture rather than loading one
+    fn load_texture(&mut self, textures: &mut TextureManager) -> Result<(), io::Error> {
+        self.instance.texture = Some(textures.image(plant_texture(), WrapMode::ClampToEdge));
+        Ok(())
+    }
+    /// Mesh of the object and how it is placed and looks
+    fn instance(&self) -> &Instance {
+        &self.instance
+    }
+    fn instance_mut(&mut self) -> &mut Instance {
+        &mut self.instance
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{plant_texture, Plant, BARK, POT, TEXTURE_SIZE};
+    use matrix::{Scalar, Vec3};
+    use render::Drawable;
+
+    #[test]
+    fn test_plant() {
+        let plant = Plant::new(2.0, 7, Vec3::origin()).mesh_data();
+        // Standing on the ground, around its trunk
+        let bounds = plant.bounds.unwrap();
+        assert!(bounds.min.y.abs() < 1e-5 && bounds.max.y > 1.0 && bounds.max.y < 3.0);
+        assert!(bounds.min.x < -0.3 && bounds.max.x > 0.3);
+        // The same seed grows the same plant
+        assert_eq!(
+            plant.vertices,
+            Plant::new(2.0, 7, Vec3::origin()).mesh_data().vertices
+        );
+        assert_ne!(
+            plant.vertices,
+            Plant::new(2.0, 8, Vec3::origin()).mesh_data().vertices
+        );
+        // The leaf is cut out of the texture, the bark and pot are solid
+        let texture = plant_texture();
+        let texel = |x: Scalar, y: Scalar| {
+            let size = TEXTURE_SIZE as Scalar;
+            texture.get_pixel((x * size * 2.0) as u32, (y * size) as u32)[3]
+        };
+        assert_eq!(texel(0.25, 0.5), 255);
+        assert_eq!(texel(0.01, 0.01), 0);
+        assert_eq!(texel(BARK.x, BARK.y), 255);
+        assert_eq!(texel(POT.x, POT.y), 255);
+    }
+}
@@ -0,0 +1,276 @@
+use material::Material;
+use matrix::{transform_direction, vec3, Quat, Scalar, Transform, Vec3};
+use mesh::{Instance, MeshData};
+use render::{icosphere_mesh, newell, polygon, tri, Drawable, Vertex};
+
+/// Solids whose faces are all the same regular polygon
+#[derive(Copy, Clone, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PlatonicSolid {
+    Tetrahedron,
+    Cube,
+    Octahedron,
+    Dodecahedron,
+    Icosahedron,
+}
+
+impl PlatonicSolid {
+    /// Corners of each face on the unit sphere, counter-clockwise seen from outside
+    pub fn faces(self) -> Vec<Vec<Vec3>> {
+        let (points, faces): (Vec<Vec3>, Vec<Vec<usize>>) = match self {
+            PlatonicSolid::Tetrahedron => (
+                vec![
+                    vec3(1.0, 1.0, 1.0),
+                    vec3(1.0, -1.0, -1.0),
+                    vec3(-1.0, 1.0, -1.0),
+                    vec3(-1.0, -1.0, 1.0),
+                ],
+                vec![vec![0, 1, 2], vec![0, 3, 1], vec![0, 2, 3], vec![1, 3, 2]],
+            ),
+            PlatonicSolid::Cube => (
+                (0..8)
+                    .map(|i| {
+                        let sign = |bit: u8| if i & bit == 0 { -1.0 } else { 1.0 };
+                        vec3(sign(4), sign(2), sign(1))
+                    })
+                    .collect(),
+                vec![
+                    vec![0, 1, 3, 2],
+                    vec![4, 6, 7, 5],
+                    vec![0, 4, 5, 1],
+                    vec![2, 3, 7, 6],
+                    vec![0, 2, 6, 4],
+                    vec![1, 5, 7, 3],
+                ],
+            ),
+            PlatonicSolid::Octahedron => (
+                vec![
+                    vec3(1.0, 0.0, 0.0),
+                    vec3(-1.0, 0.0, 0.0),
+                    vec3(0.0, 1.0, 0.0),
+                    vec3(0.0, -1.0, 0.0),
+                    vec3(0.0, 0.0, 1.0),
+                    vec3(0.0, 0.0, -1.0),
+                ],
+                vec![
+                    vec![0, 2, 4],
+                    vec![4, 2, 1],
+                    vec![1, 2, 5],
+                    vec![5, 2, 0],
+                    vec![0, 4, 3],
+                    vec![4, 1, 3],
+                    vec![1, 5, 3],
+                    vec![5, 0, 3],
+                ],
+            ),
+            PlatonicSolid::Icosahedron => {
+                let (points, triangles) = icosphere_mesh(0);
+                (points, triangles.iter().map(|face| face.to_vec()).collect())
+            }
+            // The dual of the icosahedron, a corner in the middle of each of its triangles
+            // and a face around each of its corners
+            PlatonicSolid::Dodecahedron => {
+                let (corners, triangles) = icosphere_mesh(0);
+                let points = triangles
+                    .iter()
+                    .map(|triangle| {
+                        triangle
+                            .iter()
+                            .fold(Vec3::origin(), |sum, &i| sum + corners[i])
+                    })
+                    .collect();
+                let faces = (0..corners.len())
+                    .map(|corner| {
+                        let around: Vec<usize> = (0..triangles.len())
+                            .filter(|&t| triangles[t].contains(&corner))
+                            .collect();
+                        // Each triangle's next around the corner shares its edge leaving it
+                        let mut face = vec![around[0]];
+                        while face.len() < around.len() {
+                            let triangle = triangles[face[face.len() - 1]];
+                            let at = triangle.iter().position(|&i| i == corner).unwrap_or(0);
+                            let leaving = triangle[(at + 2) % 3];
+                            let next = around.iter().find(|&&t| {
+                                let other = triangles[t];
+                                let at = other.iter().position(|&i| i == corner).unwrap_or(0);
+                                other[(at + 1) % 3] == leaving
+                            });
+                            match next {
+                                Some(&next) => face.push(next),
+                                None => break,
+                            }
+                        }
+                        face
+                    })
+                    .collect();
+                (points, faces)
+            }
+        };
+        faces
+            .iter()
+            .map(|face| {
+                let mut face: Vec<Vec3> = face.iter().map(|&i| points[i].normalize()).collect();
+                // Wound to face away from the middle whichever way it was listed
+                let middle = face.iter().fold(Vec3::origin(), |sum, &point| sum + point);
+                if newell(face.clone()).dot(&middle) < 0.0 {
+                    face.reverse();
+                }
+                face
+            })
+            .collect()
+    }
+}
+
+/// Triangles of an icosahedron with a corner at the top, each split along its edges into
+/// `frequency` pieces and pushed out onto the unit sphere, keeping those above the ground
+///
+/// Even frequencies cut the sphere along its equator, odd ones along a ragged line just below
+/// it, as geodesic domes are built.
+pub fn geodesic_dome(frequency: u16) -> Vec<[Vec3; 3]> {
+    let (corners, triangles) = icosphere_mesh(0);
+    // Turn the first corner to the top
+    let top = corners[0];
+    let up = vec3(0.0, 1.0, 0.0);
+    let angle = top.dot(&up).clamp(-1.0, 1.0).acos();
+    let turn = Quat::from_axis_angle(top.cross(up).normalize(), angle).to_mat4();
+    let corners: Vec<Vec3> = corners
+        .iter()
+        .map(|&corner| transform_direction(&turn, corner).normalize())
+        .collect();
+
+    let frequency = frequency.max(1);
+    let steps = Scalar::from(frequency);
+    let mut pieces: Vec<[Vec3; 3]> = Vec::new();
+    for triangle in &triangles {
+        let (a, b, c) = (
+            corners[triangle[0]],
+            corners[triangle[1]],
+            corners[triangle[2]],
+        );
+        // Point `i` steps towards b and `j` towards c
+        let point = |i: u16, j: u16| {
+            (a + (b - a) * (Scalar::from(i) / steps) + (c - a) * (Scalar::from(j) / steps))
+                .normalize()
+        };
+        for j in 0..frequency {
+            for i in 0..frequency - j {
+                pieces.push([point(i, j), point(i + 1, j), point(i, j + 1)]);
+                if i + j + 1 < frequency {
+                    pieces.push([point(i + 1, j), point(i + 1, j + 1), point(i, j + 1)]);
+                }
+            }
+        }
+    }
+    pieces
+        .into_iter()
+        .filter(|piece| piece.iter().map(|point| point.y).sum::<Scalar>() > 0.0)
+        .collect()
+}
+
+#[derive(Copy, Clone, Debug)]
+enum PolyhedronKind {
+    Platonic(PlatonicSolid),
+    Dome { frequency: u16 },
+}
+
+/// Shape of flat faces with its corners `radius` from its origin, either a Platonic solid
+/// around the origin or a geodesic dome standing on it, open underneath
+pub struct Polyhedron {
+    kind: PolyhedronKind,
+    radius: Scalar,
+    instance: Instance,
+}
+
+impl Polyhedron {
+    pub fn platonic(solid: PlatonicSolid, radius: Scalar, translate: Vec3) -> Polyhedron {
+        Polyhedron::with_kind(PolyhedronKind::Platonic(solid), radius, translate)
+    }
+
+    /// Creates a dome from an icosahedron with each edge split into `frequency` pieces
+    pub fn geodesic_dome(radius: Scalar, frequency: u16, translate: Vec3) -> Polyhedron {
+        Polyhedron::with_kind(PolyhedronKind::Dome { frequency }, radius, translate)
+    }
+
+    fn with_kind(kind: PolyhedronKind, radius: Scalar, translate: Vec3) -> Polyhedron {
+        Polyhedron {
+            kind,
+            radius,
+            instance: Instance::new(Transform::from_translation(translate), Material::pearl()),
+        }
+    }
+}
+
+impl Drawable for Polyhedron {
+    /// Polyhedra of the same kind and size share a mesh
+    fn mesh_key(&self) -> String {
+        format!("polyhedron {:?} {}", self.kind, self.radius)
+    }
+    /// Generates the vertices of the shape
+    fn mesh_data(&self) -> MeshData {
+        let vertices: Vec<Vertex> = match self.kind {
+            PolyhedronKind::Platonic(solid) => solid
+                .faces()
+                .iter()
+                .flat_map(|face| {
+                    let face: Vec<Vec3> = face.iter().map(|&point| point * self.radius).collect();
+                    polygon(&face)
+                })
+                .collect(),
+            PolyhedronKind::Dome { frequency } => geodesic_dome(frequency)
+                .iter()
+                .flat_map(|&[a, b, c]| {
+                    tri(a * self.radius, b * self.radius, c * self.radius).to_vec()
+                })
+                .collect(),
+        };
+        MeshData::new(&vertices)
+    }
+    /// Mesh of the object and how it is placed and looks
+    fn instance(&self) -> &Instance {
+        &self.instance
+    }
+    fn instance_mut(&mut self) -> &mut Instance {
+        &mut self.instance
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{geodesic_dome, PlatonicSolid};
+    use matrix::{Scalar, Vec3};
+    use render::newell;
+
+    #[test]
+    fn test_polyhedron() {
+        // Faces and corners of each, and the count of every face's corners
+        for &(solid, faces, corners) in &[
+            (PlatonicSolid::Tetrahedron, 4, 3),
+            (PlatonicSolid::Cube, 6, 4),
+            (PlatonicSolid::Octahedron, 8, 3),
+            (PlatonicSolid::Dodecahedron, 12, 5),
+            (PlatonicSolid::Icosahedron, 20, 3),
+        ] {
+            let all = solid.faces();
+            assert_eq!(all.len(), faces);
+            for face in &all {
+                assert_eq!(face.len(), corners);
+                // Regular, and facing out
+                let middle = face.iter().fold(Vec3::origin(), |sum, &point| sum + point);
+                assert!(newell(face.clone()).dot(&middle) > 0.0);
+                for (i, point) in face.iter().enumerate() {
+                    let next = face[(i + 1) % corners];
+                    assert!((point.length() - 1.0).abs() < 1e-5);
+                    assert!((point.distance(&next) - face[0].distance(&face[1])).abs() < 1e-5);
+                }
+            }
+        }
+        // Half of an icosahedron split twice along each edge, and the dome stands on its rim
+        let dome = geodesic_dome(2);
+        assert_eq!(dome.len(), 20 * 4 / 2);
+        let lowest = dome
+            .iter()
+            .flat_map(|piece| piece.iter().map(|point| point.y))
+            .fold(1.0 as Scalar, |low, y| low.min(y));
+        assert!(lowest.abs() < 1e-5);
+    }
+}
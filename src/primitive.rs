@@ -0,0 +1,150 @@
+use material::Material;
+use matrix::{Scalar, Transform, Vec3};
+use mesh::{Instance, MeshData};
+use render::{capsule, cone, cylinder, torus, Drawable};
+
+/// Shapes a primitive can take, each upright around the y axis and centered on its origin
+#[derive(Copy, Clone, Debug)]
+pub enum Shape {
+    /// Open at both ends unless capped
+    Cylinder {
+        radius: Scalar,
+        height: Scalar,
+        capped: bool,
+    },
+    /// Point upwards, with its base closed
+    Cone { radius: Scalar, height: Scalar },
+    /// Ring `radius` out to the middle of a tube of radius `tube`
+    Torus { radius: Scalar, tube: Scalar },
+    /// Cylinder with a half sphere on each end, `height` tall from end to end
+    Capsule { radius: Scalar, height: Scalar },
+}
+
+/// Cylinder, cone, torus or capsule drawn as an object
+pub struct Primitive {
+    shape: Shape,
+    /// Slices around the y axis, the tube of a torus is split into half as many and each end
+    /// of a capsule into a quarter as many
+    segments: u16,
+    instance: Instance,
+}
+
+impl Primitive {
+    pub fn new(shape: Shape, segments: u16, translate: Vec3) -> Primitive {
+        Primitive {
+            shape,
+            segments,
+            instance: Instance::new(Transform::from_translation(translate), Material::pearl()),
+        }
+    }
+}
+
+impl Drawable for Primitive {
+    /// Primitives of the same shape and detail share a mesh
+    fn mesh_key(&self) -> String {
+        format!("primitive {:?} {}", self.shape, self.segments)
+    }
+    /// Generates the vertices of the shape
+    fn mesh_data(&self) -> MeshData {
+        let origin = Vec3::origin();
+        let vertices = match self.shape {
+            Shape::Cylinder {
+                radius,
+                height,
+                capped,
+            } => cylinder(origin, radius, height, self.segments, capped),
+            Shape::Cone { radius, height } => cone(origin, radius, height, self.segments),
+            Shape::Torus { radius, tube } => {
+                torus(origin, radius, tube, self.segments, self.segments / 2)
+            }
+            Shape::Capsule { radius, height } => {
+                capsule(origin, radius, height, self.segments / 4, self.segments)
+            }
+        };
+        MeshData::new(&vertices)
+    }
+    /// Mesh of the object and how it is placed and looks
+    fn instance(&self) -> &Instance {
+        &self.instance
+    }
+    fn instance_mut(&mut self) -> &mut Instance {
+        &mut self.instance
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Primitive, Shape};
+    use matrix::{vec3, Scalar, Vec3};
+    use render::{Drawable, VERTEX_SIZE};
+
+    /// Positions and normals of every vertex
+    fn surface(shape: Shape) -> Vec<(Vec3, Vec3)> {
+        let data = Primitive::new(shape, 16, Vec3::origin()).mesh_data();
+        data.vertices
+            .chunks(VERTEX_SIZE)
+            .map(|vertex| {
+                let value = |i: usize| Scalar::from(vertex[i]);
+                (
+                    vec3(value(0), value(1), value(2)),
+                    vec3(value(3), value(4), value(5)),
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_primitives() {
+        // The side of a cylinder faces straight out, and its caps straight up and down
+        let tube = Shape::Cylinder {
+            radius: 1.0,
+            height: 2.0,
+            capped: false,
+        };
+        assert_eq!(surface(tube).len(), 16 * 6);
+        for (position, normal) in surface(tube) {
+            assert!(position.distance(&(normal + vec3(0.0, position.y, 0.0))) < 1e-4);
+        }
+        let capped = Shape::Cylinder {
+            radius: 1.0,
+            height: 2.0,
+            capped: true,
+        };
+        assert_eq!(surface(capped).len(), 16 * (6 + 3 + 3));
+        // A cone's normals lean up as much as its side leans in
+        let cone = Shape::Cone {
+            radius: 1.0,
+            height: 1.0,
+        };
+        for (position, normal) in surface(cone) {
+            assert!((normal.length() - 1.0).abs() < 1e-4);
+            if position.y > -0.5 {
+                assert!((normal.y - (0.5 as Scalar).sqrt()).abs() < 1e-4);
+            }
+        }
+        // Every point of a torus is a tube's radius from the circle through its middle
+        let ring = Shape::Torus {
+            radius: 2.0,
+            tube: 0.5,
+        };
+        for (position, normal) in surface(ring) {
+            let middle = vec3(position.x, 0.0, position.z).normalize() * 2.0;
+            assert!(position.distance(&(middle + normal * 0.5)) < 1e-4);
+        }
+        // Every point of a capsule is its radius from the line between the middles of its
+        // ends, and it is as tall as asked
+        let pill = Shape::Capsule {
+            radius: 0.5,
+            height: 3.0,
+        };
+        assert_eq!(surface(pill).len(), 16 * (2 * 4 + 1) * 6);
+        for (position, normal) in surface(pill) {
+            assert!((normal.length() - 1.0).abs() < 1e-4);
+            let middle = vec3(0.0, position.y.clamp(-1.0, 1.0), 0.0);
+            assert!(position.distance(&(middle + normal * 0.5)) < 1e-4);
+            assert!(position.y.abs() < 1.5 + 1e-4);
+        }
+        let data = Primitive::new(pill, 16, Vec3::origin()).mesh_data();
+        assert!((data.bounds.unwrap().max.y - 1.5).abs() < 1e-4);
+    }
+}
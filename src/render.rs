@@ -1,52 +1,246 @@
 use std::collections::HashMap;
-use std::f32::consts::PI;
 use std::io;
-use std::path::Path;
-
-use gleam::gl;
-use gleam::gl::types::{GLenum, GLint, GLsizei};
+use std::path::PathBuf;
 
 use super::Context;
+use aabb::Aabb;
 use error::io_error;
-use matrix::{identity, matmul, rotate_x, rotate_y, scale, translate, vec3, Vec2, Vec3};
+use light::Light;
+use material::Material;
+use matrix::consts::PI;
+use matrix::{
+    gl_float, identity, rotate_x, rotate_y, scale, transform_direction, transform_point, translate,
+    vec2, vec3, Mat4, Scalar, Transform, Vec2, Vec3,
+};
+use mesh::{Instance, MeshData};
+use texture::{TextureManager, TextureOptions};
 
 pub trait Drawable {
-    /// Returns buffer data
-    fn buffer_data(&mut self, vertex_start: GLint) -> Vec<f32>;
-    /// Loads texture data
-    fn load_texture(&self, ctx: &Context) {}
+    /// Names the shape, objects with the same key share one mesh
+    fn mesh_key(&self) -> String;
+    /// Generates the vertices of the shape
+    fn mesh_data(&self) -> MeshData;
+    /// The object's mesh and how it is placed and looks
+    fn instance(&self) -> &Instance;
+    fn instance_mut(&mut self) -> &mut Instance;
+    /// Loads texture data, by default the texture given to the instance
+    fn load_texture(&mut self, textures: &mut TextureManager) -> Result<(), io::Error> {
+        if let Some(path) = self.instance().texture_path.clone() {
+            let texture = textures.load(path, self.instance().texture_options)?;
+            self.instance_mut().texture = Some(texture);
+        }
+        Ok(())
+    }
+    /// Gives the shape a texture to load, sampled with `options`
+    fn set_texture(&mut self, path: PathBuf, options: TextureOptions) {
+        let instance = self.instance_mut();
+        instance.texture_path = Some(path);
+        instance.texture_options = options;
+    }
+    /// Whether the shape needs to be alpha blended
+    fn translucent(&self, _textures: &TextureManager) -> bool {
+        false
+    }
+    /// Places the shape in the world
+    fn transform(&self) -> &Transform {
+        &self.instance().transform
+    }
+    /// How the shape reflects light
+    fn material(&self) -> &Material {
+        &self.instance().material
+    }
+    /// Swaps how the shape reflects light
+    fn set_material(&mut self, material: Material) {
+        self.instance_mut().material = material;
+    }
+    /// Extent of the shape before it is transformed, None if it is never culled
+    fn bounds(&self) -> Option<Aabb> {
+        self.instance().mesh.and_then(|mesh| mesh.bounds)
+    }
+    /// Whether the shape can be drawn in one call with others sharing its mesh and material,
+    /// shapes drawing themselves in their own way can't
+    fn instanced(&self) -> bool {
+        true
+    }
+    /// World position used to sort translucent shapes
+    fn position(&self) -> Vec3 {
+        self.transform().translation
+    }
+    /// Light the shape gives off, in its own coordinates so it moves with the shape
+    fn light(&self) -> Option<Light> {
+        None
+    }
     /// Draws the shape
-    fn draw(&self, ctx: &Context);
+    fn draw(&self, ctx: &Context) {
+        self.instance().draw(ctx);
+    }
+}
+
+/// How many objects the last frame drew and how many were outside the view
+#[derive(Copy, Clone, Debug, Default)]
+pub struct DrawStats {
+    pub drawn: u32,
+    pub culled: u32,
+    /// Objects sharing a mesh and material are drawn by one call
+    pub draw_calls: u32,
 }
 
+/// Number of floats buffered per vertex
+pub const VERTEX_SIZE: usize = 11;
+
 /// Used to represent data buffered into vertex
 #[derive(Copy, Clone, Debug)]
 pub struct Vertex {
     position: Vec3,
     normal: Vec3,
     texture: Vec2,
+    tangent: Vec3,
 }
-/// Creates a vertex without texture coords
+/// Creates a vertex with texture coords projected from the side of a box its normal faces
 pub fn vertex(position: Vec3, normal: Vec3) -> Vertex {
+    let (texture, tangent) = box_projection(position, normal);
     Vertex {
         position,
         normal,
-        texture: Vec2::origin(),
+        texture,
+        tangent,
+    }
+}
+
+/// Texture coords of a point seen from the side of a box its normal faces most, and the
+/// direction they increase in
+///
+/// One unit of the shape covers the whole texture, so textures repeat at the same size
+/// across every part of a shape.
+fn box_projection(position: Vec3, normal: Vec3) -> (Vec2, Vec3) {
+    let (x, y, z) = (normal.x.abs(), normal.y.abs(), normal.z.abs());
+    // Each side is seen with the texture upright and running to the right
+    if x >= y && x >= z {
+        let side = normal.x.signum();
+        (vec2(-side * position.z, position.y), vec3(0.0, 0.0, -side))
+    } else if y >= z {
+        let side = normal.y.signum();
+        (vec2(position.x, -side * position.z), vec3(1.0, 0.0, 0.0))
+    } else {
+        let side = normal.z.signum();
+        (vec2(side * position.x, position.y), vec3(side, 0.0, 0.0))
     }
 }
 impl Vertex {
-    pub fn to_data(&self) -> [f32; 8] {
+    pub fn to_data(&self) -> [f32; VERTEX_SIZE] {
         [
-            self.position.x,
-            self.position.y,
-            self.position.z,
-            self.normal.x,
-            self.normal.y,
-            self.normal.z,
-            self.texture.x,
-            self.texture.y,
+            gl_float(self.position.x),
+            gl_float(self.position.y),
+            gl_float(self.position.z),
+            gl_float(self.normal.x),
+            gl_float(self.normal.y),
+            gl_float(self.normal.z),
+            gl_float(self.texture.x),
+            gl_float(self.texture.y),
+            gl_float(self.tangent.x),
+            gl_float(self.tangent.y),
+            gl_float(self.tangent.z),
         ]
     }
+
+    /// Reads back a vertex buffered by `to_data`
+    pub fn from_data(data: &[f32]) -> Vertex {
+        let value = |i: usize| Scalar::from(data[i]);
+        Vertex {
+            position: vec3(value(0), value(1), value(2)),
+            normal: vec3(value(3), value(4), value(5)),
+            texture: vec2(value(6), value(7)),
+            tangent: vec3(value(8), value(9), value(10)),
+        }
+    }
+
+    pub fn position(&self) -> Vec3 {
+        self.position
+    }
+
+    pub fn normal(&self) -> Vec3 {
+        self.normal
+    }
+
+    /// Vertex `t` of the way to `other`, for cutting an edge between them
+    pub fn lerp(&self, other: &Vertex, t: Scalar) -> Vertex {
+        let normal = self.normal + (other.normal - self.normal) * t;
+        Vertex {
+            position: self.position + (other.position - self.position) * t,
+            normal: if normal.length() > 0.0 {
+                normal.normalize()
+            } else {
+                normal
+            },
+            texture: vec2(
+                self.texture.x + (other.texture.x - self.texture.x) * t,
+                self.texture.y + (other.texture.y - self.texture.y) * t,
+            ),
+            tangent: self.tangent + (other.tangent - self.tangent) * t,
+        }
+    }
+
+    pub fn with_normal(self, normal: Vec3) -> Vertex {
+        Vertex { normal, ..self }
+    }
+
+    /// Gives the vertex texture coords and the direction they increase in along the surface
+    pub fn with_texture(self, texture: Vec2, tangent: Vec3) -> Vertex {
+        Vertex {
+            texture,
+            tangent,
+            ..self
+        }
+    }
+
+    /// Moves the vertex by a matrix without scaling, turning its normal and tangent with it
+    pub fn transformed(&self, matrix: &Mat4) -> Vertex {
+        Vertex {
+            position: transform_point(matrix, self.position),
+            normal: transform_direction(matrix, self.normal),
+            texture: self.texture,
+            tangent: transform_direction(matrix, self.tangent),
+        }
+    }
+}
+
+/// Box around every vertex
+pub fn bounds(vertices: &[Vertex]) -> Option<Aabb> {
+    let positions: Vec<Vec3> = vertices.iter().map(|vertex| vertex.position).collect();
+    Aabb::from_points(&positions)
+}
+
+/// Box around every vertex however far it is spun around the y axis
+pub fn spin_bounds(vertices: &[Vertex]) -> Option<Aabb> {
+    let aabb = bounds(vertices)?;
+    let radius = vertices
+        .iter()
+        .map(|vertex| vertex.position.x.hypot(vertex.position.z))
+        .fold(0.0, Scalar::max);
+    Some(Aabb::new(
+        vec3(-radius, aabb.min.y, -radius),
+        vec3(radius, aabb.max.y, radius),
+    ))
+}
+
+/// Calculates the tangent of a triangle from its texture coordinates
+pub fn tangent(positions: [Vec3; 3], texture_coords: [Vec2; 3]) -> Vec3 {
+    let edge1 = positions[1] - positions[0];
+    let edge2 = positions[2] - positions[0];
+    let (du1, dv1) = (
+        texture_coords[1].x - texture_coords[0].x,
+        texture_coords[1].y - texture_coords[0].y,
+    );
+    let (du2, dv2) = (
+        texture_coords[2].x - texture_coords[0].x,
+        texture_coords[2].y - texture_coords[0].y,
+    );
+    let det = du1 * dv2 - du2 * dv1;
+    // Degenerate texture coordinates have no tangent
+    if det.abs() < Scalar::EPSILON {
+        return Vec3::origin();
+    }
+    (edge1 * dv2 - edge2 * dv1) * (1.0 / det)
 }
 
 #[derive(Debug, PartialEq)]
@@ -139,7 +333,12 @@ impl Color {
 mod test {
     use std::io;
 
-    use super::Color;
+    use super::{
+        bounds, catmull_rom, newell, polygon, rectangular_prism, rounded_prism, smooth_normals,
+        star, Color, VERTEX_SIZE,
+    };
+    use matrix::consts::PI;
+    use matrix::{vec3, Scalar, Vec3};
 
     #[test]
     fn test_color() -> io::Result<()> {
@@ -172,6 +371,125 @@ mod test {
         );
         Ok(())
     }
+
+    #[test]
+    fn test_catmull_rom() {
+        let controls = [
+            vec3(0.0, 0.0, 0.0),
+            vec3(1.0, 1.0, 0.0),
+            vec3(2.0, 0.0, 0.0),
+            vec3(3.0, 1.0, 0.0),
+        ];
+        let curve = catmull_rom(&controls, 4);
+        // The curve passes through every control point, with the samples between them
+        assert_eq!(curve.len(), 3 * 4 + 1);
+        for (i, control) in controls.iter().enumerate() {
+            assert!(curve[i * 4].distance(control) < 1e-5);
+        }
+        assert!(curve[2].y > 0.5 && curve[2].x > 0.0 && curve[2].x < 1.0);
+    }
+
+    #[test]
+    fn test_polygon() {
+        // The star's outline ends where it starts, and every other corner is concave
+        let outline = star(5, 0.3, 1.0, 0.0, vec3(0.0, 1.0, 0.0));
+        let vertices = polygon(&outline);
+        assert_eq!(vertices.len(), 8 * 3);
+        // The triangles cover the star exactly once, all wound the way the outline is
+        let area = |a: Vec3, b: Vec3, c: Vec3| (b - a).cross(c - a).y / 2.0;
+        let expected: Scalar = outline
+            .windows(2)
+            .map(|pair| area(Vec3::origin(), pair[0], pair[1]))
+            .sum();
+        let mut total = 0.0;
+        for triangle in vertices.chunks(3) {
+            let triangle = area(
+                triangle[0].position(),
+                triangle[1].position(),
+                triangle[2].position(),
+            );
+            assert!(triangle * expected > 0.0);
+            total += triangle;
+        }
+        assert!((total - expected).abs() < 1e-5);
+    }
+
+    #[test]
+    fn test_star() {
+        let outline = star(4, 0.5, 2.0, PI / 4.0, vec3(0.0, 1.0, 0.0));
+        assert_eq!(outline.len(), 4 * 2 + 1);
+        assert!(outline[0].distance(&outline[8]) < 1e-5);
+        // Lying flat, the first corner is turned from +x towards -z, halfway to the next
+        // outer corner
+        let corner = vec3(1.0, 0.0, -1.0).normalize() * 2.0;
+        assert!(outline[0].distance(&corner) < 1e-5);
+        assert!(outline[1].distance(&vec3(0.0, 0.0, -0.5)) < 1e-5);
+        assert!(newell(outline[..8].to_vec()).y > 0.99);
+        // Standing facing +z, the first corner points straight up
+        let standing = star(5, 0.4, 1.0, 0.0, vec3(0.0, 0.0, 1.0));
+        assert!(standing[0].distance(&vec3(0.0, 1.0, 0.0)) < 1e-5);
+        assert!(newell(standing[..10].to_vec()).z > 0.99);
+    }
+
+    #[test]
+    fn test_smooth_normals() {
+        let cube = rectangular_prism(vec3(0.0, 0.0, 0.0), 2.0, 2.0, 2.0);
+        // The box's edges are right angles, sharper than the crease
+        for (smooth, flat) in smooth_normals(&cube, PI / 3.0).iter().zip(&cube) {
+            assert!(smooth.normal().distance(&flat.normal()) < 1e-5);
+        }
+        // Past the crease, every corner points out along its diagonal, its three faces each
+        // a right angle wide there
+        for vertex in smooth_normals(&cube, PI * 0.6) {
+            let diagonal = vertex.position().normalize();
+            assert!(vertex.normal().distance(&diagonal) < 1e-5);
+        }
+    }
+
+    #[test]
+    fn test_rounded_prism() {
+        let vertices = rounded_prism(vec3(1.0, 0.0, 0.0), 2.0, 4.0, 3.0, 0.5, 4);
+        // Each side is split each way into its flat middle and the segments of both edges
+        assert_eq!(vertices.len(), 6 * 9 * 9 * 6);
+        let bounds = bounds(&vertices).unwrap();
+        assert!((bounds.min.x - 0.0).abs() < 1e-5 && (bounds.max.x - 2.0).abs() < 1e-5);
+        assert!((bounds.max.y - 1.5).abs() < 1e-5 && (bounds.max.z - 2.0).abs() < 1e-5);
+        // Smooth, every normal points out from the box the rounding is wrapped around
+        for vertex in &vertices {
+            let core = vec3(0.5, 1.0, 1.5);
+            let offset = vertex.position() - vec3(1.0, 0.0, 0.0);
+            let inside = offset.clamp(&-core, &core);
+            assert!(((offset - inside).length() - 0.5).abs() < 1e-5);
+            assert!(vertex.normal().distance(&((offset - inside) * 2.0)) < 1e-5);
+        }
+        // Without any rounding it is a plain box
+        assert_eq!(
+            rounded_prism(Vec3::origin(), 2.0, 4.0, 3.0, 0.0, 4).len(),
+            36
+        );
+    }
+
+    #[test]
+    fn test_box_projection() {
+        let vertices: Vec<f32> = rectangular_prism(vec3(0.0, 0.0, 0.0), 2.0, 4.0, 3.0)
+            .iter()
+            .flat_map(|vertex| vertex.to_data().to_vec())
+            .collect();
+        for vertex in vertices.chunks(VERTEX_SIZE) {
+            let value = |i: usize| Scalar::from(vertex[i]);
+            let (position, normal) = (
+                vec3(value(0), value(1), value(2)),
+                vec3(value(3), value(4), value(5)),
+            );
+            let tangent = vec3(value(8), value(9), value(10));
+            // Texture coords follow the sides, turning to the right along the tangent
+            assert!(tangent.dot(&normal).abs() < 1e-5 && (tangent.length() - 1.0).abs() < 1e-5);
+            assert!((value(6) - tangent.dot(&position)).abs() < 1e-5);
+            if normal.y.abs() < 0.5 {
+                assert!((value(7) - position.y).abs() < 1e-5);
+            }
+        }
+    }
 }
 
 // Helper functions
@@ -244,44 +562,221 @@ pub fn quad(a: Vec3, b: Vec3, c: Vec3, d: Vec3) -> [Vertex; 6] {
     ]
 }
 
+/// Fills a flat outline with triangles wound the same way it is, concave or not
+///
+/// Triangles are clipped off as ears, corners whose triangle doesn't hold any other point of
+/// the outline, until only one is left. Outlines ending on their first point are fine.
 pub fn polygon(vertices: &[Vec3]) -> Vec<Vertex> {
-    vertices
-        .windows(3)
-        .flat_map(|vertices| {
-            let norm = newell(vec![vertices[0], vertices[1], vertices[2]]);
-            vec![
-                vertex(vertices[0], norm),
-                vertex(vertices[1], norm),
-                vertex(vertices[2], norm),
+    let mut points: Vec<Vec3> = Vec::new();
+    for &point in vertices {
+        if points
+            .last()
+            .is_none_or(|last| last.distance(&point) > 1e-6)
+        {
+            points.push(point);
+        }
+    }
+    while points.len() > 1 && points[0].distance(&points[points.len() - 1]) <= 1e-6 {
+        points.pop();
+    }
+    if points.len() < 3 {
+        return Vec::new();
+    }
+    let norm = newell(points.clone());
+    // Turning the way the outline winds, seen from the side it faces
+    let turns = |a: Vec3, b: Vec3, c: Vec3| (b - a).cross(c - b).dot(&norm);
+
+    let mut remaining: Vec<usize> = (0..points.len()).collect();
+    let mut triangles: Vec<[usize; 3]> = Vec::new();
+    while remaining.len() > 3 {
+        let count = remaining.len();
+        let corner = |i: usize| {
+            [
+                remaining[(i + count - 1) % count],
+                remaining[i],
+                remaining[(i + 1) % count],
             ]
-        })
+        };
+        let ear = (0..count).find(|&i| {
+            let [a, b, c] = corner(i);
+            let (a, b, c) = (points[a], points[b], points[c]);
+            turns(a, b, c) > 0.0
+                && remaining.iter().all(|&other| {
+                    let point = points[other];
+                    // Points on the corner itself don't block it
+                    [a, b, c]
+                        .iter()
+                        .any(|corner| corner.distance(&point) <= 1e-6)
+                        || turns(a, b, point) < 0.0
+                        || turns(b, c, point) < 0.0
+                        || turns(c, a, point) < 0.0
+                })
+        });
+        // Outlines that cross themselves may run out of ears, so clip whatever is left
+        let ear = ear.unwrap_or(0);
+        triangles.push(corner(ear));
+        remaining.remove(ear);
+    }
+    triangles.push([remaining[0], remaining[1], remaining[2]]);
+
+    triangles
+        .iter()
+        .flat_map(|triangle| triangle.iter().map(|&i| vertex(points[i], norm)))
         .collect()
 }
 
-pub fn star(num_points: u16, in_radius: f32, out_radius: f32) -> Vec<Vec3> {
-    let theta = PI / f32::from(num_points);
+/// Points closer than this are welded into one
+pub const WELD_DISTANCE: Scalar = 1e-5;
 
-    (0..=num_points)
-        .flat_map(|i| {
-            let i = f32::from(i);
+/// Key shared by every point that welds to the same place
+pub fn weld_key(position: Vec3) -> (i64, i64, i64) {
+    (
+        (position.x / WELD_DISTANCE).round() as i64,
+        (position.y / WELD_DISTANCE).round() as i64,
+        (position.z / WELD_DISTANCE).round() as i64,
+    )
+}
+
+/// Shades triangles smoothly across the corners they share, so facets look like a curve
+///
+/// Corners at the same place are welded, and each takes the normals of the triangles there
+/// facing less than `crease` radians from its own, weighted by how wide each triangle is at
+/// that corner. Edges sharper than `crease` stay hard.
+pub fn smooth_normals(vertices: &[Vertex], crease: Scalar) -> Vec<Vertex> {
+    // Normal of each corner's triangle, and the angle the triangle has there
+    let corners: Vec<(Vec3, Scalar)> = vertices
+        .chunks_exact(3)
+        .flat_map(|triangle| {
+            let [a, b, c] = [
+                triangle[0].position,
+                triangle[1].position,
+                triangle[2].position,
+            ];
+            let normal = (b - a).cross(c - a);
+            let normal = if normal.length() > 0.0 {
+                normal.normalize()
+            } else {
+                normal
+            };
+            let angle = |corner: Vec3, next: Vec3, previous: Vec3| {
+                let (next, previous) = (next - corner, previous - corner);
+                if next.length() > 0.0 && previous.length() > 0.0 {
+                    next.normalize()
+                        .dot(&previous.normalize())
+                        .clamp(-1.0, 1.0)
+                        .acos()
+                } else {
+                    0.0
+                }
+            };
             vec![
-                vec3(
-                    in_radius * (i * theta * 2.0).cos(),
-                    0.0,
-                    in_radius * (i * theta * 2.0).sin(),
-                ),
-                vec3(
-                    out_radius * (i * theta * 2.0 + 1.0).cos(),
-                    0.0,
-                    out_radius * (i * theta * 2.0 + 1.0).sin(),
-                ),
-                vec3(0.0, 0.0, 0.0),
+                (normal, angle(a, b, c)),
+                (normal, angle(b, c, a)),
+                (normal, angle(c, a, b)),
             ]
         })
+        .collect();
+    let mut welded: HashMap<(i64, i64, i64), Vec<usize>> = HashMap::new();
+    for (i, vertex) in vertices.iter().enumerate().take(corners.len()) {
+        welded.entry(weld_key(vertex.position)).or_default().push(i);
+    }
+
+    let cos_crease = crease.cos();
+    vertices
+        .iter()
+        .enumerate()
+        .map(|(i, vertex)| {
+            let own = match corners.get(i) {
+                Some(&(own, _)) if own.length() > 0.0 => own,
+                // Left over or without an area, there is nothing to smooth it with
+                _ => return *vertex,
+            };
+            let normal = welded[&weld_key(vertex.position)]
+                .iter()
+                .map(|&j| corners[j])
+                .filter(|(normal, _)| normal.dot(&own) >= cos_crease)
+                .fold(Vec3::origin(), |sum, (normal, angle)| sum + normal * angle);
+            if normal.length() > 0.0 {
+                vertex.with_normal(normal.normalize())
+            } else {
+                vertex.with_normal(own)
+            }
+        })
         .collect()
 }
+
+/// Outline of a star around `axis`, from an outer corner to the inner corner after it and on
+/// around, ending back on the first one
+///
+/// The outline is counter-clockwise seen from the way `axis` points, so it fills as a polygon
+/// facing along it and extrudes along it. The first point is `phase` radians around from the
+/// direction across the axis nearest +y, or +x for stars lying flat around the y axis.
+pub fn star(
+    num_points: u16,
+    in_radius: Scalar,
+    out_radius: Scalar,
+    phase: Scalar,
+    axis: Vec3,
+) -> Vec<Vec3> {
+    let axis = axis.normalize();
+    let up = vec3(0.0, 1.0, 0.0);
+    let start = up - axis * up.dot(&axis);
+    let start = if start.length() > 1e-6 {
+        start.normalize()
+    } else {
+        vec3(1.0, 0.0, 0.0)
+    };
+    let turned = axis.cross(start);
+    // Half the angle from one outer corner to the next
+    let theta = PI / Scalar::from(num_points);
+
+    let mut outline: Vec<Vec3> = (0..num_points * 2)
+        .map(|i| {
+            let radius = if i % 2 == 0 { out_radius } else { in_radius };
+            let angle = phase + Scalar::from(i) * theta;
+            (start * angle.cos() + turned * angle.sin()) * radius
+        })
+        .collect();
+    if let Some(&first) = outline.first() {
+        outline.push(first);
+    }
+    outline
+}
+
+/// Samples a Catmull-Rom curve through every control point, `samples` points from each one
+/// to the next
+///
+/// The ends of the curve leave the first and last points heading toward their neighbours.
+pub fn catmull_rom(controls: &[Vec3], samples: u16) -> Vec<Vec3> {
+    let samples = samples.max(1);
+    let last = match controls.len() {
+        0 => return Vec::new(),
+        len => len - 1,
+    };
+    let mut points: Vec<Vec3> = (0..last)
+        .flat_map(|i| {
+            let (p0, p1) = (controls[i.saturating_sub(1)], controls[i]);
+            let (p2, p3) = (controls[i + 1], controls[last.min(i + 2)]);
+            (0..samples).map(move |sample| {
+                let t = Scalar::from(sample) / Scalar::from(samples);
+                (p1 * 2.0
+                    + (p2 - p0) * t
+                    + (p0 * 2.0 - p1 * 5.0 + p2 * 4.0 - p3) * (t * t)
+                    + (p1 * 3.0 - p0 - p2 * 3.0 + p3) * (t * t * t))
+                    * 0.5
+            })
+        })
+        .collect();
+    points.push(controls[last]);
+    points
+}
 /// Generates a rectangular_prism, cen
-pub fn rectangular_prism(center: Vec3, width: f32, height: f32, depth: f32) -> Vec<Vertex> {
+pub fn rectangular_prism(
+    center: Vec3,
+    width: Scalar,
+    height: Scalar,
+    depth: Scalar,
+) -> Vec<Vertex> {
     // Easy access to self elements
     // Start by creating the table top
     // FRONT
@@ -304,18 +799,19 @@ pub fn rectangular_prism(center: Vec3, width: f32, height: f32, depth: f32) -> V
     let btr = vec3(width / 2.0, depth / 2.0, height / 2.0);
     // Allocate vector for each quad
     let mut vertices: Vec<Vertex> = Vec::with_capacity(36);
+    // Each face is wound counter-clockwise seen from outside the box
     // Front
-    vertices.extend_from_slice(&quad(ftl, fbl, fbr, ftr));
+    vertices.extend_from_slice(&quad(ftr, fbr, fbl, ftl));
     // Back
-    vertices.extend_from_slice(&quad(btr, bbr, bbl, btl));
+    vertices.extend_from_slice(&quad(btl, bbl, bbr, btr));
     // Left
-    vertices.extend_from_slice(&quad(btl, bbl, fbl, ftl));
+    vertices.extend_from_slice(&quad(ftl, fbl, bbl, btl));
     // Right
-    vertices.extend_from_slice(&quad(ftr, fbr, bbr, btr));
+    vertices.extend_from_slice(&quad(btr, bbr, fbr, ftr));
     // Top
-    vertices.extend_from_slice(&quad(btl, ftl, ftr, btr));
+    vertices.extend_from_slice(&quad(btr, ftr, ftl, btl));
     // Bottom
-    vertices.extend_from_slice(&quad(fbl, bbl, bbr, fbr));
+    vertices.extend_from_slice(&quad(fbr, bbr, bbl, fbl));
 
     vertices
         .iter()
@@ -324,49 +820,417 @@ pub fn rectangular_prism(center: Vec3, width: f32, height: f32, depth: f32) -> V
                  position,
                  normal,
                  texture,
+                 tangent,
              }| Vertex {
                 position: position + center,
                 normal: *normal,
                 texture: *texture,
+                tangent: *tangent,
             },
         )
         .collect()
 }
 
-pub fn get_tex_const(index: u8) -> GLenum {
-    match index {
-        0 => gl::TEXTURE0,
-        1 => gl::TEXTURE1,
-        2 => gl::TEXTURE2,
-        3 => gl::TEXTURE3,
-        4 => gl::TEXTURE4,
-        5 => gl::TEXTURE5,
-        6 => gl::TEXTURE6,
-        7 => gl::TEXTURE7,
-        8 => gl::TEXTURE8,
-        9 => gl::TEXTURE9,
-        10 => gl::TEXTURE10,
-        11 => gl::TEXTURE11,
-        12 => gl::TEXTURE12,
-        13 => gl::TEXTURE13,
-        14 => gl::TEXTURE14,
-        15 => gl::TEXTURE15,
-        16 => gl::TEXTURE16,
-        17 => gl::TEXTURE17,
-        18 => gl::TEXTURE18,
-        19 => gl::TEXTURE19,
-        20 => gl::TEXTURE20,
-        21 => gl::TEXTURE21,
-        22 => gl::TEXTURE22,
-        23 => gl::TEXTURE23,
-        24 => gl::TEXTURE24,
-        25 => gl::TEXTURE25,
-        26 => gl::TEXTURE26,
-        27 => gl::TEXTURE27,
-        28 => gl::TEXTURE28,
-        29 => gl::TEXTURE29,
-        30 => gl::TEXTURE30,
-        31 => gl::TEXTURE31,
-        _ => panic!("Out of textures"),
+/// Generates a box like `rectangular_prism` with its edges and corners rounded off by
+/// `radius`, each curve split into `segments`
+pub fn rounded_prism(
+    center: Vec3,
+    width: Scalar,
+    height: Scalar,
+    depth: Scalar,
+    radius: Scalar,
+    segments: u16,
+) -> Vec<Vertex> {
+    let half = vec3(width / 2.0, depth / 2.0, height / 2.0);
+    let radius = radius.min(half.x).min(half.y).min(half.z);
+    if radius <= 0.0 || segments == 0 {
+        return rectangular_prism(center, width, height, depth);
+    }
+    // Box the rounding is wrapped around
+    let core = vec3(half.x - radius, half.y - radius, half.z - radius);
+    // Distances around the rounding of an edge
+    let curve: Vec<Scalar> = (0..=segments)
+        .map(|i| radius * Scalar::from(i) / Scalar::from(segments))
+        .collect();
+    let steps = |half: Scalar| -> Vec<Scalar> {
+        curve
+            .iter()
+            .map(|d| d - half)
+            .chain(curve.iter().map(|d| half - radius + d))
+            .collect()
+    };
+    let (x, y, z) = (
+        vec3(1.0, 0.0, 0.0),
+        vec3(0.0, 1.0, 0.0),
+        vec3(0.0, 0.0, 1.0),
+    );
+    let mut vertices: Vec<Vertex> = Vec::new();
+    // Each face runs across two axes whose cross product faces out of it
+    for &(normal, u, v) in &[
+        (x, y, z),
+        (-x, z, y),
+        (y, z, x),
+        (-y, x, z),
+        (z, x, y),
+        (-z, y, x),
+    ] {
+        // Points on the face of the unrounded box are pushed onto the rounding
+        let point = |s: Scalar, t: Scalar| {
+            let flat = normal * normal.dot(&half).abs() + u * s + v * t;
+            let inside = flat.clamp(&-core, &core);
+            let out = (flat - inside).normalize();
+            vertex(center + inside + out * radius, out)
+        };
+        let (across, up) = (steps(u.dot(&half).abs()), steps(v.dot(&half).abs()));
+        for s in across.windows(2).filter(|s| s[1] > s[0]) {
+            for t in up.windows(2).filter(|t| t[1] > t[0]) {
+                let (bl, br) = (point(s[0], t[0]), point(s[1], t[0]));
+                let (tr, tl) = (point(s[1], t[1]), point(s[0], t[1]));
+                vertices.extend_from_slice(&[bl, br, tr, tr, tl, bl]);
+            }
+        }
     }
+    vertices
+}
+
+/// Joins a grid of vertices into quads, `point(column, row)` giving each one
+///
+/// Columns run right and rows up seen from the side the quads face.
+fn grid<F>(columns: u16, rows: u16, point: F) -> Vec<Vertex>
+where
+    F: Fn(u16, u16) -> Vertex,
+{
+    let mut vertices: Vec<Vertex> = Vec::new();
+    for row in 0..rows {
+        for column in 0..columns {
+            let (bl, br) = (point(column, row), point(column + 1, row));
+            let (tr, tl) = (point(column + 1, row + 1), point(column, row + 1));
+            vertices.extend_from_slice(&[bl, br, tr, tr, tl, bl]);
+        }
+    }
+    vertices
+}
+
+/// Point on a circle around the y axis, `turn` of the way around from +x towards -z
+fn around(turn: Scalar) -> Vec3 {
+    let angle = 2.0 * PI * turn;
+    vec3(angle.cos(), 0.0, -angle.sin())
+}
+
+/// Flat disk facing up or down at the end of a cylinder or cone, with the texture laid over
+/// it from above
+fn cap(center: Vec3, radius: Scalar, segments: u16, up: bool) -> Vec<Vertex> {
+    let normal = vec3(0.0, if up { 1.0 } else { -1.0 }, 0.0);
+    let point = |offset: Vec3| {
+        vertex(center + offset * radius, normal).with_texture(
+            vec2(0.5 + offset.x / 2.0, 0.5 - offset.z / 2.0),
+            vec3(1.0, 0.0, 0.0),
+        )
+    };
+    let middle = point(Vec3::origin());
+    (0..segments)
+        .flat_map(|i| {
+            let a = point(around(Scalar::from(i) / Scalar::from(segments)));
+            let b = point(around(Scalar::from(i + 1) / Scalar::from(segments)));
+            // Counter-clockwise seen from the side it faces
+            if up {
+                vec![middle, a, b]
+            } else {
+                vec![middle, b, a]
+            }
+        })
+        .collect()
+}
+
+/// Generates an upright cylinder around `center`, split into `segments` around the y axis
+///
+/// The texture wraps once around the side. Uncapped cylinders are open tubes, seen only
+/// from outside.
+pub fn cylinder(
+    center: Vec3,
+    radius: Scalar,
+    height: Scalar,
+    segments: u16,
+    capped: bool,
+) -> Vec<Vertex> {
+    let segments = segments.max(3);
+    let (bottom, top) = (
+        center - vec3(0.0, height / 2.0, 0.0),
+        center + vec3(0.0, height / 2.0, 0.0),
+    );
+    let mut vertices = grid(segments, 1, |column, row| {
+        let turn = Scalar::from(column) / Scalar::from(segments);
+        let normal = around(turn);
+        let end = if row == 0 { bottom } else { top };
+        vertex(end + normal * radius, normal).with_texture(
+            vec2(turn, Scalar::from(row)),
+            vec3(normal.z, 0.0, -normal.x),
+        )
+    });
+    if capped {
+        vertices.extend(cap(bottom, radius, segments, false));
+        vertices.extend(cap(top, radius, segments, true));
+    }
+    vertices
+}
+
+/// Generates an upright cone around `center`, its point `height` above its base
+///
+/// The texture wraps once around the side, gathering at the point.
+pub fn cone(center: Vec3, radius: Scalar, height: Scalar, segments: u16) -> Vec<Vertex> {
+    let segments = segments.max(3);
+    let base = center - vec3(0.0, height / 2.0, 0.0);
+    let apex = center + vec3(0.0, height / 2.0, 0.0);
+    // Sloping out from the side as steeply as the side slopes in
+    let slope = |turn: Scalar| {
+        let out = around(turn);
+        let normal = (out * height + vec3(0.0, radius, 0.0)).normalize();
+        (out, normal)
+    };
+    let mut vertices: Vec<Vertex> = Vec::new();
+    for i in 0..segments {
+        let side = |turn: Scalar, at_apex: bool| {
+            let (out, normal) = slope(turn);
+            let position = if at_apex { apex } else { base + out * radius };
+            vertex(position, normal).with_texture(
+                vec2(turn, if at_apex { 1.0 } else { 0.0 }),
+                vec3(out.z, 0.0, -out.x),
+            )
+        };
+        let (a, b) = (
+            Scalar::from(i) / Scalar::from(segments),
+            Scalar::from(i + 1) / Scalar::from(segments),
+        );
+        // The point takes the normal halfway across each triangle, so the side stays smooth
+        vertices.extend_from_slice(&[side(a, false), side(b, false), side((a + b) / 2.0, true)]);
+    }
+    vertices.extend(cap(base, radius, segments, false));
+    vertices
+}
+
+/// Generates an upright capsule around `center`, a cylinder `height` tall from end to end
+/// with a half sphere for each end, split into `rings` from each pole to the cylinder and
+/// `segments` around the y axis
+///
+/// The texture wraps once around the capsule, and runs from the bottom pole to the top one
+/// by the distance along its side. Capsules no taller than they are wide are spheres.
+pub fn capsule(
+    center: Vec3,
+    radius: Scalar,
+    height: Scalar,
+    rings: u16,
+    segments: u16,
+) -> Vec<Vertex> {
+    let (rings, segments) = (rings.max(1), segments.max(3));
+    let straight = (height - 2.0 * radius).max(0.0);
+    let arc = PI / 2.0 * radius;
+    let length = 2.0 * arc + straight;
+    // Rows up the bottom half sphere, then the same up the top one
+    grid(segments, 2 * rings + 1, |column, row| {
+        let turn = Scalar::from(column) / Scalar::from(segments);
+        let (end, ring) = if row <= rings {
+            (-1.0, row)
+        } else {
+            (1.0, row - rings - 1)
+        };
+        let fraction = Scalar::from(ring) / Scalar::from(rings);
+        let angle = PI / 2.0 * (fraction - if end < 0.0 { 1.0 } else { 0.0 });
+        let out = around(turn);
+        let normal = out * angle.cos() + vec3(0.0, angle.sin(), 0.0);
+        let along = if end < 0.0 {
+            arc * fraction
+        } else {
+            arc + straight + arc * fraction
+        };
+        vertex(
+            center + vec3(0.0, end * straight / 2.0, 0.0) + normal * radius,
+            normal,
+        )
+        .with_texture(
+            vec2(turn, if length > 0.0 { along / length } else { 0.0 }),
+            vec3(out.z, 0.0, -out.x),
+        )
+    })
+}
+
+/// Generates a ring lying flat around `center`, `radius` out to the middle of a tube of
+/// radius `tube`, split into `rings` around the y axis and `segments` around the tube
+///
+/// The texture wraps once around the ring and once around the tube.
+pub fn torus(center: Vec3, radius: Scalar, tube: Scalar, rings: u16, segments: u16) -> Vec<Vertex> {
+    let (rings, segments) = (rings.max(3), segments.max(3));
+    grid(rings, segments, |ring, segment| {
+        let (u, v) = (
+            Scalar::from(ring) / Scalar::from(rings),
+            Scalar::from(segment) / Scalar::from(segments),
+        );
+        let out = around(u);
+        // Around the tube from its outside edge, up over the top first
+        let angle = 2.0 * PI * v;
+        let normal = out * angle.cos() + vec3(0.0, angle.sin(), 0.0);
+        vertex(center + out * radius + normal * tube, normal)
+            .with_texture(vec2(u, v), vec3(out.z, 0.0, -out.x))
+    })
+}
+
+/// Generates a sphere split into `rings` from pole to pole and `segments` around the y axis
+///
+/// Texture coords wrap once around the sphere from +x, and run from the south pole up to
+/// the north pole.
+pub fn uv_sphere(center: Vec3, radius: Scalar, rings: u16, segments: u16) -> Vec<Vertex> {
+    let (rings, segments) = (rings.max(2), segments.max(3));
+    let point = |ring: u16, segment: u16| {
+        let (u, v) = (
+            Scalar::from(segment) / Scalar::from(segments),
+            Scalar::from(ring) / Scalar::from(rings),
+        );
+        // Down from the north pole, and around to the right seen from outside
+        let (theta, phi) = (PI * v, 2.0 * PI * u);
+        let normal = vec3(
+            theta.sin() * phi.cos(),
+            theta.cos(),
+            -theta.sin() * phi.sin(),
+        );
+        vertex(center + normal * radius, normal)
+            .with_texture(vec2(u, 1.0 - v), vec3(-phi.sin(), 0.0, -phi.cos()))
+    };
+    let mut vertices: Vec<Vertex> = Vec::new();
+    for ring in 0..rings {
+        for segment in 0..segments {
+            let (bl, br) = (point(ring + 1, segment), point(ring + 1, segment + 1));
+            let (tr, tl) = (point(ring, segment + 1), point(ring, segment));
+            // The rings around the poles are fans of triangles
+            if ring > 0 {
+                vertices.extend_from_slice(&[tr, tl, bl]);
+            }
+            if ring < rings - 1 {
+                vertices.extend_from_slice(&[bl, br, tr]);
+            }
+        }
+    }
+    vertices
+}
+
+/// Corners and triangles of an icosahedron on the unit sphere, each triangle split into four
+/// `levels` times with the new corners pushed out onto the sphere
+///
+/// Triangles are counter-clockwise seen from outside and share their corners, for shapes
+/// such as collision proxies that want the surface rather than vertices to draw.
+pub fn icosphere_mesh(levels: u32) -> (Vec<Vec3>, Vec<[usize; 3]>) {
+    let t = (1.0 + (5.0 as Scalar).sqrt()) / 2.0;
+    let mut points: Vec<Vec3> = [
+        (-1.0, t, 0.0),
+        (1.0, t, 0.0),
+        (-1.0, -t, 0.0),
+        (1.0, -t, 0.0),
+        (0.0, -1.0, t),
+        (0.0, 1.0, t),
+        (0.0, -1.0, -t),
+        (0.0, 1.0, -t),
+        (t, 0.0, -1.0),
+        (t, 0.0, 1.0),
+        (-t, 0.0, -1.0),
+        (-t, 0.0, 1.0),
+    ]
+    .iter()
+    .map(|&(x, y, z)| vec3(x, y, z).normalize())
+    .collect();
+    let mut triangles: Vec<[usize; 3]> = vec![
+        [0, 11, 5],
+        [0, 5, 1],
+        [0, 1, 7],
+        [0, 7, 10],
+        [0, 10, 11],
+        [1, 5, 9],
+        [5, 11, 4],
+        [11, 10, 2],
+        [10, 7, 6],
+        [7, 1, 8],
+        [3, 9, 4],
+        [3, 4, 2],
+        [3, 2, 6],
+        [3, 6, 8],
+        [3, 8, 9],
+        [4, 9, 5],
+        [2, 4, 11],
+        [6, 2, 10],
+        [8, 6, 7],
+        [9, 8, 1],
+    ];
+    for _ in 0..levels {
+        // Triangles either side of an edge share the point in its middle
+        let mut middles: HashMap<(usize, usize), usize> = HashMap::new();
+        let mut middle = |a: usize, b: usize| {
+            *middles.entry((a.min(b), a.max(b))).or_insert_with(|| {
+                points.push(((points[a] + points[b]) * 0.5).normalize());
+                points.len() - 1
+            })
+        };
+        triangles = triangles
+            .iter()
+            .flat_map(|&[a, b, c]| {
+                let (ab, bc, ca) = (middle(a, b), middle(b, c), middle(c, a));
+                vec![[a, ab, ca], [b, bc, ab], [c, ca, bc], [ab, bc, ca]]
+            })
+            .collect();
+    }
+    (points, triangles)
+}
+
+/// Generates a sphere from an icosahedron split `levels` times, its triangles all close to
+/// the same size
+///
+/// Texture coords wrap around the sphere as a UV sphere's do, running past 1 across the
+/// seam so triangles there don't stretch back over the whole texture.
+pub fn icosphere(center: Vec3, radius: Scalar, levels: u32) -> Vec<Vertex> {
+    let (points, triangles) = icosphere_mesh(levels);
+    // Around from +x towards -z, and up from the south pole
+    let coords = |normal: Vec3| {
+        let turn = (-normal.z).atan2(normal.x) / (2.0 * PI);
+        vec2(
+            if turn < 0.0 { turn + 1.0 } else { turn },
+            1.0 - normal.y.clamp(-1.0, 1.0).acos() / PI,
+        )
+    };
+    // Any way around is the pole, so poles take the way around of the rest of their triangle
+    let pole = |normal: &Vec3| normal.x.hypot(normal.z) < 1e-6;
+    triangles
+        .iter()
+        .flat_map(|triangle| {
+            let normals: Vec<Vec3> = triangle.iter().map(|&i| points[i]).collect();
+            let turns: Vec<Scalar> = normals
+                .iter()
+                .filter(|normal| !pole(normal))
+                .map(|&normal| coords(normal).x)
+                .collect();
+            let (low, high) = turns
+                .iter()
+                .fold((1.0 as Scalar, 0.0 as Scalar), |(low, high), &turn| {
+                    (low.min(turn), high.max(turn))
+                });
+            let wrap = |turn: Scalar| {
+                if high - low > 0.5 && turn < 0.5 {
+                    turn + 1.0
+                } else {
+                    turn
+                }
+            };
+            let middle =
+                turns.iter().map(|&turn| wrap(turn)).sum::<Scalar>() / turns.len() as Scalar;
+            normals
+                .iter()
+                .map(|&normal| {
+                    let mut texture = coords(normal);
+                    texture.x = if pole(&normal) {
+                        middle
+                    } else {
+                        wrap(texture.x)
+                    };
+                    let phi = 2.0 * PI * texture.x;
+                    vertex(center + normal * radius, normal)
+                        .with_texture(texture, vec3(-phi.sin(), 0.0, -phi.cos()))
+                })
+                .collect::<Vec<_>>()
+        })
+        .collect()
 }
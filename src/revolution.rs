@@ -1,18 +1,18 @@
-use std::f32::consts::PI;
-
-use gleam::gl::{self, GLint, GLsizei};
-
-use super::Context;
-use matrix::{identity, matmul, translate, vec3, Vec3};
-use render::{quad, tri, Drawable, Vertex};
+use material::Material;
+use matrix::consts::PI;
+use matrix::{vec2, vec3, Scalar, Transform, Vec3};
+use mesh::{Instance, MeshData};
+use render::{catmull_rom, newell, polygon, quad, tri, Drawable, Vertex};
 
 /// Takes a path and rotates it about the Y axis
 pub struct Revolution {
     path: Vec<Vec3>,
     resolution: u16,
-    vert_start: GLint,
-    num_verts: GLsizei,
-    translate: Vec3,
+    /// Normals follow the curve of the surface instead of each face
+    smooth: bool,
+    /// Radians the path is spun through, short of a full turn leaves the ends capped
+    angle: Scalar,
+    instance: Instance,
 }
 
 impl Revolution {
@@ -20,36 +20,134 @@ impl Revolution {
         Revolution {
             path,
             resolution,
-            vert_start: 0,
-            num_verts: 0,
-            translate,
+            smooth: false,
+            angle: 2.0 * PI,
+            instance: Instance::new(Transform::from_translation(translate), Material::red()),
         }
     }
+
+    /// Spins a smooth curve through a few control points, `samples` points between each pair
+    pub fn from_curve(
+        controls: &[Vec3],
+        samples: u16,
+        resolution: u16,
+        translate: Vec3,
+    ) -> Revolution {
+        Revolution::new(catmull_rom(controls, samples), resolution, translate)
+    }
+
+    /// Shades the surface as a smooth curve rather than flat faces
+    pub fn with_smooth(self, smooth: bool) -> Revolution {
+        Revolution { smooth, ..self }
+    }
+
+    /// Spins the path only `angle` radians from +x towards -z, closing off both ends flat
+    pub fn with_angle(self, angle: Scalar) -> Revolution {
+        Revolution { angle, ..self }
+    }
+
+    /// Flat end between the path, spun `angle` radians, and the axis, for revolutions that
+    /// stop short. It faces `out` as the path is seen before it is spun.
+    fn cap(&self, angle: Scalar, out: Vec3) -> Vec<Vertex> {
+        let (first, last) = (self.path[0], self.path[self.path.len() - 1]);
+        let mut outline = self.path.clone();
+        outline.push(vec3(0.0, last.y, 0.0));
+        outline.push(vec3(0.0, first.y, 0.0));
+        let mut outline: Vec<Vec3> = outline.iter().map(|point| point.rotate_y(angle)).collect();
+        if newell(outline.clone()).dot(&out.rotate_y(angle)) < 0.0 {
+            outline.reverse();
+        }
+        polygon(&outline)
+    }
+
+    /// Normal at each point of the path, facing away from its direction the way its faces do
+    fn path_normals(&self) -> Vec<Vec3> {
+        let last = self.path.len() - 1;
+        (0..=last)
+            .map(|i| {
+                // Across neighbouring points, so corners take the average of their sides
+                let direction = self.path[last.min(i + 1)] - self.path[i.saturating_sub(1)];
+                let normal = vec3(direction.y, -direction.x, 0.0);
+                if normal.length() > 0.0 {
+                    normal.normalize()
+                } else {
+                    normal
+                }
+            })
+            .collect()
+    }
 }
 impl Drawable for Revolution {
-    /// Returns buffer data
-    fn buffer_data(&mut self, vertex_start: GLint) -> Vec<f32> {
-        // Store the vertex starting pointer
-        self.vert_start = vertex_start;
+    /// Shapes with the same profile share a mesh
+    fn mesh_key(&self) -> String {
+        format!(
+            "revolution {:?} {} {} {}",
+            self.path, self.resolution, self.smooth, self.angle
+        )
+    }
+    /// Generates the vertices of the shape
+    fn mesh_data(&self) -> MeshData {
         // Start making vertices
         let mut vertices: Vec<Vertex> = Vec::new();
         // Start with the path
         let mut path: Vec<Vec3> = self.path.clone();
+        let mut normals = self.path_normals();
 
         // Get revolution amount per step
-        let theta = (2.0 * PI) / f32::from(self.resolution);
+        let theta = self.angle / Scalar::from(self.resolution);
+        // The texture wraps once around, and runs once along the path by its length
+        let lengths: Vec<Scalar> = self
+            .path
+            .windows(2)
+            .scan(0.0, |length, pair| {
+                *length += pair[0].distance(&pair[1]);
+                Some(*length)
+            })
+            .collect();
+        let total = lengths.last().cloned().unwrap_or(0.0).max(Scalar::EPSILON);
+        let along: Vec<Scalar> = Some(0.0)
+            .into_iter()
+            .chain(lengths.iter().map(|length| length / total))
+            .collect();
         // Apply revolutions
-        for _ in 0..self.resolution {
+        for step in 0..self.resolution {
+            let (u0, u1) = (
+                Scalar::from(step) / Scalar::from(self.resolution),
+                Scalar::from(step + 1) / Scalar::from(self.resolution),
+            );
             // Rotate the path about the y axis some split amount
             let rotated_path: Vec<Vec3> = path.iter().map(|v| v.rotate_y(theta)).collect();
+            let rotated_normals: Vec<Vec3> = normals.iter().map(|n| n.rotate_y(theta)).collect();
             // First (top/bottom) triangle
             vertices.extend_from_slice(&tri(path[0], rotated_path[0], vec3(0.0, path[0].y, 0.0)));
 
             // Make quads to connect rotated paths
-            for pair in path.windows(2).zip(rotated_path.windows(2)) {
+            for (i, pair) in path.windows(2).zip(rotated_path.windows(2)).enumerate() {
                 // Match on guaranteed window pattern
                 if let (&[a, b], &[c, d]) = pair {
-                    vertices.extend_from_slice(&quad(a, c, d, b))
+                    let (v0, v1) = (along[i], along[i + 1]);
+                    let coords = [(u0, v0), (u1, v0), (u1, v1), (u1, v1), (u0, v1), (u0, v0)];
+                    let (na, nb) = (normals[i], normals[i + 1]);
+                    let (nc, nd) = (rotated_normals[i], rotated_normals[i + 1]);
+                    let smooth = [na, nc, nd, nd, nb, na];
+                    vertices.extend(quad(a, c, d, b).iter().zip(coords.iter()).zip(&smooth).map(
+                        |((vertex, &(u, v)), &normal)| {
+                            let vertex = if self.smooth {
+                                vertex.with_normal(normal)
+                            } else {
+                                *vertex
+                            };
+                            // The texture runs around the way the path is spun
+                            let position = vertex.position();
+                            let around = vec3(position.z, 0.0, -position.x);
+                            let tangent = if around.length() > 0.0 {
+                                around.normalize()
+                            } else {
+                                around
+                            };
+                            vertex.with_texture(vec2(u, v), tangent)
+                        },
+                    ));
                 };
             }
 
@@ -61,41 +159,82 @@ impl Drawable for Revolution {
             ));
 
             path = rotated_path;
+            normals = rotated_normals;
         }
-        // Vertices
-        self.num_verts = vertices.len() as GLint;
-        // Flatten vertices and add colors
-        vertices
-            .iter()
-            .flat_map(|vertex| vertex.to_data().to_vec())
-            .collect()
+
+        // The start faces back against the way the path is spun, and the end onwards
+        if self.angle < 2.0 * PI {
+            vertices.extend(self.cap(0.0, vec3(0.0, 0.0, 1.0)));
+            vertices.extend(self.cap(self.angle, vec3(0.0, 0.0, -1.0)));
+        }
+
+        MeshData::new(&vertices)
     }
-    /// Draws the object
-    fn draw(&self, ctx: &Context) {
-        let gl = &ctx.gl;
-        let mv_location = gl.get_uniform_location(ctx.program, "uMVMatrix");
-        let m_matrix = identity();
-        let v_matrix = matmul(
-            translate(self.translate.x, self.translate.y, self.translate.z),
-            ctx.camera,
-        );
-        let mv_matrix = matmul(v_matrix, m_matrix);
-        gl.uniform_matrix_4fv(mv_location, false, &mv_matrix);
-
-        // Lighting properties
-        let ambient_location = gl.get_uniform_location(ctx.program, "uAmbientProduct");
-        let diffuse_location = gl.get_uniform_location(ctx.program, "uDiffuseProduct");
-        let specular_location = gl.get_uniform_location(ctx.program, "uSpecularProduct");
-        // Light position
-        let shininess_location = gl.get_uniform_location(ctx.program, "uShininess");
-
-        // Set lighting properties
-        //gl.uniform_4f(ambient_location, 0.6, 0.6, 0.6, 1.0);
-        gl.uniform_4f(ambient_location, 0.6, 0.0, 0.0, 1.0);
-        gl.uniform_4f(diffuse_location, 0.64, 0.64, 0.64, 1.0);
-        gl.uniform_4f(specular_location, 0.0, 0.0, 0.0, 1.0);
-        gl.uniform_1f(shininess_location, 40.078_43);
-
-        gl.draw_arrays(gl::TRIANGLES, self.vert_start / 8, self.num_verts);
+    /// Mesh of the object and how it is placed and looks
+    fn instance(&self) -> &Instance {
+        &self.instance
+    }
+    fn instance_mut(&mut self) -> &mut Instance {
+        &mut self.instance
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::Revolution;
+    use matrix::consts::PI;
+    use matrix::{vec3, Scalar, Vec3};
+    use render::{Drawable, VERTEX_SIZE};
+
+    #[test]
+    fn test_smooth() {
+        // A cylinder's side, from the bottom up
+        let profile = vec![
+            vec3(1.0, 0.0, 0.0),
+            vec3(1.0, 1.0, 0.0),
+            vec3(1.0, 2.0, 0.0),
+        ];
+        let flat = Revolution::new(profile.clone(), 8, Vec3::origin());
+        let smooth = Revolution::new(profile, 8, Vec3::origin()).with_smooth(true);
+        assert_ne!(flat.mesh_key(), smooth.mesh_key());
+        // Away from the caps, smooth normals point straight out from the axis
+        let data = smooth.mesh_data();
+        let mut side = 0;
+        for vertex in data.vertices.chunks(VERTEX_SIZE) {
+            let value = |i: usize| Scalar::from(vertex[i]);
+            let (position, normal) = (
+                vec3(value(0), value(1), value(2)),
+                vec3(value(3), value(4), value(5)),
+            );
+            if normal.y.abs() < 0.5 {
+                assert!(position.distance(&(normal + vec3(0.0, position.y, 0.0))) < 1e-4);
+                side += 1;
+            }
+        }
+        assert_eq!(side, 8 * 2 * 6);
+    }
+
+    #[test]
+    fn test_angle() {
+        // Half a cylinder, spun from +x round through -z to -x
+        let profile = vec![vec3(1.0, 0.0, 0.0), vec3(1.0, 2.0, 0.0)];
+        let half = Revolution::new(profile, 8, Vec3::origin()).with_angle(PI);
+        let data = half.mesh_data();
+        // Each step has its side and a triangle at each end, and each cap is two triangles
+        assert_eq!(data.vertices.len(), (8 * 12 + 2 * 6) * VERTEX_SIZE);
+        let bounds = data.bounds.unwrap();
+        assert!(bounds.max.z.abs() < 1e-5 && (bounds.min.z + 1.0).abs() < 1e-5);
+        assert!((bounds.min.x + 1.0).abs() < 1e-5 && (bounds.max.x - 1.0).abs() < 1e-5);
+        // The flat side where it was cut faces +z
+        for vertex in data.vertices.chunks(VERTEX_SIZE) {
+            let value = |i: usize| Scalar::from(vertex[i]);
+            let (position, normal) = (
+                vec3(value(0), value(1), value(2)),
+                vec3(value(3), value(4), value(5)),
+            );
+            if position.z.abs() < 1e-5 && normal.z.abs() > 0.5 {
+                assert!(normal.z > 0.99);
+            }
+        }
     }
 }
@@ -1,32 +1,206 @@
+use std::cmp::Ordering;
+
+use gleam::gl::GLsizei;
+
 use super::Context;
-use gleam::gl::{self, GLint, GLsizei};
-use matrix::{identity, matmul, vec3};
-use render::{quad, Drawable, Vertex};
+use material::Material;
+use matrix::{transform_point, vec3, Plane, Scalar, Transform, Vec3};
+use mesh::{Instance, Mesh, MeshData};
+use render::{quad, rectangular_prism, Drawable, Vertex};
+
+/// How wide the frame around an opening is
+const FRAME_WIDTH: Scalar = 0.1;
+/// How far the frame around an opening sticks out of both sides of the wall
+const FRAME_DEPTH: Scalar = 0.2;
+
+/// Wall of the room, named after its corners in the layout in `surfaces`
+#[derive(Copy, Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Wall {
+    /// Runs along z at x = 0
+    Left,
+    /// Runs along x at z = 0
+    Right,
+    /// Faces the right wall across the room
+    FrontLeft,
+    /// Faces the left wall across the room
+    FrontRight,
+}
+
+/// Where a wall is in a room, for laying out its panels and frames
+struct WallPlacement {
+    /// End of the wall at x = 0 or z = 0, on the floor
+    start: Vec3,
+    /// Direction the wall runs in from its start
+    along: Vec3,
+    /// Direction the wall faces, into the room
+    inward: Vec3,
+}
+
+impl WallPlacement {
+    fn point(&self, along: Scalar, up: Scalar) -> Vec3 {
+        self.start + self.along * along + vec3(0.0, up, 0.0)
+    }
+
+    /// Part of the wall between two distances along it and two heights, facing into the room
+    fn panel(
+        &self,
+        (start, end): (Scalar, Scalar),
+        (bottom, top): (Scalar, Scalar),
+    ) -> [Vertex; 6] {
+        // Quads are wound counter-clockwise seen from inside, whichever way the wall runs
+        let right = (-self.inward).cross(vec3(0.0, 1.0, 0.0));
+        let (left, right) = if self.along.dot(&right) > 0.0 {
+            (start, end)
+        } else {
+            (end, start)
+        };
+        quad(
+            self.point(left, top),
+            self.point(left, bottom),
+            self.point(right, bottom),
+            self.point(right, top),
+        )
+    }
+
+    /// Box through the wall around a point on it, `length` along the wall and `height` tall
+    fn prism(&self, along: Scalar, up: Scalar, length: Scalar, height: Scalar) -> Vec<Vertex> {
+        let center = self.point(along, up);
+        if self.along.x != 0.0 {
+            rectangular_prism(center, length, FRAME_DEPTH, height)
+        } else {
+            rectangular_prism(center, FRAME_DEPTH, length, height)
+        }
+    }
+}
+
+/// Rectangular hole through a wall, a doorway when it starts at the floor or else a window
+#[derive(Copy, Clone, Debug, Serialize, Deserialize)]
+pub struct Opening {
+    pub wall: Wall,
+    /// Distance along the wall from its end at x = 0 or z = 0
+    pub offset: Scalar,
+    /// Height of the bottom edge above the floor
+    #[serde(default)]
+    pub bottom: Scalar,
+    pub width: Scalar,
+    pub height: Scalar,
+}
 
 pub struct Room {
-    room_width: f32,
-    room_height: f32,
-    room_depth: f32,
-    vert_start: GLint,
-    num_verts: GLsizei,
+    room_width: Scalar,
+    room_height: Scalar,
+    room_depth: Scalar,
+    /// Holes through the walls, which shouldn't overlap
+    openings: Vec<Opening>,
+    instance: Instance,
 }
 
 impl Room {
-    pub fn new(room_width: f32, room_height: f32, room_depth: f32) -> Self {
+    pub fn new(
+        room_width: Scalar,
+        room_height: Scalar,
+        room_depth: Scalar,
+        openings: Vec<Opening>,
+    ) -> Self {
         Room {
             room_width,
             room_height,
             room_depth,
-            vert_start: 0,
-            num_verts: 0,
+            openings,
+            instance: Instance::new(Transform::default(), Material::pearl()),
         }
     }
-}
-impl Drawable for Room {
-    /// Returns buffer data
-    fn buffer_data(&mut self, vertex_start: GLint) -> Vec<f32> {
-        // Store the vertex starting pointer
-        self.vert_start = vertex_start;
+
+    fn placement(&self, wall: Wall) -> WallPlacement {
+        let (x, z) = (vec3(1.0, 0.0, 0.0), vec3(0.0, 0.0, 1.0));
+        let (start, along, inward) = match wall {
+            Wall::Left => (Vec3::origin(), z, x),
+            Wall::Right => (Vec3::origin(), x, z),
+            Wall::FrontLeft => (z * self.room_width, x, -z),
+            Wall::FrontRight => (x * self.room_depth, z, -x),
+        };
+        WallPlacement {
+            start,
+            along,
+            inward,
+        }
+    }
+
+    /// Panels covering a wall around its openings, with a frame around each opening
+    fn wall(&self, wall: Wall) -> Vec<Vertex> {
+        let placement = self.placement(wall);
+        let height = self.room_height;
+        let length = match wall {
+            Wall::Left | Wall::FrontRight => self.room_width,
+            Wall::Right | Wall::FrontLeft => self.room_depth,
+        };
+        let mut openings: Vec<&Opening> = self
+            .openings
+            .iter()
+            .filter(|opening| opening.wall == wall)
+            .collect();
+        openings.sort_by(|a, b| a.offset.partial_cmp(&b.offset).unwrap_or(Ordering::Equal));
+        let mut vertices: Vec<Vertex> = Vec::new();
+        let mut panel = |along: (Scalar, Scalar), up: (Scalar, Scalar)| {
+            if along.1 > along.0 && up.1 > up.0 {
+                vertices.extend_from_slice(&placement.panel(along, up));
+            }
+        };
+        // Full height up to each opening, then below and above it
+        let mut start = 0.0;
+        for opening in &openings {
+            let left = opening.offset.max(start);
+            let right = (opening.offset + opening.width).min(length);
+            let bottom = opening.bottom.max(0.0);
+            let top = (opening.bottom + opening.height).min(height);
+            panel((start, left), (0.0, height));
+            panel((left, right), (0.0, bottom));
+            panel((left, right), (top, height));
+            start = right.max(start);
+        }
+        panel((start, length), (0.0, height));
+        for opening in &openings {
+            let (left, right) = (opening.offset, opening.offset + opening.width);
+            let (bottom, top) = (opening.bottom, opening.bottom + opening.height);
+            let middle = (left + right) / 2.0;
+            let across = opening.width + 2.0 * FRAME_WIDTH;
+            // Sides, then the head across the top
+            for &side in &[left - FRAME_WIDTH / 2.0, right + FRAME_WIDTH / 2.0] {
+                vertices.extend(placement.prism(
+                    side,
+                    (bottom + top) / 2.0,
+                    FRAME_WIDTH,
+                    opening.height,
+                ));
+            }
+            vertices.extend(placement.prism(middle, top + FRAME_WIDTH / 2.0, across, FRAME_WIDTH));
+            // Windows have a sill
+            if bottom > 0.0 {
+                vertices.extend(placement.prism(
+                    middle,
+                    bottom - FRAME_WIDTH / 2.0,
+                    across,
+                    FRAME_WIDTH,
+                ));
+            }
+        }
+        vertices
+    }
+
+    /// Every wall, the ceiling and the floor, each with the plane it faces into the room along
+    fn surfaces(&self) -> Vec<(Plane, Vec<Vertex>)> {
+        let mut surfaces: Vec<(Plane, Vec<Vertex>)> =
+            [Wall::Left, Wall::Right, Wall::FrontLeft, Wall::FrontRight]
+                .iter()
+                .map(|&wall| {
+                    let placement = self.placement(wall);
+                    (
+                        Plane::from_point_normal(placement.start, placement.inward),
+                        self.wall(wall),
+                    )
+                })
+                .collect();
         // Layout of the room
         //        y
         // LTL----MT----RTR
@@ -44,52 +218,114 @@ impl Drawable for Room {
         //        \/
         //        MF
         //        x
-        // Create points
-        let ltl = vec3(0.0, self.room_height, self.room_width);
         let lbr = vec3(0.0, 0.0, self.room_width);
         let mb = vec3(0.0, 0.0, 0.0);
-        let mt = vec3(0.0, self.room_height, 0.0);
         let rbr = vec3(self.room_depth, 0.0, 0.0);
-        let rtr = vec3(self.room_depth, self.room_height, 0.0);
         let mf = vec3(self.room_depth, 0.0, self.room_width);
-        // Create vertex buffer
-        let mut vertices: Vec<Vertex> = Vec::with_capacity(18);
-        vertices.extend_from_slice(&quad(ltl, lbr, mb, mt));
-        vertices.extend_from_slice(&quad(mt, mb, rbr, rtr));
-        vertices.extend_from_slice(&quad(mb, lbr, mf, rbr));
-
-        // Vertices
-        self.num_verts = vertices.len() as GLint;
+        let up = vec3(0.0, self.room_height, 0.0);
+        surfaces.push((
+            Plane::from_point_normal(up, vec3(0.0, -1.0, 0.0)),
+            quad(mb + up, rbr + up, mf + up, lbr + up).to_vec(),
+        ));
+        surfaces.push((
+            Plane::from_point_normal(mb, vec3(0.0, 1.0, 0.0)),
+            quad(mb, lbr, mf, rbr).to_vec(),
+        ));
+        surfaces
+    }
+}
+impl Drawable for Room {
+    /// Shapes with the same sizes share a mesh
+    fn mesh_key(&self) -> String {
+        format!(
+            "room {} {} {} {:?}",
+            self.room_width, self.room_height, self.room_depth, self.openings
+        )
+    }
+    /// Generates the vertices of the shape
+    fn mesh_data(&self) -> MeshData {
+        let vertices: Vec<Vertex> = self
+            .surfaces()
+            .into_iter()
+            .flat_map(|(_, vertices)| vertices)
+            .collect();
+        MeshData::new(&vertices)
+    }
+    /// Mesh of the object and how it is placed and looks
+    fn instance(&self) -> &Instance {
+        &self.instance
+    }
+    fn instance_mut(&mut self) -> &mut Instance {
+        &mut self.instance
+    }
 
-        // Flatten vertices and add colors
-        vertices
-            .iter()
-            .flat_map(|vertex| vertex.to_data().to_vec())
-            .collect()
+    /// Which walls are left out depends on the camera
+    fn instanced(&self) -> bool {
+        false
     }
-    /// Draws the object
+
+    /// Leaves out the surfaces between the camera and the inside of the room, so it can be
+    /// seen into from any side
     fn draw(&self, ctx: &Context) {
-        let gl = &ctx.gl;
-        let mv_location = gl.get_uniform_location(ctx.program, "uMVMatrix");
-        let m_matrix = identity();
-        let v_matrix = ctx.camera;
-        let mv_matrix = matmul(v_matrix, m_matrix);
-        gl.uniform_matrix_4fv(mv_location, false, &mv_matrix);
-
-        // Lighting properties
-        let ambient_location = gl.get_uniform_location(ctx.program, "uAmbientProduct");
-        let diffuse_location = gl.get_uniform_location(ctx.program, "uDiffuseProduct");
-        let specular_location = gl.get_uniform_location(ctx.program, "uSpecularProduct");
-        // Light position
-        let shininess_location = gl.get_uniform_location(ctx.program, "uShininess");
-
-        // Set lighting properties
-        gl.uniform_4f(ambient_location, 0.25, 0.20725, 0.20725, 1.0);
-        gl.uniform_4f(diffuse_location, 1.0, 0.829, 0.829, 1.0);
-        gl.uniform_4f(specular_location, 0.296_648, 0.296_648, 0.296_648, 1.0);
-
-        gl.uniform_1f(shininess_location, 0.088 * 128.0);
-
-        gl.draw_arrays(gl::TRIANGLES, self.vert_start / 8, self.num_verts);
+        let mesh = match self.instance.mesh {
+            Some(mesh) => mesh,
+            None => return,
+        };
+        let m_matrix = self.instance.model_matrix(ctx);
+        // The shadow map sees every surface, hidden from the camera or not
+        let eye = match m_matrix.inverse() {
+            Some(inverse) if !ctx.drawing_shadows() => {
+                Some(transform_point(&inverse, ctx.camera.eye))
+            }
+            _ => None,
+        };
+        let mut first = mesh.first;
+        let mut parts: Vec<Mesh> = Vec::new();
+        for (plane, vertices) in self.surfaces() {
+            let count = vertices.len() as GLsizei;
+            if eye.is_none_or(|eye| plane.signed_distance(&eye) >= 0.0) {
+                parts.push(Mesh {
+                    first,
+                    count,
+                    ..mesh
+                });
+            }
+            first += count;
+        }
+        self.instance.draw_parts(ctx, &m_matrix, &parts);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Opening, Room, Wall};
+    use render::{Drawable, VERTEX_SIZE};
+
+    #[test]
+    fn test_openings() {
+        let vertices = |openings| Room::new(10.0, 10.0, 10.0, openings).mesh_data().vertices;
+        let plain = vertices(Vec::new());
+        // A doorway splits its wall in three and is framed on three sides
+        let door = Opening {
+            wall: Wall::Left,
+            offset: 6.5,
+            bottom: 0.0,
+            width: 2.0,
+            height: 4.5,
+        };
+        assert_eq!(
+            vertices(vec![door]).len(),
+            plain.len() + (2 * 6 + 3 * 36) * VERTEX_SIZE
+        );
+        // A window also leaves the wall below it, and has a sill
+        let window = Opening {
+            wall: Wall::Right,
+            bottom: 3.0,
+            ..door
+        };
+        assert_eq!(
+            vertices(vec![window]).len(),
+            plain.len() + (3 * 6 + 4 * 36) * VERTEX_SIZE
+        );
     }
 }
@@ -0,0 +1,159 @@
+use std::ffi::OsStr;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use super::Context;
+use atlas::AtlasRegion;
+use material::Material;
+use matrix::{vec2, vec3, Quat, Scalar, Transform, Vec3};
+use mesh::{Instance, MeshData};
+use render::{quad, Drawable};
+use texture::{TextureManager, TextureOptions, WrapMode};
+
+/// How far rugs float above the floor, enough that the floor doesn't show through them
+const RUG_LIFT: Scalar = 0.005;
+
+/// Flat textured rectangle lying on the floor, facing up
+pub struct Rug {
+    /// Along x
+    width: Scalar,
+    /// Along z
+    depth: Scalar,
+    texture_path: PathBuf,
+    /// How the texture is sampled
+    texture_options: TextureOptions,
+    /// Where the texture is if it was packed into an atlas
+    region: Option<AtlasRegion>,
+    instance: Instance,
+}
+
+impl Rug {
+    /// Creates a rug with the texture stretched across it once
+    pub fn new<P>(
+        width: Scalar,
+        depth: Scalar,
+        texture_path: P,
+        wrap: WrapMode,
+        translate: Vec3,
+    ) -> Rug
+    where
+        P: AsRef<OsStr>,
+    {
+        Rug {
+            width,
+            depth,
+            texture_path: Path::new(&texture_path).to_path_buf(),
+            texture_options: wrap.into(),
+            region: None,
+            instance: Instance::new(Transform::from_translation(translate), Material::pearl()),
+        }
+    }
+
+    /// Sets how the texture is sampled, replacing the wrap mode given to `new`
+    pub fn with_texture_options(self, texture_options: TextureOptions) -> Rug {
+        Rug {
+            texture_options,
+            ..self
+        }
+    }
+
+    /// Turns the rug about its middle by `angle` radians, counter-clockwise seen from above
+    pub fn with_rotation(mut self, angle: Scalar) -> Rug {
+        self.instance.transform.rotation = Quat::from_axis_angle(vec3(0.0, 1.0, 0.0), angle);
+        self
+    }
+}
+
+impl Drawable for Rug {
+    /// Texture coordinates depend on where the texture is, so rugs share a mesh only when
+    /// they share a texture
+    fn mesh_key(&self) -> String {
+        format!(
+            "rug {} {} {}",
+            self.width,
+            self.depth,
+            self.texture_path.display()
+        )
+    }
+    /// Generates the vertices of the shape
+    fn mesh_data(&self) -> MeshData {
+        let (x, z) = (self.width / 2.0, self.depth / 2.0);
+        let vertices: Vec<_> = quad(
+            vec3(-x, RUG_LIFT, z),
+            vec3(x, RUG_LIFT, z),
+            vec3(x, RUG_LIFT, -z),
+            vec3(-x, RUG_LIFT, -z),
+        )
+        .iter()
+        .map(|vertex| {
+            // The texture runs along x and down z
+            let position = vertex.position();
+            let texture = vec2((position.x + x) / self.width, (position.z + z) / self.depth);
+            let texture = self.region.map_or(texture, |region| region.map(texture));
+            vertex.with_texture(texture, vec3(1.0, 0.0, 0.0))
+        })
+        .collect();
+        MeshData::new(&vertices)
+    }
+    /// Loads the texture
+    fn load_texture(&mut self, textures: &mut TextureManager) -> Result<(), io::Error> {
+        let texture = textures.load_packed(&self.texture_path, self.texture_options)?;
+        self.instance.texture = Some(texture);
+        self.region = textures.region(texture, &self.texture_path);
+        Ok(())
+    }
+    /// Whether the rug needs blending
+    fn translucent(&self, textures: &TextureManager) -> bool {
+        // Textures with an alpha channel need blending
+        match self.instance.texture {
+            Some(texture) => textures.has_alpha(texture),
+            None => false,
+        }
+    }
+    /// Rugs bind their own textures
+    fn instanced(&self) -> bool {
+        false
+    }
+    /// Mesh of the rug and how it is placed and looks
+    fn instance(&self) -> &Instance {
+        &self.instance
+    }
+    fn instance_mut(&mut self) -> &mut Instance {
+        &mut self.instance
+    }
+    /// Draws the rug with its texture
+    fn draw(&self, ctx: &Context) {
+        // Rugs whose texture failed to load are marked by the missing texture
+        if self.instance.texture.is_none() {
+            ctx.textures.bind(ctx.missing_texture, ctx.program);
+        }
+        self.instance.draw(ctx);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Rug, RUG_LIFT};
+    use matrix::consts::PI;
+    use matrix::{transform_point, vec3, Scalar, Vec3};
+    use render::{Drawable, VERTEX_SIZE};
+    use texture::WrapMode;
+
+    #[test]
+    fn test_rug() {
+        let rug = Rug::new(2.0, 1.0, "rug.tga", WrapMode::Repeat, Vec3::origin());
+        let data = rug.mesh_data();
+        // Just above the floor, facing up, with the texture stretched across it
+        let bounds = data.bounds.unwrap();
+        assert!((bounds.min.y - RUG_LIFT).abs() < 1e-5 && (bounds.max.y - RUG_LIFT).abs() < 1e-5);
+        assert!((bounds.max.x - 1.0).abs() < 1e-5 && (bounds.max.z - 0.5).abs() < 1e-5);
+        for vertex in data.vertices.chunks(VERTEX_SIZE) {
+            assert!((Scalar::from(vertex[4]) - 1.0).abs() < 1e-5);
+            assert!(vertex[6] >= 0.0 && vertex[6] <= 1.0 && vertex[7] >= 0.0 && vertex[7] <= 1.0);
+        }
+        // Turning the rug leaves it lying flat
+        let turned = rug.with_rotation(PI / 2.0);
+        let corner = transform_point(&turned.transform().to_matrix(), vec3(1.0, 0.0, 0.5));
+        assert!(corner.y.abs() < 1e-5 && (corner.z + 1.0).abs() < 1e-5);
+    }
+}
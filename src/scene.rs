@@ -0,0 +1,479 @@
+use std::collections::HashMap;
+use std::ptr;
+
+use matrix::{identity, Mat4, Quat, Ray, Scalar, Transform, Vec3};
+use render::Drawable;
+use scene_file::ObjectKind;
+
+/// An object with the world matrix of the node drawing it
+pub type PlacedObject<'a> = (Mat4, &'a Drawable);
+
+/// How every node in a layer is drawn, changed together from the page
+#[derive(Copy, Clone, Debug)]
+pub struct Layer {
+    /// Hidden layers are skipped like hidden nodes
+    pub visible: bool,
+    /// Objects in highlighted layers are tinted like the selected object
+    pub highlighted: bool,
+    /// Applied in world space after the nodes' own placement, moving the layer as a whole
+    pub transform: Transform,
+}
+
+impl Default for Layer {
+    fn default() -> Layer {
+        Layer {
+            visible: true,
+            highlighted: false,
+            transform: Transform::default(),
+        }
+    }
+}
+
+/// State of each layer by name, layers never changed are drawn as placed
+#[derive(Default)]
+pub struct Layers {
+    layers: HashMap<String, Layer>,
+}
+
+impl Layers {
+    pub fn new() -> Layers {
+        Default::default()
+    }
+
+    pub fn get(&self, name: &str) -> Layer {
+        self.layers.get(name).cloned().unwrap_or_default()
+    }
+
+    /// State of a layer to change, starting from the default for layers not seen before
+    pub fn get_mut(&mut self, name: &str) -> &mut Layer {
+        self.layers.entry(name.into()).or_default()
+    }
+}
+
+/// An object placed relative to its parent, carrying the objects placed relative to it
+///
+/// Moving a node moves everything under it, so a cat sitting on a table moves with the table.
+pub struct SceneNode {
+    /// Name the node is looked up by, None if nothing needs to find it
+    pub name: Option<String>,
+    /// Placement relative to the parent node
+    pub transform: Transform,
+    /// Drawn with the node's world matrix, None for nodes that only group others
+    pub object: Option<Box<Drawable>>,
+    pub children: Vec<SceneNode>,
+    /// Hidden nodes are skipped when drawing, along with everything on them
+    pub visible: bool,
+    /// What the scene file built the object from, so the scene can be saved again
+    pub kind: Option<ObjectKind>,
+    /// Layer the node and everything on it are in, unless they name another
+    pub layer: Option<String>,
+}
+
+impl SceneNode {
+    /// Creates an empty node to group others under
+    pub fn new(transform: Transform) -> SceneNode {
+        SceneNode {
+            name: None,
+            transform,
+            object: None,
+            children: Vec::new(),
+            visible: true,
+            kind: None,
+            layer: None,
+        }
+    }
+
+    /// Creates a node drawing an object where its parent is
+    pub fn from_object(object: Box<Drawable>) -> SceneNode {
+        SceneNode {
+            object: Some(object),
+            ..SceneNode::new(Transform::default())
+        }
+    }
+
+    /// Adds a child node, returning it so more can be placed on it
+    pub fn add_child(&mut self, child: SceneNode) -> &mut SceneNode {
+        self.children.push(child);
+        self.children.last_mut().unwrap()
+    }
+
+    /// Adds an object where this node is, returning its node
+    pub fn add_object(&mut self, object: Box<Drawable>) -> &mut SceneNode {
+        self.add_child(SceneNode::from_object(object))
+    }
+
+    /// Gives the node a name to look it up by
+    pub fn with_name(self, name: &str) -> SceneNode {
+        SceneNode {
+            name: Some(name.into()),
+            ..self
+        }
+    }
+
+    /// Puts the node and what is on it in a layer, to show or move them with the rest of it
+    pub fn with_layer(self, layer: &str) -> SceneNode {
+        SceneNode {
+            layer: Some(layer.into()),
+            ..self
+        }
+    }
+
+    /// Moves the node relative to its parent, taking what is on it along
+    pub fn set_translation(&mut self, translation: Vec3) {
+        self.transform.translation = translation;
+    }
+
+    /// Turns the node around its origin
+    pub fn set_rotation(&mut self, rotation: Quat) {
+        self.transform.rotation = rotation;
+    }
+
+    /// Sizes the node along its own axes, which spreads out what is on it too
+    pub fn set_scale(&mut self, scale: Vec3) {
+        self.transform.scale = scale;
+    }
+
+    /// First node in the tree with a name, searching parents before children
+    pub fn find(&self, name: &str) -> Option<&SceneNode> {
+        if self.name.as_deref() == Some(name) {
+            return Some(self);
+        }
+        self.children.iter().find_map(|child| child.find(name))
+    }
+
+    /// First node in the tree with a name, to move it or change its object
+    pub fn find_mut(&mut self, name: &str) -> Option<&mut SceneNode> {
+        if self.name.as_deref() == Some(name) {
+            return Some(self);
+        }
+        self.children
+            .iter_mut()
+            .find_map(|child| child.find_mut(name))
+    }
+
+    /// Takes a named node and everything on it out of the tree
+    ///
+    /// The node itself can't be removed, only nodes under it.
+    pub fn remove(&mut self, name: &str) -> Option<SceneNode> {
+        if let Some(index) = self
+            .children
+            .iter()
+            .position(|child| child.name.as_deref() == Some(name))
+        {
+            return Some(self.children.remove(index));
+        }
+        self.children
+            .iter_mut()
+            .find_map(|child| child.remove(name))
+    }
+
+    /// Moves a named node onto another, placed at an offset from it
+    ///
+    /// From then on the child follows the parent wherever it goes. Returns false, leaving
+    /// the tree as it was, if either is missing or the parent is on the child.
+    pub fn attach(&mut self, child: &str, parent: &str, offset: Transform) -> bool {
+        match self.find(child) {
+            Some(node) if node.find(parent).is_none() && self.find(parent).is_some() => {}
+            _ => return false,
+        }
+        let mut node = match self.remove(child) {
+            Some(node) => node,
+            None => return false,
+        };
+        node.transform = offset;
+        self.find_mut(parent).unwrap().add_child(node);
+        true
+    }
+
+    /// Calls `visit` with every node in the tree and its world matrix, parents before children
+    pub fn walk<'a>(&'a self, parent: &Mat4, visit: &mut FnMut(&'a SceneNode, &Mat4)) {
+        // The node's own transform applies first
+        let world = self.transform.to_matrix() * *parent;
+        visit(self, &world);
+        for child in &self.children {
+            child.walk(&world, visit);
+        }
+    }
+
+    /// World matrix of a visible node and the layer it is in, None if it is hidden
+    ///
+    /// A node entering a layer from outside it is moved by the layer's transform, which
+    /// carries along everything on it.
+    fn enter<'a>(
+        &'a self,
+        parent: &Mat4,
+        layer: Option<&'a str>,
+        layers: &Layers,
+    ) -> Option<(Mat4, Option<&'a str>)> {
+        if !self.visible {
+            return None;
+        }
+        let world = self.transform.to_matrix() * *parent;
+        match self.layer.as_deref() {
+            Some(own) if Some(own) != layer => {
+                let state = layers.get(own);
+                if !state.visible {
+                    return None;
+                }
+                Some((world * state.transform.to_matrix(), Some(own)))
+            }
+            _ => Some((world, layer)),
+        }
+    }
+
+    /// Every visible object in the tree with the world matrix of its node
+    pub fn objects(&self, layers: &Layers) -> Vec<PlacedObject<'_>> {
+        let mut objects = Vec::new();
+        self.visible_objects(&identity(), None, layers, &mut objects);
+        objects
+    }
+
+    fn visible_objects<'a>(
+        &'a self,
+        parent: &Mat4,
+        layer: Option<&'a str>,
+        layers: &Layers,
+        objects: &mut Vec<PlacedObject<'a>>,
+    ) {
+        let (world, layer) = match self.enter(parent, layer, layers) {
+            Some(entered) => entered,
+            None => return,
+        };
+        if let Some(ref object) = self.object {
+            objects.push((world, object.as_ref()));
+        }
+        for child in &self.children {
+            child.visible_objects(&world, layer, layers, objects);
+        }
+    }
+
+    /// Layer of the node drawing an object, None if neither it nor a node above it has one
+    pub fn layer_of(&self, object: &Drawable) -> Option<&str> {
+        self.find_layer(object, None).and_then(|layer| layer)
+    }
+
+    fn find_layer<'a>(
+        &'a self,
+        object: &Drawable,
+        layer: Option<&'a str>,
+    ) -> Option<Option<&'a str>> {
+        let layer = self.layer.as_deref().or(layer);
+        let draws = self.object.as_ref().is_some_and(|own| {
+            // Compare addresses alone, the same object can have different vtables
+            ptr::eq(
+                own.as_ref() as *const Drawable as *const u8,
+                object as *const Drawable as *const u8,
+            )
+        });
+        if draws {
+            return Some(layer);
+        }
+        self.children
+            .iter()
+            .find_map(|child| child.find_layer(object, layer))
+    }
+
+    /// Visible node whose object is nearest along a ray, for finding what was clicked
+    ///
+    /// Objects are hit anywhere in their bounds, and those without bounds can't be hit.
+    pub fn pick(&self, ray: &Ray, layers: &Layers) -> Option<&SceneNode> {
+        let mut nearest: Option<(Scalar, &SceneNode)> = None;
+        self.pick_nearest(&identity(), None, layers, ray, &mut nearest);
+        nearest.map(|(_, node)| node)
+    }
+
+    fn pick_nearest<'a>(
+        &'a self,
+        parent: &Mat4,
+        layer: Option<&'a str>,
+        layers: &Layers,
+        ray: &Ray,
+        nearest: &mut Option<(Scalar, &'a SceneNode)>,
+    ) {
+        let (world, layer) = match self.enter(parent, layer, layers) {
+            Some(entered) => entered,
+            None => return,
+        };
+        let distance = self.object.as_ref().and_then(|object| {
+            object
+                .bounds()?
+                .transformed(&(object.transform().to_matrix() * world))
+                .intersect_ray(ray)
+        });
+        if let Some(distance) = distance {
+            if nearest.is_none_or(|(closest, _)| distance < closest) {
+                *nearest = Some((distance, self));
+            }
+        }
+        for child in &self.children {
+            child.pick_nearest(&world, layer, layers, ray, nearest);
+        }
+    }
+
+    /// Every object in the tree, for setting them up
+    pub fn objects_mut(&mut self) -> Vec<&mut Box<Drawable>> {
+        let mut objects: Vec<&mut Box<Drawable>> = self.object.iter_mut().collect();
+        for child in &mut self.children {
+            objects.extend(child.objects_mut());
+        }
+        objects
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Layers, SceneNode};
+    use matrix::consts::PI;
+    use matrix::{identity, transform_point, vec3, Mat4, Quat, Ray, Transform};
+    use mesh::MeshLibrary;
+    use room::Room;
+
+    #[test]
+    fn test_walk() {
+        let mut table = SceneNode::new(Transform::from_translation(vec3(5.0, 0.0, 5.0)));
+        table
+            .add_child(
+                SceneNode::new(
+                    Transform::from_translation(vec3(0.0, 3.5, 0.0))
+                        .with_scale(vec3(2.0, 2.0, 2.0)),
+                )
+                .with_name("cat"),
+            )
+            .add_child(SceneNode::new(Transform::from_translation(vec3(
+                1.0, 0.0, 0.0,
+            ))));
+        let mut worlds: Vec<Mat4> = Vec::new();
+        table.walk(&identity(), &mut |_, world| worlds.push(*world));
+        // Children are placed relative to their parents, which scale their offsets too
+        let origins: Vec<_> = worlds
+            .iter()
+            .map(|world| transform_point(world, vec3(0.0, 0.0, 0.0)))
+            .collect();
+        assert!(origins[0].distance(&vec3(5.0, 0.0, 5.0)) < 1e-5);
+        assert!(origins[1].distance(&vec3(5.0, 3.5, 5.0)) < 1e-5);
+        assert!(origins[2].distance(&vec3(7.0, 3.5, 5.0)) < 1e-5);
+        // Moving the parent moves everything on it
+        table.transform.translation = vec3(0.0, 0.0, 0.0);
+        let mut moved = Vec::new();
+        table.walk(&identity(), &mut |_, world| {
+            moved.push(transform_point(world, vec3(0.0, 0.0, 0.0)))
+        });
+        assert!(moved[2].distance(&vec3(2.0, 3.5, 0.0)) < 1e-5);
+        // Named nodes can be found wherever they are in the tree
+        table.find_mut("cat").unwrap().transform.translation = vec3(0.0, 0.0, 0.0);
+        assert_eq!(table.find("cat").unwrap().transform.translation.y, 0.0);
+        assert!(table.find("dog").is_none());
+        // Hiding a node hides what is on it
+        table
+            .find_mut("cat")
+            .unwrap()
+            .add_object(Box::new(Room::new(1.0, 1.0, 1.0, Vec::new())));
+        assert_eq!(table.objects(&Layers::new()).len(), 1);
+        table.find_mut("cat").unwrap().visible = false;
+        assert!(table.objects(&Layers::new()).is_empty());
+        // Turning the table a quarter turn swings what is on it around
+        table.set_translation(vec3(5.0, 0.0, 5.0));
+        table.set_rotation(Quat::from_axis_angle(vec3(0.0, 1.0, 0.0), PI / 2.0));
+        let mut turned = Vec::new();
+        table.walk(&identity(), &mut |_, world| {
+            turned.push(transform_point(world, vec3(0.0, 0.0, 0.0)))
+        });
+        assert!(turned[2].distance(&vec3(5.0, 0.0, 3.0)) < 1e-5);
+    }
+
+    #[test]
+    fn test_pick() {
+        // Unit boxes in front of and behind each other
+        let node = |name: &str, z| {
+            let mut room = Room::new(1.0, 1.0, 1.0, Vec::new());
+            MeshLibrary::new().add(&mut room);
+            SceneNode {
+                transform: Transform::from_translation(vec3(0.0, 0.0, z)),
+                ..SceneNode::from_object(Box::new(room)).with_name(name)
+            }
+        };
+        let mut scene = SceneNode::new(Transform::default());
+        scene.add_child(node("far", -5.0));
+        scene.add_child(node("near", 5.0));
+        // The nearer of two objects along the ray is picked
+        let ray = Ray::new(vec3(0.5, 0.5, 10.0), vec3(0.0, 0.0, -1.0));
+        let layers = Layers::new();
+        assert_eq!(
+            scene.pick(&ray, &layers).unwrap().name.as_deref(),
+            Some("near")
+        );
+        // Hidden objects can't be clicked, and missing everything picks nothing
+        scene.find_mut("near").unwrap().visible = false;
+        assert_eq!(
+            scene.pick(&ray, &layers).unwrap().name.as_deref(),
+            Some("far")
+        );
+        let miss = Ray::new(vec3(5.0, 0.5, 10.0), vec3(0.0, 0.0, -1.0));
+        assert!(scene.pick(&miss, &layers).is_none());
+    }
+
+    #[test]
+    fn test_layers() {
+        let room = || Box::new(Room::new(1.0, 1.0, 1.0, Vec::new()));
+        let mut scene = SceneNode::new(Transform::default());
+        scene
+            .add_child(SceneNode::from_object(room()).with_layer("furniture"))
+            .add_child(SceneNode::from_object(room()).with_layer("decor"))
+            .add_object(room());
+        scene.add_object(room());
+        let mut layers = Layers::new();
+        assert_eq!(scene.objects(&layers).len(), 4);
+        // Children are in their parent's layer unless they name their own
+        let objects = scene.objects(&layers);
+        let named: Vec<_> = objects
+            .iter()
+            .map(|(_, object)| scene.layer_of(*object))
+            .collect();
+        assert_eq!(
+            named,
+            [Some("furniture"), Some("decor"), Some("decor"), None]
+        );
+        // Moving a layer moves its objects once, even those in it twice over
+        layers.get_mut("decor").transform = Transform::from_translation(vec3(1.0, 0.0, 0.0));
+        let origins: Vec<_> = scene
+            .objects(&layers)
+            .iter()
+            .map(|(world, _)| transform_point(world, vec3(0.0, 0.0, 0.0)))
+            .collect();
+        assert!(origins[0].distance(&vec3(0.0, 0.0, 0.0)) < 1e-5);
+        assert!(origins[2].distance(&vec3(1.0, 0.0, 0.0)) < 1e-5);
+        // Hiding a layer hides whatever is in it, including other layers on it
+        layers.get_mut("furniture").visible = false;
+        assert_eq!(scene.objects(&layers).len(), 1);
+    }
+
+    #[test]
+    fn test_attach() {
+        let mut scene = SceneNode::new(Transform::default());
+        scene.add_child(
+            SceneNode::new(Transform::from_translation(vec3(2.0, 0.0, 0.0))).with_name("girl"),
+        );
+        scene
+            .add_child(SceneNode::new(Transform::default()).with_name("table"))
+            .add_child(SceneNode::new(Transform::default()).with_name("staff"));
+        // The staff moves from the table to the girl's hand
+        let hand = Transform::from_translation(vec3(0.5, 1.0, 0.0));
+        assert!(scene.attach("staff", "girl", hand));
+        assert!(scene.find("table").unwrap().find("staff").is_none());
+        scene
+            .find_mut("girl")
+            .unwrap()
+            .set_translation(vec3(4.0, 0.0, 0.0));
+        let mut staff = None;
+        scene.walk(&identity(), &mut |node, world| {
+            if node.name.as_deref() == Some("staff") {
+                staff = Some(transform_point(world, vec3(0.0, 0.0, 0.0)));
+            }
+        });
+        assert!(staff.unwrap().distance(&vec3(4.5, 1.0, 0.0)) < 1e-5);
+        // Nodes can't be attached to what is on them, or to nothing
+        assert!(!scene.attach("girl", "staff", Transform::default()));
+        assert!(!scene.attach("staff", "dog", Transform::default()));
+        assert!(scene.find("girl").unwrap().find("staff").is_some());
+    }
+}
@@ -0,0 +1,1146 @@
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use serde_json;
+
+use camera::CameraPose;
+use ceiling_light::CeilingLight;
+use csg::{Csg, Operation};
+use error::io_error;
+use extrusion::Extrusion;
+use furniture::{Bed, Furniture, Sofa};
+use lamp::Lamp;
+use material::{Material, ShadingModel};
+use matrix::{vec2, vec3, Quat, Scalar, Transform, Vec3};
+use obj::Obj;
+use parametric::{ParametricShape, ParametricSurface};
+use plant::Plant;
+use polyhedron::{PlatonicSolid, Polyhedron};
+use primitive::{Primitive, Shape};
+use render::{star, Drawable};
+use revolution::Revolution;
+use room::{Opening, Room};
+use rug::Rug;
+use scene::SceneNode;
+use sphere::Sphere;
+use stairs::Stairs;
+use sweep::Sweep;
+use terrain::Terrain;
+use text::Text3D;
+use texture::{Filter, TextureOptions, WrapMode};
+use tube::Tube;
+
+/// Layout of the scene, read from a JSON file so it can change without recompiling
+#[derive(Serialize, Deserialize, Debug)]
+pub struct SceneFile {
+    pub objects: Vec<ObjectDescription>,
+    /// Where the camera starts, the usual view if None
+    #[serde(default)]
+    pub camera: Option<CameraDescription>,
+}
+
+/// Point the camera is at and the point it looks at
+#[derive(Serialize, Deserialize, Debug)]
+pub struct CameraDescription {
+    pub eye: [Scalar; 3],
+    pub target: [Scalar; 3],
+}
+
+impl CameraDescription {
+    pub fn from_pose(pose: CameraPose) -> CameraDescription {
+        CameraDescription {
+            eye: from_vec3(pose.eye),
+            target: from_vec3(pose.target),
+        }
+    }
+
+    pub fn to_pose(&self) -> CameraPose {
+        CameraPose {
+            eye: to_vec3(self.eye),
+            target: to_vec3(self.target),
+        }
+    }
+}
+
+/// An object and the objects placed on it
+#[derive(Serialize, Deserialize, Debug)]
+pub struct ObjectDescription {
+    /// Name to look the object up by
+    pub name: Option<String>,
+    #[serde(flatten)]
+    pub kind: ObjectKind,
+    /// Placement relative to the parent object
+    #[serde(default)]
+    pub transform: TransformDescription,
+    /// Changes to the object's usual material
+    pub material: Option<MaterialDescription>,
+    #[serde(default)]
+    pub children: Vec<ObjectDescription>,
+    #[serde(default = "default_visible")]
+    pub visible: bool,
+    /// Layer the object and its children are in, such as "furniture" or "decor"
+    #[serde(default)]
+    pub layer: Option<String>,
+}
+
+/// What is drawn, set by the `type` field
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ObjectKind {
+    /// Draws nothing, only places its children
+    Group,
+    Room {
+        size: [Scalar; 3],
+        /// Doorways and windows through the walls
+        #[serde(default)]
+        openings: Vec<Opening>,
+    },
+    /// Top and leg sizes are width, height and depth. Furniture has its edges slightly
+    /// rounded unless given `edge_radius`, which can be 0 for sharp ones
+    Desk {
+        top: [Scalar; 3],
+        legs: [Scalar; 3],
+        #[serde(default)]
+        edge_radius: Option<Scalar>,
+    },
+    /// The back is as tall as the seat is wide unless given, and leans back by `back_tilt`
+    /// degrees
+    Chair {
+        top: [Scalar; 3],
+        legs: [Scalar; 3],
+        #[serde(default)]
+        back: Option<Scalar>,
+        #[serde(default)]
+        back_tilt: Scalar,
+        #[serde(default)]
+        edge_radius: Option<Scalar>,
+    },
+    /// Top on legs, such as a stool, a bench, or a bookcase when given shelves
+    Furniture {
+        top: [Scalar; 3],
+        legs: [Scalar; 3],
+        /// Height of a back rising above the top, leaning back by `back_tilt` degrees
+        #[serde(default)]
+        back: Option<Scalar>,
+        #[serde(default)]
+        back_tilt: Scalar,
+        #[serde(default)]
+        shelves: u32,
+        #[serde(default)]
+        edge_radius: Option<Scalar>,
+    },
+    /// Sofa facing +x, `width` across its armrests along z
+    Sofa {
+        width: Scalar,
+        depth: Scalar,
+        height: Scalar,
+    },
+    /// Bed with its headboard at -x, `height` to the top of the mattress
+    Bed {
+        width: Scalar,
+        length: Scalar,
+        height: Scalar,
+    },
+    /// Standing lamp lighting the room with a bulb of its own
+    Lamp {
+        height: Scalar,
+        #[serde(default = "default_bulb_color")]
+        color: [Scalar; 3],
+    },
+    /// Light hanging `drop` below where it is placed, under a shade `radius` wide
+    CeilingLight {
+        drop: Scalar,
+        radius: Scalar,
+        #[serde(default = "default_bulb_color")]
+        color: [Scalar; 3],
+    },
+    /// Steps climbing along x, with a railing of some height on both sides if given
+    Stairs {
+        steps: u32,
+        rise: Scalar,
+        run: Scalar,
+        width: Scalar,
+        #[serde(default)]
+        railing: Option<Scalar>,
+    },
+    /// Textured rug on the floor, `size` along x and z, turned by `angle` degrees
+    ///
+    /// Textures are smooth and mipmapped unless given a `filter` of nearest, for pixel art, or
+    /// `mipmaps` off.
+    Rug {
+        size: [Scalar; 2],
+        texture: String,
+        #[serde(default = "default_wrap")]
+        wrap: WrapMode,
+        #[serde(default = "default_filter")]
+        filter: Filter,
+        #[serde(default = "default_mipmaps")]
+        mipmaps: bool,
+        #[serde(default)]
+        angle: Scalar,
+    },
+    /// Model loaded from an OBJ file, its texture and maps sampled like a rug's
+    Obj {
+        model: String,
+        texture: String,
+        #[serde(default = "default_wrap")]
+        wrap: WrapMode,
+        #[serde(default = "default_filter")]
+        filter: Filter,
+        #[serde(default = "default_mipmaps")]
+        mipmaps: bool,
+        /// Tangent space normal map laid over the texture, such as wood grain
+        #[serde(default)]
+        normal_map: Option<String>,
+        /// Map scaling the specular color, so parts of the model shine and the rest is matte
+        #[serde(default)]
+        specular_map: Option<String>,
+        /// Map of parts of the model that glow, tinted by the material's emissive color if
+        /// it is given
+        #[serde(default)]
+        emissive_map: Option<String>,
+    },
+    /// Star extruded upwards by `depth`, its first point turned `phase` degrees from +x
+    /// towards -z
+    Star {
+        points: u16,
+        inner_radius: Scalar,
+        outer_radius: Scalar,
+        depth: Scalar,
+        #[serde(default)]
+        phase: Scalar,
+    },
+    /// Outline of x and z points lying flat, extruded upwards by `depth`, shaded as a smooth
+    /// curve if `smooth`
+    ///
+    /// Unless `closed` is false, the outline joins its last point back to its first and is
+    /// capped. Left open, it is a wall standing along its points.
+    Extrusion {
+        outline: Vec<[Scalar; 2]>,
+        depth: Scalar,
+        #[serde(default = "default_closed")]
+        closed: bool,
+        #[serde(default)]
+        smooth: bool,
+    },
+    /// Ball with texture coords wrapped around it, finer the more rings and segments it has
+    ///
+    /// Given `subdivisions`, the ball is instead an icosahedron split that many times.
+    Sphere {
+        radius: Scalar,
+        #[serde(default = "default_sphere_rings")]
+        rings: u16,
+        #[serde(default = "default_segments")]
+        segments: u16,
+        #[serde(default)]
+        subdivisions: Option<u32>,
+    },
+    /// Potted plant about `height` tall, its branches grown from `seed`
+    Plant {
+        height: Scalar,
+        #[serde(default)]
+        seed: u32,
+    },
+    /// Surface traced by a formula the renderer knows, about `size` across and split into
+    /// `resolution` steps around it
+    Parametric {
+        shape: ParametricShape,
+        size: Scalar,
+        #[serde(default = "default_parametric_resolution")]
+        resolution: u16,
+    },
+    /// Platonic solid with its corners `radius` from its middle
+    Polyhedron {
+        solid: PlatonicSolid,
+        radius: Scalar,
+    },
+    /// Half a sphere of flat triangles, each edge of an icosahedron split into `frequency`
+    /// pieces, standing open on the ground
+    GeodesicDome {
+        radius: Scalar,
+        #[serde(default = "default_dome_frequency")]
+        frequency: u16,
+    },
+    /// Upright cylinder, open at both ends unless capped
+    Cylinder {
+        radius: Scalar,
+        height: Scalar,
+        #[serde(default = "default_capped")]
+        capped: bool,
+        #[serde(default = "default_segments")]
+        segments: u16,
+    },
+    /// Cone pointing upwards
+    Cone {
+        radius: Scalar,
+        height: Scalar,
+        #[serde(default = "default_segments")]
+        segments: u16,
+    },
+    /// Ring lying flat, `radius` out to the middle of a tube of radius `tube`
+    Torus {
+        radius: Scalar,
+        tube: Scalar,
+        #[serde(default = "default_segments")]
+        segments: u16,
+    },
+    /// Upright cylinder rounded off at both ends, `height` tall from end to end
+    Capsule {
+        radius: Scalar,
+        height: Scalar,
+        #[serde(default = "default_segments")]
+        segments: u16,
+    },
+    /// Profile of x and y points spun around the y axis, shaded as a smooth curve if `smooth`
+    ///
+    /// Given `samples`, the profile is instead the control points of a curve through them,
+    /// with that many points from each one to the next. Spun less than 360 `degrees`, from +x
+    /// towards -z, both ends are closed off flat.
+    Revolution {
+        profile: Vec<[Scalar; 2]>,
+        resolution: u16,
+        #[serde(default)]
+        smooth: bool,
+        #[serde(default)]
+        samples: Option<u16>,
+        #[serde(default = "default_revolution_degrees")]
+        degrees: Scalar,
+    },
+    /// Outline of x and y points carried along a path, joined back to its start if `closed`
+    ///
+    /// The section's x points along the normal of the path, as close to up as the path
+    /// allows, and its y across both. Given `samples`, the path is instead the control points
+    /// of a curve through them, with that many points from each one to the next.
+    Sweep {
+        section: Vec<[Scalar; 2]>,
+        path: Vec<[Scalar; 3]>,
+        #[serde(default)]
+        closed: bool,
+        #[serde(default)]
+        smooth: bool,
+        #[serde(default)]
+        samples: Option<u16>,
+    },
+    /// Round pipe of `radius` along a line of points, mitred at its corners and joined back to
+    /// its start if `closed`
+    Tube {
+        path: Vec<[Scalar; 3]>,
+        radius: Scalar,
+        #[serde(default = "default_segments")]
+        sides: u16,
+        #[serde(default)]
+        closed: bool,
+    },
+    /// Ground shaped by a grayscale heightmap, `size` wide, high at its white pixels and deep,
+    /// with the texture repeated every `tile` units
+    Terrain {
+        heightmap: String,
+        size: [Scalar; 3],
+        #[serde(default = "default_tile")]
+        tile: Scalar,
+    },
+    /// Block letters `size` tall and `depth` deep, facing +z from the start of their first
+    /// line
+    Text {
+        text: String,
+        size: Scalar,
+        #[serde(default = "default_text_depth")]
+        depth: Scalar,
+    },
+    /// Shapes combined by `operation`, the first with each of the others in turn, such as a
+    /// box with holes subtracted from it
+    Csg {
+        operation: Operation,
+        operands: Vec<OperandDescription>,
+    },
+}
+
+/// Shape combined with others, placed relative to the result
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct OperandDescription {
+    #[serde(flatten)]
+    pub kind: ObjectKind,
+    #[serde(default)]
+    pub transform: TransformDescription,
+}
+
+fn default_wrap() -> WrapMode {
+    WrapMode::Repeat
+}
+
+fn default_filter() -> Filter {
+    Filter::Linear
+}
+
+fn default_mipmaps() -> bool {
+    true
+}
+
+/// Sampling for a texture, `filter` used whether it is smaller or larger on screen
+fn texture_options(wrap: WrapMode, filter: Filter, mipmaps: bool) -> TextureOptions {
+    TextureOptions {
+        wrap,
+        min_filter: filter,
+        mag_filter: filter,
+        mipmaps,
+    }
+}
+
+fn default_tile() -> Scalar {
+    1.0
+}
+
+fn default_text_depth() -> Scalar {
+    0.1
+}
+
+fn default_revolution_degrees() -> Scalar {
+    360.0
+}
+
+fn default_sphere_rings() -> u16 {
+    16
+}
+
+fn default_parametric_resolution() -> u16 {
+    16
+}
+
+fn default_dome_frequency() -> u16 {
+    2
+}
+
+fn default_segments() -> u16 {
+    32
+}
+
+fn default_closed() -> bool {
+    true
+}
+
+fn default_capped() -> bool {
+    true
+}
+
+fn default_bulb_color() -> [Scalar; 3] {
+    [1.0, 0.85, 0.6]
+}
+
+fn default_visible() -> bool {
+    true
+}
+
+fn to_vec3(v: [Scalar; 3]) -> Vec3 {
+    vec3(v[0], v[1], v[2])
+}
+
+fn from_vec3(v: Vec3) -> [Scalar; 3] {
+    [v.x, v.y, v.z]
+}
+
+/// Rotation by an angle around an axis
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct RotationDescription {
+    pub axis: [Scalar; 3],
+    pub degrees: Scalar,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(default)]
+pub struct TransformDescription {
+    pub translation: [Scalar; 3],
+    pub rotation: Option<RotationDescription>,
+    pub scale: [Scalar; 3],
+}
+
+impl Default for TransformDescription {
+    fn default() -> TransformDescription {
+        TransformDescription {
+            translation: [0.0; 3],
+            rotation: None,
+            scale: [1.0; 3],
+        }
+    }
+}
+
+impl TransformDescription {
+    pub fn from_transform(transform: &Transform) -> TransformDescription {
+        let (axis, angle) = transform.rotation.to_axis_angle();
+        TransformDescription {
+            translation: from_vec3(transform.translation),
+            // Leave out rotations too small to see
+            rotation: if angle.abs() > 1e-6 {
+                Some(RotationDescription {
+                    axis: from_vec3(axis),
+                    degrees: angle.to_degrees(),
+                })
+            } else {
+                None
+            },
+            scale: from_vec3(transform.scale),
+        }
+    }
+
+    pub fn to_transform(&self) -> Transform {
+        let rotation = match self.rotation {
+            Some(ref rotation) => Quat::from_axis_angle(
+                to_vec3(rotation.axis).normalize(),
+                rotation.degrees.to_radians(),
+            ),
+            None => Quat::identity(),
+        };
+        Transform::from_translation(to_vec3(self.translation))
+            .with_rotation(rotation)
+            .with_scale(to_vec3(self.scale))
+    }
+}
+
+/// Starts from a preset, or the object's own material, and replaces any colors given
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub struct MaterialDescription {
+    pub preset: Option<String>,
+    pub ambient: Option<[Scalar; 3]>,
+    pub diffuse: Option<[Scalar; 3]>,
+    pub specular: Option<[Scalar; 3]>,
+    pub shininess: Option<Scalar>,
+    pub emissive: Option<[Scalar; 3]>,
+    /// Blinn-Phong highlights when true, Phong when false
+    pub blinn_phong: Option<bool>,
+    /// Giving either shades the object physically
+    pub metallic: Option<Scalar>,
+    pub roughness: Option<Scalar>,
+    /// Parts of the texture less opaque than this are cut out
+    pub alpha_cutoff: Option<Scalar>,
+    /// Image repeated over shapes built here, models, rugs and plants use their own texture
+    /// instead
+    pub texture: Option<String>,
+    /// How the texture's edges wrap, repeated unless set
+    pub wrap: Option<WrapMode>,
+}
+
+impl MaterialDescription {
+    /// Describes every part of a material, so it doesn't depend on the object's usual one
+    pub fn from_material(material: &Material) -> MaterialDescription {
+        let (blinn_phong, metallic, roughness) = match material.shading {
+            ShadingModel::Phong => (Some(false), None, None),
+            ShadingModel::BlinnPhong => (Some(true), None, None),
+            ShadingModel::MetallicRoughness {
+                metallic,
+                roughness,
+            } => (None, Some(metallic), Some(roughness)),
+        };
+        MaterialDescription {
+            preset: None,
+            ambient: Some(from_vec3(material.ambient)),
+            diffuse: Some(from_vec3(material.diffuse)),
+            specular: Some(from_vec3(material.specular)),
+            shininess: Some(material.shininess),
+            emissive: Some(from_vec3(material.emissive)),
+            blinn_phong,
+            metallic,
+            roughness,
+            alpha_cutoff: Some(material.alpha_cutoff).filter(|&cutoff| cutoff > 0.0),
+            texture: None,
+            wrap: None,
+        }
+    }
+
+    /// Applies the description to the object's usual material
+    pub fn to_material(&self, material: Material) -> Result<Material, io::Error> {
+        let mut material = match self.preset {
+            Some(ref preset) => Material::preset(preset)
+                .ok_or_else(|| io_error(format!("Unknown material preset {}", preset)))?,
+            None => material,
+        };
+        if let Some(ambient) = self.ambient {
+            material.ambient = to_vec3(ambient);
+        }
+        if let Some(diffuse) = self.diffuse {
+            material.diffuse = to_vec3(diffuse);
+        }
+        if let Some(specular) = self.specular {
+            material.specular = to_vec3(specular);
+        }
+        if let Some(shininess) = self.shininess {
+            material.shininess = shininess;
+        }
+        if let Some(emissive) = self.emissive {
+            material.emissive = to_vec3(emissive);
+        }
+        match self.blinn_phong {
+            Some(true) => material.shading = ShadingModel::BlinnPhong,
+            Some(false) => material.shading = ShadingModel::Phong,
+            None => {}
+        }
+        if self.metallic.is_some() || self.roughness.is_some() {
+            material.shading = ShadingModel::MetallicRoughness {
+                metallic: self.metallic.unwrap_or(0.0),
+                roughness: self.roughness.unwrap_or(0.5),
+            };
+        }
+        if let Some(alpha_cutoff) = self.alpha_cutoff {
+            material.alpha_cutoff = alpha_cutoff;
+        }
+        Ok(material)
+    }
+}
+
+impl ObjectKind {
+    /// Creates the object at the origin, None for groups
+    pub fn build(&self) -> Result<Option<Box<Drawable>>, io::Error> {
+        let origin = Vec3::origin();
+        Ok(match *self {
+            ObjectKind::Group => None,
+            ObjectKind::Room { size, ref openings } => Some(Box::new(Room::new(
+                size[0],
+                size[1],
+                size[2],
+                openings.clone(),
+            ))),
+            ObjectKind::Desk {
+                top,
+                legs,
+                edge_radius,
+            } => {
+                let mut desk =
+                    Furniture::desk(top[0], top[1], top[2], legs[0], legs[1], legs[2], origin);
+                if let Some(edge_radius) = edge_radius {
+                    desk = desk.with_edge_radius(edge_radius);
+                }
+                Some(Box::new(desk))
+            }
+            ObjectKind::Chair {
+                top,
+                legs,
+                back,
+                back_tilt,
+                edge_radius,
+            } => {
+                let mut chair =
+                    Furniture::chair(top[0], top[1], top[2], legs[0], legs[1], legs[2], origin)
+                        .with_back(back.unwrap_or(top[0]), back_tilt.to_radians());
+                if let Some(edge_radius) = edge_radius {
+                    chair = chair.with_edge_radius(edge_radius);
+                }
+                Some(Box::new(chair))
+            }
+            ObjectKind::Furniture {
+                top,
+                legs,
+                back,
+                back_tilt,
+                shelves,
+                edge_radius,
+            } => {
+                let mut furniture =
+                    Furniture::new(top[0], top[1], top[2], legs[0], legs[1], legs[2], origin)
+                        .with_shelves(shelves);
+                if let Some(back) = back {
+                    furniture = furniture.with_back(back, back_tilt.to_radians());
+                }
+                if let Some(edge_radius) = edge_radius {
+                    furniture = furniture.with_edge_radius(edge_radius);
+                }
+                Some(Box::new(furniture))
+            }
+            ObjectKind::Sofa {
+                width,
+                depth,
+                height,
+            } => Some(Box::new(Sofa::new(width, depth, height, origin))),
+            ObjectKind::Bed {
+                width,
+                length,
+                height,
+            } => Some(Box::new(Bed::new(width, length, height, origin))),
+            ObjectKind::Lamp { height, color } => {
+                Some(Box::new(Lamp::new(height, to_vec3(color), origin)))
+            }
+            ObjectKind::CeilingLight {
+                drop,
+                radius,
+                color,
+            } => Some(Box::new(CeilingLight::new(
+                drop,
+                radius,
+                to_vec3(color),
+                origin,
+            ))),
+            ObjectKind::Stairs {
+                steps,
+                rise,
+                run,
+                width,
+                railing,
+            } => {
+                let stairs = Stairs::new(steps, rise, run, width, origin);
+                Some(Box::new(match railing {
+                    Some(height) => stairs.with_railing(height),
+                    None => stairs,
+                }))
+            }
+            ObjectKind::Rug {
+                size,
+                ref texture,
+                wrap,
+                filter,
+                mipmaps,
+                angle,
+            } => Some(Box::new(
+                Rug::new(size[0], size[1], texture, wrap, origin)
+                    .with_texture_options(texture_options(wrap, filter, mipmaps))
+                    .with_rotation(angle.to_radians()),
+            )),
+            ObjectKind::Obj {
+                ref model,
+                ref texture,
+                wrap,
+                filter,
+                mipmaps,
+                ref normal_map,
+                ref specular_map,
+                ref emissive_map,
+            } => {
+                let mut obj =
+                    Obj::load(model.as_str(), texture, wrap, vec3(1.0, 1.0, 1.0), origin)?;
+                obj.set_texture_options(texture_options(wrap, filter, mipmaps));
+                if let Some(normal_map) = normal_map {
+                    obj.set_normal_map(normal_map);
+                }
+                if let Some(specular_map) = specular_map {
+                    obj.set_specular_map(specular_map);
+                }
+                if let Some(emissive_map) = emissive_map {
+                    obj.set_emissive_map(emissive_map);
+                }
+                Some(Box::new(obj))
+            }
+            ObjectKind::Star {
+                points,
+                inner_radius,
+                outer_radius,
+                depth,
+                phase,
+            } => Some(Box::new(Extrusion::new(
+                star(
+                    points,
+                    inner_radius,
+                    outer_radius,
+                    phase.to_radians(),
+                    vec3(0.0, 1.0, 0.0),
+                ),
+                vec3(0.0, depth, 0.0),
+                origin,
+            ))),
+            ObjectKind::Extrusion {
+                ref outline,
+                depth,
+                closed,
+                smooth,
+            } => Some(Box::new(
+                Extrusion::new(
+                    outline
+                        .iter()
+                        .map(|point| vec3(point[0], 0.0, point[1]))
+                        .collect(),
+                    vec3(0.0, depth, 0.0),
+                    origin,
+                )
+                .with_closed(closed)
+                .with_smooth(smooth),
+            )),
+            ObjectKind::Sphere {
+                radius,
+                rings,
+                segments,
+                subdivisions,
+            } => Some(Box::new(match subdivisions {
+                Some(levels) => Sphere::icosphere(radius, levels, origin),
+                None => Sphere::new(radius, rings, segments, origin),
+            })),
+            ObjectKind::Plant { height, seed } => Some(Box::new(Plant::new(height, seed, origin))),
+            ObjectKind::Parametric {
+                shape,
+                size,
+                resolution,
+            } => Some(Box::new(ParametricSurface::shape(
+                shape, size, resolution, origin,
+            ))),
+            ObjectKind::Polyhedron { solid, radius } => {
+                Some(Box::new(Polyhedron::platonic(solid, radius, origin)))
+            }
+            ObjectKind::GeodesicDome { radius, frequency } => Some(Box::new(
+                Polyhedron::geodesic_dome(radius, frequency, origin),
+            )),
+            ObjectKind::Cylinder {
+                radius,
+                height,
+                capped,
+                segments,
+            } => Some(Box::new(Primitive::new(
+                Shape::Cylinder {
+                    radius,
+                    height,
+                    capped,
+                },
+                segments,
+                origin,
+            ))),
+            ObjectKind::Cone {
+                radius,
+                height,
+                segments,
+            } => Some(Box::new(Primitive::new(
+                Shape::Cone { radius, height },
+                segments,
+                origin,
+            ))),
+            ObjectKind::Torus {
+                radius,
+                tube,
+                segments,
+            } => Some(Box::new(Primitive::new(
+                Shape::Torus { radius, tube },
+                segments,
+                origin,
+            ))),
+            ObjectKind::Capsule {
+                radius,
+                height,
+                segments,
+            } => Some(Box::new(Primitive::new(
+                Shape::Capsule { radius, height },
+                segments,
+                origin,
+            ))),
+            ObjectKind::Revolution {
+                ref profile,
+                resolution,
+                smooth,
+                samples,
+                degrees,
+            } => {
+                let profile: Vec<Vec3> = profile
+                    .iter()
+                    .map(|point| vec3(point[0], point[1], 0.0))
+                    .collect();
+                let revolution = match samples {
+                    Some(samples) => Revolution::from_curve(&profile, samples, resolution, origin),
+                    None => Revolution::new(profile, resolution, origin),
+                };
+                Some(Box::new(
+                    revolution
+                        .with_smooth(smooth)
+                        .with_angle(degrees.to_radians()),
+                ))
+            }
+            ObjectKind::Sweep {
+                ref section,
+                ref path,
+                closed,
+                smooth,
+                samples,
+            } => {
+                let section = section
+                    .iter()
+                    .map(|point| vec2(point[0], point[1]))
+                    .collect();
+                let path: Vec<Vec3> = path
+                    .iter()
+                    .map(|point| vec3(point[0], point[1], point[2]))
+                    .collect();
+                let sweep = match samples {
+                    Some(samples) => Sweep::from_curve(section, &path, samples, origin),
+                    None => Sweep::new(section, path, origin),
+                };
+                Some(Box::new(sweep.with_closed(closed).with_smooth(smooth)))
+            }
+            ObjectKind::Tube {
+                ref path,
+                radius,
+                sides,
+                closed,
+            } => {
+                let path = path
+                    .iter()
+                    .map(|point| vec3(point[0], point[1], point[2]))
+                    .collect();
+                Some(Box::new(
+                    Tube::new(path, radius, sides, origin).with_closed(closed),
+                ))
+            }
+            ObjectKind::Terrain {
+                ref heightmap,
+                size,
+                tile,
+            } => Some(Box::new(
+                Terrain::load(heightmap, size[0], size[1], size[2], origin)?.with_tile(tile),
+            )),
+            ObjectKind::Text {
+                ref text,
+                size,
+                depth,
+            } => Some(Box::new(Text3D::new(text, size, depth, origin))),
+            ObjectKind::Csg {
+                operation,
+                ref operands,
+            } => {
+                let operands = operands
+                    .iter()
+                    .map(|operand| {
+                        let mut object = operand.kind.build()?.ok_or_else(|| {
+                            io_error("Groups can't be combined, they have no shape")
+                        })?;
+                        object.instance_mut().transform = operand.transform.to_transform();
+                        Ok(object)
+                    })
+                    .collect::<Result<_, io::Error>>()?;
+                Some(Box::new(Csg::new(operation, operands, origin)))
+            }
+        })
+    }
+}
+
+impl ObjectDescription {
+    /// Describes a node as it is now, objects not built from a scene file are left out
+    pub fn from_node(node: &SceneNode) -> ObjectDescription {
+        ObjectDescription {
+            name: node.name.clone(),
+            kind: node.kind.clone().unwrap_or(ObjectKind::Group),
+            transform: TransformDescription::from_transform(&node.transform),
+            material: node.object.as_ref().map(|object| {
+                let instance = object.instance();
+                MaterialDescription {
+                    texture: instance
+                        .texture_path
+                        .as_ref()
+                        .map(|path| path.display().to_string()),
+                    wrap: instance
+                        .texture_path
+                        .as_ref()
+                        .map(|_| instance.texture_options.wrap),
+                    ..MaterialDescription::from_material(object.material())
+                }
+            }),
+            children: node
+                .children
+                .iter()
+                .map(ObjectDescription::from_node)
+                .collect(),
+            visible: node.visible,
+            layer: node.layer.clone(),
+        }
+    }
+
+    /// Creates the object and its children, each at the origin of its node
+    pub fn build(&self) -> Result<SceneNode, io::Error> {
+        let mut object = self.kind.build()?;
+        if let (Some(object), Some(material)) = (object.as_mut(), self.material.as_ref()) {
+            if let Some(ref texture) = material.texture {
+                let wrap = material.wrap.unwrap_or(WrapMode::Repeat);
+                object.set_texture(PathBuf::from(texture), wrap.into());
+            }
+            let material = material.to_material(*object.material())?;
+            object.set_material(material);
+        }
+        let mut node = SceneNode {
+            name: self.name.clone(),
+            object,
+            visible: self.visible,
+            kind: Some(self.kind.clone()),
+            layer: self.layer.clone(),
+            ..SceneNode::new(self.transform.to_transform())
+        };
+        for child in &self.children {
+            node.add_child(child.build()?);
+        }
+        Ok(node)
+    }
+}
+
+impl SceneFile {
+    /// Reads a scene file from a path
+    pub fn load<P>(path: P) -> Result<SceneFile, io::Error>
+    where
+        P: AsRef<Path>,
+    {
+        SceneFile::parse(&fs::read_to_string(path)?)
+    }
+
+    /// Parses the contents of a scene file
+    pub fn parse(data: &str) -> Result<SceneFile, io::Error> {
+        serde_json::from_str(data).map_err(|err| io_error(format!("Invalid scene file: {}", err)))
+    }
+
+    /// Describes the objects under a root node and where the camera is
+    pub fn from_scene(root: &SceneNode, camera: Option<CameraPose>) -> SceneFile {
+        SceneFile {
+            objects: root
+                .children
+                .iter()
+                .map(ObjectDescription::from_node)
+                .collect(),
+            camera: camera.map(CameraDescription::from_pose),
+        }
+    }
+
+    /// Writes the file out as JSON, which `parse` reads back
+    pub fn to_json(&self) -> Result<String, io::Error> {
+        serde_json::to_string_pretty(self)
+            .map_err(|err| io_error(format!("Couldn't write scene file: {}", err)))
+    }
+
+    /// Creates every object in the file under one root node
+    pub fn build(&self) -> Result<SceneNode, io::Error> {
+        let mut root = SceneNode::new(Transform::default());
+        for object in &self.objects {
+            root.add_child(object.build()?);
+        }
+        Ok(root)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::io;
+
+    use super::SceneFile;
+    use camera::CameraPose;
+    use material::ShadingModel;
+    use matrix::{identity, transform_point, vec3, Quat, Scalar};
+    use texture::WrapMode;
+
+    #[test]
+    fn test_build() -> io::Result<()> {
+        let scene = SceneFile::parse(
+            r#"{
+                "objects": [
+                    {
+                        "name": "room",
+                        "type": "room",
+                        "size": [10, 10, 10],
+                        "material": {"texture": "wall.tga", "wrap": "mirrored_repeat"}
+                    },
+                    {
+                        "name": "table",
+                        "type": "desk",
+                        "top": [4, 4, 0.2],
+                        "legs": [0.2, 0.2, 3],
+                        "transform": {"translation": [5, 0, 5]},
+                        "material": {"preset": "bronze", "metallic": 1},
+                        "children": [
+                            {"type": "group", "transform": {"translation": [0, 3.5, 0]}}
+                        ]
+                    }
+                ]
+            }"#,
+        )?
+        .build()?;
+        assert_eq!(scene.children.len(), 2);
+        let desk = scene.find("table").unwrap();
+        match desk.object.as_ref().map(|desk| desk.material().shading) {
+            Some(ShadingModel::MetallicRoughness { metallic, .. }) => assert_eq!(metallic, 1.0),
+            _ => panic!("Desk material wasn't applied"),
+        }
+        // Textures given in the material are sampled as it says
+        let room = scene.find("room").unwrap().object.as_ref().unwrap();
+        assert_eq!(
+            room.instance().texture_options.wrap,
+            WrapMode::MirroredRepeat
+        );
+        // Children are placed on their parents
+        let mut origins = Vec::new();
+        scene.walk(&identity(), &mut |_, world| {
+            origins.push(transform_point(world, vec3(0.0, 0.0, 0.0)))
+        });
+        assert!(origins[3].distance(&vec3(5.0, 3.5, 5.0)) < 1e-5);
+        // Unknown types and presets are errors
+        assert!(SceneFile::parse(r#"{"objects": [{"type": "sofa"}]}"#).is_err());
+        let unknown_preset = r#"{"objects": [{"type": "group"}, {"type": "room",
+            "size": [1, 1, 1], "material": {"preset": "cheese"}}]}"#;
+        assert!(SceneFile::parse(unknown_preset)?.build().is_err());
+        // Combined shapes are placed by their own transforms, and need a shape to combine
+        let hollowed = r#"{"objects": [{"name": "mug", "type": "csg", "operation": "subtract",
+            "operands": [{"type": "cylinder", "radius": 1, "height": 2},
+            {"type": "cylinder", "radius": 0.5, "height": 2, "transform": {"translation": [0, 1.5, 0]}}]}]}"#;
+        let mug = SceneFile::parse(hollowed)?.build()?;
+        let bounds = mug
+            .find("mug")
+            .unwrap()
+            .object
+            .as_ref()
+            .unwrap()
+            .mesh_data()
+            .bounds;
+        assert!((bounds.unwrap().max.y - 1.0).abs() < 1e-4);
+        let grouped = hollowed.replace(
+            "\"cylinder\", \"radius\": 0.5",
+            "\"group\", \"radius\": 0.5",
+        );
+        assert!(SceneFile::parse(&grouped)?.build().is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_save() -> io::Result<()> {
+        let mut scene = SceneFile::parse(
+            r#"{
+                "objects": [
+                    {
+                        "name": "table",
+                        "type": "desk",
+                        "top": [4, 4, 0.2],
+                        "legs": [0.2, 0.2, 3],
+                        "material": {"preset": "bronze", "texture": "wood.tga"},
+                        "layer": "furniture",
+                        "children": [{"name": "cat", "type": "group", "visible": false}]
+                    }
+                ]
+            }"#,
+        )?
+        .build()?;
+        // Rearrange the room, then save and reload it
+        scene
+            .find_mut("table")
+            .unwrap()
+            .set_rotation(Quat::from_axis_angle(
+                vec3(0.0, 1.0, 0.0),
+                (90.0 as Scalar).to_radians(),
+            ));
+        let pose = CameraPose {
+            eye: vec3(1.0, 2.0, 3.0),
+            target: vec3(4.0, 5.0, 6.0),
+        };
+        let saved = SceneFile::from_scene(&scene, Some(pose)).to_json()?;
+        let file = SceneFile::parse(&saved)?;
+        assert!(
+            file.camera
+                .as_ref()
+                .unwrap()
+                .to_pose()
+                .eye
+                .distance(&pose.eye)
+                < 1e-5
+        );
+        let reloaded = file.build()?;
+        let table = reloaded.find("table").unwrap();
+        let turned = transform_point(&table.transform.to_matrix(), vec3(1.0, 0.0, 0.0));
+        assert!(turned.distance(&vec3(0.0, 0.0, -1.0)) < 1e-5);
+        match table.object.as_ref().map(|desk| desk.material().shading) {
+            Some(ShadingModel::Phong) => {}
+            _ => panic!("Desk material wasn't saved"),
+        }
+        assert!(!reloaded.find("cat").unwrap().visible);
+        assert_eq!(table.layer.as_deref(), Some("furniture"));
+        let texture = table
+            .object
+            .as_ref()
+            .unwrap()
+            .instance()
+            .texture_path
+            .clone();
+        assert_eq!(texture, Some("wood.tga".into()));
+        Ok(())
+    }
+}
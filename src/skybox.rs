@@ -0,0 +1,131 @@
+use gleam::gl::{self, GLuint};
+
+use super::{create_program, Context, GlPtr, FLOAT_SIZE};
+use matrix::{Projection, Scalar};
+use texture::TextureId;
+use tonemap::{upload_tone_map, TONE_MAP_SRC};
+
+/// Cube surrounding the camera, sampled by direction
+pub struct Skybox {
+    program: GLuint,
+    vertex_array: GLuint,
+    texture: TextureId,
+}
+
+impl Skybox {
+    /// Creates a skybox from a cubemap texture
+    pub fn new(gl: &GlPtr, texture: TextureId) -> Skybox {
+        let program = create_program(gl, SKYBOX_VS_SRC, SKYBOX_FS_SRC).unwrap();
+        // Two triangles for each face of a unit cube
+        #[cfg_attr(rustfmt, rustfmt_skip)]
+        let corners = [
+            [-1.0, -1.0, -1.0], [1.0, -1.0, -1.0], [1.0, 1.0, -1.0], [-1.0, 1.0, -1.0],
+            [-1.0, -1.0, 1.0], [1.0, -1.0, 1.0], [1.0, 1.0, 1.0], [-1.0, 1.0, 1.0],
+        ];
+        #[cfg_attr(rustfmt, rustfmt_skip)]
+        let faces = [
+            [0, 1, 2, 3], [5, 4, 7, 6], [4, 0, 3, 7],
+            [1, 5, 6, 2], [3, 2, 6, 7], [4, 5, 1, 0],
+        ];
+        let vertices: Vec<f32> = faces
+            .iter()
+            .flat_map(|f| vec![f[0], f[1], f[2], f[2], f[3], f[0]])
+            .flat_map(|corner| corners[corner].to_vec())
+            .collect();
+        // Buffer the cube into its own vertex array
+        let vertex_array = gl.gen_vertex_arrays(1)[0];
+        gl.bind_vertex_array(vertex_array);
+        let buffer = gl.gen_buffers(1)[0];
+        gl.bind_buffer(gl::ARRAY_BUFFER, buffer);
+        gl.buffer_data_untyped(
+            gl::ARRAY_BUFFER,
+            (FLOAT_SIZE * vertices.len()) as isize,
+            vertices.as_ptr() as *const _,
+            gl::STATIC_DRAW,
+        );
+        gl.enable_vertex_attrib_array(0);
+        gl.vertex_attrib_pointer(0, 3, gl::FLOAT, false, 3 * FLOAT_SIZE as i32, 0);
+        gl.bind_vertex_array(0);
+        Skybox {
+            program,
+            vertex_array,
+            texture,
+        }
+    }
+
+    /// Cubemap the sky is drawn with
+    pub fn texture(&self) -> TextureId {
+        self.texture
+    }
+
+    /// Draws the skybox behind everything else
+    pub fn draw(&self, ctx: &Context) {
+        let gl = &ctx.gl;
+        gl.use_program(self.program);
+        // Only rotate with the camera, the sky is infinitely far away
+        let mut v_matrix = ctx.camera.view_matrix();
+        v_matrix[12] = 0.0;
+        v_matrix[13] = 0.0;
+        v_matrix[14] = 0.0;
+        let v_location = gl.get_uniform_location(self.program, "uVMatrix");
+        gl.uniform_matrix_4fv(v_location, false, &v_matrix.to_gl());
+        // The sky gets its own perspective so it works under an orthographic scene
+        let p_matrix = Projection::perspective(
+            (60.0 as Scalar).to_radians(),
+            ctx.width as Scalar / ctx.height as Scalar,
+            0.1,
+            10.0,
+        )
+        .matrix();
+        let p_location = gl.get_uniform_location(self.program, "uPMatrix");
+        gl.uniform_matrix_4fv(p_location, false, &p_matrix.to_gl());
+        ctx.textures.bind(self.texture, self.program);
+        upload_tone_map(gl, self.program, ctx.tone_map, ctx.exposure);
+        // The camera is inside the cube and nothing should be hidden by it
+        gl.disable(gl::CULL_FACE);
+        gl.depth_mask(false);
+        gl.bind_vertex_array(self.vertex_array);
+        gl.draw_arrays(gl::TRIANGLES, 0, 36);
+        gl.depth_mask(true);
+        gl.enable(gl::CULL_FACE);
+    }
+}
+
+#[cfg_attr(rustfmt, rustfmt_skip)]
+const SKYBOX_VS_SRC: &[&[u8]] = &[
+b"#version 300 es
+
+layout(location = 0) in vec3 aPosition;
+
+// Rotation-only view matrix
+uniform mat4 uVMatrix;
+uniform mat4 uPMatrix;
+
+out vec3 vDirection;
+
+void main() {
+    vDirection = aPosition;
+    gl_Position = uPMatrix * uVMatrix * vec4(aPosition, 1.0);
+}
+"];
+
+#[cfg_attr(rustfmt, rustfmt_skip)]
+const SKYBOX_FS_SRC: &[&[u8]] = &[
+b"#version 300 es
+
+precision mediump float;
+
+in vec3 vDirection;
+
+uniform samplerCube uSampler;
+
+out vec4 oFragColor;
+",
+TONE_MAP_SRC,
+b"
+void main() {
+    oFragColor = texture(uSampler, vDirection);
+    // Faces are decoded to linear when sampled, so the sky is exposed like the scene
+    oFragColor.rgb = toneMap(oFragColor.rgb);
+}
+"];
@@ -0,0 +1,121 @@
+use material::Material;
+use matrix::{Scalar, Transform, Vec3};
+use mesh::{Instance, MeshData};
+use render::{icosphere, uv_sphere, Drawable};
+
+/// How a sphere's surface is split into triangles
+#[derive(Copy, Clone, Debug)]
+pub enum Tessellation {
+    /// Bands from pole to pole, and slices around the y axis
+    Uv { rings: u16, segments: u16 },
+    /// Icosahedron with each triangle split into four `levels` times, so they are spread
+    /// evenly instead of bunching up at the poles
+    Ico { levels: u32 },
+}
+
+/// Ball centered on its origin, with texture coords wrapping around it like a globe's
+pub struct Sphere {
+    radius: Scalar,
+    tessellation: Tessellation,
+    instance: Instance,
+}
+
+impl Sphere {
+    pub fn new(radius: Scalar, rings: u16, segments: u16, translate: Vec3) -> Sphere {
+        Sphere::with_tessellation(radius, Tessellation::Uv { rings, segments }, translate)
+    }
+
+    /// Creates a sphere from an icosahedron split `levels` times
+    pub fn icosphere(radius: Scalar, levels: u32, translate: Vec3) -> Sphere {
+        Sphere::with_tessellation(radius, Tessellation::Ico { levels }, translate)
+    }
+
+    fn with_tessellation(radius: Scalar, tessellation: Tessellation, translate: Vec3) -> Sphere {
+        Sphere {
+            radius,
+            tessellation,
+            instance: Instance::new(Transform::from_translation(translate), Material::pearl()),
+        }
+    }
+}
+
+impl Drawable for Sphere {
+    /// Spheres with the same size and detail share a mesh
+    fn mesh_key(&self) -> String {
+        format!("sphere {} {:?}", self.radius, self.tessellation)
+    }
+    /// Generates the vertices of the shape
+    fn mesh_data(&self) -> MeshData {
+        let center = Vec3::origin();
+        MeshData::new(&match self.tessellation {
+            Tessellation::Uv { rings, segments } => uv_sphere(center, self.radius, rings, segments),
+            Tessellation::Ico { levels } => icosphere(center, self.radius, levels),
+        })
+    }
+    /// Mesh of the object and how it is placed and looks
+    fn instance(&self) -> &Instance {
+        &self.instance
+    }
+    fn instance_mut(&mut self) -> &mut Instance {
+        &mut self.instance
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::Sphere;
+    use matrix::{vec3, Scalar, Vec3};
+    use render::{Drawable, VERTEX_SIZE};
+
+    #[test]
+    fn test_sphere() {
+        let data = Sphere::new(2.0, 8, 12, Vec3::origin()).mesh_data();
+        // Each band is two triangles a slice, except the fans at the poles
+        assert_eq!(data.vertices.len(), (8 - 1) * 12 * 2 * 3 * VERTEX_SIZE);
+        let bounds = data.bounds.unwrap();
+        assert!((bounds.max.y - 2.0).abs() < 1e-5 && (bounds.min.y + 2.0).abs() < 1e-5);
+        assert!((bounds.max.x - 2.0).abs() < 1e-5);
+        for vertex in data.vertices.chunks(VERTEX_SIZE) {
+            let value = |i: usize| Scalar::from(vertex[i]);
+            let position = vec3(value(0), value(1), value(2));
+            let normal = vec3(value(3), value(4), value(5));
+            // Every normal points straight out, and the texture is only wrapped around once
+            assert!(position.distance(&(normal * 2.0)) < 1e-4);
+            assert!((0.0..=1.0).contains(&value(6)) && (0.0..=1.0).contains(&value(7)));
+            // The north pole is at the top of the texture
+            if value(1) > 1.99 {
+                assert!((value(7) - 1.0).abs() < 1e-5);
+            }
+        }
+    }
+
+    #[test]
+    fn test_icosphere() {
+        // Twenty triangles, each split into four twice
+        let data = Sphere::icosphere(2.0, 2, Vec3::origin()).mesh_data();
+        assert_eq!(data.vertices.len(), 20 * 16 * 3 * VERTEX_SIZE);
+        let corners: Vec<Vec3> = data
+            .vertices
+            .chunks(VERTEX_SIZE)
+            .map(|vertex| {
+                let value = |i: usize| Scalar::from(vertex[i]);
+                let (position, normal) = (
+                    vec3(value(0), value(1), value(2)),
+                    vec3(value(3), value(4), value(5)),
+                );
+                assert!(position.distance(&(normal * 2.0)) < 1e-4);
+                position
+            })
+            .collect();
+        // Every triangle faces out, and none wraps back across the texture at the seam
+        for (triangle, vertices) in corners.chunks(3).zip(data.vertices.chunks(3 * VERTEX_SIZE)) {
+            let facing = (triangle[1] - triangle[0]).cross(triangle[2] - triangle[0]);
+            assert!(facing.dot(&triangle[0]) > 0.0);
+            let turns: Vec<f32> = vertices
+                .chunks(VERTEX_SIZE)
+                .map(|vertex| vertex[6])
+                .collect();
+            assert!(turns.iter().all(|turn| (turn - turns[0]).abs() < 0.5));
+        }
+    }
+}
@@ -0,0 +1,141 @@
+use material::Material;
+use matrix::{translate, vec3, Quat, Scalar, Transform, Vec3};
+use mesh::{Instance, MeshData};
+use render::{rectangular_prism, Drawable, Vertex};
+
+/// Width and depth of the railing's posts and handrail
+const RAIL_THICKNESS: Scalar = 0.05;
+
+/// Flight of solid steps climbing along x, centered on z
+pub struct Stairs {
+    steps: u32,
+    /// Height of each step
+    rise: Scalar,
+    /// Depth of each step
+    run: Scalar,
+    width: Scalar,
+    /// Height of the railing on both sides above the steps, None for no railing
+    railing: Option<Scalar>,
+    instance: Instance,
+}
+
+impl Stairs {
+    /// Creates stairs whose bottom step starts at `translate`
+    pub fn new(steps: u32, rise: Scalar, run: Scalar, width: Scalar, translate: Vec3) -> Stairs {
+        Stairs {
+            steps,
+            rise,
+            run,
+            width,
+            railing: None,
+            instance: Instance::new(Transform::from_translation(translate), Material::wood()),
+        }
+    }
+
+    /// Adds a post on both sides of every step, joined by a handrail `height` above them
+    pub fn with_railing(self, height: Scalar) -> Stairs {
+        Stairs {
+            railing: Some(height),
+            ..self
+        }
+    }
+
+    /// Posts and handrails on both sides of the steps
+    fn railing(&self, height: Scalar) -> Vec<Vertex> {
+        let mut vertices: Vec<Vertex> = Vec::new();
+        let steps = self.steps as Scalar;
+        for &z in &[
+            -self.width / 2.0 + RAIL_THICKNESS / 2.0,
+            self.width / 2.0 - RAIL_THICKNESS / 2.0,
+        ] {
+            for step in 0..self.steps {
+                let step = step as Scalar;
+                vertices.extend_from_slice(&rectangular_prism(
+                    vec3(
+                        self.run * (step + 0.5),
+                        self.rise * (step + 1.0) + height / 2.0,
+                        z,
+                    ),
+                    RAIL_THICKNESS,
+                    RAIL_THICKNESS,
+                    height,
+                ));
+            }
+            if self.steps < 2 {
+                continue;
+            }
+            // Handrail sloping from the top of the first post to the top of the last
+            let length = (self.run.hypot(self.rise) * (steps - 1.0)) + RAIL_THICKNESS;
+            let middle = vec3(
+                self.run * steps / 2.0,
+                self.rise * (steps + 1.0) / 2.0 + height,
+                z,
+            );
+            let slope = Quat::from_axis_angle(vec3(0.0, 0.0, 1.0), self.rise.atan2(self.run))
+                .to_mat4()
+                * translate(middle.x, middle.y, middle.z);
+            vertices.extend(
+                rectangular_prism(Vec3::origin(), length, RAIL_THICKNESS, RAIL_THICKNESS)
+                    .iter()
+                    .map(|vertex| vertex.transformed(&slope)),
+            );
+        }
+        vertices
+    }
+}
+
+impl Drawable for Stairs {
+    /// Stairs with the same sizes share a mesh
+    fn mesh_key(&self) -> String {
+        format!(
+            "stairs {} {} {} {} {:?}",
+            self.steps, self.rise, self.run, self.width, self.railing
+        )
+    }
+    /// Generates the vertices of the shape
+    fn mesh_data(&self) -> MeshData {
+        let mut vertices: Vec<Vertex> = Vec::new();
+        // Each step is a block down to the floor, so the flight is solid underneath
+        for step in 0..self.steps {
+            let step = step as Scalar;
+            let top = self.rise * (step + 1.0);
+            vertices.extend_from_slice(&rectangular_prism(
+                vec3(self.run * (step + 0.5), top / 2.0, 0.0),
+                self.run,
+                self.width,
+                top,
+            ));
+        }
+        if let Some(height) = self.railing {
+            vertices.extend(self.railing(height));
+        }
+
+        MeshData::new(&vertices)
+    }
+    /// Mesh of the object and how it is placed and looks
+    fn instance(&self) -> &Instance {
+        &self.instance
+    }
+    fn instance_mut(&mut self) -> &mut Instance {
+        &mut self.instance
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::Stairs;
+    use matrix::Vec3;
+    use render::Drawable;
+
+    #[test]
+    fn test_stairs() {
+        let stairs = Stairs::new(4, 0.25, 0.3, 1.0, Vec3::origin());
+        let bounds = stairs.mesh_data().bounds.unwrap();
+        assert!(bounds.min.y.abs() < 1e-5 && (bounds.max.y - 1.0).abs() < 1e-5);
+        assert!((bounds.max.x - 1.2).abs() < 1e-5 && (bounds.max.z - 0.5).abs() < 1e-5);
+        // The handrail runs along the tops of the posts, no further out than the steps
+        let railed = stairs.with_railing(0.9).mesh_data().bounds.unwrap();
+        assert!(railed.max.y > 1.9 && railed.max.y < 1.95);
+        assert!((railed.max.x - 1.2).abs() < 1e-5 && (railed.max.z - 0.5).abs() < 1e-5);
+    }
+}
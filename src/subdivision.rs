@@ -0,0 +1,212 @@
+use std::collections::HashMap;
+
+use matrix::{Scalar, Vec3};
+use render::{newell, vertex, weld_key, Vertex};
+
+/// Mesh of four sided faces joined at the corners they share, for smoothing by Catmull-Clark
+/// subdivision
+///
+/// Each pass splits every face into four and pulls the points toward a smooth surface, so a
+/// few passes round a box into a cushion. Edges belonging to only one face are kept as the
+/// rim of an open surface.
+#[derive(Clone, Debug)]
+pub struct QuadMesh {
+    points: Vec<Vec3>,
+    /// Corners of each face, counter-clockwise seen from outside
+    faces: Vec<[usize; 4]>,
+}
+
+/// Both ends of an edge, the lower first so each edge has one key
+fn edge(a: usize, b: usize) -> (usize, usize) {
+    (a.min(b), a.max(b))
+}
+
+/// Average of some points
+fn average<I>(points: I) -> Vec3
+where
+    I: IntoIterator<Item = Vec3>,
+{
+    let (sum, count) = points
+        .into_iter()
+        .fold((Vec3::origin(), 0.0), |(sum, count), point| {
+            (sum + point, count + 1.0)
+        });
+    sum * (1.0 / count)
+}
+
+impl QuadMesh {
+    /// Joins up the quads of a shape, six vertices to each as `quad` generates them
+    pub fn from_quads(vertices: &[Vertex]) -> QuadMesh {
+        let mut points: Vec<Vec3> = Vec::new();
+        let mut welded: HashMap<(i64, i64, i64), usize> = HashMap::new();
+        let mut index = |position: Vec3| {
+            *welded.entry(weld_key(position)).or_insert_with(|| {
+                points.push(position);
+                points.len() - 1
+            })
+        };
+        let faces = vertices
+            .chunks_exact(6)
+            .map(|quad| {
+                [
+                    index(quad[0].position()),
+                    index(quad[1].position()),
+                    index(quad[2].position()),
+                    index(quad[4].position()),
+                ]
+            })
+            .collect();
+        QuadMesh { points, faces }
+    }
+
+    /// Splits every face into four, moving the points toward a smooth surface
+    pub fn subdivide(&self) -> QuadMesh {
+        let face_points: Vec<Vec3> = self
+            .faces
+            .iter()
+            .map(|face| average(face.iter().map(|&i| self.points[i])))
+            .collect();
+        // Faces on each edge, and the edges and faces around each point
+        let mut edge_faces: HashMap<(usize, usize), Vec<usize>> = HashMap::new();
+        let mut point_faces: Vec<Vec<usize>> = vec![Vec::new(); self.points.len()];
+        for (f, face) in self.faces.iter().enumerate() {
+            for i in 0..4 {
+                edge_faces
+                    .entry(edge(face[i], face[(i + 1) % 4]))
+                    .or_default()
+                    .push(f);
+                point_faces[face[i]].push(f);
+            }
+        }
+        let mut point_edges: Vec<Vec<(usize, usize)>> = vec![Vec::new(); self.points.len()];
+        for &(a, b) in edge_faces.keys() {
+            point_edges[a].push((a, b));
+            point_edges[b].push((a, b));
+        }
+
+        // New points go after the moved old ones: a point for each edge, then each face
+        let mut points: Vec<Vec3> = Vec::new();
+        for (i, &point) in self.points.iter().enumerate() {
+            let edges = &point_edges[i];
+            let rim: Vec<Vec3> = edges
+                .iter()
+                .filter(|key| edge_faces[key].len() == 1)
+                .map(|&(a, b)| self.points[if a == i { b } else { a }])
+                .collect();
+            let moved = if rim.len() == 2 {
+                // Points on a rim only follow the rim
+                (rim[0] + rim[1] + point * 6.0) * (1.0 / 8.0)
+            } else if !rim.is_empty() || edges.is_empty() {
+                point
+            } else {
+                let count = point_faces[i].len() as Scalar;
+                let faces = average(point_faces[i].iter().map(|&f| face_points[f]));
+                let middles = average(
+                    edges
+                        .iter()
+                        .map(|&(a, b)| (self.points[a] + self.points[b]) * 0.5),
+                );
+                (faces + middles * 2.0 + point * (count - 3.0)) * (1.0 / count)
+            };
+            points.push(moved);
+        }
+        let mut edge_points: HashMap<(usize, usize), usize> = HashMap::new();
+        for (&(a, b), faces) in &edge_faces {
+            let ends = self.points[a] + self.points[b];
+            let point = match faces[..] {
+                [f, g] => (ends + face_points[f] + face_points[g]) * 0.25,
+                _ => ends * 0.5,
+            };
+            edge_points.insert((a, b), points.len());
+            points.push(point);
+        }
+        let first_face_point = points.len();
+        points.extend_from_slice(&face_points);
+
+        let faces = self
+            .faces
+            .iter()
+            .enumerate()
+            .flat_map(|(f, face)| {
+                let middle = first_face_point + f;
+                let edge_point = |i: usize, j: usize| edge_points[&edge(face[i], face[j])];
+                (0..4)
+                    .map(|i| {
+                        [
+                            face[i],
+                            edge_point(i, (i + 1) % 4),
+                            middle,
+                            edge_point((i + 3) % 4, i),
+                        ]
+                    })
+                    .collect::<Vec<_>>()
+            })
+            .collect();
+        QuadMesh { points, faces }
+    }
+
+    /// Triangles covering the mesh, shaded smoothly across the faces meeting at each point
+    pub fn vertices(&self) -> Vec<Vertex> {
+        let mut normals = vec![Vec3::origin(); self.points.len()];
+        for face in &self.faces {
+            let normal = newell(face.iter().map(|&i| self.points[i]).collect());
+            for &i in face {
+                normals[i] += normal;
+            }
+        }
+        self.faces
+            .iter()
+            .flat_map(|face| {
+                // Wound as `quad` winds them
+                [face[0], face[1], face[2], face[2], face[3], face[0]]
+                    .iter()
+                    .map(|&i| {
+                        let normal = normals[i];
+                        let normal = if normal.length() > 0.0 {
+                            normal.normalize()
+                        } else {
+                            normal
+                        };
+                        vertex(self.points[i], normal)
+                    })
+                    .collect::<Vec<_>>()
+            })
+            .collect()
+    }
+}
+
+/// Smooths a shape made of quads, such as a `rectangular_prism`, splitting each quad into
+/// four `levels` times
+pub fn subdivide(vertices: &[Vertex], levels: u32) -> Vec<Vertex> {
+    let mesh = (0..levels).fold(QuadMesh::from_quads(vertices), |mesh, _| mesh.subdivide());
+    mesh.vertices()
+}
+
+#[cfg(test)]
+mod test {
+    use super::{subdivide, QuadMesh};
+    use matrix::{vec3, Scalar};
+    use render::rectangular_prism;
+
+    #[test]
+    fn test_subdivide() {
+        let cube = QuadMesh::from_quads(&rectangular_prism(vec3(0.0, 0.0, 0.0), 2.0, 2.0, 2.0));
+        assert_eq!((cube.points.len(), cube.faces.len()), (8, 6));
+        // Each face splits in four, with a point for every old point, edge and face
+        let once = cube.subdivide();
+        assert_eq!((once.points.len(), once.faces.len()), (8 + 12 + 6, 24));
+        // Corners are pulled in to five ninths of the way out
+        let corner = vec3(5.0, 5.0, 5.0) * (1.0 / 9.0);
+        assert!(once
+            .points
+            .iter()
+            .any(|point| point.distance(&corner) < 1e-5));
+        // Smoothed, every normal points out from the middle
+        let vertices = subdivide(&rectangular_prism(vec3(0.0, 0.0, 0.0), 2.0, 2.0, 2.0), 2);
+        assert_eq!(vertices.len(), 6 * 16 * 6);
+        for vertex in &vertices {
+            assert!(vertex.normal().dot(&vertex.position()) > 0.0);
+            assert!(vertex.position().length() < (3.0 as Scalar).sqrt());
+        }
+    }
+}
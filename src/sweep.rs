@@ -0,0 +1,329 @@
+use material::Material;
+use matrix::{transform_direction, vec2, vec3, Quat, Scalar, Transform, Vec2, Vec3};
+use mesh::{Instance, MeshData};
+use render::{catmull_rom, polygon, quad, Drawable, Vertex};
+
+/// Cross-section carried along a path, like a pipe, a rail or the moulding of a frame
+///
+/// The section is laid across the path in a frame that turns with it as little as it can
+/// (parallel transport), so the surface doesn't twist between points. Its x runs along the
+/// frame's normal, which starts as close to up as the path allows, and its y along the
+/// binormal, the direction of the path crossed with the normal.
+pub struct Sweep {
+    /// Outline of the section, counter-clockwise so its faces point out
+    section: Vec<Vec2>,
+    path: Vec<Vec3>,
+    /// The end of the path joins its start, for rings and frames
+    closed: bool,
+    /// Normals follow the curve of the section instead of each face
+    smooth: bool,
+    instance: Instance,
+}
+
+/// Where the section sits at a point on the path
+#[derive(Copy, Clone, Debug)]
+struct Frame {
+    normal: Vec3,
+    binormal: Vec3,
+    /// Sideways through a bend, the way the section is stretched to meet its other side
+    bend: Vec3,
+    /// How far the section is stretched along `bend`
+    stretch: Scalar,
+}
+
+impl Frame {
+    /// Point of the section `offset` from the path, stretched to fill a bend
+    fn place(&self, offset: Vec2) -> Vec3 {
+        let offset = self.normal * offset.x + self.binormal * offset.y;
+        offset + self.bend * (offset.dot(&self.bend) * (self.stretch - 1.0))
+    }
+}
+
+impl Sweep {
+    pub fn new(section: Vec<Vec2>, path: Vec<Vec3>, translate: Vec3) -> Sweep {
+        Sweep {
+            section,
+            path,
+            closed: false,
+            smooth: false,
+            instance: Instance::new(Transform::from_translation(translate), Material::wood()),
+        }
+    }
+
+    /// Sweeps along a smooth curve through a few control points, `samples` points between
+    /// each pair
+    pub fn from_curve(
+        section: Vec<Vec2>,
+        controls: &[Vec3],
+        samples: u16,
+        translate: Vec3,
+    ) -> Sweep {
+        Sweep::new(section, catmull_rom(controls, samples), translate)
+    }
+
+    /// Joins the end of the path back to its start instead of capping both ends
+    pub fn with_closed(self, closed: bool) -> Sweep {
+        Sweep { closed, ..self }
+    }
+
+    /// Shades the surface as a smooth curve around the section rather than flat faces
+    pub fn with_smooth(self, smooth: bool) -> Sweep {
+        Sweep { smooth, ..self }
+    }
+
+    /// Frame of the section at each point of the path
+    fn frames(&self) -> Vec<Frame> {
+        let last = self.path.len() - 1;
+        let direction = |from: usize, to: usize| {
+            let direction = self.path[to] - self.path[from];
+            if direction.length() > 0.0 {
+                direction.normalize()
+            } else {
+                direction
+            }
+        };
+        // Direction into and out of each point, wrapping around a closed path
+        let sides: Vec<(Vec3, Vec3)> = (0..=last)
+            .map(|i| {
+                let before = match i {
+                    0 if self.closed => direction(last, 0),
+                    0 => direction(0, 1),
+                    i => direction(i - 1, i),
+                };
+                let after = match i {
+                    i if i < last => direction(i, i + 1),
+                    _ if self.closed => direction(last, 0),
+                    _ => direction(last - 1, last),
+                };
+                (before, after)
+            })
+            .collect();
+        let tangent = |(before, after): (Vec3, Vec3)| {
+            let tangent = before + after;
+            if tangent.length() > 0.0 {
+                tangent.normalize()
+            } else {
+                after
+            }
+        };
+
+        // Start as near up as the path allows, or along x for paths going straight up
+        let first = tangent(sides[0]);
+        let up = if first.y.abs() < 0.99 {
+            vec3(0.0, 1.0, 0.0)
+        } else {
+            vec3(1.0, 0.0, 0.0)
+        };
+        let mut normal = (up - first * up.dot(&first)).normalize();
+        let mut previous = first;
+        sides
+            .iter()
+            .map(|&(before, after)| {
+                // Turn the normal only as far as the path turns
+                let tangent = tangent((before, after));
+                let axis = previous.cross(tangent);
+                if axis.length() > 1e-6 {
+                    let angle = previous.dot(&tangent).clamp(-1.0, 1.0).acos();
+                    let turn = Quat::from_axis_angle(axis.normalize(), angle).to_mat4();
+                    normal = transform_direction(&turn, normal).normalize();
+                }
+                previous = tangent;
+                // Through a bend the section is cut at an angle, so it widens across it
+                let bend = after - before;
+                let bend = bend - tangent * bend.dot(&tangent);
+                let (bend, stretch) = if bend.length() > 1e-6 {
+                    (bend.normalize(), 1.0 / tangent.dot(&after).max(0.1))
+                } else {
+                    (Vec3::origin(), 1.0)
+                };
+                Frame {
+                    normal,
+                    binormal: tangent.cross(normal),
+                    bend,
+                    stretch,
+                }
+            })
+            .collect()
+    }
+
+    /// Normal at each point of the section in its own plane, facing out of the outline
+    fn section_normals(&self) -> Vec<Vec2> {
+        let count = self.section.len();
+        (0..count)
+            .map(|i| {
+                let (before, after) = (
+                    self.section[(i + count - 1) % count],
+                    self.section[(i + 1) % count],
+                );
+                // Across neighbouring points, so corners take the average of their sides
+                let normal = vec2(after.y - before.y, before.x - after.x);
+                let length = normal.x.hypot(normal.y);
+                if length > 0.0 {
+                    vec2(normal.x / length, normal.y / length)
+                } else {
+                    normal
+                }
+            })
+            .collect()
+    }
+}
+
+impl Drawable for Sweep {
+    /// Sweeps of the same section along the same path share a mesh
+    fn mesh_key(&self) -> String {
+        format!(
+            "sweep {:?} {:?} {} {}",
+            self.section, self.path, self.closed, self.smooth
+        )
+    }
+    /// Generates the vertices of the shape
+    fn mesh_data(&self) -> MeshData {
+        if self.path.len() < 2 || self.section.len() < 3 {
+            return MeshData::new(&[]);
+        }
+        let frames = self.frames();
+        let rings: Vec<Vec<Vec3>> = self
+            .path
+            .iter()
+            .zip(&frames)
+            .map(|(&point, frame)| {
+                self.section
+                    .iter()
+                    .map(|&offset| point + frame.place(offset))
+                    .collect()
+            })
+            .collect();
+        let normals = self.section_normals();
+
+        // The texture runs around the section and along the path, one unit to one unit
+        let mut around = vec![0.0];
+        for (i, point) in self.section.iter().enumerate() {
+            let next = self.section[(i + 1) % self.section.len()];
+            let length = (next.x - point.x).hypot(next.y - point.y);
+            around.push(around[i] + length);
+        }
+        let mut along = vec![0.0];
+        for i in 1..self.path.len() {
+            along.push(along[i - 1] + self.path[i - 1].distance(&self.path[i]));
+        }
+        if self.closed {
+            along.push(
+                along[self.path.len() - 1] + self.path[self.path.len() - 1].distance(&self.path[0]),
+            );
+        }
+
+        let mut vertices: Vec<Vertex> = Vec::new();
+        let lengths = if self.closed {
+            self.path.len()
+        } else {
+            self.path.len() - 1
+        };
+        let count = self.section.len();
+        for i in 0..lengths {
+            let next = (i + 1) % self.path.len();
+            for j in 0..count {
+                let k = (j + 1) % count;
+                let (a, b) = (rings[i][j], rings[i][k]);
+                let (c, d) = (rings[next][k], rings[next][j]);
+                let (u0, u1) = (around[j], around[j + 1]);
+                let (v0, v1) = (along[i], along[i + 1]);
+                let coords = [(u0, v0), (u1, v0), (u1, v1), (u1, v1), (u0, v1), (u0, v0)];
+                let place = |frame: &Frame, normal: Vec2| {
+                    (frame.normal * normal.x + frame.binormal * normal.y).normalize()
+                };
+                let (na, nb) = (place(&frames[i], normals[j]), place(&frames[i], normals[k]));
+                let (nc, nd) = (
+                    place(&frames[next], normals[k]),
+                    place(&frames[next], normals[j]),
+                );
+                let smooth = [na, nb, nc, nc, nd, na];
+                // Counter-clockwise seen from outside, as the section is seen from ahead
+                let tangent = b - a;
+                let tangent = if tangent.length() > 0.0 {
+                    tangent.normalize()
+                } else {
+                    tangent
+                };
+                vertices.extend(quad(a, b, c, d).iter().zip(coords.iter()).zip(&smooth).map(
+                    |((vertex, &(u, v)), &normal)| {
+                        let vertex = if self.smooth {
+                            vertex.with_normal(normal)
+                        } else {
+                            *vertex
+                        };
+                        vertex.with_texture(vec2(u, v), tangent)
+                    },
+                ));
+            }
+        }
+
+        // Open paths are capped, facing back along the path at the start and on at the end
+        if !self.closed {
+            let start: Vec<Vec3> = rings[0].iter().rev().cloned().collect();
+            vertices.extend(polygon(&start));
+            vertices.extend(polygon(&rings[rings.len() - 1]));
+        }
+
+        MeshData::new(&vertices)
+    }
+    /// Mesh of the object and how it is placed and looks
+    fn instance(&self) -> &Instance {
+        &self.instance
+    }
+    fn instance_mut(&mut self) -> &mut Instance {
+        &mut self.instance
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::Sweep;
+    use matrix::{vec2, vec3, Scalar, Vec3};
+    use render::{Drawable, VERTEX_SIZE};
+
+    #[test]
+    fn test_sweep() {
+        // A square frame of square moulding, lying in the xy plane
+        let section = vec![
+            vec2(-0.1, 0.0),
+            vec2(0.1, 0.0),
+            vec2(0.1, 0.1),
+            vec2(-0.1, 0.1),
+        ];
+        let path = vec![
+            vec3(-1.0, -1.0, 0.0),
+            vec3(1.0, -1.0, 0.0),
+            vec3(1.0, 1.0, 0.0),
+            vec3(-1.0, 1.0, 0.0),
+        ];
+        let frame = Sweep::new(section.clone(), path.clone(), Vec3::origin()).with_closed(true);
+        let data = frame.mesh_data();
+        // Four sides of four faces each, without caps
+        assert_eq!(data.vertices.len(), 4 * 4 * 6 * VERTEX_SIZE);
+        // The section keeps its width through the corners, and stands out of the plane
+        let bounds = data.bounds.unwrap();
+        assert!((bounds.max.x - 1.1).abs() < 1e-4 && (bounds.min.y + 1.1).abs() < 1e-4);
+        assert!(bounds.min.z.abs() < 1e-4 && (bounds.max.z - 0.1).abs() < 1e-4);
+        // Every face points away from the middle of the side of the frame it is on
+        let mut checked = 0;
+        for face in data.vertices.chunks(6 * VERTEX_SIZE) {
+            let value = |i: usize| Scalar::from(face[i]);
+            let center = (0..6).fold(Vec3::origin(), |sum, corner| {
+                let i = corner * VERTEX_SIZE;
+                sum + vec3(value(i), value(i + 1), value(i + 2)) * (1.0 / 6.0)
+            });
+            let normal = vec3(value(3), value(4), value(5));
+            let middle = if center.x.abs() < center.y.abs() {
+                vec3(center.x, center.y.signum(), 0.05)
+            } else {
+                vec3(center.x.signum(), center.y, 0.05)
+            };
+            assert!(normal.dot(&(center - middle)) > 0.0);
+            checked += 1;
+        }
+        assert_eq!(checked, 4 * 4);
+        // Left open, the ends are capped
+        let open = Sweep::new(section, path, Vec3::origin()).mesh_data();
+        assert_eq!(open.vertices.len(), (3 * 4 * 6 + 2 * 2 * 3) * VERTEX_SIZE);
+    }
+}
@@ -0,0 +1,177 @@
+use std::io;
+use std::path::Path;
+
+use image::DynamicImage;
+
+use material::Material;
+use matrix::{vec2, vec3, Scalar, Transform, Vec3};
+use mesh::{Instance, MeshData};
+use render::{vertex, Drawable, Vertex};
+use texture::open_image;
+
+/// Ground shaped by a grayscale heightmap, black at the bottom and white `height` above it
+///
+/// The map is stretched over `width` along x and `depth` along z, centered on the origin,
+/// with a point of the surface at each of its pixels.
+pub struct Terrain {
+    /// Heights from 0 to 1, row by row down the map
+    heights: Vec<Scalar>,
+    /// Pixels across the map along x
+    columns: u32,
+    /// Pixels down the map along z
+    rows: u32,
+    width: Scalar,
+    depth: Scalar,
+    height: Scalar,
+    /// Distance one copy of the texture covers
+    tile: Scalar,
+    /// Where the map was read from, to tell terrains apart
+    source: String,
+    instance: Instance,
+}
+
+impl Terrain {
+    /// Shapes the ground from an image, brighter pixels higher
+    pub fn from_image(
+        image: &DynamicImage,
+        width: Scalar,
+        height: Scalar,
+        depth: Scalar,
+        translate: Vec3,
+    ) -> Terrain {
+        let map = image.to_luma();
+        Terrain {
+            heights: map
+                .pixels()
+                .map(|pixel| Scalar::from(pixel[0]) / 255.0)
+                .collect(),
+            columns: map.width(),
+            rows: map.height(),
+            width,
+            depth,
+            height,
+            tile: 1.0,
+            source: String::new(),
+            instance: Instance::new(Transform::from_translation(translate), Material::wood()),
+        }
+    }
+
+    /// Reads the heightmap from an image file
+    pub fn load<P>(
+        path: P,
+        width: Scalar,
+        height: Scalar,
+        depth: Scalar,
+        translate: Vec3,
+    ) -> Result<Terrain, io::Error>
+    where
+        P: AsRef<Path>,
+    {
+        let image = open_image(path.as_ref())?;
+        Ok(Terrain {
+            source: path.as_ref().display().to_string(),
+            ..Terrain::from_image(&image, width, height, depth, translate)
+        })
+    }
+
+    /// Repeats the texture every `tile` units across the ground
+    pub fn with_tile(self, tile: Scalar) -> Terrain {
+        Terrain { tile, ..self }
+    }
+
+    /// Height of the map at a pixel, the nearest edge pixel past the edges
+    fn height_at(&self, column: i64, row: i64) -> Scalar {
+        let column = column.clamp(0, i64::from(self.columns) - 1) as usize;
+        let row = row.clamp(0, i64::from(self.rows) - 1) as usize;
+        self.heights[row * self.columns as usize + column] * self.height
+    }
+
+    /// Point of the surface at a pixel, with its normal across its neighbours
+    fn point(&self, column: u32, row: u32) -> Vertex {
+        let spacing = vec2(
+            self.width / (self.columns - 1) as Scalar,
+            self.depth / (self.rows - 1) as Scalar,
+        );
+        let position = vec3(
+            column as Scalar * spacing.x - self.width / 2.0,
+            self.height_at(i64::from(column), i64::from(row)),
+            row as Scalar * spacing.y - self.depth / 2.0,
+        );
+        let (column, row) = (i64::from(column), i64::from(row));
+        let slope = vec2(
+            (self.height_at(column + 1, row) - self.height_at(column - 1, row)) / (2.0 * spacing.x),
+            (self.height_at(column, row + 1) - self.height_at(column, row - 1)) / (2.0 * spacing.y),
+        );
+        vertex(position, vec3(-slope.x, 1.0, -slope.y).normalize()).with_texture(
+            vec2(position.x / self.tile, position.z / self.tile),
+            vec3(1.0, 0.0, 0.0),
+        )
+    }
+}
+
+impl Drawable for Terrain {
+    /// Terrains from the same map at the same size share a mesh
+    fn mesh_key(&self) -> String {
+        format!(
+            "terrain {} {} {} {} {}",
+            self.source, self.width, self.height, self.depth, self.tile
+        )
+    }
+    /// Generates the vertices of the shape
+    fn mesh_data(&self) -> MeshData {
+        let mut vertices: Vec<Vertex> = Vec::new();
+        if self.columns < 2 || self.rows < 2 {
+            return MeshData::new(&vertices);
+        }
+        for row in 0..self.rows - 1 {
+            for column in 0..self.columns - 1 {
+                // Counter-clockwise seen from above
+                let (a, b) = (self.point(column, row + 1), self.point(column + 1, row + 1));
+                let (c, d) = (self.point(column + 1, row), self.point(column, row));
+                vertices.extend_from_slice(&[a, b, c, c, d, a]);
+            }
+        }
+        MeshData::new(&vertices)
+    }
+    /// Mesh of the object and how it is placed and looks
+    fn instance(&self) -> &Instance {
+        &self.instance
+    }
+    fn instance_mut(&mut self) -> &mut Instance {
+        &mut self.instance
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::Terrain;
+    use image::{DynamicImage, GrayImage, Luma};
+    use matrix::{vec3, Scalar, Vec3};
+    use render::{Drawable, VERTEX_SIZE};
+
+    #[test]
+    fn test_terrain() {
+        // A single white peak in the middle of a black map
+        let map = GrayImage::from_fn(3, 3, |x, y| Luma([if (x, y) == (1, 1) { 255 } else { 0 }]));
+        let terrain = Terrain::from_image(
+            &DynamicImage::ImageLuma8(map),
+            4.0,
+            2.0,
+            6.0,
+            Vec3::origin(),
+        );
+        let data = terrain.mesh_data();
+        assert_eq!(data.vertices.len(), 2 * 2 * 6 * VERTEX_SIZE);
+        let bounds = data.bounds.unwrap();
+        assert!((bounds.max.y - 2.0).abs() < 1e-5 && bounds.min.y.abs() < 1e-5);
+        assert!((bounds.max.x - 2.0).abs() < 1e-5 && (bounds.min.z + 3.0).abs() < 1e-5);
+        // The ground faces up, and the peak's sides face away from it
+        for vertex in data.vertices.chunks(VERTEX_SIZE) {
+            let value = |i: usize| Scalar::from(vertex[i]);
+            let position = vec3(value(0), value(1), value(2));
+            let normal = vec3(value(3), value(4), value(5));
+            assert!(normal.y > 0.0);
+            assert!(normal.x * position.x >= 0.0 && normal.z * position.z >= 0.0);
+        }
+    }
+}
@@ -0,0 +1,172 @@
+use material::Material;
+use matrix::{vec3, Scalar, Transform, Vec3};
+use mesh::{Instance, MeshData};
+use render::{rectangular_prism, Drawable, Vertex};
+
+/// Pixels across each glyph of the font
+const GLYPH_WIDTH: usize = 5;
+/// Pixels down each glyph of the font, as tall as a capital letter
+const GLYPH_HEIGHT: usize = 7;
+/// Pixels left between letters and between lines
+const GLYPH_SPACING: usize = 1;
+
+/// Block font embedded for labels, each row of a glyph from the top with its leftmost pixel
+/// in the highest bit
+#[cfg_attr(rustfmt, rustfmt_skip)]
+const GLYPHS: &[(char, [u8; GLYPH_HEIGHT])] = &[
+    (' ', [0, 0, 0, 0, 0, 0, 0]),
+    ('A', [0b01110, 0b10001, 0b10001, 0b11111, 0b10001, 0b10001, 0b10001]),
+    ('B', [0b11110, 0b10001, 0b10001, 0b11110, 0b10001, 0b10001, 0b11110]),
+    ('C', [0b01110, 0b10001, 0b10000, 0b10000, 0b10000, 0b10001, 0b01110]),
+    ('D', [0b11100, 0b10010, 0b10001, 0b10001, 0b10001, 0b10010, 0b11100]),
+    ('E', [0b11111, 0b10000, 0b10000, 0b11110, 0b10000, 0b10000, 0b11111]),
+    ('F', [0b11111, 0b10000, 0b10000, 0b11110, 0b10000, 0b10000, 0b10000]),
+    ('G', [0b01110, 0b10001, 0b10000, 0b10111, 0b10001, 0b10001, 0b01111]),
+    ('H', [0b10001, 0b10001, 0b10001, 0b11111, 0b10001, 0b10001, 0b10001]),
+    ('I', [0b01110, 0b00100, 0b00100, 0b00100, 0b00100, 0b00100, 0b01110]),
+    ('J', [0b00111, 0b00010, 0b00010, 0b00010, 0b00010, 0b10010, 0b01100]),
+    ('K', [0b10001, 0b10010, 0b10100, 0b11000, 0b10100, 0b10010, 0b10001]),
+    ('L', [0b10000, 0b10000, 0b10000, 0b10000, 0b10000, 0b10000, 0b11111]),
+    ('M', [0b10001, 0b11011, 0b10101, 0b10101, 0b10001, 0b10001, 0b10001]),
+    ('N', [0b10001, 0b10001, 0b11001, 0b10101, 0b10011, 0b10001, 0b10001]),
+    ('O', [0b01110, 0b10001, 0b10001, 0b10001, 0b10001, 0b10001, 0b01110]),
+    ('P', [0b11110, 0b10001, 0b10001, 0b11110, 0b10000, 0b10000, 0b10000]),
+    ('Q', [0b01110, 0b10001, 0b10001, 0b10001, 0b10101, 0b10010, 0b01101]),
+    ('R', [0b11110, 0b10001, 0b10001, 0b11110, 0b10100, 0b10010, 0b10001]),
+    ('S', [0b01111, 0b10000, 0b10000, 0b01110, 0b00001, 0b00001, 0b11110]),
+    ('T', [0b11111, 0b00100, 0b00100, 0b00100, 0b00100, 0b00100, 0b00100]),
+    ('U', [0b10001, 0b10001, 0b10001, 0b10001, 0b10001, 0b10001, 0b01110]),
+    ('V', [0b10001, 0b10001, 0b10001, 0b10001, 0b10001, 0b01010, 0b00100]),
+    ('W', [0b10001, 0b10001, 0b10001, 0b10101, 0b10101, 0b10101, 0b01010]),
+    ('X', [0b10001, 0b10001, 0b01010, 0b00100, 0b01010, 0b10001, 0b10001]),
+    ('Y', [0b10001, 0b10001, 0b10001, 0b01010, 0b00100, 0b00100, 0b00100]),
+    ('Z', [0b11111, 0b00001, 0b00010, 0b00100, 0b01000, 0b10000, 0b11111]),
+    ('0', [0b01110, 0b10001, 0b10011, 0b10101, 0b11001, 0b10001, 0b01110]),
+    ('1', [0b00100, 0b01100, 0b00100, 0b00100, 0b00100, 0b00100, 0b01110]),
+    ('2', [0b01110, 0b10001, 0b00001, 0b00010, 0b00100, 0b01000, 0b11111]),
+    ('3', [0b11111, 0b00010, 0b00100, 0b00010, 0b00001, 0b10001, 0b01110]),
+    ('4', [0b00010, 0b00110, 0b01010, 0b10010, 0b11111, 0b00010, 0b00010]),
+    ('5', [0b11111, 0b10000, 0b11110, 0b00001, 0b00001, 0b10001, 0b01110]),
+    ('6', [0b00110, 0b01000, 0b10000, 0b11110, 0b10001, 0b10001, 0b01110]),
+    ('7', [0b11111, 0b00001, 0b00010, 0b00100, 0b01000, 0b01000, 0b01000]),
+    ('8', [0b01110, 0b10001, 0b10001, 0b01110, 0b10001, 0b10001, 0b01110]),
+    ('9', [0b01110, 0b10001, 0b10001, 0b01111, 0b00001, 0b00010, 0b01100]),
+    ('.', [0, 0, 0, 0, 0, 0b01100, 0b01100]),
+    (',', [0, 0, 0, 0, 0b01100, 0b00100, 0b01000]),
+    ('!', [0b00100, 0b00100, 0b00100, 0b00100, 0b00100, 0, 0b00100]),
+    ('?', [0b01110, 0b10001, 0b00001, 0b00010, 0b00100, 0, 0b00100]),
+    ('-', [0, 0, 0, 0b11111, 0, 0, 0]),
+    (':', [0, 0b01100, 0b01100, 0, 0b01100, 0b01100, 0]),
+    ('\'', [0b01100, 0b00100, 0b01000, 0, 0, 0, 0]),
+    ('/', [0, 0b00001, 0b00010, 0b00100, 0b01000, 0b10000, 0]),
+];
+
+/// Rows of a character's glyph, lower case drawn as upper case and anything else the font
+/// doesn't have as a question mark
+fn glyph(character: char) -> [u8; GLYPH_HEIGHT] {
+    let character = character.to_ascii_uppercase();
+    let find = |character| {
+        GLYPHS
+            .iter()
+            .find(|&&(glyph, _)| glyph == character)
+            .map(|&(_, rows)| rows)
+    };
+    find(character).or_else(|| find('?')).unwrap_or_default()
+}
+
+/// Label of block letters standing up and facing +z, its first line starting at the origin
+///
+/// Each run of pixels in a row of a glyph is extruded into a block `depth` deep, centered on
+/// z = 0. Lines after the first go down by the height of a letter and a gap.
+pub struct Text3D {
+    text: String,
+    /// Height of a capital letter
+    size: Scalar,
+    depth: Scalar,
+    instance: Instance,
+}
+
+impl Text3D {
+    pub fn new(text: &str, size: Scalar, depth: Scalar, translate: Vec3) -> Text3D {
+        Text3D {
+            text: text.to_string(),
+            size,
+            depth,
+            instance: Instance::new(Transform::from_translation(translate), Material::gold()),
+        }
+    }
+}
+
+impl Drawable for Text3D {
+    /// Labels with the same text at the same size share a mesh
+    fn mesh_key(&self) -> String {
+        format!("text {:?} {} {}", self.text, self.size, self.depth)
+    }
+    /// Generates the vertices of the shape
+    fn mesh_data(&self) -> MeshData {
+        let pixel = self.size / GLYPH_HEIGHT as Scalar;
+        let mut vertices: Vec<Vertex> = Vec::new();
+        for (line, text) in self.text.lines().enumerate() {
+            let top = -((line * (GLYPH_HEIGHT + GLYPH_SPACING)) as Scalar) * pixel + self.size;
+            for (index, character) in text.chars().enumerate() {
+                let left = (index * (GLYPH_WIDTH + GLYPH_SPACING)) as Scalar * pixel;
+                for (row, bits) in glyph(character).iter().enumerate() {
+                    let y = top - (row as Scalar + 0.5) * pixel;
+                    // Runs of pixels along the row, as the columns they start and end at
+                    let mut column = 0;
+                    while column < GLYPH_WIDTH {
+                        let lit = |column: usize| bits & (1 << (GLYPH_WIDTH - 1 - column)) != 0;
+                        if !lit(column) {
+                            column += 1;
+                            continue;
+                        }
+                        let start = column;
+                        while column < GLYPH_WIDTH && lit(column) {
+                            column += 1;
+                        }
+                        let width = (column - start) as Scalar * pixel;
+                        vertices.extend(rectangular_prism(
+                            vec3(left + start as Scalar * pixel + width / 2.0, y, 0.0),
+                            width,
+                            self.depth,
+                            pixel,
+                        ));
+                    }
+                }
+            }
+        }
+        MeshData::new(&vertices)
+    }
+    /// Mesh of the object and how it is placed and looks
+    fn instance(&self) -> &Instance {
+        &self.instance
+    }
+    fn instance_mut(&mut self) -> &mut Instance {
+        &mut self.instance
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{glyph, Text3D};
+    use matrix::Vec3;
+    use render::{Drawable, VERTEX_SIZE};
+
+    #[test]
+    fn test_text() {
+        // H has two runs of pixels in six of its rows and one across its middle, I has one
+        // run in each row
+        let label = Text3D::new("Hi", 0.7, 0.2, Vec3::origin()).mesh_data();
+        assert_eq!(label.vertices.len(), (13 + 7) * 36 * VERTEX_SIZE);
+        // Two letters and the gap between them standing on the origin, the I narrower than
+        // its glyph
+        let bounds = label.bounds.unwrap();
+        assert!(bounds.min.x.abs() < 1e-5 && (bounds.max.x - 1.0).abs() < 1e-5);
+        assert!(bounds.min.y.abs() < 1e-5 && (bounds.max.y - 0.7).abs() < 1e-5);
+        assert!((bounds.max.z - 0.1).abs() < 1e-5);
+        // Lines go downwards, and the font lacks some characters
+        let lines = Text3D::new("A\nB", 0.7, 0.2, Vec3::origin()).mesh_data();
+        assert!((lines.bounds.unwrap().min.y + 0.8).abs() < 1e-5);
+        assert_eq!(glyph('~'), glyph('?'));
+    }
+}
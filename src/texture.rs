@@ -0,0 +1,803 @@
+use std::collections::{HashMap, VecDeque};
+use std::ffi::OsStr;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+use gleam::gl::{self, GLenum, GLuint};
+use image::{DynamicImage, GenericImageView, ImageError, Rgba, RgbaImage};
+
+use super::GlPtr;
+use atlas::{AtlasBuilder, AtlasRegion};
+use error::io_error;
+use ktx::Ktx2;
+
+/// Handle to a texture owned by a `TextureManager`
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct TextureId(usize);
+
+/// How texture coordinates outside [0, 1] are handled
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum WrapMode {
+    Repeat,
+    ClampToEdge,
+    MirroredRepeat,
+}
+impl WrapMode {
+    pub fn to_gl(self) -> GLenum {
+        match self {
+            WrapMode::Repeat => gl::REPEAT,
+            WrapMode::ClampToEdge => gl::CLAMP_TO_EDGE,
+            WrapMode::MirroredRepeat => gl::MIRRORED_REPEAT,
+        }
+    }
+}
+
+/// How texels are blended when sampled
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Filter {
+    Nearest,
+    Linear,
+}
+impl Filter {
+    pub fn to_gl(self) -> GLenum {
+        match self {
+            Filter::Nearest => gl::NEAREST,
+            Filter::Linear => gl::LINEAR,
+        }
+    }
+}
+
+/// How a texture is sampled
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct TextureOptions {
+    pub wrap: WrapMode,
+    /// Filter used when the texture is smaller on screen than its image
+    pub min_filter: Filter,
+    /// Filter used when the texture is larger on screen than its image
+    pub mag_filter: Filter,
+    /// Whether mipmaps are generated and sampled when minified
+    pub mipmaps: bool,
+}
+impl TextureOptions {
+    /// GL minification filter, including how mip levels are picked
+    fn min_filter_gl(self) -> GLenum {
+        match (self.min_filter, self.mipmaps) {
+            (Filter::Nearest, false) => gl::NEAREST,
+            (Filter::Linear, false) => gl::LINEAR,
+            (Filter::Nearest, true) => gl::NEAREST_MIPMAP_NEAREST,
+            (Filter::Linear, true) => gl::LINEAR_MIPMAP_LINEAR,
+        }
+    }
+}
+/// Smooth, mipmapped sampling with the given wrap mode
+impl From<WrapMode> for TextureOptions {
+    fn from(wrap: WrapMode) -> Self {
+        TextureOptions {
+            wrap,
+            min_filter: Filter::Linear,
+            mag_filter: Filter::Linear,
+            mipmaps: true,
+        }
+    }
+}
+
+/// How a texture's values are encoded
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum ColorSpace {
+    /// Colors, decoded to linear by the GPU when sampled
+    Srgb,
+    /// Data such as normals, sampled as stored
+    Linear,
+}
+
+/// Whether a decoded image has an alpha channel
+fn image_has_alpha(image: &DynamicImage) -> bool {
+    matches!(
+        image,
+        DynamicImage::ImageLumaA8(_) | DynamicImage::ImageRgba8(_) | DynamicImage::ImageBgra8(_)
+    )
+}
+
+/// Converts any decoded image into a GL internal format, pixel format and raw bytes
+fn gl_pixels(image: DynamicImage, space: ColorSpace) -> (GLenum, GLenum, Vec<u8>) {
+    // SRGB8 can't have mipmaps generated in GLES, so sRGB is always uploaded with alpha
+    if space == ColorSpace::Srgb {
+        return (gl::SRGB8_ALPHA8, gl::RGBA, image.to_rgba().into_raw());
+    }
+    let (format, pixels) = match image {
+        DynamicImage::ImageLuma8(image) => (gl::LUMINANCE, image.into_raw()),
+        DynamicImage::ImageLumaA8(image) => (gl::LUMINANCE_ALPHA, image.into_raw()),
+        DynamicImage::ImageRgb8(image) => (gl::RGB, image.into_raw()),
+        DynamicImage::ImageRgba8(image) => (gl::RGBA, image.into_raw()),
+        // GLES has no BGR formats, so swizzle on the CPU
+        image @ DynamicImage::ImageBgr8(_) => (gl::RGB, image.to_rgb().into_raw()),
+        image @ DynamicImage::ImageBgra8(_) => (gl::RGBA, image.to_rgba().into_raw()),
+    };
+    (format, format, pixels)
+}
+
+/// Reads an image file, describing unsupported formats in the error
+pub fn open_image(path: &Path) -> Result<DynamicImage, io::Error> {
+    image::open(path).map_err(|err| match err {
+        ImageError::UnsupportedColor(color) => io_error(format!(
+            "{}: unsupported texture color type {:?}",
+            path.display(),
+            color
+        )),
+        err => io_error(err),
+    })
+}
+
+/// Color shown while an image is still being decoded
+const PLACEHOLDER_COLOR: [u8; 4] = [128, 128, 128, 255];
+
+/// Frames between checks for changed texture files
+const RELOAD_INTERVAL: u32 = 60;
+
+/// Pixels left between images in an atlas
+const ATLAS_PADDING: u32 = 2;
+
+/// Cube faces in the order GL numbers them, +X, -X, +Y, -Y, +Z, -Z
+const CUBE_FACES: [GLenum; 6] = [
+    gl::TEXTURE_CUBE_MAP_POSITIVE_X,
+    gl::TEXTURE_CUBE_MAP_NEGATIVE_X,
+    gl::TEXTURE_CUBE_MAP_POSITIVE_Y,
+    gl::TEXTURE_CUBE_MAP_NEGATIVE_Y,
+    gl::TEXTURE_CUBE_MAP_POSITIVE_Z,
+    gl::TEXTURE_CUBE_MAP_NEGATIVE_Z,
+];
+
+/// A texture uploaded to the GPU
+struct Texture {
+    /// GL texture name
+    name: GLuint,
+    /// TEXTURE_2D or TEXTURE_CUBE_MAP
+    target: GLenum,
+    /// Whether the texture was uploaded with an alpha channel
+    has_alpha: bool,
+    /// False while the texture still holds the placeholder
+    loaded: bool,
+    /// True when its image failed to decode, the missing texture is bound instead
+    failed: bool,
+    /// Image file the texture is reloaded from when it changes
+    source: Option<Source>,
+}
+
+/// Where a reloadable texture came from
+struct Source {
+    path: PathBuf,
+    space: ColorSpace,
+    options: TextureOptions,
+    /// Modification time when last read
+    modified: Option<SystemTime>,
+}
+
+/// Modification time of a file, if it can be read
+fn modified(path: &Path) -> Option<SystemTime> {
+    fs::metadata(path)
+        .and_then(|metadata| metadata.modified())
+        .ok()
+}
+
+/// Owns every texture in the scene
+pub struct TextureManager {
+    gl: GlPtr,
+    textures: Vec<Texture>,
+    /// Already loaded textures, keyed by the path and settings they were loaded with
+    paths: HashMap<(PathBuf, TextureOptions, ColorSpace), TextureId>,
+    /// Atlases images were packed into and where they are in them
+    regions: HashMap<PathBuf, (TextureId, TextureOptions, AtlasRegion)>,
+    /// Largest anisotropy the hardware supports, 1.0 if the extension is missing
+    max_anisotropy: f32,
+    /// Anisotropy applied to textures
+    anisotropy: f32,
+    /// Extensions supported by the context
+    extensions: String,
+    /// Textures showing a placeholder until their image is decoded
+    pending: VecDeque<TextureId>,
+    /// Frames since texture files were last checked for changes
+    frames: u32,
+    /// Bound in place of textures whose image failed to decode
+    missing: Option<TextureId>,
+}
+
+impl TextureManager {
+    pub fn new(gl: GlPtr) -> Self {
+        let extensions = gl.get_string(gl::EXTENSIONS);
+        // Check for anisotropic filtering support
+        let max_anisotropy = if extensions.contains("EXT_texture_filter_anisotropic") {
+            let mut max_anisotropy = [1.0];
+            unsafe {
+                gl.get_float_v(gl::MAX_TEXTURE_MAX_ANISOTROPY_EXT, &mut max_anisotropy);
+            }
+            max_anisotropy[0]
+        } else {
+            1.0
+        };
+        TextureManager {
+            gl,
+            textures: Vec::new(),
+            paths: HashMap::new(),
+            regions: HashMap::new(),
+            max_anisotropy,
+            anisotropy: max_anisotropy,
+            extensions,
+            pending: VecDeque::new(),
+            frames: 0,
+            missing: None,
+        }
+    }
+
+    /// Largest anisotropy supported by the hardware
+    pub fn max_anisotropy(&self) -> f32 {
+        self.max_anisotropy
+    }
+
+    /// Sets the anisotropy of every texture, clamped to what the hardware supports
+    pub fn set_anisotropy(&mut self, anisotropy: f32) {
+        self.anisotropy = anisotropy.max(1.0).min(self.max_anisotropy);
+        for texture in &self.textures {
+            self.gl.bind_texture(texture.target, texture.name);
+            self.apply_anisotropy(texture.target);
+        }
+    }
+
+    /// Applies the anisotropy setting to the bound texture
+    fn apply_anisotropy(&self, target: GLenum) {
+        // Setting the parameter without the extension is an error
+        if self.max_anisotropy > 1.0 {
+            self.gl
+                .tex_parameter_f(target, gl::TEXTURE_MAX_ANISOTROPY_EXT, self.anisotropy);
+        }
+    }
+
+    /// Loads a color texture from a path, reusing it if it was already loaded
+    ///
+    /// Takes either a `WrapMode` or full `TextureOptions`.
+    pub fn load<P, O>(&mut self, path: P, options: O) -> Result<TextureId, io::Error>
+    where
+        P: AsRef<Path>,
+        O: Into<TextureOptions>,
+    {
+        self.load_in(path.as_ref(), options.into(), ColorSpace::Srgb)
+    }
+
+    /// Loads a color texture like `load`, but gives the atlas it was packed into instead
+    /// when the atlas was made with the same options
+    ///
+    /// Texture coordinates must then be moved into the image's `region`.
+    pub fn load_packed<P, O>(&mut self, path: P, options: O) -> Result<TextureId, io::Error>
+    where
+        P: AsRef<Path>,
+        O: Into<TextureOptions>,
+    {
+        let options = options.into();
+        match self.regions.get(path.as_ref()) {
+            Some(&(atlas, atlas_options, _)) if atlas_options == options => Ok(atlas),
+            _ => self.load(path, options),
+        }
+    }
+
+    /// Loads a texture holding data rather than colors, such as a normal map
+    pub fn load_linear<P, O>(&mut self, path: P, options: O) -> Result<TextureId, io::Error>
+    where
+        P: AsRef<Path>,
+        O: Into<TextureOptions>,
+    {
+        self.load_in(path.as_ref(), options.into(), ColorSpace::Linear)
+    }
+
+    /// Loads a texture in a color space, reusing it if it was already loaded
+    fn load_in(
+        &mut self,
+        path: &Path,
+        options: TextureOptions,
+        space: ColorSpace,
+    ) -> Result<TextureId, io::Error> {
+        // Don't upload the same image twice
+        let key = (path.to_path_buf(), options, space);
+        if let Some(id) = self.paths.get(&key) {
+            return Ok(*id);
+        }
+        // Compressed textures are uploaded as is, anything else is decoded
+        let id = if path.extension() == Some(OsStr::new("ktx2")) {
+            self.load_compressed(path, options)?
+        } else {
+            self.load_image(path, options, space)?
+        };
+        self.paths.insert(key, id);
+        Ok(id)
+    }
+
+    /// Creates and binds a new texture, returning its name
+    fn create(&self, target: GLenum, options: TextureOptions) -> GLuint {
+        let gl = &self.gl;
+        // Create a texture, units are only picked when drawing
+        let name = gl.gen_textures(1)[0];
+        gl.active_texture(gl::TEXTURE0);
+        gl.bind_texture(target, name);
+        gl.tex_parameter_i(
+            target,
+            gl::TEXTURE_MIN_FILTER,
+            options.min_filter_gl() as i32,
+        );
+        gl.tex_parameter_i(
+            target,
+            gl::TEXTURE_MAG_FILTER,
+            options.mag_filter.to_gl() as i32,
+        );
+        gl.tex_parameter_i(target, gl::TEXTURE_WRAP_S, options.wrap.to_gl() as i32);
+        gl.tex_parameter_i(target, gl::TEXTURE_WRAP_T, options.wrap.to_gl() as i32);
+        self.apply_anisotropy(target);
+        name
+    }
+
+    /// Stores a created texture
+    fn push(&mut self, texture: Texture) -> TextureId {
+        self.textures.push(texture);
+        TextureId(self.textures.len() - 1)
+    }
+
+    /// Uploads a placeholder and queues the image file to be decoded by `update`
+    fn load_image(
+        &mut self,
+        path: &Path,
+        options: TextureOptions,
+        space: ColorSpace,
+    ) -> Result<TextureId, io::Error> {
+        // Report missing files now rather than when decoding
+        if !path.is_file() {
+            return Err(io_error(format!("{}: no such file", path.display())));
+        }
+        let name = self.create(gl::TEXTURE_2D, options);
+        let placeholder = RgbaImage::from_pixel(1, 1, Rgba(PLACEHOLDER_COLOR));
+        self.fill_image(DynamicImage::ImageRgba8(placeholder), space, options);
+        let id = self.push(Texture {
+            name,
+            target: gl::TEXTURE_2D,
+            has_alpha: false,
+            loaded: false,
+            failed: false,
+            source: Some(Source {
+                path: path.to_path_buf(),
+                space,
+                options,
+                modified: modified(path),
+            }),
+        });
+        self.pending.push_back(id);
+        Ok(id)
+    }
+
+    /// Decodes one queued image, replacing its placeholder
+    ///
+    /// Called once a frame so decoding is spread out instead of blocking startup. Images are
+    /// still decoded on the main thread, so a large one holds up the frame it is decoded in.
+    /// Decoding in the background would need a web worker, which the build doesn't have.
+    pub fn update(&mut self) {
+        self.frames += 1;
+        if self.frames >= RELOAD_INTERVAL {
+            self.frames = 0;
+            self.reload_changed();
+        }
+        let id = match self.pending.pop_front() {
+            Some(id) => id,
+            None => return,
+        };
+        let texture = &self.textures[id.0];
+        let source = match texture.source {
+            Some(ref source) => source,
+            None => return,
+        };
+        match open_image(&source.path) {
+            Ok(tex_image) => {
+                self.gl.active_texture(gl::TEXTURE0);
+                self.gl.bind_texture(gl::TEXTURE_2D, texture.name);
+                let has_alpha = self.fill_image(tex_image, source.space, source.options);
+                let texture = &mut self.textures[id.0];
+                texture.has_alpha = has_alpha;
+                texture.loaded = true;
+                texture.failed = false;
+            }
+            Err(err) => {
+                eprintln!("{}: {}", source.path.display(), err);
+                // A bad reload keeps the old image, one never decoded shows as missing
+                let texture = &mut self.textures[id.0];
+                texture.failed = !texture.loaded;
+            }
+        }
+    }
+
+    /// Queues every texture whose image file changed since it was read
+    ///
+    /// On the web, writing a new file into the virtual filesystem triggers this.
+    /// Atlases, cubemaps and compressed textures aren't reloaded.
+    pub fn reload_changed(&mut self) {
+        for (index, texture) in self.textures.iter_mut().enumerate() {
+            if let Some(ref mut source) = texture.source {
+                let modified = modified(&source.path);
+                if modified != source.modified {
+                    source.modified = modified;
+                    // The old image stays bound until the new one is decoded
+                    self.pending.push_back(TextureId(index));
+                }
+            }
+        }
+    }
+
+    /// Whether a texture holds its real image rather than a placeholder
+    pub fn loaded(&self, id: TextureId) -> bool {
+        self.textures[id.0].loaded
+    }
+
+    /// Creates an empty texture to render into
+    pub fn create_target(&mut self, width: u32, height: u32) -> TextureId {
+        let options = TextureOptions {
+            mipmaps: false,
+            ..WrapMode::ClampToEdge.into()
+        };
+        let name = self.create(gl::TEXTURE_2D, options);
+        self.gl.tex_image_2d(
+            gl::TEXTURE_2D,
+            0,
+            gl::RGBA8 as i32,
+            width as i32,
+            height as i32,
+            0,
+            gl::RGBA,
+            gl::UNSIGNED_BYTE,
+            None,
+        );
+        self.push(Texture {
+            name,
+            target: gl::TEXTURE_2D,
+            has_alpha: false,
+            loaded: true,
+            failed: false,
+            source: None,
+        })
+    }
+
+    /// Creates a depth texture for a framebuffer to render into
+    ///
+    /// Sampling it compares against the stored depth, and linear filtering blends neighboring
+    /// comparisons so shadow edges are softened.
+    pub fn create_depth_target(&mut self, width: u32, height: u32) -> TextureId {
+        let options = TextureOptions {
+            mipmaps: false,
+            ..WrapMode::ClampToEdge.into()
+        };
+        let name = self.create(gl::TEXTURE_2D, options);
+        self.gl.tex_parameter_i(
+            gl::TEXTURE_2D,
+            gl::TEXTURE_COMPARE_MODE,
+            gl::COMPARE_REF_TO_TEXTURE as i32,
+        );
+        self.gl
+            .tex_parameter_i(gl::TEXTURE_2D, gl::TEXTURE_COMPARE_FUNC, gl::LEQUAL as i32);
+        self.gl.tex_image_2d(
+            gl::TEXTURE_2D,
+            0,
+            gl::DEPTH_COMPONENT24 as i32,
+            width as i32,
+            height as i32,
+            0,
+            gl::DEPTH_COMPONENT,
+            gl::UNSIGNED_INT,
+            None,
+        );
+        self.push(Texture {
+            name,
+            target: gl::TEXTURE_2D,
+            has_alpha: false,
+            loaded: true,
+            failed: false,
+            source: None,
+        })
+    }
+
+    /// Creates a 1x1 texture of a single color
+    pub fn solid(&mut self, color: [u8; 4]) -> TextureId {
+        let image = RgbaImage::from_pixel(1, 1, Rgba(color));
+        self.upload_image(
+            DynamicImage::ImageRgba8(image),
+            WrapMode::Repeat.into(),
+            ColorSpace::Srgb,
+        )
+    }
+
+    /// Uploads an image drawn in code
+    pub fn image(&mut self, image: RgbaImage, wrap: WrapMode) -> TextureId {
+        self.upload_image(
+            DynamicImage::ImageRgba8(image),
+            wrap.into(),
+            ColorSpace::Srgb,
+        )
+    }
+
+    /// Creates a checkerboard texture of two colors, with squares of `size` pixels
+    pub fn checkerboard(&mut self, size: u32, a: [u8; 4], b: [u8; 4]) -> TextureId {
+        let image = RgbaImage::from_fn(size * 8, size * 8, |x, y| {
+            if (x / size + y / size) % 2 == 0 {
+                Rgba(a)
+            } else {
+                Rgba(b)
+            }
+        });
+        // Keep the squares sharp up close
+        let options = TextureOptions {
+            mag_filter: Filter::Nearest,
+            ..WrapMode::Repeat.into()
+        };
+        self.upload_image(DynamicImage::ImageRgba8(image), options, ColorSpace::Srgb)
+    }
+
+    /// Uploads a decoded image
+    fn upload_image(
+        &mut self,
+        tex_image: DynamicImage,
+        options: TextureOptions,
+        space: ColorSpace,
+    ) -> TextureId {
+        let name = self.create(gl::TEXTURE_2D, options);
+        let has_alpha = self.fill_image(tex_image, space, options);
+        self.push(Texture {
+            name,
+            target: gl::TEXTURE_2D,
+            has_alpha,
+            loaded: true,
+            failed: false,
+            source: None,
+        })
+    }
+
+    /// Uploads a decoded image to the bound texture, returning whether it has alpha
+    fn fill_image(
+        &self,
+        tex_image: DynamicImage,
+        space: ColorSpace,
+        options: TextureOptions,
+    ) -> bool {
+        // Extract dimensions
+        let (width, height) = tex_image.dimensions();
+        let has_alpha = image_has_alpha(&tex_image);
+        // Get image as raw bytes in a format GL understands
+        let (internal_format, format, tex_image) = gl_pixels(tex_image, space);
+
+        let gl = &self.gl;
+        // RGB and luminance rows aren't necessarily 4 byte aligned
+        gl.pixel_store_i(gl::UNPACK_ALIGNMENT, 1);
+        gl.tex_image_2d(
+            gl::TEXTURE_2D,
+            0,
+            internal_format as i32,
+            width as i32,
+            height as i32,
+            0,
+            format,
+            gl::UNSIGNED_BYTE,
+            Some(&tex_image),
+        );
+        if options.mipmaps {
+            gl.generate_mipmap(gl::TEXTURE_2D);
+        }
+        has_alpha
+    }
+
+    /// Packs several images into one texture
+    ///
+    /// Each path is then loaded as the atlas by `load_packed` with `WrapMode::ClampToEdge`
+    /// and no mipmaps, and `region` gives where its image is in the atlas.
+    pub fn load_atlas<P>(
+        &mut self,
+        paths: &[P],
+        width: u32,
+        height: u32,
+    ) -> Result<TextureId, io::Error>
+    where
+        P: AsRef<Path>,
+    {
+        let mut builder = AtlasBuilder::new(width, height, ATLAS_PADDING);
+        for path in paths {
+            builder.add(open_image(path.as_ref())?.to_rgba());
+        }
+        let (atlas, regions) = builder.build()?;
+        // Neighboring images would wrap into each other, so clamp, and smaller mip levels
+        // would blur them together past the padding
+        let options = TextureOptions {
+            mipmaps: false,
+            ..WrapMode::ClampToEdge.into()
+        };
+        let id = self.upload_image(DynamicImage::ImageRgba8(atlas), options, ColorSpace::Srgb);
+        for (path, region) in paths.iter().zip(regions) {
+            self.regions
+                .insert(path.as_ref().to_path_buf(), (id, options, region));
+        }
+        Ok(id)
+    }
+
+    /// Where an image is in a texture, if the texture is an atlas it was packed into
+    pub fn region<P>(&self, id: TextureId, path: P) -> Option<AtlasRegion>
+    where
+        P: AsRef<Path>,
+    {
+        match self.regions.get(path.as_ref()) {
+            Some(&(atlas, _, region)) if atlas == id => Some(region),
+            _ => None,
+        }
+    }
+
+    /// Uploads a compressed KTX2 texture without decoding it
+    ///
+    /// Only files already in a BC, ETC2 or ASTC format work; Basis Universal files are refused
+    /// since nothing here transcodes them.
+    fn load_compressed(
+        &mut self,
+        path: &Path,
+        options: TextureOptions,
+    ) -> Result<TextureId, io::Error> {
+        let ktx = Ktx2::load(path)?;
+        // Make sure the GPU can sample the format
+        if !self.extensions.contains(ktx.extension) {
+            return Err(io_error(format!(
+                "{}: {} is not supported",
+                path.display(),
+                ktx.extension
+            )));
+        }
+
+        // Only files with mip levels can be sampled with them
+        let options = TextureOptions {
+            mipmaps: options.mipmaps && ktx.levels.len() > 1,
+            ..options
+        };
+        let name = self.create(gl::TEXTURE_2D, options);
+        let gl = &self.gl;
+        // Upload every mip level, mipmaps can't be generated for compressed formats
+        for (level, data) in ktx.levels.iter().enumerate() {
+            gl.compressed_tex_image_2d(
+                gl::TEXTURE_2D,
+                level as i32,
+                ktx.internal_format,
+                (ktx.width >> level).max(1) as i32,
+                (ktx.height >> level).max(1) as i32,
+                0,
+                data,
+            );
+        }
+        gl.tex_parameter_i(
+            gl::TEXTURE_2D,
+            gl::TEXTURE_MAX_LEVEL,
+            ktx.levels.len() as i32 - 1,
+        );
+        // Compressed textures are treated as opaque
+        Ok(self.push(Texture {
+            name,
+            target: gl::TEXTURE_2D,
+            has_alpha: false,
+            loaded: true,
+            failed: false,
+            source: None,
+        }))
+    }
+
+    /// Loads a cubemap from six images, ordered +X, -X, +Y, -Y, +Z, -Z
+    pub fn load_cubemap<P>(&mut self, paths: &[P; 6]) -> Result<TextureId, io::Error>
+    where
+        P: AsRef<Path>,
+    {
+        let faces = paths
+            .iter()
+            .map(|path| open_image(path.as_ref()))
+            .collect::<Result<Vec<DynamicImage>, io::Error>>()?;
+        self.upload_cubemap(faces)
+    }
+
+    /// Loads a cubemap from a single image laid out as a horizontal cross
+    ///     +Y
+    /// -X  +Z  +X  -Z
+    ///     -Y
+    pub fn load_cubemap_cross<P>(&mut self, path: P) -> Result<TextureId, io::Error>
+    where
+        P: AsRef<Path>,
+    {
+        let path = path.as_ref();
+        let mut cross = open_image(path)?;
+        // Faces are square, 4 across and 3 down
+        let (width, height) = cross.dimensions();
+        let size = width / 4;
+        if size == 0 || height != size * 3 {
+            return Err(io_error(format!(
+                "{}: cubemap cross must be 4:3, found {}x{}",
+                path.display(),
+                width,
+                height
+            )));
+        }
+        // Cell of each face in the cross, in GL face order
+        let cells = [(2, 1), (0, 1), (1, 0), (1, 2), (1, 1), (3, 1)];
+        let faces = cells
+            .iter()
+            .map(|&(x, y)| cross.crop(x * size, y * size, size, size))
+            .collect();
+        self.upload_cubemap(faces)
+    }
+
+    /// Uploads six decoded faces as a cubemap
+    fn upload_cubemap(&mut self, faces: Vec<DynamicImage>) -> Result<TextureId, io::Error> {
+        let name = self.create(gl::TEXTURE_CUBE_MAP, WrapMode::ClampToEdge.into());
+        let gl = &self.gl;
+        // Don't let seams show between faces
+        gl.tex_parameter_i(
+            gl::TEXTURE_CUBE_MAP,
+            gl::TEXTURE_WRAP_R,
+            gl::CLAMP_TO_EDGE as i32,
+        );
+        gl.pixel_store_i(gl::UNPACK_ALIGNMENT, 1);
+        let mut has_alpha = false;
+        for (face, image) in CUBE_FACES.iter().zip(faces) {
+            let (width, height) = image.dimensions();
+            has_alpha |= image_has_alpha(&image);
+            let (internal_format, format, pixels) = gl_pixels(image, ColorSpace::Srgb);
+            gl.tex_image_2d(
+                *face,
+                0,
+                internal_format as i32,
+                width as i32,
+                height as i32,
+                0,
+                format,
+                gl::UNSIGNED_BYTE,
+                Some(&pixels),
+            );
+        }
+        gl.generate_mipmap(gl::TEXTURE_CUBE_MAP);
+        Ok(self.push(Texture {
+            name,
+            target: gl::TEXTURE_CUBE_MAP,
+            has_alpha,
+            loaded: true,
+            failed: false,
+            source: None,
+        }))
+    }
+
+    /// GL name of a texture, for attaching it to framebuffers
+    pub fn gl_name(&self, id: TextureId) -> GLuint {
+        self.textures[id.0].name
+    }
+
+    /// Whether a texture has an alpha channel
+    pub fn has_alpha(&self, id: TextureId) -> bool {
+        self.textures[id.0].has_alpha
+    }
+
+    /// Sets the texture bound in place of images that failed to decode
+    pub fn set_missing(&mut self, id: TextureId) {
+        self.missing = Some(id);
+    }
+
+    /// Binds a texture to the first unit and points the program's sampler at it
+    pub fn bind(&self, id: TextureId, program: GLuint) {
+        self.bind_sampler(id, program, "uSampler", 0);
+    }
+
+    /// Binds a texture to a unit and points a named sampler at it
+    ///
+    /// Samplers used in the same draw need different units.
+    pub fn bind_sampler(&self, id: TextureId, program: GLuint, sampler: &str, unit: u32) {
+        let gl = &self.gl;
+        let texture = match self.missing {
+            Some(missing) if self.textures[id.0].failed => &self.textures[missing.0],
+            _ => &self.textures[id.0],
+        };
+        gl.active_texture(gl::TEXTURE0 + unit);
+        gl.bind_texture(texture.target, texture.name);
+        let sampler_location = gl.get_uniform_location(program, sampler);
+        gl.uniform_1i(sampler_location, unit as i32);
+    }
+}
@@ -0,0 +1,66 @@
+use gleam::gl::{GLint, GLuint};
+
+use super::GlPtr;
+use matrix::{gl_float, Scalar};
+
+/// How light brighter than the screen can show is brought into range
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum ToneMap {
+    /// Bright light is clipped
+    None,
+    /// Everything is compressed, bright light most
+    Reinhard,
+    /// Filmic curve fit to ACES, with more contrast than Reinhard
+    Aces,
+}
+
+impl ToneMap {
+    /// Tone map switched to after this one
+    pub fn next(self) -> ToneMap {
+        match self {
+            ToneMap::None => ToneMap::Reinhard,
+            ToneMap::Reinhard => ToneMap::Aces,
+            ToneMap::Aces => ToneMap::None,
+        }
+    }
+
+    /// Number the shader uses for the tone map
+    fn index(self) -> GLint {
+        match self {
+            ToneMap::None => 0,
+            ToneMap::Reinhard => 1,
+            ToneMap::Aces => 2,
+        }
+    }
+}
+
+/// Sets the uniforms of `TONE_MAP_SRC` in a program
+pub fn upload_tone_map(gl: &GlPtr, program: GLuint, tone_map: ToneMap, exposure: Scalar) {
+    let tone_map_location = gl.get_uniform_location(program, "uToneMap");
+    gl.uniform_1i(tone_map_location, tone_map.index());
+    let exposure_location = gl.get_uniform_location(program, "uExposure");
+    gl.uniform_1f(exposure_location, gl_float(exposure));
+}
+
+/// Last step of every fragment shader, turning linear light into sRGB for the canvas
+pub const TONE_MAP_SRC: &[u8] = b"
+const int TONE_MAP_REINHARD = 1;
+const int TONE_MAP_ACES = 2;
+
+// Scales the light before it is tone mapped
+uniform float uExposure;
+uniform int uToneMap;
+
+vec3 toneMap(vec3 color) {
+    color *= uExposure;
+    if (uToneMap == TONE_MAP_REINHARD) {
+        color = color / (1.0 + color);
+    } else if (uToneMap == TONE_MAP_ACES) {
+        // Krzysztof Narkowicz's fit
+        color = clamp(
+            (color * (2.51 * color + 0.03)) / (color * (2.43 * color + 0.59) + 0.14), 0.0, 1.0);
+    }
+    // The canvas expects sRGB
+    return pow(color, vec3(1.0 / 2.2));
+}
+";
@@ -0,0 +1,95 @@
+use matrix::consts::PI;
+use matrix::{vec2, Scalar, Vec2, Vec3};
+use mesh::{Instance, MeshData};
+use render::Drawable;
+use sweep::Sweep;
+
+/// Round pipe along a line of points, such as a cable or a curtain rod
+///
+/// A circle swept along the points, mitred where they turn so the tube keeps its width
+/// through the bends. Its ends are capped unless it is closed into a loop.
+pub struct Tube {
+    sweep: Sweep,
+}
+
+/// Circle of `sides` points, counter-clockwise so its faces point out
+fn circle(radius: Scalar, sides: u16) -> Vec<Vec2> {
+    (0..sides)
+        .map(|i| {
+            let angle = Scalar::from(i) * 2.0 * PI / Scalar::from(sides);
+            vec2(angle.cos() * radius, angle.sin() * radius)
+        })
+        .collect()
+}
+
+impl Tube {
+    pub fn new(points: Vec<Vec3>, radius: Scalar, sides: u16, translate: Vec3) -> Tube {
+        Tube {
+            sweep: Sweep::new(circle(radius, sides), points, translate).with_smooth(true),
+        }
+    }
+
+    /// Joins the last point back to the first, for rings
+    pub fn with_closed(self, closed: bool) -> Tube {
+        Tube {
+            sweep: self.sweep.with_closed(closed),
+        }
+    }
+}
+
+impl Drawable for Tube {
+    /// Tubes share a mesh with sweeps of the same circle
+    fn mesh_key(&self) -> String {
+        self.sweep.mesh_key()
+    }
+    /// Generates the vertices of the shape
+    fn mesh_data(&self) -> MeshData {
+        self.sweep.mesh_data()
+    }
+    /// Mesh of the object and how it is placed and looks
+    fn instance(&self) -> &Instance {
+        self.sweep.instance()
+    }
+    fn instance_mut(&mut self) -> &mut Instance {
+        self.sweep.instance_mut()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::Tube;
+    use matrix::{vec3, Scalar, Vec3};
+    use render::{Drawable, VERTEX_SIZE};
+
+    #[test]
+    fn test_tube() {
+        // A rod coming out of the wall, along it and back in
+        let path = vec![
+            vec3(0.0, 0.0, 0.0),
+            vec3(0.0, 0.0, 1.0),
+            vec3(2.0, 0.0, 1.0),
+            vec3(2.0, 0.0, 0.0),
+        ];
+        let data = Tube::new(path, 0.1, 8, Vec3::origin()).mesh_data();
+        // Three lengths of eight sides, and both ends capped
+        assert_eq!(data.vertices.len(), (3 * 8 * 6 + 2 * 6 * 3) * VERTEX_SIZE);
+        // Mitred, the tube is as wide through its corners as along its lengths
+        let bounds = data.bounds.unwrap();
+        assert!((bounds.max.z - 1.1).abs() < 1e-4 && (bounds.min.x + 0.1).abs() < 1e-4);
+        assert!((bounds.max.y - 0.1).abs() < 1e-4);
+        // Smooth, every normal points away from the line through the middle of its length
+        for vertex in data.vertices.chunks(VERTEX_SIZE) {
+            let value = |i: usize| Scalar::from(vertex[i]);
+            let position = vec3(value(0), value(1), value(2));
+            let normal = vec3(value(3), value(4), value(5));
+            let middle = if position.z > 0.2 && position.z < 0.8 {
+                vec3(position.x.round(), 0.0, position.z)
+            } else if position.x > 0.2 && position.x < 1.8 {
+                vec3(position.x, 0.0, 1.0)
+            } else {
+                continue;
+            };
+            assert!(normal.dot(&(position - middle)) > 0.0);
+        }
+    }
+}